@@ -1,43 +1,134 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 
-use muskox::board::Bitboard;
-use muskox::search::{Engine, SearchConstraint, Searchable};
+use muskox::perft;
+use muskox::prelude::*;
 
-static BOARDS_FENS: [&'static str; 4] = [
+// the search benchmarks run every position on each of THREADS, which split the actions
+// at the root between them, to show how the search scales. they use a small table so
+// that clearing it between cold searches costs little next to the search itself
+
+static THREADS: [usize; 3] = [1, 2, 4];
+
+const HASH_SIZE: usize = 16;
+
+/// Positions searched with a cold and a warm transposition table
+static TT_FENS: [&str; 2] = [
+    "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12",
+    "W:W17,21,22,24,25,26,27,28,30,31,32:B1,2,3,5,6,7,9,10,11,12,15",
+];
+
+/// Middle games full of multi-jumps that branch
+static JUMP_FENS: [&str; 3] = [
+    "B:W6,7,14,15,22,23,24:BK1,K3",
+    "W:W29,30,31,32:B1,2,3,10,11,18,19,25,26,27",
+    "B:W9,10,11,17,18,19,25,26,27:B1,2,3,5,6",
+];
+
+/// Endgames of kings that go on for many moves before anything is decided
+static KING_FENS: [&str; 3] = [
+    "B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29",
+    "W:W9,K11,19,K26,27,30:B15,22,25,K32",
+    "B:WK10,K15,K27:BK1,K5",
+];
+
+/// Positions move generation is measured on
+static GENERATE_FENS: [&str; 5] = [
     "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12",
     "B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29",
     "W:W9,K11,19,K26,27,30:B15,22,25,K32",
     "B:WK11,3:B",
+    "B:W6,7,14,15,22,23,24:BK1,K3",
 ];
 
-pub fn movepick_benchmarker(c: &mut Criterion) {
-    let mut engine = Engine::new();
-    let constraint = SearchConstraint::none();
-
-    let mut group = c.benchmark_group("engine");
-    for (i, board) in BOARDS_FENS
-        .iter()
-        .map(|s| Bitboard::from_fen(s).unwrap())
-        .enumerate()
-    {
-        group.bench_with_input(i.to_string(), &board, |b, &board| {
-            b.iter(|| engine.search(&board, &constraint));
-        });
+fn boards(fens: &[&str]) -> Vec<Bitboard> {
+    fens.iter().map(|s| Bitboard::from_fen(s).unwrap()).collect()
+}
+
+fn engine(threads: usize) -> Engine<Bitboard> {
+    Engine::builder()
+        .threads(threads)
+        .tt_size_mb(HASH_SIZE)
+        .build()
+        .unwrap()
+}
+
+/// Searches every board from scratch on each thread count
+fn search_group(c: &mut Criterion, name: &str, fens: &[&str], depth: u32) {
+    let constraint = SearchConstraint::Depth(depth);
+
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    for threads in THREADS {
+        let mut engine = engine(threads);
+        for (i, board) in boards(fens).into_iter().enumerate() {
+            let id = BenchmarkId::new(format!("{} threads", threads), i);
+            group.bench_with_input(id, &board, |b, board| {
+                b.iter(|| {
+                    engine.reset();
+                    engine.search(board, &constraint)
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+pub fn tt_benchmarker(c: &mut Criterion) {
+    search_group(c, "tt cold", &TT_FENS, 9);
+
+    // searching a position again is mostly answered by the table the first search filled
+    let constraint = SearchConstraint::Depth(9);
+    let mut group = c.benchmark_group("tt warm");
+    group.sample_size(10);
+    for threads in THREADS {
+        let engine = engine(threads);
+        for (i, board) in boards(&TT_FENS).into_iter().enumerate() {
+            engine.search(&board, &constraint);
+            let id = BenchmarkId::new(format!("{} threads", threads), i);
+            group.bench_with_input(id, &board, |b, board| {
+                b.iter(|| engine.search(board, &constraint));
+            });
+        }
     }
+    group.finish();
+}
+
+pub fn jump_benchmarker(c: &mut Criterion) {
+    search_group(c, "multi-jumps", &JUMP_FENS, 9);
+}
+
+pub fn king_benchmarker(c: &mut Criterion) {
+    search_group(c, "king endgames", &KING_FENS, 11);
 }
 
 pub fn generate_benchmarker(c: &mut Criterion) {
     let mut group = c.benchmark_group("generate all moves");
-    for (i, board) in BOARDS_FENS
-        .iter()
-        .map(|s| Bitboard::from_fen(s).unwrap())
-        .enumerate()
-    {
-        group.bench_with_input(i.to_string(), &board, |b, &board| {
+    for (i, board) in boards(&GENERATE_FENS).into_iter().enumerate() {
+        group.bench_with_input(BenchmarkId::new("actions", i), &board, |b, board| {
             b.iter(|| board.generate_all_actions());
         });
+        group.bench_with_input(BenchmarkId::new("successors", i), &board, |b, board| {
+            b.iter(|| board.successors());
+        });
+    }
+    group.finish();
+
+    // perft generates at every node of the tree, so the vectors are allocated over and
+    // over the way they are in a search
+    let mut group = c.benchmark_group("perft");
+    for (i, board) in boards(&GENERATE_FENS[..3]).into_iter().enumerate() {
+        group.bench_with_input(BenchmarkId::new("depth 4", i), &board, |b, board| {
+            b.iter(|| perft::perft(board, 4));
+        });
     }
+    group.finish();
 }
 
-criterion_group!(benches, movepick_benchmarker, generate_benchmarker);
+criterion_group!(
+    benches,
+    tt_benchmarker,
+    jump_benchmarker,
+    king_benchmarker,
+    generate_benchmarker
+);
 criterion_main!(benches);
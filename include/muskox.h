@@ -0,0 +1,51 @@
+/*
+ * C interface of the muskox checkers engine. Build the dynamic library with
+ * `cargo build --release --features ffi` and link against it.
+ *
+ * Positions are passed as FEN strings and actions as movetext. Strings returned by the
+ * library belong to the caller and have to be given back to muskox_string_free. No
+ * function lets a panic of the engine unwind into the caller.
+ */
+
+#ifndef MUSKOX_H
+#define MUSKOX_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MUSKOX_OK 0
+#define MUSKOX_ERROR -1
+
+/* An engine along with the position it searches */
+typedef struct MuskoxEngine MuskoxEngine;
+
+/* Creates an engine set up at the starting position. Free it with muskox_engine_free.
+ * Returns null if it cannot be created. */
+MuskoxEngine *muskox_engine_new(void);
+
+/* Destroys an engine. Null pointers are ignored. */
+void muskox_engine_free(MuskoxEngine *engine);
+
+/* Sets the position from a FEN string. Returns MUSKOX_ERROR and keeps the old position
+ * if the FEN is invalid. */
+int muskox_set_position(MuskoxEngine *engine, const char *fen);
+
+/* Returns the legal actions as movetext separated by spaces, empty once the game is
+ * over, or null if they cannot be listed. */
+char *muskox_legal_moves(const MuskoxEngine *engine);
+
+/* Searches for at most `milliseconds` and returns the movetext of the best action, or
+ * null if the game is over or the search failed. Blocks until the search ends. */
+char *muskox_search(const MuskoxEngine *engine, uint32_t milliseconds);
+
+/* Frees a string returned by the library. Null pointers are ignored. */
+void muskox_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MUSKOX_H */
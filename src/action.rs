@@ -1,13 +1,11 @@
-use std::cmp;
+use std::convert::TryFrom;
 use std::fmt;
 
 use crate::error::ParseError;
 use crate::parse;
 
-// need lookup table for square index for next direction
-
 /// Represents one of the four directions one can move in the game of checkers
-#[derive(PartialEq, Debug)] // dont need to keep debug
+#[derive(PartialEq, Debug, Clone, Copy)] // dont need to keep debug
 pub enum Direction {
     UpLeft,
     UpRight,
@@ -15,107 +13,108 @@ pub enum Direction {
     DownRight,
 }
 
-impl Direction {
-    // these three methods need an assessment of implementation and naming
-
-    pub(crate) fn between(source: u8, destination: u8) -> Option<Self> {
-        // this function is not safe yet... and its really messy
+// moving around the board happens in the hottest loops of move generation, so where each
+// direction leads from every square is worked out at compile time instead of with row
+// and column arithmetic on every call
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::UpLeft,
+    Direction::UpRight,
+    Direction::DownLeft,
+    Direction::DownRight,
+];
+
+// the `(row, col)` step taken by a single square in each direction
+const OFFSETS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+/// The squares a single step leads to, by square and then direction
+const STEPS: [[Option<u8>; 4]; 32] = walk_table(1);
+/// The squares a jump lands on, by square and then direction
+const JUMPS: [[Option<u8>; 4]; 32] = walk_table(2);
+/// The direction from a square to every other square on a diagonal with it and how many
+/// squares away it is, by source and then destination
+const DIAGONALS: [[Option<(Direction, u8)>; 32]; 32] = diagonal_table();
+
+/// The most jumps an action can be made of
+pub(crate) const MAX_JUMPS: u8 = 8;
+
+/// Walks `distance` squares from the square `index` in the direction `direction`
+const fn walk(index: usize, direction: usize, distance: i8) -> Option<u8> {
+    // rows alternate between starting on a dark and a light square
+    let row = (index / 4) as i8;
+    let col = ((index % 4) * 2 + 1) as i8 - row % 2;
+
+    let row = row + OFFSETS[direction].0 * distance;
+    let col = col + OFFSETS[direction].1 * distance;
+
+    if row < 0 || row > 7 || col < 0 || col > 7 {
+        return None;
+    }
+    Some((row * 4 + col / 2) as u8)
+}
 
-        let diff = (destination as i8) - (source as i8);
-        // should aim to try to change this or move it elsewhere
+const fn walk_table(distance: i8) -> [[Option<u8>; 4]; 32] {
+    let mut table = [[None; 4]; 32];
 
-        // check jump
-        match diff {
-            -9 => return Some(Direction::UpLeft),
-            -7 => return Some(Direction::UpRight),
-            7 => return Some(Direction::DownLeft),
-            9 => return Some(Direction::DownRight),
-            _ => (),
+    let mut index = 0;
+    while index < 32 {
+        let mut direction = 0;
+        while direction < 4 {
+            table[index][direction] = walk(index, direction, distance);
+            direction += 1;
         }
+        index += 1;
+    }
+    table
+}
 
-        // check move
-        if source / 4 % 2 == 0 {
-            // even rows
-            match diff {
-                -4 => Some(Direction::UpLeft),
-                -3 => Some(Direction::UpRight),
-                4 => Some(Direction::DownLeft),
-                5 => Some(Direction::DownRight),
-                _ => None,
-            }
-        } else {
-            // odd rows
-            match diff {
-                -5 => Some(Direction::UpLeft),
-                -4 => Some(Direction::UpRight),
-                3 => Some(Direction::DownLeft),
-                4 => Some(Direction::DownRight),
-                _ => None,
+const fn diagonal_table() -> [[Option<(Direction, u8)>; 32]; 32] {
+    let mut table = [[None; 32]; 32];
+
+    let mut index = 0;
+    while index < 32 {
+        let mut direction = 0;
+        while direction < 4 {
+            let mut distance = 1;
+            while distance < 8 {
+                if let Some(square) = walk(index, direction, distance) {
+                    table[index][square as usize] = Some((DIRECTIONS[direction], distance as u8));
+                }
+                distance += 1;
             }
+            direction += 1;
         }
+        index += 1;
     }
+    table
+}
 
-    pub(crate) fn relative_to(&self, position: u8) -> Option<u8> {
-        // need to check boundaries
-        if position > 31 {
-            return None;
-        }
-        let position = position as i8;
-        let out;
-        // maybe make this a cache would be better in terms of simplicity and performance?
-        // could defintely make it more compact
-        if position / 4 % 2 == 0 {
-            // even rows
-            out = match *self {
-                Direction::UpLeft => position - 4,
-                Direction::UpRight => position - 3,
-                Direction::DownLeft => position + 4,
-                Direction::DownRight => position + 5,
-            }
-        } else {
-            // odd rows
-            out = match *self {
-                Direction::UpLeft => position - 5,
-                Direction::UpRight => position - 4,
-                Direction::DownLeft => position + 3,
-                Direction::DownRight => position + 4,
-            }
-        }
-        if !(0..=31).contains(&out) {
-            return None;
-        }
-        // ensure that we dont escape the bounds on the board.
-        // maybe change this strat later
-        let in_col = position % 4;
-        let out_col = out % 4;
-        if out_col - in_col != 0 && out_col - in_col != 1 && out_col - in_col != -1 {
-            return None;
-        }
-        Some(out as u8)
+impl Direction {
+    /// Returns the direction leading from `source` to `destination` and how many squares
+    /// away it is, if they are on a diagonal together
+    #[inline]
+    pub(crate) fn towards(source: u8, destination: u8) -> Option<(Self, u8)> {
+        *DIAGONALS.get(source as usize)?.get(destination as usize)?
     }
 
-    pub(crate) fn relative_jump_from(&self, position: u8) -> Option<u8> {
-        let position = position as i8;
-        // maybe rename this method
-        if position > 31 {
-            return None;
-        }
-        let out = match *self {
-            Direction::UpLeft => position - 9,
-            Direction::UpRight => position - 7,
-            Direction::DownLeft => position + 7,
-            Direction::DownRight => position + 9,
-        };
-        if !(0..=31).contains(&out) {
-            return None;
+    /// Returns the square `distance` squares away from `position` in this direction
+    #[inline]
+    pub(crate) fn walk_from(&self, position: u8, distance: u8) -> Option<u8> {
+        match distance {
+            1 => self.relative_to(position),
+            2 => self.relative_jump_from(position),
+            _ => walk(position as usize, *self as usize, distance as i8),
         }
+    }
 
-        let in_col = (position % 4) as i8;
-        let out_col = (out % 4) as i8;
-        if out_col - in_col != -1 && out_col - in_col != 1 {
-            return None;
-        }
-        Some(out as u8)
+    #[inline]
+    pub(crate) fn relative_to(&self, position: u8) -> Option<u8> {
+        STEPS.get(position as usize)?[*self as usize]
+    }
+
+    #[inline]
+    pub(crate) fn relative_jump_from(&self, position: u8) -> Option<u8> {
+        JUMPS.get(position as usize)?[*self as usize]
     }
 }
 
@@ -126,12 +125,45 @@ pub enum ActionType {
     Jump,
 }
 
-// source: 5, destination: 5, jump length: 5, jump directions: 8 * 2 bits (four directions), unused: 1
+// source: 5, destination: 5, jump length: 4, then for every jump its direction (2 bits) and
+// how many squares it goes (3 bits), as kings fly across several squares in some variants
 /// Represents an action that can be made on a checkerboard
-#[derive(PartialEq, Clone, Copy)]
-pub struct Action(u32);
+#[derive(Clone, Copy)]
+pub struct Action(u64);
 
 impl Action {
+    /// Creates a new checkers action from the standard position numbers it visits, in
+    /// order. Any iterator will do, so moves can be built without allocating.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - The standard position numbers representing a move
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Action;
+    ///
+    /// let action = Action::from_positions([8, 15, 22]).unwrap();
+    /// assert_eq!(action.destination(), 21);
+    /// assert_eq!(Action::from_positions((1..4).map(|n| n * 7 + 1)).unwrap(), action);
+    /// ```
+    pub fn from_positions<I>(positions: I) -> Result<Self, ParseError>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut indices = [0; MAX_JUMPS as usize + 1];
+        let mut len = 0;
+
+        for position in positions {
+            // a tenth position is one too many
+            *indices.get_mut(len).ok_or(ParseError::MoveQuantityError)? = position.wrapping_sub(1); // position 0 wraps around and is caught as out of range
+            len += 1;
+        }
+
+        Self::from_indices(&indices[..len])
+    }
+
     /// Creates a new checkers action from a vector of positions.
     ///
     /// # Arguments
@@ -146,50 +178,68 @@ impl Action {
     /// let action = Action::from_vec(vec![19, 24]).unwrap();
     /// assert_eq!(action.source(), 18);  // note that internal representation starts from 0, no longer 1.
     /// ```
+    #[inline]
     pub fn from_vec(positions: Vec<u8>) -> Result<Self, ParseError> {
-        // maybe make this method work for all iterators and not just vectors
-        let positions: Vec<_> = positions.iter().map(|x| x - 1).collect();
+        Self::from_positions(positions)
+    }
 
+    /// Creates an action from the internal indices of the squares it visits, which start
+    /// from 0 instead of 1. Move generation builds actions this way without allocating.
+    pub(crate) fn from_indices(positions: &[u8]) -> Result<Self, ParseError> {
         // check that all of the position numbers are in the right range
         if positions.iter().any(|&x| x > 31) {
             return Err(ParseError::PositionValueError);
         }
 
         // check to see if it is a valid length of position vector with max number of moves is 8
-        if positions.len() < 2 || positions.len() > 9 {
+        if positions.len() < 2 || positions.len() > MAX_JUMPS as usize + 1 {
             return Err(ParseError::MoveQuantityError);
         }
 
         let source = *positions.first().unwrap();
         let destination = *positions.last().unwrap();
 
-        let mut data = source as u32; // source
-        data |= (destination as u32) << 5; // destination
-
-        let abs_diff = cmp::max(source, destination) - cmp::min(source, destination);
+        // a step to a neighboring square is a move, and anything else a chain of jumps.
+        // a flying king moving further along a diagonal is read as a capture here, and
+        // boards read it as a move where there is nothing to capture
+        if let [_, _] = positions {
+            if let Some((_, 1)) = Direction::towards(source, destination) {
+                return Ok(Self::ends(source, destination));
+            }
+        }
 
-        // check if this action has jumps in it
-        if positions.len() > 2 || (abs_diff != 3 && abs_diff != 4 && abs_diff != 5) {
-            data |= ((positions.len() - 1) << 10) as u32; // jump length
+        let mut data = Self::ends(source, destination).0;
+        data |= ((positions.len() - 1) << 10) as u64; // jump length
 
-            for i in 0..(positions.len() - 1) {
-                let diff = (positions[i + 1] as i8) - (positions[i] as i8);
-                let direction = match diff {
-                    -9 => Direction::UpLeft,
-                    -7 => Direction::UpRight,
-                    7 => Direction::DownLeft,
-                    9 => Direction::DownRight,
-                    _ => return Err(ParseError::PositionValueError),
-                };
+        for (i, pair) in positions.windows(2).enumerate() {
+            let (direction, distance) = match Direction::towards(pair[0], pair[1]) {
+                Some((direction, distance)) if distance > 1 => (direction, distance),
+                _ => return Err(ParseError::PositionValueError),
+            };
 
-                let shift = i * 2 + 15;
-                data |= (direction as u32) << shift; // jump direction
-            }
+            let shift = i * 5 + 14;
+            data |= (direction as u64 | (distance as u64) << 2) << shift; // jump
         }
 
         Ok(Action(data))
     }
 
+    /// Creates the move from `source` to `destination` along a diagonal, however far
+    /// apart they are
+    pub(crate) fn straight_move(source: u8, destination: u8) -> Option<Self> {
+        Direction::towards(source, destination)?;
+        Some(Self::ends(source, destination))
+    }
+
+    /// Creates the capture from `source` to `destination` along a diagonal
+    pub(crate) fn straight_jump(source: u8, destination: u8) -> Option<Self> {
+        Self::from_indices(&[source, destination]).ok()
+    }
+
+    fn ends(source: u8, destination: u8) -> Self {
+        Action(source as u64 | (destination as u64) << 5)
+    }
+
     /// Creates a new checkers action from a string movetext according to Portable Draughts Notation.
     /// (PDN). Read more about the notation [here](https://en.wikipedia.org/wiki/Portable_Draughts_Notation).
     ///
@@ -227,6 +277,11 @@ impl Action {
         ((self.0 >> 10) & 15) as u8
     }
 
+    #[inline]
+    fn jump_bits(&self, i: u8) -> u64 {
+        self.0 >> (i as u64 * 5 + 14) & 31
+    }
+
     /// Returns the direction of a particular jump
     ///
     /// This is wrapped in an option, because if no jumps were performed then
@@ -242,7 +297,7 @@ impl Action {
         if i >= self.jump_len() {
             return None;
         }
-        match (self.0 >> (i * 2 + 15)) & 3 {
+        match self.jump_bits(i) & 3 {
             0 => Some(Direction::UpLeft),
             1 => Some(Direction::UpRight),
             2 => Some(Direction::DownLeft),
@@ -251,6 +306,16 @@ impl Action {
         }
     }
 
+    /// Returns how many squares a particular jump goes, which is two unless a flying king
+    /// makes it
+    #[inline]
+    pub fn jump_distance(&self, i: u8) -> Option<u8> {
+        match i < self.jump_len() {
+            true => Some((self.jump_bits(i) >> 2) as u8),
+            false => None,
+        }
+    }
+
     /// Returns the type of a particular action
     #[inline]
     pub fn action_type(&self) -> ActionType {
@@ -273,34 +338,95 @@ impl Action {
         let source = self.source();
         let destination = self.destination();
 
-        Direction::between(source, destination)
+        Direction::towards(source, destination).map(|(direction, _)| direction)
     }
 
-    /// Generate movetext for a particular action
-    pub fn movetext(&self) -> String {
+    /// Returns the bits the action is packed into, which files store it as
+    #[inline]
+    pub(crate) fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    /// Unpacks an action from `to_bits`. The bits are not checked, so actions read from
+    /// files have to be checked against the legal ones before they are taken.
+    #[inline]
+    pub(crate) fn from_bits(bits: u64) -> Self {
+        Action(bits)
+    }
+
+    /// Returns the squares the piece stands on along the action, from the source to the
+    /// destination, numbered 1 to 32 like in movetext
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Action;
+    ///
+    /// let action = Action::from_movetext("8-15-22").unwrap();
+    /// assert_eq!(action.positions(), vec![8, 15, 22]);
+    /// ```
+    pub fn positions(&self) -> Vec<u8> {
         let source = self.source();
 
         match self.action_type() {
-            ActionType::Move => format!("{}-{}", source + 1, self.destination() + 1),
+            ActionType::Move => vec![source + 1, self.destination() + 1],
             ActionType::Jump => {
-                let mut out = format!("{}-", source + 1);
+                let mut positions = vec![source + 1];
                 let mut curr = source;
 
                 for i in 0..self.jump_len() {
                     curr = self
                         .jump_direction(i)
                         .unwrap()
-                        .relative_jump_from(curr)
+                        .walk_from(curr, self.jump_distance(i).unwrap())
                         .unwrap();
-                    out.push_str(&format!("{}-", curr + 1));
+                    positions.push(curr + 1);
                 }
 
-                out.pop(); // excess '-'
-
-                out
+                positions
             }
         }
     }
+
+    /// Generate movetext for a particular action
+    pub fn movetext(&self) -> String {
+        self.positions()
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// Generate movetext in Portable Draughts Notation, where the squares of a capture
+    /// are separated by `x` rather than `-`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Action;
+    ///
+    /// let action = Action::from_movetext("8-15-22").unwrap();
+    /// assert_eq!(action.pdn_movetext(), "8x15x22");
+    /// ```
+    pub fn pdn_movetext(&self) -> String {
+        match self.action_type() {
+            ActionType::Move => self.movetext(),
+            ActionType::Jump => self.movetext().replace('-', "x"),
+        }
+    }
+}
+
+// a path along a single diagonal is a move or a capture depending on the board once kings
+// fly. captures are compulsory, so only one of the two is ever legal, and actions are told
+// apart by the squares they visit
+impl PartialEq for Action {
+    fn eq(&self, other: &Self) -> bool {
+        let straight = |action: &Action| action.jump_len() <= 1;
+        self.0 == other.0
+            || straight(self)
+                && straight(other)
+                && (self.source(), self.destination()) == (other.source(), other.destination())
+    }
 }
 
 impl fmt::Display for Action {
@@ -309,6 +435,87 @@ impl fmt::Display for Action {
     }
 }
 
+// a blanket TryFrom over every iterator of positions would overlap the one the standard
+// library gives every type, so the common containers get one each
+
+impl TryFrom<&[u8]> for Action {
+    type Error = ParseError;
+
+    fn try_from(positions: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_positions(positions.iter().copied())
+    }
+}
+
+impl<const N: usize> TryFrom<[u8; N]> for Action {
+    type Error = ParseError;
+
+    fn try_from(positions: [u8; N]) -> Result<Self, Self::Error> {
+        Self::from_positions(positions)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Action {
+    type Error = ParseError;
+
+    fn try_from(positions: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::from_positions(positions)
+    }
+}
+
+impl TryFrom<&str> for Action {
+    type Error = ParseError;
+
+    /// Parses movetext like `from_movetext`
+    fn try_from(movetext: &str) -> Result<Self, Self::Error> {
+        Self::from_movetext(movetext)
+    }
+}
+
+// actions are saved as their movetext
+
+impl serde::Serialize for Action {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.movetext())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Action {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let movetext = String::deserialize(deserializer)?;
+        Self::from_movetext(&movetext).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl<'a> arbitrary::Arbitrary<'a> for Action {
+    /// Creates a well formed action from unstructured bytes: a step or a chain of up to
+    /// eight jumps along the diagonals. It is not necessarily legal on any board.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut squares = vec![u.int_in_range(0..=31)?];
+
+        for _ in 0..u.int_in_range(0..=8)? {
+            let last = *squares.last().unwrap() as usize;
+            let jumps: Vec<_> = JUMPS[last].iter().flatten().copied().collect();
+            match jumps.is_empty() {
+                true => break,
+                false => squares.push(*u.choose(&jumps)?),
+            }
+        }
+
+        // every square has a step to take
+        if squares.len() == 1 {
+            let steps: Vec<_> = STEPS[squares[0] as usize]
+                .iter()
+                .flatten()
+                .copied()
+                .collect();
+            squares.push(*u.choose(&steps)?);
+        }
+
+        Action::from_indices(&squares).map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
 impl fmt::Debug for Action {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(f, "Action({})", self.movetext())
@@ -326,11 +533,15 @@ mod tests {
 
     #[test]
     fn relative_position_test() {
-        let dir = Direction::between(1, 10);
-        assert_eq!(dir, Some(Direction::DownRight));
+        let dir = Direction::towards(1, 10);
+        assert_eq!(dir, Some((Direction::DownRight, 2)));
+
+        let dir = Direction::towards(15, 11);
+        assert_eq!(dir, Some((Direction::UpRight, 1)));
 
-        let dir = Direction::between(15, 11);
-        assert_eq!(dir, Some(Direction::UpRight));
+        let dir = Direction::towards(3, 28);
+        assert_eq!(dir, Some((Direction::DownLeft, 7)));
+        assert_eq!(Direction::DownLeft.walk_from(3, 7), Some(28));
 
         let pos = Direction::DownRight.relative_to(1);
         assert_eq!(pos, Some(6));
@@ -346,6 +557,51 @@ mod tests {
 
         let pos = Direction::UpRight.relative_jump_from(7);
         assert_eq!(pos, None); // does not work yet
+
+        // the tables agree with walking the grid
+        for square in (0..32).map(crate::board::Square::new) {
+            let (row, col) = square.coords();
+            for (direction, (d_row, d_col)) in DIRECTIONS.iter().zip(&OFFSETS) {
+                let walk = |distance: i8| {
+                    let row = (row as i8 + d_row * distance) as u8;
+                    let col = (col as i8 + d_col * distance) as u8;
+                    crate::board::Square::from_coords(row, col).map(|s| s.index())
+                };
+                assert_eq!(direction.relative_to(square.index()), walk(1));
+                assert_eq!(direction.relative_jump_from(square.index()), walk(2));
+                for distance in 1..8 {
+                    assert_eq!(
+                        direction.walk_from(square.index(), distance as u8),
+                        walk(distance)
+                    );
+                    if let Some(target) = walk(distance) {
+                        let step = (*direction, distance as u8);
+                        assert_eq!(Direction::towards(square.index(), target), Some(step));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conversion_test() {
+        let action = Action::from_movetext(TEST_MOVE_2).unwrap();
+        let positions: Vec<u8> = TEST_MOVE_2.split('-').map(|p| p.parse().unwrap()).collect();
+
+        assert_eq!(Action::try_from(positions.as_slice()).unwrap(), action);
+        assert_eq!(Action::try_from(positions.clone()).unwrap(), action);
+        assert_eq!(Action::from_vec(positions).unwrap(), action);
+        assert_eq!(Action::try_from(TEST_MOVE_2).unwrap(), action);
+        assert_eq!(Action::try_from([19, 24]).unwrap().source(), 18);
+
+        assert!(matches!(
+            Action::from_positions(1..=10),
+            Err(ParseError::MoveQuantityError)
+        ));
+        assert!(matches!(
+            Action::try_from([0, 5]),
+            Err(ParseError::PositionValueError)
+        ));
     }
 
     #[test]
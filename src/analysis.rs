@@ -0,0 +1,237 @@
+use std::fmt;
+
+use crate::board::{Action, Bitboard, Color};
+use crate::pdn::Game;
+use crate::search::{Engine, Score, SearchConstraint, Searchable};
+
+// annotating a game searches every position it went through. the score of a move is the
+// score of the position it led to, and a move loses as much as that score is worse for
+// the player who made it than the score of the best move in the position before
+
+/// Moves losing at least this much (half a man) are mistakes
+pub const MISTAKE_THRESHOLD: f32 = 0.5;
+/// Moves losing at least this much (a man) are blunders
+pub const BLUNDER_THRESHOLD: f32 = 1.;
+
+/// Represents how good a move was compared to the best one in the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+    Good,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    fn from_loss(loss: f32) -> Self {
+        if loss >= BLUNDER_THRESHOLD {
+            Judgement::Blunder
+        } else if loss >= MISTAKE_THRESHOLD {
+            Judgement::Mistake
+        } else {
+            Judgement::Good
+        }
+    }
+
+    /// Returns the numeric annotation glyph of the judgement: `$2` (poor move) for
+    /// mistakes and `$4` (very poor move) for blunders
+    pub fn nag(&self) -> Option<&'static str> {
+        match self {
+            Judgement::Good => None,
+            Judgement::Mistake => Some("$2"),
+            Judgement::Blunder => Some("$4"),
+        }
+    }
+}
+
+impl fmt::Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Judgement::Good => write!(f, "good"),
+            Judgement::Mistake => write!(f, "mistake"),
+            Judgement::Blunder => write!(f, "blunder"),
+        }
+    }
+}
+
+/// Describes a move of an annotated game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Annotation {
+    action: Action,
+    score: Score,
+    best: Score,
+    loss: f32,
+    judgement: Judgement,
+}
+
+impl Annotation {
+    /// Returns the move that was played
+    #[inline]
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    /// Returns the score of the position the move led to, from black's point of view
+    #[inline]
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Returns the score of the best move in the position, from black's point of view
+    #[inline]
+    pub fn best(&self) -> Score {
+        self.best
+    }
+
+    /// Returns how much worse the move was than the best one for the player who made it.
+    /// It is infinite for moves that threw away a win or walked into a loss.
+    #[inline]
+    pub fn loss(&self) -> f32 {
+        self.loss
+    }
+
+    #[inline]
+    pub fn judgement(&self) -> Judgement {
+        self.judgement
+    }
+
+    /// Returns the NAG and the comment holding the score, as written after the move
+    fn note(&self) -> String {
+        match self.judgement.nag() {
+            Some(nag) => format!("{} {{{}}}", nag, self.score),
+            None => format!("{{{}}}", self.score),
+        }
+    }
+}
+
+/// Represents a game along with an annotation for each of its moves
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotatedGame {
+    game: Game,
+    annotations: Vec<Annotation>,
+}
+
+impl AnnotatedGame {
+    #[inline]
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Returns the annotation of every move, in order
+    #[inline]
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Returns the game in PDN with the score of every move as a comment, and mistakes and
+    /// blunders marked with NAGs
+    pub fn pdn(&self) -> String {
+        let notes: Vec<_> = self.annotations.iter().map(|a| a.note()).collect();
+        self.game.pdn_with(&notes)
+    }
+}
+
+impl fmt::Display for AnnotatedGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pdn())
+    }
+}
+
+/// Returns the score of a position from black's point of view: the score of its best
+/// action, or its evaluation once the game is over
+fn score(
+    engine: &mut Engine<Bitboard>,
+    history: &[u64],
+    board: &Bitboard,
+    constraint: &SearchConstraint,
+) -> Score {
+    engine.set_history(history);
+    match engine.search(board, constraint).best() {
+        Some(best) => best.score(),
+        None => board.evaluate(),
+    }
+}
+
+/// Returns how much worse `after` is than `before` for `color`
+fn loss(color: Color, before: Score, after: Score) -> f32 {
+    // keeping a win or a loss costs nothing, and throwing one away costs everything
+    let loss = match color {
+        Color::Black => before.saturating_sub(after),
+        Color::White => after.saturating_sub(before),
+    };
+    f32::from(loss)
+}
+
+/// Searches every position of a game and judges each move by how much worse it was than
+/// the best one in its position
+///
+/// # Examples
+///
+/// ```
+/// use muskox::analysis::{self, Judgement};
+/// use muskox::pdn;
+/// use muskox::search::SearchConstraint;
+///
+/// // black gives a man away with 14-18
+/// let game = &pdn::parse_games("[FEN \"B:W22,23,27:B14,15,10\"]\n1. 14-18 *").unwrap()[0];
+/// let annotated = analysis::annotate(game, &SearchConstraint::Depth(4));
+/// assert_eq!(annotated.annotations()[0].judgement(), Judgement::Blunder);
+/// ```
+pub fn annotate(game: &Game, constraint: &SearchConstraint) -> AnnotatedGame {
+    let mut engine = Engine::new();
+    let hashes = game.hashes();
+
+    let mut board = game.start();
+    let mut best = score(&mut engine, &hashes[..1], &board, constraint);
+    let mut annotations = Vec::new();
+
+    for (i, &action) in game.actions().iter().enumerate() {
+        let next = board.take_action(action).unwrap();
+        let after = score(&mut engine, &hashes[..i + 2], &next, constraint);
+        let loss = loss(board.turn(), best, after).max(0.);
+
+        annotations.push(Annotation {
+            action,
+            score: after,
+            best,
+            loss,
+            judgement: Judgement::from_loss(loss),
+        });
+
+        board = next;
+        best = after;
+    }
+
+    AnnotatedGame {
+        game: game.clone(),
+        annotations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotate_test() {
+        let game = &crate::pdn::parse_games("1. 11-15 23-19 2. 8-11 22-17 *").unwrap()[0];
+        let annotated = annotate(game, &SearchConstraint::Depth(4));
+
+        assert_eq!(annotated.annotations().len(), 4);
+        for (annotation, action) in annotated.annotations().iter().zip(game.actions()) {
+            assert_eq!(annotation.action(), *action);
+            assert!(annotation.loss() >= 0.);
+        }
+        // the best move keeps the score of the position
+        let first = annotated.annotations()[0];
+        assert_eq!(first.loss() == 0., first.score() == first.best());
+
+        let pdn = annotated.pdn();
+        assert!(pdn.contains(&format!("1. 11-15 {{{}}}", first.score())));
+        // annotations are skipped when reading the game back
+        assert_eq!(crate::pdn::parse_games(&pdn).unwrap()[0], *game);
+
+        assert_eq!(Judgement::from_loss(0.7), Judgement::Mistake);
+        assert_eq!(Judgement::from_loss(f32::INFINITY).nag(), Some("$4"));
+        assert_eq!(loss(Color::White, Score::INFINITY, Score::INFINITY), 0.);
+    }
+}
@@ -1,11 +1,31 @@
 use std::default;
-use std::io::{self, Write};
+use std::fs;
+use std::io::{self, BufRead, Write};
 use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Scope, ScopedJoinHandle};
+use std::time::Duration;
 
-use crate::board::{Action, Bitboard};
+use serde_json::{json, Value};
+
+use crate::analysis::{self, AnnotatedGame};
+use crate::bench::{self, BenchReport};
+use crate::board::{
+    Action, Bitboard, Color, EvalBreakdown, PrettyOptions, Prng, RandomSpec, Square, Variant,
+};
 use crate::error::ParseError;
-use crate::parse;
-use crate::search::{Engine, SearchConstraint, Searchable};
+use crate::openings::ballot;
+use crate::options::{EngineOptions, Searcher};
+use crate::parse::{self, CommandSpec};
+use crate::pdn::{self, Game};
+use crate::players::{Clock, Player};
+use crate::search::{
+    Engine, GameState, MctsEngine, RootActions, Score, SearchConstraint, SearchInfo, SearchOutcome,
+    SearchResult, SearchTree, Searchable, Winner,
+};
+use crate::session::Session;
+use crate::testsuite::{self, SuiteReport};
 
 // convert this to lifetimes later...
 pub(crate) enum Command {
@@ -15,13 +35,41 @@ pub(crate) enum Command {
     ValidateAction(Action),
     TakeAction(Action),
     GenerateAllActions,
-    Search(SearchConstraint),
+    Search(SearchConstraint, RootActions<Action>),
     PickAction(SearchConstraint),
     EvaluateBoard(SearchConstraint),
+    ExplainEvaluation,
+    DebugTree(u32),
     GetTurn,
-    Print,
+    Print(PrettyOptions),
     GetMoveHistory,
+    PrintVariant,
+    SetVariant(Variant),
+    Ballot(usize),
+    RandomBallot,
+    Random(u32, u32),
+    Put(Square, Color, bool),
+    Remove(Square),
+    SetTurn(Color),
+    ClearBoard,
+    SetMode(OutputMode),
+    SetOption(String, String),
+    ShowOptions,
+    Analyze,
+    Stop,
+    Hint(Option<usize>),
+    Save(String),
+    Load(String, usize),
+    Annotate(String, usize),
+    SaveSession(String, bool),
+    LoadSession(String),
+    TestSuite(String, SearchConstraint),
+    Bench,
+    Next,
+    Prev,
     Clear,
+    Help(Option<&'static str>),
+    Complete(String),
     Exit,
 }
 use Command::*;
@@ -30,7 +78,434 @@ use Command::*;
 
 impl Command {
     fn parse(command: &str) -> Result<Command, ParseError> {
-        Ok(parse::command_primary(command)?.1)
+        parse::command(command)
+    }
+}
+
+/// Represents how the replies to commands are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Free-form text meant for people
+    Text,
+    /// A single line JSON object per command meant for scripts and GUIs
+    Json,
+}
+
+/// Represents what a command has to tell the user. Every reply can be rendered as text
+/// or as JSON.
+enum Reply {
+    Done,
+    Fen(String),
+    GameState(GameState<Bitboard>),
+    Validation(Result<(), String>),
+    Actions(Vec<Action>),
+    SearchResults(SearchResult<Bitboard>),
+    Best(Option<Action>),
+    Evaluation(Score),
+    Explanation(EvalBreakdown, [(u32, u32); 2]), // and the mobility of black, then white
+    Tree(SearchTree<Bitboard>),
+    Turn(Color),
+    Board(Bitboard, PrettyOptions),
+    History(Game),
+    Variant(Variant),
+    Ballot(usize, ballot::Ballot),
+    Options(EngineOptions),
+    Hint(Action, Score),
+    Candidates(Vec<Candidate>),
+    Annotated(AnnotatedGame),
+    SuiteReport(SuiteReport),
+    Bench(BenchReport),
+    Loaded {
+        number: usize,
+        games: usize,
+        moves: usize,
+    },
+    SessionLoaded {
+        moves: usize,
+        table: bool, // whether the transposition table was restored
+    },
+    Help(Option<&'static CommandSpec>),
+    Completions(Vec<String>),
+    Error(String),
+    UnknownCommand(String), // followed by the list of commands
+}
+
+// how deep the quick search behind `hint n` looks and how long its lines are
+const HINT_DEPTH: u32 = 6;
+const HINT_LINE_LENGTH: usize = 4;
+const HINT_CANDIDATES: usize = 3;
+const ANNOTATION_DEPTH: u32 = 8;
+
+/// Represents one of the best actions suggested by `hint`
+struct Candidate {
+    action: Action,
+    score: Score,
+    line: Vec<Action>,
+    cached: bool, // whether the score was already in the transposition table
+}
+
+fn join_actions(actions: &[Action]) -> String {
+    actions
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub(crate) fn json_actions(actions: &[Action]) -> Value {
+    actions.iter().map(|a| a.to_string()).collect()
+}
+
+pub(crate) fn json_score(score: Score) -> Value {
+    // json has no infinities so won positions are written out as strings
+    let value = f32::from(score);
+    match value.is_finite() {
+        true => json!(value),
+        false => json!(score.to_string()),
+    }
+}
+
+/// Writes a score out, followed by the chance of winning it stands for if asked to
+fn score_text(score: Score, win_probability: bool) -> String {
+    match win_probability {
+        true => format!("{:+} ({:.0}%)", score, score.to_win_probability() * 100.),
+        false => score.to_string(),
+    }
+}
+
+pub(crate) fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::Black => "black",
+        Color::White => "white",
+    }
+}
+
+impl Reply {
+    /// Returns the text printed for the reply, or `None` if there is nothing to print.
+    /// Scores are followed by the chance of winning they stand for if `win_probability`
+    /// is set.
+    fn text(&self, win_probability: bool) -> Option<String> {
+        let score = |score: Score| score_text(score, win_probability);
+        let text = match self {
+            Reply::Done => return None,
+            Reply::Fen(fen) => fen.clone(),
+            Reply::GameState(game_state) => game_state.to_string(),
+            Reply::Validation(Ok(())) => "Ok".to_string(),
+            Reply::Validation(Err(err)) => format!("Error: {}", err),
+            Reply::Actions(actions) if actions.is_empty() => "no valid actions".to_string(),
+            Reply::Actions(actions) => join_actions(actions),
+            Reply::SearchResults(result) if result.results().is_empty() => {
+                "no valid actions".to_string()
+            }
+            Reply::SearchResults(result) => {
+                let results = result
+                    .results()
+                    .iter()
+                    .map(|p| format!("{} ({})", p.action(), score(p.score())))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let collisions = match result.tt_collisions() {
+                    0 => String::new(),
+                    n => format!(", {} tt collisions", n),
+                };
+                let early = match result.stopped_early() {
+                    true => ", stopped early on an easy move",
+                    false => "",
+                };
+                format!(
+                    "{}\ndepth {} (max {}), {} nodes in {:.2}s ({} nps), {:.1}% tt hits, {} tb hits{}{}",
+                    results,
+                    result.depth(),
+                    result.max_depth(),
+                    result.nodes(),
+                    result.elapsed().as_secs_f64(),
+                    result.nps(),
+                    result.tt_hit_rate() * 100.,
+                    result.tb_hits(),
+                    collisions,
+                    early
+                )
+            }
+            Reply::Best(Some(action)) => action.to_string(),
+            Reply::Best(None) => "no action to take!".to_string(),
+            Reply::Evaluation(s) => score(*s),
+            Reply::Explanation(breakdown, [black, white]) => format!(
+                "{}\n{:<10}{} black, {} white\n{:<10}{} black, {} white",
+                breakdown, "movers", black.0, white.0, "jumpers", black.1, white.1
+            ),
+            Reply::Turn(color) => format!("{:?}", color),
+            Reply::Board(board, options) => board.pretty_with(*options),
+            Reply::History(game) if game.actions().is_empty() => "no moves taken yet".to_string(),
+            Reply::History(game) => match game.opening() {
+                Some(opening) => format!("{}\n{}", opening, game.movetext()),
+                None => game.movetext(),
+            },
+            Reply::Variant(variant) => variant.to_string(),
+            Reply::Ballot(number, b) => format!("ballot {}: {}", number, b),
+            Reply::Options(options) => options
+                .values()
+                .iter()
+                .map(|(name, value)| format!("{} {}", name, value))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Reply::Hint(action, s) => format!("{} ({})", action, score(*s)),
+            Reply::Candidates(candidates) if candidates.is_empty() => {
+                "no valid actions".to_string()
+            }
+            Reply::Candidates(candidates) => candidates
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    format!(
+                        "{}. {} ({}) from {}: {}",
+                        i + 1,
+                        c.action,
+                        score(c.score),
+                        match c.cached {
+                            true => "tt",
+                            false => "search",
+                        },
+                        c.line
+                            .iter()
+                            .map(|a| a.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Reply::Annotated(annotated) => annotated.pdn(),
+            Reply::SuiteReport(report) => report.to_string(),
+            Reply::Bench(report) => report.to_string(),
+            Reply::Loaded {
+                number,
+                games,
+                moves,
+            } => format!("loaded game {} of {} ({} moves)", number, games, moves),
+            Reply::SessionLoaded { moves, table } => format!(
+                "loaded session ({} moves{})",
+                moves,
+                if *table { ", transposition table" } else { "" }
+            ),
+            Reply::Help(Some(spec)) => match spec.aliases() {
+                [] => format!("{}\n{}", spec.usage(), spec.description()),
+                aliases => format!(
+                    "{}\n{}\nalso: {}",
+                    spec.usage(),
+                    spec.description(),
+                    aliases.join(", ")
+                ),
+            },
+            Reply::Help(None) => help_summary(),
+            Reply::Completions(words) => words.join(" "),
+            Reply::Tree(tree) => tree.to_dot().trim_end().to_string(),
+            Reply::Error(err) => format!("Error: {}", err),
+            Reply::UnknownCommand(err) => format!("Error: {}\n\n{}", err, help_summary()),
+        };
+
+        Some(text)
+    }
+
+    /// Returns the reply as a JSON object
+    fn json(&self) -> Value {
+        match self {
+            Reply::Done => json!({ "ok": true }),
+            Reply::Fen(fen) => json!({ "fen": fen }),
+            Reply::GameState(GameState::InProgress) => json!({ "state": "in progress" }),
+            Reply::GameState(GameState::Completed(winner)) => {
+                let winner = match winner {
+                    Winner::Player(color) => color_name(*color),
+                    Winner::Draw => "draw",
+                };
+                json!({ "state": "completed", "winner": winner })
+            }
+            Reply::Validation(Ok(())) => json!({ "valid": true }),
+            Reply::Validation(Err(err)) => json!({ "valid": false, "error": err }),
+            Reply::Actions(actions) => json!({ "actions": json_actions(actions) }),
+            Reply::SearchResults(result) => {
+                let results: Vec<_> = result
+                    .results()
+                    .iter()
+                    .map(|p| json!({ "action": p.action().to_string(), "score": json_score(p.score()) }))
+                    .collect();
+                json!({
+                    "results": results,
+                    "depth": result.depth(),
+                    "max_depth": result.max_depth(),
+                    "nodes": result.nodes(),
+                    "time": result.elapsed().as_secs_f64(),
+                    "nps": result.nps(),
+                    "tt_hit_rate": result.tt_hit_rate(),
+                    "tb_hits": result.tb_hits(),
+                    "tt_collisions": result.tt_collisions(),
+                    "stopped_early": result.stopped_early(),
+                })
+            }
+            Reply::Best(action) => json!({ "action": action.map(|a| a.to_string()) }),
+            Reply::Evaluation(score) => json!({ "score": json_score(*score) }),
+            Reply::Explanation(breakdown, [black, white]) => {
+                let terms: serde_json::Map<_, _> = breakdown
+                    .terms()
+                    .iter()
+                    .map(|(name, score)| (name.to_string(), json_score(*score)))
+                    .collect();
+                let mobility =
+                    |(movers, jumpers): (u32, u32)| json!({ "movers": movers, "jumpers": jumpers });
+                json!({
+                    "terms": terms,
+                    "total": json_score(breakdown.total()),
+                    "mobility": { "black": mobility(*black), "white": mobility(*white) },
+                })
+            }
+            Reply::Turn(color) => json!({ "turn": color_name(*color) }),
+            Reply::Board(board, options) => json!({
+                "fen": board.fen(),
+                "turn": color_name(board.turn()),
+                "board": board.pretty_with(*options),
+            }),
+            Reply::History(game) => json!({
+                "history": json_actions(game.actions()),
+                "opening": game.opening().map(|o| o.name()),
+                "pdn": game.pdn(),
+            }),
+            Reply::Variant(variant) => json!({ "variant": variant.name() }),
+            Reply::Ballot(number, b) => json!({
+                "ballot": number,
+                "actions": json_actions(b.actions()),
+                "fen": b.board().fen(),
+            }),
+            Reply::Options(options) => {
+                let values: serde_json::Map<_, _> = options
+                    .values()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), json!(value)))
+                    .collect();
+                json!({ "options": values })
+            }
+            Reply::Hint(action, score) => json!({
+                "action": action.to_string(),
+                "score": json_score(*score),
+            }),
+            Reply::Candidates(candidates) => {
+                let candidates: Vec<_> = candidates
+                    .iter()
+                    .map(|c| {
+                        json!({
+                            "action": c.action.to_string(),
+                            "score": json_score(c.score),
+                            "pv": json_actions(&c.line),
+                            "source": if c.cached { "tt" } else { "search" },
+                        })
+                    })
+                    .collect();
+                json!({ "candidates": candidates })
+            }
+            Reply::Bench(report) => json!({
+                "positions": report.position_nodes(),
+                "nodes": report.nodes(),
+                "time": report.elapsed().as_secs_f64(),
+                "nps": report.nps(),
+            }),
+            Reply::SuiteReport(report) => {
+                let positions: Vec<_> = report
+                    .outcomes()
+                    .iter()
+                    .map(|o| {
+                        json!({
+                            "id": o.id(),
+                            "found": o.found().map(|a| a.to_string()),
+                            "solved": o.solved(),
+                            "time": o.time_to_solution().map(|t| t.as_secs_f64()),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "positions": positions,
+                    "solved": report.solved(),
+                    "total": report.outcomes().len(),
+                    "time": report.total_time().as_secs_f64(),
+                })
+            }
+            Reply::Annotated(annotated) => {
+                let annotations: Vec<_> = annotated
+                    .annotations()
+                    .iter()
+                    .map(|a| {
+                        json!({
+                            "action": a.action().to_string(),
+                            "score": json_score(a.score()),
+                            "best": json_score(a.best()),
+                            "judgement": a.judgement().to_string(),
+                        })
+                    })
+                    .collect();
+                json!({ "annotations": annotations, "pdn": annotated.pdn() })
+            }
+            Reply::Loaded {
+                number,
+                games,
+                moves,
+            } => json!({ "game": number, "games": games, "moves": moves }),
+            Reply::SessionLoaded { moves, table } => json!({ "moves": moves, "table": table }),
+            Reply::Help(Some(spec)) => json_command(spec),
+            Reply::Help(None) => {
+                let commands: Vec<_> = parse::COMMANDS.iter().map(json_command).collect();
+                json!({ "commands": commands })
+            }
+            Reply::Completions(words) => json!({ "completions": words }),
+            Reply::Tree(tree) => json!({ "tree": tree.to_json() }),
+            Reply::Error(err) => json!({ "error": err }),
+            Reply::UnknownCommand(err) => {
+                let names: Vec<_> = parse::COMMANDS.iter().map(|spec| spec.name()).collect();
+                json!({ "error": err, "commands": names })
+            }
+        }
+    }
+}
+
+/// Lists every command with what it does, a line each
+fn help_summary() -> String {
+    let width = parse::COMMANDS
+        .iter()
+        .map(|spec| spec.name().len())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<_> = parse::COMMANDS
+        .iter()
+        .map(|spec| {
+            format!(
+                "{:width$}  {}",
+                spec.name(),
+                spec.description(),
+                width = width
+            )
+        })
+        .collect();
+    format!("{}\n\nhelp COMMAND describes a command", lines.join("\n"))
+}
+
+fn json_command(spec: &CommandSpec) -> Value {
+    json!({
+        "name": spec.name(),
+        "aliases": spec.aliases(),
+        "usage": spec.usage(),
+        "description": spec.description(),
+    })
+}
+
+/// Reads the `number`th game of a PDN file, returning it along with the number of games
+/// in the file
+fn read_game(path: &str, number: usize) -> Result<(Game, usize), Reply> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| Reply::Error(format!("could not read {}: {}", path, err)))?;
+    let games = pdn::parse_games(&text).map_err(|err| Reply::Error(err.to_string()))?;
+
+    match number.checked_sub(1).and_then(|i| games.get(i)) {
+        Some(game) => Ok((game.clone(), games.len())),
+        None => {
+            let error = format!("there is no game {} (1 to {})", number, games.len());
+            Err(Reply::Error(error))
+        }
     }
 }
 
@@ -39,207 +514,1064 @@ impl Command {
 struct State {
     board: Bitboard,
     engine: Engine<Bitboard>,
-    action_history: Vec<Action>,
+    mcts: MctsEngine<Bitboard>,
+    options: EngineOptions,
+    game: Game,
+    loaded: Option<Game>, // the game from the last load, which next and prev step through
+    mode: OutputMode,
+    hint: Option<(Bitboard, Action, Score)>, // the best action of the last analysis
 }
 
 impl default::Default for State {
     fn default() -> State {
         let board = Bitboard::default();
         let engine = Engine::new();
-        let action_history = Vec::new();
+        let mcts = MctsEngine::new();
+        let options = EngineOptions::default();
+        let game = Game::new(board);
+        let loaded = None;
+        let mode = OutputMode::Text;
+        let hint = None;
         State {
             board,
             engine,
-            action_history,
+            mcts,
+            options,
+            game,
+            loaded,
+            mode,
+            hint,
         }
     }
 }
 
 impl State {
-    fn execute(&mut self, command: &Command) {
+    fn execute(&mut self, command: &Command) -> Reply {
         // match an abstract command to the function
         match command {
             SetFen(board) => self.set_board(board),
-            PrintFen => self.print_fen(),
-            GetGameState => self.get_game_state(),
+            PrintFen => Reply::Fen(self.board.fen()),
+            GetGameState => Reply::GameState(self.board.get_game_state()),
             ValidateAction(action) => self.validate_action(*action),
             TakeAction(action) => self.take_action(*action),
             GenerateAllActions => self.generate_all_actions(),
-            GetTurn => self.get_turn(),
-            Search(constraint) => self.search(constraint),
+            GetTurn => Reply::Turn(self.board.turn()),
+            Search(constraint, root) => self.search(constraint, root),
             PickAction(constraint) => self.pick_action(constraint),
             EvaluateBoard(constraint) => self.evaluate_board(constraint),
-            Print => self.print(),
-            GetMoveHistory => self.get_move_history(),
+            ExplainEvaluation => Reply::Explanation(
+                self.board.explain(),
+                [
+                    self.board.mobility(Color::Black),
+                    self.board.mobility(Color::White),
+                ],
+            ),
+            DebugTree(depth) => Reply::Tree(self.engine.debug_tree(&self.board, *depth)),
+            Print(options) => Reply::Board(self.board, *options),
+            GetMoveHistory => Reply::History(self.game.clone()),
+            PrintVariant => Reply::Variant(self.board.variant()),
+            SetVariant(variant) => self.set_variant(*variant),
+            Ballot(number) => self.ballot(*number),
+            RandomBallot => self.random_ballot(),
+            Random(men, kings) => self.random(*men, *kings),
+            Put(square, color, king) => match self.board.with_piece(*square, *color, *king) {
+                Ok(board) => self.edit_board(board),
+                Err(err) => Reply::Error(err.to_string()),
+            },
+            Remove(square) => self.edit_board(self.board.without_piece(*square)),
+            SetTurn(color) => self.edit_board(self.board.with_turn(*color)),
+            ClearBoard => self.edit_board(self.board.cleared()),
+            SetMode(mode) => self.set_mode(*mode),
+            SetOption(name, value) => self.set_option(name, value),
+            ShowOptions => Reply::Options(self.options.clone()),
+            Hint(count) => self.hint(*count),
+            Save(path) => self.save(path),
+            Load(path, number) => self.load(path, *number),
+            Annotate(path, number) => self.annotate(path, *number),
+            SaveSession(path, table) => self.save_session(path, *table),
+            LoadSession(path) => self.load_session(path),
+            TestSuite(path, constraint) => self.test_suite(path, constraint),
+            Bench => Reply::Bench(bench::run(bench::DEPTH)),
+            Next => self.next(),
+            Prev => self.prev(),
+            Analyze | Stop => Reply::Done, // analysis is run by the session loop
             Clear => self.clear(),
-            Exit => process::exit(1),
+            Help(name) => Reply::Help((*name).and_then(parse::command_spec)),
+            Complete(line) => Reply::Completions(self.completions(line)),
+            Exit => Reply::Done, // the session loop stops on its own
         }
     }
 
     #[inline]
-    fn set_board(&mut self, board: &Bitboard) {
+    fn set_board(&mut self, board: &Bitboard) -> Reply {
         self.board = *board;
-        self.action_history = Vec::new();
+        self.options.set_variant(board.variant());
+        self.game = Game::new(*board);
+        Reply::Done
     }
 
     #[inline]
-    fn print_fen(&self) {
-        println!("\n{}", self.board.fen());
+    fn validate_action(&self, action: Action) -> Reply {
+        let validate = self.board.validate_action(action);
+        Reply::Validation(validate.map_err(|err| err.to_string()))
+    }
+
+    fn generate_all_actions(&self) -> Reply {
+        let actions = self
+            .board
+            .generate_all_actions()
+            .iter()
+            .map(|p| *p.action())
+            .collect();
+        Reply::Actions(actions)
+    }
+
+    /// Lets the engine know which positions the game went through so that it can tell
+    /// when a line repeats one of them
+    fn prepare_search(&mut self) {
+        self.engine.set_history(&self.game.hashes());
+    }
+
+    /// Searches the board with the searcher picked in the options
+    fn run_search(&mut self, constraint: &SearchConstraint) -> SearchResult<Bitboard> {
+        match self.options.searcher() {
+            Searcher::AlphaBeta => {
+                self.prepare_search();
+                self.engine.search(&self.board, constraint)
+            }
+            Searcher::Mcts => self.mcts.search(&self.board, constraint),
+        }
+    }
+
+    /// Replies with the scored actions, or with how the game ended if there was nothing
+    /// to search
+    fn search_reply(&self, result: SearchResult<Bitboard>) -> Reply {
+        match result.is_game_over() {
+            true => Reply::GameState(self.board.get_game_state()),
+            false => Reply::SearchResults(result),
+        }
+    }
+
+    fn search(&mut self, constraint: &SearchConstraint, root: &RootActions<Action>) -> Reply {
+        if *root == RootActions::All {
+            let result = self.run_search(constraint);
+            return self.search_reply(result);
+        }
+        if self.options.searcher() == Searcher::Mcts {
+            return Reply::Error("only alphabeta searches can leave actions out".to_string());
+        }
+
+        self.prepare_search();
+        let stop = AtomicBool::new(false);
+        let result = self
+            .engine
+            .search_among(&self.board, constraint, root, &stop, |_| ());
+        self.search_reply(result)
     }
 
     #[inline]
-    fn get_game_state(&self) {
-        let game_state = self.board.get_game_state();
-        println!("\n{}", game_state);
+    fn pick_action(&mut self, constraint: &SearchConstraint) -> Reply {
+        match self.run_search(constraint).into_outcome() {
+            SearchOutcome::Moves(results) => Reply::Best(results.first().map(|p| p.action())),
+            SearchOutcome::GameOver(state) => Reply::GameState(state),
+        }
     }
 
     #[inline]
-    fn validate_action(&self, action: Action) {
-        let validate = self.board.validate_action(action);
-        match validate {
-            Ok(()) => println!("\nOk"),
-            Err(err) => println!("\nError: {}", err),
+    fn evaluate_board(&mut self, constraint: &SearchConstraint) -> Reply {
+        match self.run_search(constraint).best() {
+            Some(p) => Reply::Evaluation(p.score()),
+            None => Reply::GameState(self.board.get_game_state()), // the game is over
         }
     }
 
-    fn generate_all_actions(&self) {
-        let mut out = String::new();
-        let all_action_pairs = self.board.generate_all_actions();
+    #[inline]
+    fn take_action(&mut self, action: Action) -> Reply {
+        match self.game.take_action(action) {
+            Ok(()) => {
+                self.board = self.game.board();
+                Reply::Done
+            }
+            Err(err) => Reply::Error(err.to_string()),
+        }
+    }
+
+    #[inline]
+    fn set_variant(&mut self, variant: Variant) -> Reply {
+        self.board = self.board.with_variant(variant);
+        self.options.set_variant(variant);
+        // the moves so far were played by other rules so the game starts over from here
+        self.game = Game::new(self.board);
+        // the transposition table does not know about rules so stored scores are stale
+        self.engine.reset();
+        Reply::Done
+    }
 
-        if all_action_pairs.is_empty() {
-            println!("\nno valid actions");
-            return;
+    fn ballot(&mut self, number: usize) -> Reply {
+        match ballot::get(number) {
+            Some(b) => self.play_ballot(number, b),
+            None => Reply::Error(format!(
+                "there is no ballot {} (1 to {})",
+                number,
+                ballot::deck().len()
+            )),
         }
+    }
 
-        all_action_pairs
-            .iter()
-            .map(|p| p.action())
-            .map(|a| a.to_string())
-            .for_each(|t| {
-                out.push_str(&t);
-                out.push_str(", ")
-            });
+    #[inline]
+    fn random_ballot(&mut self) -> Reply {
+        let (number, b) = ballot::random();
+        self.play_ballot(number, b)
+    }
 
-        // unneeded extra ', '
-        out.pop();
-        out.pop();
+    fn play_ballot(&mut self, number: usize, b: &ballot::Ballot) -> Reply {
+        // ballots are only defined for american checkers
+        self.game = Game::default();
+        for action in b.actions() {
+            self.game.take_action(*action).unwrap();
+        }
+        self.board = self.game.board();
+        self.options.set_variant(self.board.variant());
+        Reply::Ballot(number, *b)
+    }
 
-        println!("\n{}", out);
+    fn random(&mut self, men: u32, kings: u32) -> Reply {
+        let spec = RandomSpec {
+            men_per_side: men,
+            kings_per_side: kings,
+            turn: Color::Black,
+        };
+        match Bitboard::random(&mut Prng::from_time(), spec) {
+            Some(board) => {
+                self.set_board(&board);
+                Reply::Fen(board.fen())
+            }
+            None => Reply::Error("each side needs from 1 to 12 pieces".to_string()),
+        }
+    }
+
+    /// Sets up an edited board, which starts a game of its own, and replies with its FEN
+    fn edit_board(&mut self, board: Bitboard) -> Reply {
+        self.set_board(&board);
+        Reply::Fen(board.fen())
     }
 
     #[inline]
-    fn get_turn(&self) {
-        println!("\n{:?}", self.board.turn());
+    fn set_mode(&mut self, mode: OutputMode) -> Reply {
+        self.mode = mode;
+        Reply::Done
     }
 
-    fn search(&mut self, constraint: &SearchConstraint) {
-        let mut out = String::new();
+    fn set_option(&mut self, name: &str, value: &str) -> Reply {
+        if let Err(err) = self.options.set(name, value) {
+            return Reply::Error(err.to_string());
+        }
+
+        // the variant is part of the board rather than the engine
+        if name == "variant" {
+            return self.set_variant(self.options.variant());
+        }
+
+        self.options.apply(&mut self.engine);
+        Reply::Done
+    }
 
-        let search = self.engine.search(&self.board, constraint);
+    #[inline]
+    fn clear(&mut self) -> Reply {
+        self.board = Bitboard::default().with_variant(self.options.variant());
+        self.game = Game::new(self.board);
+        self.engine.reset();
+        Reply::Done
+    }
 
-        if search.is_empty() {
-            println!("\nno valid actions");
-            return;
+    fn hint(&mut self, count: Option<usize>) -> Reply {
+        match (count, self.hint) {
+            (None, Some((board, action, score))) if board == self.board => {
+                Reply::Hint(action, score)
+            }
+            (count, _) => self.candidates(count.unwrap_or(HINT_CANDIDATES)),
         }
+    }
 
-        for pair in search {
-            out.push_str(&format!("{} ({}), ", pair.action(), pair.score()));
+    /// Returns the words the last word of `line` could be completed to: the names of
+    /// commands, and the arguments of the command the line starts with, such as the
+    /// legal moves after `take`
+    fn completions(&self, line: &str) -> Vec<String> {
+        let (head, word) = match line.rsplit_once(' ') {
+            Some((head, word)) => (head.trim(), word),
+            None => ("", line),
+        };
+        let command_names = || parse::COMMANDS.iter().map(|spec| spec.name().to_string());
+        let command = head.split(' ').next().and_then(parse::command_spec);
+
+        let words: Vec<String> = match (head, command.map(|spec| spec.name())) {
+            ("", _) | (_, Some("help")) => command_names().collect(),
+            (_, Some("take" | "validate" | "search")) => self
+                .board
+                .generate_all_actions()
+                .iter()
+                .map(|p| p.action().to_string())
+                .collect(),
+            (_, Some("fen")) => vec![self.board.fen(), Bitboard::default().fen()],
+            (_, Some("set")) => EngineOptions::NAMES.iter().map(|n| n.to_string()).collect(),
+            (_, Some("variant")) => Variant::ALL.iter().map(|v| v.name().to_string()).collect(),
+            (_, Some("mode")) => vec!["text".to_string(), "json".to_string()],
+            _ => Vec::new(),
+        };
+
+        let mut words: Vec<_> = words.into_iter().filter(|w| w.starts_with(word)).collect();
+        words.sort();
+        words.dedup();
+        words
+    }
+
+    /// Runs a quick search and returns the `count` best actions with the lines of play
+    /// expected after them
+    fn candidates(&mut self, count: usize) -> Reply {
+        let children: Vec<_> = self
+            .board
+            .generate_all_actions()
+            .iter()
+            .map(|p| (*p.action(), *p.state()))
+            .collect();
+
+        // check the table before searching, as the search fills it for every action
+        let cached: Vec<_> = children
+            .iter()
+            .filter(|(_, state)| self.engine.cached_score(state, HINT_DEPTH).is_some())
+            .map(|(action, _)| *action)
+            .collect();
+
+        let constraint = SearchConstraint::Depth(HINT_DEPTH);
+        self.prepare_search();
+        let results = self.engine.search(&self.board, &constraint);
+        if results.is_game_over() {
+            return self.search_reply(results);
         }
 
-        // unneeded extra ', '
-        out.pop();
-        out.pop();
+        let candidates = results
+            .results()
+            .iter()
+            .take(count)
+            .map(|p| {
+                let action = p.action();
+                let mut line = vec![action];
+                if let Some((_, state)) = children.iter().find(|(a, _)| *a == action) {
+                    line.extend(self.engine.principal_variation(state, HINT_LINE_LENGTH - 1));
+                }
+
+                Candidate {
+                    action,
+                    score: p.score(),
+                    line,
+                    cached: cached.contains(&action),
+                }
+            })
+            .collect();
 
-        println!("\n{}", out);
+        Reply::Candidates(candidates)
     }
 
-    #[inline]
-    fn pick_action(&mut self, constraint: &SearchConstraint) {
-        match self.engine.search(&self.board, constraint).get(0) {
-            Some(p) => println!("\n{}", p.action()),
-            None => println!("no action to take!"),
+    fn save(&self, path: &str) -> Reply {
+        match fs::write(path, self.game.pdn()) {
+            Ok(()) => Reply::Done,
+            Err(err) => Reply::Error(format!("could not save to {}: {}", path, err)),
+        }
+    }
+
+    fn load(&mut self, path: &str, number: usize) -> Reply {
+        let (game, games) = match read_game(path, number) {
+            Ok(read) => read,
+            Err(reply) => return reply,
         };
+
+        let moves = game.actions().len();
+        self.board = game.board();
+        self.options.set_variant(self.board.variant());
+        self.game = game.clone();
+        self.loaded = Some(game);
+
+        Reply::Loaded {
+            number,
+            games,
+            moves,
+        }
     }
 
-    #[inline]
-    fn evaluate_board(&mut self, constraint: &SearchConstraint) {
-        match self.engine.search(&self.board, constraint).get(0) {
-            Some(p) => println!("\n{}", p.score()),
-            None => self.get_game_state(), // the game is over
+    fn save_session(&self, path: &str, table: bool) -> Reply {
+        let session = Session::new(&self.game, &self.options);
+        let session = match table {
+            true => session.with_table(&self.engine),
+            false => session,
+        };
+
+        match session.save(path) {
+            Ok(()) => Reply::Done,
+            Err(err) => Reply::Error(err.to_string()),
         }
     }
 
-    #[inline]
-    fn take_action(&mut self, action: Action) {
-        let validate = self.board.take_action(action);
-        self.action_history.push(action);
-        match validate {
-            Ok(board_p) => self.board = board_p,
-            Err(err) => println!("\nError: {}", err),
+    fn load_session(&mut self, path: &str) -> Reply {
+        let loaded = Session::load(path).and_then(|s| Ok((s.game()?, s.options()?, s)));
+        let (game, options, session) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => return Reply::Error(err.to_string()),
+        };
+
+        self.options = options;
+        self.options.apply(&mut self.engine);
+        // the table of the last session knows nothing about this one
+        self.engine.reset();
+        self.board = game.board();
+        self.options.set_variant(self.board.variant());
+        self.game = game;
+
+        Reply::SessionLoaded {
+            moves: self.game.actions().len(),
+            table: session.restore_table(&self.engine),
         }
     }
 
-    #[inline]
-    fn print(&self) {
-        println!("\n{}", self.board.pretty())
+    fn annotate(&self, path: &str, number: usize) -> Reply {
+        match read_game(path, number) {
+            Ok((game, _)) => {
+                let constraint = SearchConstraint::Depth(ANNOTATION_DEPTH);
+                Reply::Annotated(analysis::annotate(&game, &constraint))
+            }
+            Err(reply) => reply,
+        }
     }
 
-    #[inline]
-    fn get_move_history(&self) {
-        // going to have to make sure we comply with PDN later
-        let mut out = String::new();
+    fn test_suite(&self, path: &str, constraint: &SearchConstraint) -> Reply {
+        let suite = match fs::read_to_string(path) {
+            Ok(text) => testsuite::parse_suite(&text),
+            Err(err) => return Reply::Error(format!("could not read {}: {}", path, err)),
+        };
 
-        if self.action_history.is_empty() {
-            println!("\nno moves taken yet");
-            return;
+        match suite {
+            Ok(suite) => Reply::SuiteReport(testsuite::run(&self.engine, &suite, constraint)),
+            Err(err) => Reply::Error(err.to_string()),
         }
+    }
 
-        self.action_history
-            .iter()
-            .map(|a| a.movetext())
-            .for_each(|t| {
-                out.push_str(&t);
-                out.push_str(", ")
-            });
-        // unneeded extra ', '
-        out.pop();
-        out.pop();
+    /// Plays the next move of the loaded game, as long as the board is still on it
+    fn next(&mut self) -> Reply {
+        let action = match &self.loaded {
+            Some(loaded)
+                if loaded.start() == self.game.start()
+                    && loaded.actions().starts_with(self.game.actions()) =>
+            {
+                loaded.actions().get(self.game.actions().len()).copied()
+            }
+            Some(_) => return Reply::Error("the board left the loaded game".to_string()),
+            None => return Reply::Error("no game loaded".to_string()),
+        };
 
-        println!("\n{}", out);
+        match action {
+            Some(action) => self.take_action(action),
+            None => Reply::Error("the loaded game has no more moves".to_string()),
+        }
     }
 
     #[inline]
-    fn clear(&mut self) {
-        self.board = Bitboard::default();
-        self.action_history = Vec::new();
-        self.engine.reset();
+    fn prev(&mut self) -> Reply {
+        match self.game.undo() {
+            Some(_) => {
+                self.board = self.game.board();
+                Reply::Done
+            }
+            None => Reply::Error("no move to take back".to_string()),
+        }
+    }
+
+    fn print<W: Write>(&self, output: &Mutex<W>, reply: &Reply) -> io::Result<()> {
+        let mut output = output.lock().unwrap();
+        match self.mode {
+            OutputMode::Text => match reply.text(self.options.win_probability()) {
+                Some(text) => writeln!(output, "\n{}", text),
+                None => Ok(()),
+            },
+            OutputMode::Json => writeln!(output, "{}", reply.json()),
+        }?;
+        output.flush()
+    }
+}
+
+/// A player at the prompt. It is asked for its moves as PDN movetext and told about the
+/// moves of its opponent. Typing `resign`, or ending the input, gives the game up.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::app::HumanPlayer;
+/// use muskox::board::Bitboard;
+/// use muskox::players::{Clock, Player};
+///
+/// let mut output = Vec::new();
+/// let mut human = HumanPlayer::new("12-17\n11-15\n".as_bytes(), &mut output);
+/// let action = human.choose(&Bitboard::default(), &Clock::unlimited());
+/// assert_eq!(action.unwrap().to_string(), "11-15");
+/// ```
+pub struct HumanPlayer<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> HumanPlayer<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        HumanPlayer { input, output }
+    }
+
+    fn prompt(&mut self, board: &Bitboard, clock: &Clock) -> io::Result<Option<Action>> {
+        loop {
+            let turn = color_name(board.turn());
+            match clock.is_unlimited() {
+                true => write!(self.output, "\n{} to move: ", turn)?,
+                false => write!(
+                    self.output,
+                    "\n{} to move ({}s left): ",
+                    turn,
+                    clock.remaining(&board.turn()).as_secs()
+                )?,
+            }
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            match line.trim() {
+                "resign" => return Ok(None),
+                movetext => match pdn::find_action(board, movetext) {
+                    Some(action) => return Ok(Some(action)),
+                    None => writeln!(self.output, "Error: {} is not a legal move", movetext)?,
+                },
+            }
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Player<Bitboard> for HumanPlayer<R, W> {
+    fn choose(&mut self, state: &Bitboard, clock: &Clock) -> Option<Action> {
+        // a prompt that can no longer be read from or written to has nobody behind it
+        self.prompt(state, clock).unwrap_or(None)
+    }
+
+    fn observe(&mut self, state: &Bitboard, action: Action) {
+        let _ = writeln!(
+            self.output,
+            "\n{} played {}",
+            color_name(state.turn()),
+            action
+        );
     }
 }
 
 pub fn run() -> ! {
-    println!("Developed by James in Cary");
+    run_with(OutputMode::Text)
+}
+
+/// Runs the interactive prompt on stdin and stdout, printing replies in a particular
+/// mode. The mode can still be switched with the `mode` command.
+pub fn run_with(mode: OutputMode) -> ! {
+    let stdin = io::stdin();
+    let code = repl(stdin.lock(), io::stdout(), mode).expect("Error with your standard input!");
+    process::exit(code)
+}
 
-    let mut state = State::default();
+/// Runs the interactive prompt until the input ends or `exit` is given. Mistyped
+/// commands are reported and the prompt carries on. Returns the exit code of the
+/// session, which is always 0.
+///
+/// # Arguments
+///
+/// * `input` - Where commands are read from, one per line
+/// * `output` - Where the prompt and replies are written to
+/// * `mode` - How replies are printed until a `mode` command changes it
+pub fn repl<R: BufRead, W: Write + Send>(input: R, output: W, mode: OutputMode) -> io::Result<i32> {
+    session(input, output, mode, true)
+}
 
+/// Executes a script of commands, one per line, stopping at the first one that fails.
+/// Blank lines and lines starting with `#` are skipped. Returns the exit code of the
+/// script: 0 if every command succeeded and 1 otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::app::{self, OutputMode};
+///
+/// let script = "take 9-13\n# white answers\ntake 22-18\nfen\n";
+/// let mut output = Vec::new();
+///
+/// let code = app::exec(script.as_bytes(), &mut output, OutputMode::Json).unwrap();
+/// assert_eq!(code, 0);
+/// assert!(String::from_utf8(output).unwrap().contains("\"fen\""));
+/// ```
+pub fn exec<R: BufRead, W: Write + Send>(input: R, output: W, mode: OutputMode) -> io::Result<i32> {
+    session(input, output, mode, false)
+}
+
+/// Represents a search of the current position running until it is stopped
+struct Analysis<'scope> {
+    stop: Arc<AtomicBool>,
+    handle: ScopedJoinHandle<'scope, SearchResult<Bitboard>>,
+    board: Bitboard,
+}
+
+impl<'scope> Analysis<'scope> {
+    /// Starts analysing the board of `state`, writing every finished iteration to
+    /// `output` as it comes in
+    fn start<'env, W: Write + Send>(
+        scope: &'scope Scope<'scope, 'env>,
+        state: &State,
+        output: &'scope Mutex<W>,
+    ) -> Self {
+        let engine = state.engine.clone();
+        let board = state.board;
+        let mode = state.mode;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+
+        let handle = scope.spawn(move || {
+            let constraint = SearchConstraint::Time(Duration::MAX);
+            engine.search_with(&board, &constraint, &flag, |info| {
+                // the prompt does not need to hear about a broken pipe twice
+                let _ = print_info(&mut *output.lock().unwrap(), mode, info);
+            })
+        });
+
+        Analysis {
+            stop,
+            handle,
+            board,
+        }
+    }
+
+    /// Stops the search and returns the best action it found along with its score
+    fn finish(self) -> Option<(Bitboard, Action, Score)> {
+        self.stop.store(true, Ordering::Relaxed);
+        let results = self.handle.join().expect("analysis thread panicked");
+        let board = self.board;
+        results.best().map(|p| (board, p.action(), p.score()))
+    }
+}
+
+fn print_info<W: Write>(
+    output: &mut W,
+    mode: OutputMode,
+    info: &SearchInfo<Bitboard>,
+) -> io::Result<()> {
+    let best = match info.best() {
+        Some(best) => best,
+        None => return Ok(()),
+    };
+
+    match mode {
+        OutputMode::Text => writeln!(
+            output,
+            "depth {} score {} time {:.2} pv {}",
+            info.depth(),
+            best.score(),
+            info.elapsed().as_secs_f64(),
+            best.action()
+        )?,
+        OutputMode::Json => writeln!(
+            output,
+            "{}",
+            json!({
+                "depth": info.depth(),
+                "score": json_score(best.score()),
+                "time": info.elapsed().as_secs_f64(),
+                "pv": [best.action().to_string()],
+            })
+        )?,
+    }
+    output.flush()
+}
+
+fn session<R: BufRead, W: Write + Send>(
+    input: R,
+    output: W,
+    mode: OutputMode,
+    interactive: bool,
+) -> io::Result<i32> {
+    let output = Mutex::new(output);
+    thread::scope(|scope| session_loop(scope, input, &output, mode, interactive))
+}
+
+fn session_loop<'scope, 'env, R: BufRead, W: Write + Send>(
+    scope: &'scope Scope<'scope, 'env>,
+    input: R,
+    output: &'scope Mutex<W>,
+    mode: OutputMode,
+    interactive: bool,
+) -> io::Result<i32> {
+    let mut state = State {
+        mode,
+        ..State::default()
+    };
+    let mut analysis: Option<Analysis> = None;
+
+    let prompt = |state: &State| interactive && state.mode == OutputMode::Text;
+
+    if prompt(&state) {
+        writeln!(output.lock().unwrap(), "Developed by James in Cary")?;
+    }
+
+    let mut lines = input.lines();
     let mut counter = 0;
 
-    loop {
-        print!("\n[{}]: ", counter);
-        let _ = io::stdout().flush();
+    let code = loop {
+        if prompt(&state) {
+            let mut output = output.lock().unwrap();
+            write!(output, "\n[{}]: ", counter)?;
+            output.flush()?;
+        }
 
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Error with your standard input!");
-        let input = input.trim();
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break 0, // the input ran out
+        };
+        let line = line.trim();
 
-        let command = Command::parse(input);
+        if !interactive && (line.is_empty() || line.starts_with('#')) {
+            continue;
+        }
 
-        match command {
+        let command = Command::parse(line);
+
+        // whatever comes next ends a running analysis so that nothing else searches or
+        // changes the board underneath it
+        if let (Some(a), Ok(_)) = (analysis.take(), &command) {
+            state.hint = a.finish().or(state.hint);
+        }
+
+        let reply = match command {
+            Ok(Exit) => break 0,
+            Ok(Analyze) => {
+                state.prepare_search();
+                analysis = Some(Analysis::start(scope, &state, output));
+                Reply::Done
+            }
+            Ok(Stop) => state.hint(None),
             Ok(cmd) => state.execute(&cmd),
-            Err(err) => println!("\nError: {}", err),
+            Err(err @ ParseError::UnknownCommand { .. }) => Reply::UnknownCommand(err.to_string()),
+            Err(err) => Reply::Error(err.to_string()),
+        };
+        state.print(output, &reply)?;
+
+        if !interactive && matches!(reply, Reply::Error(_) | Reply::UnknownCommand(_)) {
+            break 1;
         }
 
         counter += 1;
+    };
+
+    if let Some(a) = analysis {
+        a.finish();
+    }
+
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_reply_test() {
+        let mut state = State::default();
+
+        let reply = state.execute(&Command::parse("generate").unwrap());
+        let actions = &reply.json()["actions"];
+        assert_eq!(actions.as_array().unwrap().len(), 7);
+        assert!(actions.as_array().unwrap().contains(&json!("9-13")));
+
+        let reply = state.execute(&Command::parse("search depth 2 except 9-13 11-15").unwrap());
+        assert_eq!(reply.json()["results"].as_array().unwrap().len(), 5);
+        let reply = state.execute(&Command::parse("search depth 2 only 9-13").unwrap());
+        assert_eq!(reply.json()["results"][0]["action"], json!("9-13"));
+        assert!(Command::parse("search depth 2 only").is_err());
+
+        let reply = state.execute(&Command::parse("take 9-13").unwrap());
+        assert_eq!(reply.json(), json!({ "ok": true }));
+
+        let reply = state.execute(&Command::parse("take 9-13").unwrap());
+        assert!(reply.json()["error"].is_string());
+
+        let reply = state.execute(&Command::parse("history").unwrap());
+        assert_eq!(reply.json()["history"], json!(["9-13"]));
+        assert_eq!(reply.json()["opening"], "Edinburgh");
+        assert_eq!(reply.text(false).unwrap(), "Edinburgh\n1. 9-13 *");
+
+        let reply = state.execute(&Command::parse("random 13 0").unwrap());
+        assert!(reply.json()["error"].is_string());
+
+        let reply = state.execute(&Command::parse("explain").unwrap());
+        assert_eq!(
+            reply.json(),
+            json!({
+                "terms": {
+                    "material": 0.,
+                    "kings": 0.,
+                    "center": 0.,
+                    "tempo": 0.015625,
+                    "turn": -0.0625,
+                    "bridge": 0.,
+                    "dog-hole": 0.,
+                    "apex": 0.,
+                    "opposition": 0.,
+                    "mobility": 0.,
+                },
+                "total": -0.046875,
+                "mobility": {
+                    "black": { "movers": 6, "jumpers": 0 },
+                    "white": { "movers": 4, "jumpers": 0 },
+                },
+            })
+        );
+
+        let reply = state.execute(&Command::parse("tree 1").unwrap());
+        let tree = reply.json()["tree"].as_array().unwrap().clone();
+        assert_eq!(tree[0]["parent"], Value::Null);
+        let children = tree
+            .iter()
+            .filter(|node| node["parent"] == json!(0))
+            .count();
+        assert_eq!(children, state.board.generate_all_actions().len());
+        assert!(reply.text(false).unwrap().starts_with("digraph search {"));
+
+        let reply = state.execute(&Command::parse("fen B:WK11,3:B").unwrap());
+        assert!(reply.text(false).is_none());
+        let reply = state.execute(&Command::parse("gamestate").unwrap());
+        assert_eq!(
+            reply.json(),
+            json!({ "state": "completed", "winner": "white" })
+        );
+
+        // searching a finished game reports how it ended
+        for command in ["best timed 0", "search depth 3", "hint 2"] {
+            let reply = state.execute(&Command::parse(command).unwrap());
+            assert_eq!(reply.text(false).unwrap(), "Winner: White");
+        }
+    }
+
+    #[test]
+    fn analyze_test() {
+        let script = "analyze\nstop\nhint\ntake 9-13\nhint 2\nhint 1\n";
+        let mut output = Vec::new();
+        assert_eq!(
+            exec(script.as_bytes(), &mut output, OutputMode::Json).unwrap(),
+            0
+        );
+
+        let replies: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert!(replies.iter().any(|r| r["depth"] == json!(1)));
+
+        // stop and hint both give the best action of the analysis
+        let n = replies.len();
+        assert!(replies[n - 5]["action"].is_string());
+        assert_eq!(replies[n - 5], replies[n - 4]);
+        assert_eq!(replies[n - 3], json!({ "ok": true }));
+
+        // the analysis is stale after a move so a quick search gives the candidates
+        let candidates = replies[n - 2]["candidates"].as_array().unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0]["pv"][0], candidates[0]["action"]);
+        // the line stops early where a null move pruned the search
+        let length = candidates[0]["pv"].as_array().unwrap().len();
+        assert!((1..=HINT_LINE_LENGTH).contains(&length));
+
+        // asking again finds the scores in the transposition table
+        let candidates = replies[n - 1]["candidates"].as_array().unwrap();
+        assert_eq!(candidates[0]["source"], json!("tt"));
+    }
+
+    #[test]
+    fn load_test() {
+        let path = std::env::temp_dir().join("muskox_load_test.pdn");
+        let text = "1. 11-15 23-19 2. 8-11 *\n\n[FEN \"W:W9,K11,19:B15\"]\n1. 19x10 0-1\n";
+        fs::write(&path, text).unwrap();
+        let path = path.to_str().unwrap();
+
+        let mut state = State::default();
+        let reply = state.execute(&Command::parse(&format!("load {}", path)).unwrap());
+        assert_eq!(reply.json(), json!({ "game": 1, "games": 2, "moves": 3 }));
+
+        state.execute(&Command::parse("prev").unwrap());
+        state.execute(&Command::parse("prev").unwrap());
+        assert_eq!(state.game.actions().len(), 1);
+        state.execute(&Command::parse("next").unwrap());
+        assert_eq!(state.board.turn(), Color::Black);
+        assert_eq!(
+            state
+                .execute(&Command::parse("history").unwrap())
+                .text(false),
+            Some("Old Faithful\n1. 11-15 23-19 *".to_string())
+        );
+
+        // stepping only works while the board follows the loaded game
+        state.execute(&Command::parse("take 9-14").unwrap());
+        assert!(state.execute(&Command::parse("next").unwrap()).json()["error"].is_string());
+
+        let reply = state.execute(&Command::parse(&format!("load {} 2", path)).unwrap());
+        assert_eq!(reply.json()["moves"], json!(1));
+        assert_eq!(
+            state.board.get_game_state(),
+            state.game.board().get_game_state()
+        );
+        let reply = state.execute(&Command::parse(&format!("load {} 3", path)).unwrap());
+        assert!(reply.json()["error"].is_string());
+
+        let reply = state.execute(&Command::parse(&format!("annotate {} 2", path)).unwrap());
+        assert_eq!(reply.json()["annotations"][0]["action"], json!("19-10"));
+        assert!(reply.text(false).unwrap().contains("1... 19x10 {"));
+
+        // a session brings back the game, the options and the table
+        let path = std::env::temp_dir().join("muskox_load_test.session");
+        let path = path.to_str().unwrap();
+        let mut state = State::default();
+        for command in ["set hash 1", "set depth 5", "take 11-15", "search depth 4"] {
+            state.execute(&Command::parse(command).unwrap());
+        }
+        let reply = state.execute(&Command::parse(&format!("session save {} tt", path)).unwrap());
+        assert_eq!(reply.json(), json!({ "ok": true }));
+
+        let mut restored = State::default();
+        let reply = restored.execute(&Command::parse(&format!("session load {}", path)).unwrap());
+        assert_eq!(reply.json(), json!({ "moves": 1, "table": true }));
+        assert_eq!(restored.board, state.board);
+        assert_eq!(restored.options, state.options);
+        assert!(Command::parse("session save").is_err());
+    }
+
+    #[test]
+    fn options_test() {
+        let mut state = State::default();
+
+        let reply = state.execute(&Command::parse("set depth 3").unwrap());
+        assert_eq!(reply.json(), json!({ "ok": true }));
+        let reply = state.execute(&Command::parse("set threads lots").unwrap());
+        assert!(reply.json()["error"].is_string());
+
+        state.execute(&Command::parse("set variant giveaway").unwrap());
+        assert_eq!(state.board.variant(), Variant::Giveaway);
+
+        let reply = state.execute(&Command::parse("show options").unwrap());
+        assert_eq!(reply.json()["options"]["depth"], json!("3"));
+        assert_eq!(reply.json()["options"]["variant"], json!("giveaway"));
+
+        // the board's variant and the option stay in step
+        state.execute(&Command::parse("variant russian").unwrap());
+        let reply = state.execute(&Command::parse("show options").unwrap());
+        assert!(reply.text(false).unwrap().contains("variant russian"));
+
+        state.execute(&Command::parse("set searcher mcts").unwrap());
+        let reply = state.execute(&Command::parse("search depth 1").unwrap());
+        assert_eq!(reply.json()["tt_hit_rate"], json!(0.));
+
+        assert_eq!(
+            Reply::Evaluation(Score::from(0.8)).text(false).unwrap(),
+            "0.8"
+        );
+        assert_eq!(
+            Reply::Evaluation(Score::from(0.8)).text(true).unwrap(),
+            "+0.8 (73%)"
+        );
+    }
+
+    #[test]
+    fn completion_test() {
+        let mut state = State::default();
+        let mut complete = |line: &str| match state.execute(&Complete(line.to_string())) {
+            Reply::Completions(words) => words,
+            _ => panic!("expected completions"),
+        };
+
+        assert_eq!(complete("te"), ["testsuite"]);
+        assert_eq!(complete("take 9"), ["9-13", "9-14"]);
+        assert_eq!(
+            complete("search depth 4 only 1"),
+            ["10-14", "10-15", "11-15", "11-16", "12-16"]
+        );
+        assert_eq!(complete("variant r"), ["russian"]);
+        assert_eq!(complete("help gam"), ["gamestate"]);
+        assert_eq!(complete("fen B:W21"), [Bitboard::default().fen()]);
+        assert!(complete("bench ").is_empty());
+    }
+
+    #[test]
+    fn exec_test() {
+        let script = "# open with the single corner\ntake 11-15\n\ntake 22-18\nturn\n";
+        let mut output = Vec::new();
+        assert_eq!(
+            exec(script.as_bytes(), &mut output, OutputMode::Text).unwrap(),
+            0
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), "\nBlack\n");
+
+        // positions can be set up a piece at a time
+        let script = "clearboard\nput 14 b\nput a1 w king\nsetturn w\nremove 14\nput 2 w\n";
+        let mut output = Vec::new();
+        assert_eq!(
+            exec(script.as_bytes(), &mut output, OutputMode::Text).unwrap(),
+            1
+        );
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = output.lines().filter(|l| !l.is_empty()).collect();
+        assert_eq!(
+            lines,
+            [
+                "B:W:B",
+                "B:W:B14",
+                "B:WK29:B14",
+                "W:WK29:B14",
+                "W:WK29:B",
+                "Error: A White man on 2 would already have been crowned!"
+            ]
+        );
+
+        // stops at the illegal move and reports failure
+        let script = "take 11-15\ntake 11-15\nturn\n";
+        let mut output = Vec::new();
+        assert_eq!(
+            exec(script.as_bytes(), &mut output, OutputMode::Json).unwrap(),
+            1
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 2);
+        assert!(output.lines().last().unwrap().contains("error"));
+
+        // the prompt keeps going after errors and stops at exit
+        let mut output = Vec::new();
+        let input = "take 11-15\ntake 11-15\nexit\nturn\n";
+        assert_eq!(
+            repl(input.as_bytes(), &mut output, OutputMode::Json).unwrap(),
+            0
+        );
+        assert_eq!(String::from_utf8(output).unwrap().lines().count(), 2);
+
+        // unknown commands are followed by the list of commands
+        let mut output = Vec::new();
+        assert_eq!(
+            exec("tkae 9-13\n".as_bytes(), &mut output, OutputMode::Text).unwrap(),
+            1
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("\nError: Unknown command tkae! Did you mean `take`?"));
+        assert!(output.lines().any(|l| l.starts_with("generate ")));
+
+        let mut output = Vec::new();
+        exec("help g\n".as_bytes(), &mut output, OutputMode::Text).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\ngenerate\nlist the legal moves\nalso: g\n"
+        );
     }
 }
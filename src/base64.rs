@@ -0,0 +1,61 @@
+// the url safe base64 alphabet without padding, which fits in urls and json strings
+// without escaping. only what packed boards need, so there is no streaming
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Writes bytes out as base64, four characters for every three bytes
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | u32::from(b) << (16 - 8 * i));
+        // a chunk of n bytes needs n + 1 characters
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+/// Reads base64 written by `encode`, or returns `None` if it is not
+pub(crate) fn decode(text: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| ALPHABET.iter().position(|&a| a == c).map(|v| v as u32);
+
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return None; // a lone character cannot hold a whole byte
+        }
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .try_fold(0u32, |acc, (i, &c)| Some(acc | value(c)? << (18 - 6 * i)))?;
+        for i in 0..chunk.len() - 1 {
+            out.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_test() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode(&[0xfb, 0xff]), "-_8");
+
+        for length in 0..16 {
+            let bytes: Vec<u8> = (0..length).map(|i| (i * 37 + 200) as u8).collect();
+            assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+        }
+        assert_eq!(decode("Zm9vYmFy").unwrap(), b"foobar");
+        assert!(decode("Zm9=").is_none());
+        assert!(decode("Zm9vY").is_none());
+    }
+}
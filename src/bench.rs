@@ -0,0 +1,126 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::board::Bitboard;
+use crate::search::{Engine, SearchConstraint};
+
+// the bench searches the same positions the same way on every run: one thread, a fresh
+// transposition table for every search and a fixed depth. the node count only changes
+// when the search does, which makes it a signature of a build, and the speed compares
+// builds and machines
+
+/// The positions searched by the bench: the start, a middle game, an endgame with kings
+/// and a runaway
+pub const POSITIONS: [&str; 5] = [
+    "B:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12",
+    "W:W17,21,22,24,25,26,27,28,30,31,32:B1,2,3,5,6,7,9,10,11,12,15",
+    "B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29",
+    "W:W9,K11,19,K26,27,30:B15,22,25,K32",
+    "B:W30,31:B5,6",
+];
+
+/// The depth every position is searched to
+pub const DEPTH: u32 = 13;
+
+// small enough to be cleared quickly before every search
+const HASH_SIZE: usize = 16;
+
+/// Represents the work done by a bench
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchReport {
+    nodes: Vec<u64>,
+    elapsed: Duration,
+}
+
+impl BenchReport {
+    /// Returns the nodes searched in each position, in the order of `POSITIONS`
+    #[inline]
+    pub fn position_nodes(&self) -> &[u64] {
+        &self.nodes
+    }
+
+    /// Returns the nodes searched altogether
+    pub fn nodes(&self) -> u64 {
+        self.nodes.iter().sum()
+    }
+
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the nodes searched per second
+    pub fn nps(&self) -> u64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0. => (self.nodes() as f64 / secs) as u64,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, nodes) in self.nodes.iter().enumerate() {
+            writeln!(f, "position {}: {} nodes", i + 1, nodes)?;
+        }
+        writeln!(f, "nodes {}", self.nodes())?;
+        writeln!(f, "time {:.2}s", self.elapsed.as_secs_f64())?;
+        write!(f, "nps {}", self.nps())
+    }
+}
+
+/// Searches every bench position to `depth` with a deterministic engine
+///
+/// # Examples
+///
+/// ```
+/// use muskox::bench;
+///
+/// let report = bench::run(4);
+/// assert_eq!(report.nodes(), bench::run(4).nodes());
+/// ```
+pub fn run(depth: u32) -> BenchReport {
+    let engine: Engine<Bitboard> = Engine::builder()
+        .threads(1)
+        .tt_size_mb(HASH_SIZE)
+        .deterministic(true)
+        .build()
+        .unwrap();
+    let constraint = SearchConstraint::Depth(depth);
+
+    let results: Vec<_> = POSITIONS
+        .iter()
+        .map(|fen| engine.search(&Bitboard::from_fen(fen).unwrap(), &constraint))
+        .collect();
+
+    BenchReport {
+        nodes: results.iter().map(|r| r.nodes()).collect(),
+        elapsed: results.iter().map(|r| r.elapsed()).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bench_test() {
+        for fen in &POSITIONS {
+            assert!(Bitboard::from_fen(fen).is_ok(), "{}", fen);
+        }
+
+        let report = run(3);
+        assert_eq!(report.position_nodes().len(), POSITIONS.len());
+        assert!(report.position_nodes().iter().all(|&n| n > 0));
+        assert_eq!(
+            report,
+            BenchReport {
+                elapsed: report.elapsed(),
+                ..run(3)
+            }
+        );
+        assert!(report
+            .to_string()
+            .contains(&format!("nodes {}\n", report.nodes())));
+    }
+}
@@ -1,12 +1,18 @@
+use std::cmp::Reverse;
 use std::collections::VecDeque;
+use std::convert::TryInto;
 use std::default;
 
-use crate::board::{Action, ActionType, Direction};
-use crate::error::{ActionError, ParseError};
-use crate::evaluation::GLOBAL_EVAL;
+use crate::action::MAX_JUMPS;
+use crate::base64;
+use crate::board::{
+    Action, ActionType, CapturePrecedence, Direction, MidCaptureCrowning, Square, Variant,
+};
+use crate::error::{ActionError, BoardError, ParseError};
+use crate::evaluation::{self, EvalBreakdown, Phase};
 use crate::parse;
 use crate::search::{ActionStatePair, GameState, Optim, Score, Searchable, Side, Winner};
-use crate::zobrist;
+use crate::zobrist::{self, Prng};
 
 type Mask = u32;
 
@@ -17,6 +23,63 @@ const MASK_L5: Mask = 0xe0e0e0e0;
 const MASK_R3: Mask = 0xe0e0e0e0;
 const MASK_R5: Mask = 0x07070707;
 
+// below this many pieces on either side zugzwang is too common for null moves
+const NULL_MOVE_MIN_PIECES: u32 = 4;
+
+// the squares of the double corners, 1 and 5 on black's side and 28 and 32 on white's
+pub(crate) const DOUBLE_CORNERS: [Mask; 2] = [1 << 0 | 1 << 4, 1 << 27 | 1 << 31];
+// the rows men of each color are crowned on, 29 to 32 for black and 1 to 4 for white
+pub(crate) const CROWNING_ROWS: [Mask; 2] = [0xf0000000, 0x0000000f];
+const MAX_PIECES: u32 = 12;
+
+/// The number of bytes `Bitboard::pack` packs a board into
+pub const PACKED_SIZE: usize = 13;
+
+/// The number of features `Bitboard::features` describes a position with
+pub const FEATURE_COUNT: usize = 4 * 32 + 1 + 4 + 2;
+
+// the most squares a jump chain visits
+const MAX_CHAIN: usize = MAX_JUMPS as usize + 1;
+
+/// Holds the squares a piece visits during a jump in order. Chains have at most nine
+/// squares, so they are kept on the stack and copied instead of growing a vector.
+#[derive(Clone, Copy)]
+struct JumpChain {
+    squares: [u8; MAX_CHAIN],
+    len: usize,
+}
+
+impl JumpChain {
+    fn new(square: u8) -> Self {
+        let mut squares = [0; MAX_CHAIN];
+        squares[0] = square;
+        JumpChain { squares, len: 1 }
+    }
+
+    /// Returns the chain extended by `square`
+    fn push(mut self, square: u8) -> Self {
+        self.squares[self.len] = square;
+        self.len += 1;
+        self
+    }
+
+    #[inline]
+    fn last(&self) -> u8 {
+        self.squares[self.len - 1]
+    }
+
+    #[inline]
+    fn squares(&self) -> &[u8] {
+        &self.squares[..self.len]
+    }
+
+    /// Returns whether the chain is as long as an action can hold
+    #[inline]
+    fn is_full(&self) -> bool {
+        self.len == MAX_CHAIN
+    }
+}
+
 /// Represents of the two colors that exists on a checkerboard
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Color {
@@ -26,8 +89,17 @@ pub enum Color {
 use Color::*;
 
 impl Color {
+    /// Returns the other color
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Color;
+    ///
+    /// assert_eq!(Color::Black.opposite(), Color::White);
+    /// ```
     #[inline]
-    fn opponent(&self) -> Color {
+    pub fn opposite(&self) -> Color {
         match self {
             Black => White,
             White => Black,
@@ -43,15 +115,53 @@ impl Side for Color {
             Black => Optim::Max,
         }
     }
+
+    #[inline]
+    fn opposite(&self) -> Color {
+        Color::opposite(self)
+    }
+}
+
+const ANSI_BLACK: &str = "\x1b[1;31m";
+const ANSI_WHITE: &str = "\x1b[1;97m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// Represents what is drawn besides the pieces when a board is pretty printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PrettyOptions {
+    /// Writes the PDN number of every empty dark square
+    pub numbers: bool,
+    /// Colors the pieces of the two sides with ANSI escape codes
+    pub colors: bool,
+    /// Writes whose turn it is below the board
+    pub turn: bool,
+    /// Draws the board from black's side, with black's pieces at the bottom
+    pub flipped: bool,
 }
 
-/// Represents a single state of a checkerboard
+/// Describes the random positions `Bitboard::random` creates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RandomSpec {
+    /// How many men each side has
+    pub men_per_side: u32,
+    /// How many kings each side has
+    pub kings_per_side: u32,
+    /// Which side is to move
+    pub turn: Color,
+}
+
+/// Represents a single state of a checkerboard: the pieces, the side to move and the
+/// variant. Equality and hashing compare exactly these and never how the position came
+/// about, which the transposition table relies on. Whatever depends on the moves before,
+/// like repetitions, belongs to a [`Game`](crate::pdn::Game).
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Bitboard {
     blacks: Mask,
     whites: Mask,
     kings: Mask,
     turn: Color,
+    variant: Variant,
 }
 
 impl default::Default for Bitboard {
@@ -63,6 +173,7 @@ impl default::Default for Bitboard {
             whites: 0xfff00000,
             kings: 0,
             turn: Black,
+            variant: Variant::American,
         }
     }
 }
@@ -74,12 +185,37 @@ impl Bitboard {
             whites,
             kings,
             turn,
+            variant: Variant::American,
         }
     }
 
+    /// Returns the same position played under the rules of a different variant
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Variant};
+    ///
+    /// let board = Bitboard::default().with_variant(Variant::Russian);
+    /// assert_eq!(board.variant(), Variant::Russian);
+    /// ```
+    pub fn with_variant(&self, variant: Variant) -> Self {
+        Bitboard { variant, ..*self }
+    }
+
+    /// Returns the variant whose rules the board is played by
+    #[inline]
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
     /// Creates a new bitboard from a string FEN tag according to Portable Draughts Notation.
     /// (PDN). Read more about the notation [here](https://en.wikipedia.org/wiki/Portable_Draughts_Notation).
     ///
+    /// The tag may be prefixed by the PDN `GameType` number of the variant followed by a
+    /// colon (`25:W:W...`), or by its name for variants without one (`giveaway:W:W...`).
+    /// Without it american rules are used.
+    ///
     /// # Arguments
     ///
     /// * `fen_string` - A string slice that that represents the checkers board from
@@ -88,16 +224,148 @@ impl Bitboard {
     /// # Examples
     ///
     /// ```
-    /// use muskox::board::Bitboard;
+    /// use muskox::board::{Bitboard, Variant};
     ///
     /// let board = Bitboard::from_fen("B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29");
     /// // will put proof that it works here
+    ///
+    /// let board = Bitboard::from_fen("25:W:W18,24:B12,16").unwrap();
+    /// assert_eq!(board.variant(), Variant::Russian);
     /// ```
     pub fn from_fen(fen_string: &str) -> Result<Self, ParseError> {
         let (_, board) = parse::board_fen_primary(fen_string)?;
         Ok(board)
     }
 
+    /// Packs the board into a fixed number of bytes: the black, white and king masks as
+    /// little endian integers, then a byte holding the side to move in its lowest bit
+    /// and the variant's place in `Variant::ALL` above it. Equal boards always pack the
+    /// same way, so packed boards can be compared and hashed as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, PACKED_SIZE};
+    ///
+    /// let board = Bitboard::from_fen("W:W18,K30:B14,K1").unwrap();
+    /// let packed: [u8; PACKED_SIZE] = board.pack();
+    /// assert_eq!(Bitboard::unpack(&packed).unwrap(), board);
+    /// ```
+    pub fn pack(&self) -> [u8; PACKED_SIZE] {
+        let variant = Variant::ALL
+            .iter()
+            .position(|v| *v == self.variant)
+            .unwrap();
+
+        let mut packed = [0; PACKED_SIZE];
+        packed[0..4].copy_from_slice(&self.blacks.to_le_bytes());
+        packed[4..8].copy_from_slice(&self.whites.to_le_bytes());
+        packed[8..12].copy_from_slice(&self.kings.to_le_bytes());
+        packed[12] = (self.turn == White) as u8 | (variant as u8) << 1;
+        packed
+    }
+
+    /// Unpacks a board from `pack`. Fails if the bytes are not a board, such as when a
+    /// square holds pieces of both colors.
+    pub fn unpack(packed: &[u8; PACKED_SIZE]) -> Result<Self, ParseError> {
+        let mask = |i: usize| {
+            Mask::from_le_bytes([packed[i], packed[i + 1], packed[i + 2], packed[i + 3]])
+        };
+        let (blacks, whites, kings) = (mask(0), mask(4), mask(8));
+        if blacks & whites != 0 || kings & !(blacks | whites) != 0 {
+            return Err(ParseError::PackedBoardError);
+        }
+
+        let turn = match packed[12] & 1 {
+            0 => Black,
+            _ => White,
+        };
+        let variant = Variant::ALL
+            .get(usize::from(packed[12] >> 1))
+            .ok_or(ParseError::PackedBoardError)?;
+        Ok(Bitboard::new(blacks, whites, kings, turn).with_variant(*variant))
+    }
+
+    /// Writes the packed board out in url safe base64, which is shorter than a FEN and
+    /// needs no escaping in urls
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    ///
+    /// let board = Bitboard::default();
+    /// assert_eq!(board.to_base64(), "_w8AAAAA8P8AAAAAAA");
+    /// assert_eq!(Bitboard::from_base64("_w8AAAAA8P8AAAAAAA").unwrap(), board);
+    /// ```
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.pack())
+    }
+
+    /// Reads a board written by `to_base64`
+    pub fn from_base64(text: &str) -> Result<Self, ParseError> {
+        let bytes = base64::decode(text).ok_or(ParseError::PackedBoardError)?;
+        let packed = bytes.try_into().map_err(|_| ParseError::PackedBoardError)?;
+        Self::unpack(&packed)
+    }
+
+    /// Creates a random position of american checkers with the pieces of a spec. Men are
+    /// never placed on the row they would be crowned on, and the position is always one
+    /// where the game goes on. Returns `None` if a side would have no pieces or more than
+    /// twelve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Color, Prng, RandomSpec};
+    /// use muskox::search::{GameState, Searchable};
+    ///
+    /// let spec = RandomSpec { men_per_side: 2, kings_per_side: 1, turn: Color::White };
+    /// let board = Bitboard::random(&mut Prng::new(7), spec).unwrap();
+    /// assert_eq!(board.turn(), Color::White);
+    /// assert_eq!(board.get_game_state(), GameState::InProgress);
+    /// ```
+    pub fn random(prng: &mut Prng, spec: RandomSpec) -> Option<Self> {
+        let pieces = spec.men_per_side + spec.kings_per_side;
+        if pieces == 0 || pieces > MAX_PIECES {
+            return None;
+        }
+
+        // picks one of the squares of a mask at random
+        let mut pick = |squares: Mask| {
+            let mut squares = squares;
+            for _ in 0..prng.rand64() % u64::from(squares.count_ones()) {
+                squares &= squares - 1;
+            }
+            1 << squares.trailing_zeros()
+        };
+
+        loop {
+            let mut sides: [Mask; 2] = [0; 2];
+            let mut kings = 0;
+            let mut occupied = 0;
+
+            for (side, crowning) in sides.iter_mut().zip(&CROWNING_ROWS) {
+                for _ in 0..spec.men_per_side {
+                    let man = pick(!occupied & !crowning);
+                    *side |= man;
+                    occupied |= man;
+                }
+                for _ in 0..spec.kings_per_side {
+                    let king = pick(!occupied);
+                    *side |= king;
+                    kings |= king;
+                    occupied |= king;
+                }
+            }
+
+            let board = Bitboard::new(sides[0], sides[1], kings, spec.turn);
+            if board.get_game_state() == GameState::InProgress {
+                return Some(board);
+            }
+        }
+    }
+
     /// Determines whether a certain action is valid or not.
     ///
     /// This information is encoded in a rust `Result`. If the action is valid, `Ok(())`
@@ -128,8 +396,258 @@ impl Bitboard {
         Ok(())
     }
 
+    /// Describes the effects an action has on the board: where the moving piece starts
+    /// and lands, which squares are captured along the way and whether the piece gets
+    /// crowned. This does not check that the action is legal, only that its path stays
+    /// on the board. Use `validate_action` for that.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - An [action](muskox/action/struct.Action.html) to find the effects of
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Action, Square};
+    ///
+    /// let board = Bitboard::from_fen("B:W11,18,26,27:B8").unwrap();
+    /// let action = Action::from_movetext("8-15-22-31").unwrap();
+    ///
+    /// let effects = board.action_effects(&action).unwrap();
+    /// let captured: Vec<_> = effects.captured_squares().map(|s| s.pdn()).collect();
+    /// assert_eq!(captured, vec![11, 18, 26]);
+    /// assert!(effects.promoted());
+    /// ```
+    pub fn action_effects(&self, action: &Action) -> Result<ActionEffects, ActionError> {
+        let action = self.interpret(*action);
+        let source = action.source();
+        let destination = action.destination();
+
+        let color = match self.coloring_eq(source, White) {
+            true => White,
+            false => Black,
+        };
+
+        let mut captured = 0;
+        let mut curr = source;
+        let mut crossed_far_row = false;
+
+        // walk along the path of the jumps collecting the pieces that are leapt over
+        for i in 0..action.jump_len() {
+            let direction = action.jump_direction(i).unwrap();
+            let distance = action.jump_distance(i).unwrap();
+            if distance < 2 {
+                return Err(ActionError::NotDiagonalError);
+            }
+
+            let landing = direction
+                .walk_from(curr, distance)
+                .ok_or(ActionError::NotDiagonalError)?;
+            let skipped_over = self.jumped_square(curr, direction, distance, source);
+            curr = landing;
+
+            captured |= 1 << skipped_over;
+            crossed_far_row |= Self::crowns(curr, color);
+        }
+
+        // single pieces are crowned when they finish on the far row, or anywhere on it where
+        // they go on capturing as kings
+        let promoted = !self.is_king(source)
+            && (Self::crowns(destination, color)
+                || crossed_far_row
+                    && self.variant.rules().mid_capture_crowning
+                        == MidCaptureCrowning::ContinuesAsKing);
+
+        Ok(ActionEffects {
+            action,
+            captured,
+            promoted,
+        })
+    }
+
+    /// Finds the legal action going from `source` to `destination` that captures the
+    /// pieces on `captured`. Engine protocols often describe moves this way rather than
+    /// listing every landing square. An empty `captured` slice matches any capture
+    /// between the two squares.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Action, Square};
+    ///
+    /// let board = Bitboard::from_fen("B:W11,18,26,27:B8").unwrap();
+    /// let square = |n| Square::from_pdn(n).unwrap();
+    ///
+    /// let action = board.find_action(square(8), square(31), &[]);
+    /// assert_eq!(action, Some(Action::from_movetext("8-15-22-31").unwrap()));
+    /// ```
+    pub fn find_action(
+        &self,
+        source: Square,
+        destination: Square,
+        captured: &[Square],
+    ) -> Option<Action> {
+        let captured = captured.iter().fold(0, |acc, s| acc | s.mask());
+
+        self.generate_all_actions()
+            .iter()
+            .map(|p| *p.action())
+            .find(|action| {
+                let effects = match self.action_effects(action) {
+                    Ok(effects) => effects,
+                    Err(_) => return false,
+                };
+
+                effects.source() == source
+                    && effects.destination() == destination
+                    && (captured == 0 || captured == effects.captured())
+            })
+    }
+
+    /// Returns every legal action of the piece on `square`, with each branch of a
+    /// multi-jump as an action of its own. Frontends use it to let a piece be picked and
+    /// its targets highlighted. The list is empty when the piece cannot move, which
+    /// includes having to leave the capturing to another piece.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Square};
+    ///
+    /// let board = Bitboard::from_fen("B:W6,7,14,15,22,23,24:BK1,K3").unwrap();
+    /// let square = |n| Square::from_pdn(n).unwrap();
+    ///
+    /// let actions: Vec<_> = board
+    ///     .actions_from(square(3))
+    ///     .iter()
+    ///     .map(|a| a.to_string())
+    ///     .collect();
+    /// assert_eq!(
+    ///     actions,
+    ///     ["3-10-19-28", "3-10-17-26-19-28", "3-10-17-26-19-10", "3-10-19-26-17-10"]
+    /// );
+    /// assert!(board.actions_from(square(6)).is_empty());
+    /// ```
+    pub fn actions_from(&self, square: Square) -> Vec<Action> {
+        self.generate_all_actions()
+            .iter()
+            .map(|p| *p.action())
+            .filter(|action| action.source() == square.index())
+            .collect()
+    }
+
+    /// Tells how the squares a piece has been moved through so far, numbered 1 to 32,
+    /// can go on. Frontends that enter a jump a square at a time use it to know when the
+    /// jump is finished and which squares to offer when two jumps share a beginning.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Action, Bitboard, JumpContinuation, Square};
+    ///
+    /// let board = Bitboard::from_fen("B:W6,7,14,15,22,23,24:BK1,K3").unwrap();
+    /// let square = |n| Square::from_pdn(n).unwrap();
+    ///
+    /// assert_eq!(
+    ///     board.continue_jump(&[3, 10]),
+    ///     JumpContinuation::Ambiguous(vec![square(17), square(19)])
+    /// );
+    /// assert_eq!(
+    ///     board.continue_jump(&[3, 10, 19, 26]),
+    ///     JumpContinuation::Forced(Action::from_movetext("3-10-19-26-17-10").unwrap())
+    /// );
+    /// assert_eq!(
+    ///     board.continue_jump(&[3, 10, 19, 28]),
+    ///     JumpContinuation::Complete(Action::from_movetext("3-10-19-28").unwrap())
+    /// );
+    /// assert_eq!(board.continue_jump(&[3, 12]), JumpContinuation::Invalid);
+    /// ```
+    pub fn continue_jump(&self, prefix: &[u8]) -> JumpContinuation {
+        let matching: Vec<_> = self
+            .generate_all_actions()
+            .iter()
+            .map(|p| (*p.action(), p.action().positions()))
+            .filter(|(_, positions)| positions.starts_with(prefix))
+            .collect();
+
+        match matching.as_slice() {
+            [] => JumpContinuation::Invalid,
+            [(action, positions)] if positions.len() == prefix.len() => {
+                JumpContinuation::Complete(*action)
+            }
+            [(action, _)] => JumpContinuation::Forced(*action),
+            _ => {
+                let mut next: Vec<_> = matching
+                    .iter()
+                    .filter_map(|(_, positions)| positions.get(prefix.len()))
+                    .filter_map(|&p| Square::from_pdn(p))
+                    .collect();
+                next.sort();
+                next.dedup();
+                JumpContinuation::Ambiguous(next)
+            }
+        }
+    }
+
+    /// Returns the key positions are told apart by in searches and when looking for
+    /// repetitions: the zobrist hash of the pieces and the side to move. Equal boards
+    /// always have equal keys, though different boards may rarely share one. The variant
+    /// is left out as the rules never change during a game.
+    #[inline]
+    pub fn position_key(&self) -> u64 {
+        self.zobrist_hash()
+    }
+
+    /// Returns a key shared by a position and its `mirrored` twin, the smaller of their
+    /// position keys. The two are the same game with the colors exchanged, so anything
+    /// stored under this key holds for both, as long as it is told from the side to move.
+    /// Left-right reflections are not symmetries of the 8x8 board (see
+    /// `flipped_horizontal`) and are not folded in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    ///
+    /// let board = Bitboard::from_fen("B:W18,K30:B14").unwrap();
+    /// assert_eq!(board.canonical_key(), board.mirrored().canonical_key());
+    /// assert_ne!(board.position_key(), board.mirrored().position_key());
+    /// ```
+    pub fn canonical_key(&self) -> u64 {
+        self.position_key().min(self.mirrored().position_key())
+    }
+
+    /// Returns every legal action along with the board it leads to. Searches use
+    /// `generate_all_actions`, whose pairs also carry the zobrist hash of every child.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Action, Bitboard};
+    /// use muskox::search::Searchable;
+    ///
+    /// let board = Bitboard::default();
+    /// let successors = board.successors();
+    /// assert_eq!(successors.len(), 7);
+    ///
+    /// let action = Action::from_movetext("11-15").unwrap();
+    /// let pair = successors.iter().find(|p| p.action() == action).unwrap();
+    /// assert_eq!(pair.board(), board.take_action(action).unwrap());
+    /// ```
+    pub fn successors(&self) -> Vec<ActionBitboardPair> {
+        self.generate_all_actions()
+            .iter()
+            .map(|p| ActionBitboardPair {
+                action: *p.action(),
+                board: *p.state(),
+            })
+            .collect()
+    }
+
     /// Creates string FEN tag according to Portable Draughts Notation (PDN). Read more
     /// about the notation [here](https://en.wikipedia.org/wiki/Portable_Draughts_Notation).
+    /// Boards of variants other than american checkers are prefixed by their `GameType`
+    /// (or name if they don't have one).
     ///
     /// # Examples
     ///
@@ -142,6 +660,13 @@ impl Bitboard {
     pub fn fen(&self) -> String {
         let mut out = String::new();
 
+        if self.variant != Variant::American {
+            match self.variant.game_type() {
+                Some(game_type) => out.push_str(&format!("{}:", game_type)),
+                None => out.push_str(&format!("{}:", self.variant.name())),
+            }
+        }
+
         // turn
         out.push(match self.turn {
             Black => 'B',
@@ -161,7 +686,10 @@ impl Bitboard {
                 it >>= 1;
                 pos += 1;
             }
-            out.pop(); // remove unnecessary last comma
+            // remove the last comma, if a piece was written at all
+            if out.ends_with(',') {
+                out.pop();
+            }
         };
 
         out.push_str(":W");
@@ -173,6 +701,44 @@ impl Bitboard {
         out
     }
 
+    /// Returns how far along the game is, from the pieces left on the board
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Stage};
+    ///
+    /// assert_eq!(Bitboard::default().phase().stage(), Stage::Opening);
+    /// assert_eq!(Bitboard::default().phase().endgame_weight(), 0.);
+    ///
+    /// let board = Bitboard::from_fen("W:W9,K11,19,K26,27,30:B15,22,25,K32").unwrap();
+    /// assert_eq!(board.phase().pieces(), 10);
+    /// assert_eq!(board.phase().stage(), Stage::Midgame);
+    /// ```
+    #[inline]
+    pub fn phase(&self) -> Phase {
+        Phase::new((self.blacks() | self.whites()).count_ones())
+    }
+
+    /// Returns the terms the evaluation of the board is made of, such as material and
+    /// kings, with what each adds to it from black's point of view
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    /// use muskox::search::{Score, Searchable};
+    ///
+    /// let b = Bitboard::from_fen("B:WK18:B1,2").unwrap();
+    /// let breakdown = b.explain();
+    /// assert_eq!(breakdown.terms()[0], ("material", Score::from(1.)));
+    /// assert_eq!(breakdown.terms()[1], ("kings", Score::from(-0.5)));
+    /// assert_eq!(breakdown.total(), b.evaluate());
+    /// ```
+    pub fn explain(&self) -> EvalBreakdown {
+        evaluation::current_evaluator().explain(self)
+    }
+
     /// Returns a string graphically representing the board. The `b`'s represent
     /// the black pieces and the `w`'s represent the white pieces. A capital letters
     /// indicate that a certain piece is a king.
@@ -186,51 +752,357 @@ impl Bitboard {
     /// println!("{}", board.pretty());
     /// ```
     pub fn pretty(&self) -> String {
-        let mut out = String::with_capacity(1024);
+        self.pretty_with(PrettyOptions::default())
+    }
 
-        // maybe change this model
-        let mut blacks_iter = self.blacks;
-        let mut whites_iter = self.whites;
-        let mut kings_iter = self.kings;
+    /// Returns a string graphically representing the board like [pretty](#method.pretty),
+    /// with extra information drawn in as set by the options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, PrettyOptions};
+    ///
+    /// let options = PrettyOptions {
+    ///     numbers: true,
+    ///     flipped: true,
+    ///     ..PrettyOptions::default()
+    /// };
+    /// let pretty = Bitboard::default().pretty_with(options);
+    /// // seen from black's side, square 32 is in the top left corner
+    /// assert!(pretty.starts_with("+---+---+---+---+---+---+---+---+\n|   | w |"));
+    /// ```
+    pub fn pretty_with(&self, options: PrettyOptions) -> String {
+        const RULE: &str = "+---+---+---+---+---+---+---+---+";
+
+        let mut out = String::with_capacity(1024);
 
-        // maybe get rid of i and j for something else
         for i in 0_u8..8 {
             // rows
-            out.push_str("+---+---+---+---+---+---+---+---+\n");
+            out.push_str(RULE);
+            out.push('\n');
             for j in 0_u8..8 {
-                // cols
-                if (i + j) % 2 == 0 {
-                    out.push_str("|   ");
-                    continue;
-                }
-                let c = {
-                    let mut c = ' ';
-                    if blacks_iter % 2 == 1 {
-                        c = 'b';
-                    }
-                    if whites_iter % 2 == 1 {
-                        c = 'w';
-                    }
-                    if kings_iter % 2 == 1 {
-                        c = c.to_uppercase().next().unwrap();
+                // cols. seen from black the board is turned around
+                let (row, col) = match options.flipped {
+                    true => (7 - i, 7 - j),
+                    false => (i, j),
+                };
+
+                let square = match Square::from_coords(row, col) {
+                    Some(square) => square,
+                    None => {
+                        out.push_str("|   ");
+                        continue;
                     }
-                    c
                 };
-                out.push_str("| ");
-                out.push(c);
-                out.push(' ');
-                blacks_iter >>= 1;
-                whites_iter >>= 1;
-                kings_iter >>= 1;
+
+                out.push('|');
+                out.push_str(&self.pretty_square(square, &options));
             }
             out.push_str("|\n");
         }
 
-        out.push_str("+---+---+---+---+---+---+---+---+");
+        out.push_str(RULE);
+
+        if options.turn {
+            out.push_str(&format!("\n{:?} to move", self.turn));
+        }
 
         out
     }
 
+    /// Returns the three characters drawn inside a dark square
+    fn pretty_square(&self, square: Square, options: &PrettyOptions) -> String {
+        let mask = square.mask();
+        let king = self.kings & mask != 0;
+
+        let (c, color) = match (self.blacks & mask != 0, self.whites & mask != 0) {
+            (true, _) => (if king { 'B' } else { 'b' }, ANSI_BLACK),
+            (_, true) => (if king { 'W' } else { 'w' }, ANSI_WHITE),
+            _ if options.numbers => {
+                let number = format!("{:>2} ", square.pdn());
+                return match options.colors {
+                    true => format!("{}{}{}", ANSI_DIM, number, ANSI_RESET),
+                    false => number,
+                };
+            }
+            _ => return "   ".to_string(),
+        };
+
+        match options.colors {
+            true => format!(" {}{}{} ", color, c, ANSI_RESET),
+            false => format!(" {} ", c),
+        }
+    }
+
+    /// Returns the same position seen from the other side of the table. The board is
+    /// rotated 180 degrees, the colors of all pieces are swapped, and the turn is handed
+    /// to the other color. The resulting position is strategically identical to the
+    /// original with the roles of black and white exchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Color};
+    /// use muskox::search::Searchable;
+    ///
+    /// let board = Bitboard::default().mirrored();
+    /// assert_eq!(board.fen(), "W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,11,12");
+    /// assert_eq!(board.turn(), Color::White);
+    /// ```
+    pub fn mirrored(&self) -> Self {
+        // reversing the bits sends square i to square 31 - i which is a 180 degree rotation
+        Bitboard {
+            blacks: self.whites.reverse_bits(),
+            whites: self.blacks.reverse_bits(),
+            kings: self.kings.reverse_bits(),
+            turn: self.turn.opposite(),
+            variant: self.variant,
+        }
+    }
+
+    /// Returns the position with each row read right to left instead of left to right.
+    ///
+    /// A true left-right reflection of an 8x8 board sends every dark square onto a light
+    /// one, so this is not a symmetry of the american rules. It is the transform between
+    /// the two board orientations (the double corner on the right versus on the left, as
+    /// in italian diagrams), and is useful for converting positions and augmenting data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    ///
+    /// let board = Bitboard::from_fen("B:W32:B1").unwrap();
+    /// assert_eq!(board.flipped_horizontal().fen(), "B:W29:B4");
+    /// ```
+    pub fn flipped_horizontal(&self) -> Self {
+        let flip = |mask: Mask| {
+            // reverse everything, then put the rows (nibbles) back in their original order
+            let mask = mask.reverse_bits().swap_bytes();
+            ((mask & 0x0f0f0f0f) << 4) | ((mask >> 4) & 0x0f0f0f0f)
+        };
+
+        Bitboard {
+            blacks: flip(self.blacks),
+            whites: flip(self.whites),
+            kings: flip(self.kings),
+            turn: self.turn,
+            variant: self.variant,
+        }
+    }
+
+    /// Returns the same position with the other player to move, as if the player of the
+    /// current turn had skipped it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Color};
+    /// use muskox::search::Searchable;
+    ///
+    /// let board = Bitboard::default().pass_turn();
+    /// assert_eq!(board.turn(), Color::White);
+    /// assert_eq!(board.pass_turn(), Bitboard::default());
+    /// ```
+    pub fn pass_turn(&self) -> Self {
+        Bitboard {
+            turn: self.turn.opposite(),
+            ..*self
+        }
+    }
+
+    /// Returns how many pieces of `color` could step to a neighboring square and how many
+    /// could jump, as if it were their turn. It only counts the bits of two masks, so it
+    /// is far cheaper than generating the actions. Pieces that can jump often have a move
+    /// too and are counted in both.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Color};
+    ///
+    /// let board = Bitboard::default();
+    /// assert_eq!(board.mobility(Color::Black), (4, 0));
+    ///
+    /// let board = Bitboard::from_fen("B:W18,32:B14,K23").unwrap();
+    /// assert_eq!(board.mobility(Color::Black), (2, 0));
+    /// assert_eq!(board.mobility(Color::White), (2, 1));
+    /// ```
+    pub fn mobility(&self, color: Color) -> (u32, u32) {
+        (
+            self.get_movers(color).count_ones(),
+            self.get_jumpers(color).count_ones(),
+        )
+    }
+
+    /// Returns how many steps to a neighboring square the kings of `color` could take
+    /// between them, leaving jumps out. Like `mobility` it works on masks alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Color};
+    ///
+    /// let board = Bitboard::from_fen("B:WK1,K14:BK5,9").unwrap();
+    /// assert_eq!(board.king_mobility(Color::White), 4);
+    /// assert_eq!(board.king_mobility(Color::Black), 0);
+    /// ```
+    pub fn king_mobility(&self, color: Color) -> u32 {
+        let kings = self.kings
+            & match color {
+                Black => self.blacks,
+                White => self.whites,
+            };
+        let not_occupied = !(self.whites | self.blacks);
+
+        // every direction a king can step in, as the empty squares shifted onto the
+        // squares they can be reached from
+        [
+            not_occupied >> 4,
+            (not_occupied & MASK_L3) >> 3,
+            (not_occupied & MASK_L5) >> 5,
+            not_occupied << 4,
+            (not_occupied & MASK_R3) << 3,
+            (not_occupied & MASK_R5) << 5,
+        ]
+        .iter()
+        .map(|&reachable| (reachable & kings).count_ones())
+        .sum()
+    }
+
+    /// Returns the same position with `turn` to move
+    pub fn with_turn(&self, turn: Color) -> Self {
+        Bitboard { turn, ..*self }
+    }
+
+    /// Returns the board with every piece taken off, keeping the side to move and the
+    /// variant
+    pub fn cleared(&self) -> Self {
+        Bitboard {
+            blacks: 0,
+            whites: 0,
+            kings: 0,
+            ..*self
+        }
+    }
+
+    /// Returns the board with a piece of `color` put on `square` in place of whatever
+    /// was there. A man cannot stand on the row it is crowned on, and neither side can
+    /// have more than twelve pieces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Color, Square};
+    /// use muskox::error::BoardError;
+    ///
+    /// let square = |n| Square::from_pdn(n).unwrap();
+    /// let board = Bitboard::default()
+    ///     .cleared()
+    ///     .with_piece(square(14), Color::Black, false)
+    ///     .and_then(|board| board.with_piece(square(1), Color::White, true))
+    ///     .unwrap()
+    ///     .with_turn(Color::White);
+    /// assert_eq!(board.fen(), "W:WK1:B14");
+    /// assert_eq!(board.without_piece(square(1)).fen(), "W:W:B14");
+    ///
+    /// assert_eq!(
+    ///     board.with_piece(square(30), Color::Black, false),
+    ///     Err(BoardError::UncrownedManError { square: 30, color: Color::Black })
+    /// );
+    /// ```
+    pub fn with_piece(&self, square: Square, color: Color, king: bool) -> Result<Self, BoardError> {
+        let (own, crowning) = match color {
+            Black => (self.blacks, CROWNING_ROWS[0]),
+            White => (self.whites, CROWNING_ROWS[1]),
+        };
+        let mask = square.mask();
+        if !king && crowning & mask != 0 {
+            return Err(BoardError::UncrownedManError {
+                square: square.pdn(),
+                color,
+            });
+        }
+        if own & !mask == own && own.count_ones() >= MAX_PIECES {
+            return Err(BoardError::TooManyPiecesError { color });
+        }
+
+        let mut board = self.without_piece(square);
+        match color {
+            Black => board.blacks |= mask,
+            White => board.whites |= mask,
+        }
+        if king {
+            board.kings |= mask;
+        }
+        Ok(board)
+    }
+
+    /// Returns the board with `square` emptied
+    pub fn without_piece(&self, square: Square) -> Self {
+        let mask = !square.mask();
+        Bitboard {
+            blacks: self.blacks & mask,
+            whites: self.whites & mask,
+            kings: self.kings & mask,
+            ..*self
+        }
+    }
+
+    /// Describes the position as numbers for machine learning. Every model and tool
+    /// working with muskox positions encodes them this way, so that they all agree. The
+    /// features are laid out as follows, with square n at index n - 1 of each plane:
+    ///
+    /// * `0..32`: 1 where there is a black man
+    /// * `32..64`: 1 where there is a black king
+    /// * `64..96`: 1 where there is a white man
+    /// * `96..128`: 1 where there is a white king
+    /// * `128`: 1 when white is to move
+    /// * `129..133`: the number of black men, black kings, white men and white kings
+    /// * `133..135`: the number of legal actions of black and then of white, as if each
+    ///   of them were to move
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, FEATURE_COUNT};
+    ///
+    /// let features = Bitboard::from_fen("W:WK18:B1").unwrap().features();
+    /// assert_eq!(features.len(), FEATURE_COUNT);
+    /// assert_eq!(features[0], 1.); // a black man on square 1
+    /// assert_eq!(features[96 + 17], 1.); // a white king on square 18
+    /// assert_eq!(features[128], 1.); // white to move
+    /// assert_eq!(features[129..133], [1., 0., 0., 1.]);
+    /// assert_eq!(features[133..], [2., 4.]);
+    /// ```
+    pub fn features(&self) -> [f32; FEATURE_COUNT] {
+        let mut features = [0.; FEATURE_COUNT];
+
+        let planes = [
+            self.blacks & !self.kings,
+            self.blacks & self.kings,
+            self.whites & !self.kings,
+            self.whites & self.kings,
+        ];
+        for (i, plane) in planes.iter().enumerate() {
+            for square in 0..32 {
+                features[32 * i + square] = (plane >> square & 1) as f32;
+            }
+            features[129 + i] = plane.count_ones() as f32;
+        }
+        features[128] = (self.turn == White) as u8 as f32;
+
+        let (black, white) = match self.turn {
+            Black => (*self, self.pass_turn()),
+            White => (self.pass_turn(), *self),
+        };
+        features[133] = black.generate_all_actions().len() as f32;
+        features[134] = white.generate_all_actions().len() as f32;
+
+        features
+    }
+
     /// Returns a u32 mask that represents all of the white pieces that can move.
     /// Recognize that this does not include the white pieces that can jump. To
     /// access those use `get_jumpers`.
@@ -283,93 +1155,230 @@ impl Bitboard {
 
         let not_occupied = !(self.whites | self.blacks);
 
-        match color {
-            White => {
-                let white_kings = self.whites & self.kings;
-
-                let mut jumpers = 0;
-                let mut temp = (not_occupied << 4) & self.blacks;
+        // pieces that can jump a target towards the top of the board (whites forward)
+        let jumpers_up = |pieces: Mask, targets: Mask| {
+            if pieces == 0 {
+                return 0;
+            }
 
-                jumpers |= ((temp & MASK_R3) << 3) | ((temp & MASK_R5) << 5);
+            let mut jumpers = 0;
+            let mut temp = (not_occupied << 4) & targets;
 
-                temp = (((not_occupied & MASK_R3) << 3) | ((not_occupied & MASK_R5) << 5))
-                    & self.blacks;
-                jumpers |= temp << 4;
+            jumpers |= ((temp & MASK_R3) << 3) | ((temp & MASK_R5) << 5);
 
-                jumpers &= self.whites;
+            temp = (((not_occupied & MASK_R3) << 3) | ((not_occupied & MASK_R5) << 5)) & targets;
+            jumpers |= temp << 4;
 
-                if white_kings != 0 {
-                    temp = (not_occupied >> 4) & self.blacks;
-                    jumpers |= (((temp & MASK_L3) >> 3) | ((temp & MASK_L5) >> 5)) & white_kings;
-                    temp = (((not_occupied & MASK_L3) >> 3) | ((not_occupied & MASK_L5) >> 5))
-                        & self.blacks;
-                    jumpers |= (temp >> 4) & white_kings;
-                }
+            jumpers & pieces
+        };
 
-                jumpers
+        // pieces that can jump a target towards the bottom of the board (blacks forward)
+        let jumpers_down = |pieces: Mask, targets: Mask| {
+            if pieces == 0 {
+                return 0;
             }
-            Black => {
-                let black_kings = self.blacks & self.kings;
 
-                let mut jumpers = 0;
-                let mut temp = (not_occupied >> 4) & self.whites;
+            let mut jumpers = 0;
+            let mut temp = (not_occupied >> 4) & targets;
 
-                jumpers |= ((temp & MASK_L3) >> 3) | ((temp & MASK_L5) >> 5);
+            jumpers |= ((temp & MASK_L3) >> 3) | ((temp & MASK_L5) >> 5);
 
-                temp = (((not_occupied & MASK_L3) >> 3) | ((not_occupied & MASK_L5) >> 5))
-                    & self.whites;
-                jumpers |= temp >> 4;
+            temp = (((not_occupied & MASK_L3) >> 3) | ((not_occupied & MASK_L5) >> 5)) & targets;
+            jumpers |= temp >> 4;
 
-                jumpers &= self.blacks;
+            jumpers & pieces
+        };
 
-                if black_kings != 0 {
-                    temp = (not_occupied << 4) & self.whites;
-                    jumpers |= (((temp & MASK_R3) << 3) | ((temp & MASK_R5) << 5)) & black_kings;
-                    temp = (((not_occupied & MASK_R3) << 3) | ((not_occupied & MASK_R5) << 5))
-                        & self.whites;
-                    jumpers |= (temp << 4) & black_kings;
-                }
+        let (pieces, opponents) = match color {
+            White => (self.whites, self.blacks),
+            Black => (self.blacks, self.whites),
+        };
 
-                jumpers
+        let rules = self.variant.rules();
+
+        let kings = pieces & self.kings;
+        let men = pieces & !self.kings;
+
+        // flying kings capture from afar, which shifting masks cannot see
+        let (kings, flying) = match rules.flying_kings {
+            true => {
+                let board = self.with_turn(color);
+                let flying = (0..32)
+                    .filter(|&k| kings & 1 << k != 0)
+                    .filter(|&k| board.captures_from(k, true, 0).next().is_some())
+                    .fold(0, |mask, k| mask | 1 << k);
+                (0, flying)
             }
-        }
-    }
+            false => (kings, 0),
+        };
 
-    /// Retrives all of the possible next positions from a certain position given a particular action type
-    fn next_position_possibilities(&self, position: u8, action_type: ActionType) -> Vec<u8> {
-        let mut directions = match self.turn {
-            White => vec![Direction::UpLeft, Direction::UpRight],
-            Black => vec![Direction::DownLeft, Direction::DownRight],
+        let backward_men = match rules.men_capture_backwards {
+            true => men,
+            false => 0,
         };
 
-        if self.is_king(position) {
-            directions.extend(match self.turn {
-                White => vec![Direction::DownLeft, Direction::DownRight],
-                Black => vec![Direction::UpLeft, Direction::UpRight],
-            });
+        let (men_up, men_down) = match color {
+            White => (men, backward_men),
+            Black => (backward_men, men),
+        };
+
+        let jumpers = if rules.men_capture_kings {
+            jumpers_up(men_up | kings, opponents) | jumpers_down(men_down | kings, opponents)
+        } else {
+            let men_targets = opponents & !self.kings;
+
+            jumpers_up(men_up, men_targets)
+                | jumpers_down(men_down, men_targets)
+                | jumpers_up(kings, opponents)
+                | jumpers_down(kings, opponents)
+        };
+        jumpers | flying
+    }
+
+    /// Returns the directions a piece of the side to move goes in, forward first. Kings go
+    /// backwards and in some variants single pieces capture backwards as well.
+    fn directions(&self, is_king: bool, capturing: bool) -> &'static [Direction] {
+        use Direction::*;
+
+        let backward = is_king || capturing && self.variant.rules().men_capture_backwards;
+        match (self.turn, backward) {
+            (White, false) => &[UpLeft, UpRight],
+            (White, true) => &[UpLeft, UpRight, DownLeft, DownRight],
+            (Black, false) => &[DownLeft, DownRight],
+            (Black, true) => &[DownLeft, DownRight, UpLeft, UpRight],
+        }
+    }
+
+    /// Returns how many squares a piece of the side to move goes at a time
+    fn reach(&self, is_king: bool) -> u8 {
+        match is_king && self.variant.rules().flying_kings {
+            true => 7,
+            false => 1,
         }
+    }
 
-        let opponent_color = self.turn.opponent();
+    /// Retrieves the squares the piece on `position` can move to without capturing
+    fn move_destinations(&self, position: u8) -> impl Iterator<Item = u8> + '_ {
+        let is_king = self.is_king(position);
+        let reach = self.reach(is_king);
 
-        directions
+        self.directions(is_king, false).iter().flat_map(move |d| {
+            (1..=reach)
+                .map_while(move |distance| d.walk_from(position, distance))
+                .take_while(move |&p| self.is_empty(p)) // must be landing in empty spot
+        })
+    }
+
+    /// Retrieves the captures the piece on `position` can make as the square of the piece
+    /// it captures and a square it can land on. `dead` holds the pieces captured earlier in
+    /// the same action, which still stand in the way but cannot be captured again.
+    fn captures_from(
+        &self,
+        position: u8,
+        is_king: bool,
+        dead: Mask,
+    ) -> impl Iterator<Item = (u8, u8)> + '_ {
+        let rules = self.variant.rules();
+        let reach = self.reach(is_king);
+        let opponent_color = self.turn.opposite();
+
+        self.directions(is_king, true)
             .iter()
-            .filter_map(|d| match action_type {
-                ActionType::Move => d.relative_to(position),
-                ActionType::Jump => d.relative_jump_from(position),
-            })
-            .filter(|&p| {
-                // check to ensure we are only jumping over opponents pieces
-                // this is an inefficient way of getting dir..
-                if action_type == ActionType::Jump {
-                    let dir = Direction::between(position, p).unwrap();
-                    if !self.coloring_eq(dir.relative_to(position).unwrap(), opponent_color) {
-                        return false;
+            .filter_map(move |d| {
+                // a flying king passes over empty squares to get to the piece it captures
+                let mut distance = 1;
+                let skipped_over = loop {
+                    let square = d.walk_from(position, distance)?;
+                    if !self.is_empty(square) || distance == reach {
+                        break square;
                     }
+                    distance += 1;
+                };
+
+                // check to ensure we are only jumping over opponents pieces
+                if !self.coloring_eq(skipped_over, opponent_color) || dead & 1 << skipped_over != 0
+                {
+                    return None;
                 }
-                true
+                if !is_king && !rules.men_capture_kings && self.is_king(skipped_over) {
+                    return None;
+                }
+                Some((d, distance, skipped_over))
+            })
+            .flat_map(move |(d, distance, skipped_over)| {
+                (distance + 1..=distance + reach)
+                    .map_while(move |distance| d.walk_from(position, distance))
+                    .take_while(move |&p| self.is_empty(p)) // must be landing in empty spot
+                    .map(move |landing| (skipped_over, landing))
             })
-            .filter(|&p| self.is_empty(p)) // must be landing in empty spot
-            .collect::<Vec<_>>()
+    }
+
+    /// Returns the square a jump from `from` going `distance` squares in `direction`
+    /// captures: the piece in the way nearest to where it lands, or the square just before
+    /// that when nothing is in the way. The moving piece has left `source`.
+    fn jumped_square(&self, from: u8, direction: Direction, distance: u8, source: u8) -> u8 {
+        (1..distance)
+            .rev()
+            .filter_map(|d| direction.walk_from(from, d))
+            .find(|&p| p != source && !self.is_empty(p))
+            .or_else(|| direction.walk_from(from, distance - 1))
+            .unwrap()
+    }
+
+    /// Reads an action the way the board has it. Once kings fly, a path along a single
+    /// diagonal is a capture when the side to move has one to make and a move otherwise.
+    fn interpret(&self, action: Action) -> Action {
+        if !self.variant.rules().flying_kings || action.jump_len() > 1 {
+            return action;
+        }
+
+        let (source, destination) = (action.source(), action.destination());
+        let read = match self.get_jumpers(self.turn) {
+            0 => Action::straight_move(source, destination),
+            _ => Action::straight_jump(source, destination),
+        };
+        read.unwrap_or(action)
+    }
+
+    /// Returns whether neither side has any winning chances left: a lone king against a
+    /// lone king, each in a double corner of its own, can never be forced out. Giveaway
+    /// is left alone as both sides want to lose their pieces there.
+    fn is_dead_draw(&self) -> bool {
+        let lone_kings = self.blacks.count_ones() == 1
+            && self.whites.count_ones() == 1
+            && self.kings == self.blacks | self.whites;
+
+        lone_kings
+            && !self.variant.rules().giveaway
+            && DOUBLE_CORNERS
+                .iter()
+                .all(|&corner| corner & self.kings != 0)
+    }
+
+    /// Returns a key that orders capture sequences by the precedence rules of the variant.
+    /// Sequences with a greater key have to be taken over those with a lesser one.
+    fn capture_precedence_key(&self, action: &Action) -> (u8, bool, u32, Reverse<usize>) {
+        let jumps = action.jump_len();
+
+        match self.variant.rules().capture_precedence {
+            CapturePrecedence::Free => (0, false, 0, Reverse(0)),
+            CapturePrecedence::MostPieces => (jumps, false, 0, Reverse(0)),
+            CapturePrecedence::Italian => {
+                let effects = match self.action_effects(action) {
+                    Ok(effects) => effects,
+                    Err(_) => return (0, false, 0, Reverse(0)),
+                };
+
+                let by_king = self.is_king(action.source());
+                let kings_captured = (effects.captured() & self.kings).count_ones();
+                let first_king = effects
+                    .captured_squares()
+                    .position(|s| self.is_king(s.index()))
+                    .unwrap_or(usize::MAX);
+
+                (jumps, by_king, kings_captured, Reverse(first_king))
+            }
+        }
     }
 
     /// Returns whether a given position is empty or not
@@ -394,6 +1403,16 @@ impl Bitboard {
         (color_mask >> position) % 2 == 1
     }
 
+    /// Returns whether a man of a color is crowned on a position
+    #[inline]
+    fn crowns(position: u8, color: Color) -> bool {
+        let row = match color {
+            Black => CROWNING_ROWS[0],
+            White => CROWNING_ROWS[1],
+        };
+        row & 1 << position != 0
+    }
+
     /// Removes the piece from the given position on the board. Note that this mutates the board
     #[inline]
     fn remove_piece(&mut self, position: u8) {
@@ -432,6 +1451,80 @@ impl Bitboard {
     }
 }
 
+/// Describes what taking a particular action does to a board. Created by
+/// [action_effects](struct.Bitboard.html#method.action_effects).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActionEffects {
+    action: Action,
+    captured: Mask,
+    promoted: bool,
+}
+
+/// Represents how a move entered a square at a time can go on, as told by
+/// `Bitboard::continue_jump`
+#[derive(Debug, Clone, PartialEq)]
+pub enum JumpContinuation {
+    /// The squares so far make up a legal action
+    Complete(Action),
+    /// Only one legal action begins with the squares so far
+    Forced(Action),
+    /// Several legal actions begin with the squares so far. These are the squares that
+    /// can come next.
+    Ambiguous(Vec<Square>),
+    /// No legal action begins with the squares so far
+    Invalid,
+}
+
+impl ActionEffects {
+    /// Returns the square the moving piece starts on
+    #[inline]
+    pub fn source(&self) -> Square {
+        Square::new(self.action.source())
+    }
+
+    /// Returns the square the moving piece lands on
+    #[inline]
+    pub fn destination(&self) -> Square {
+        Square::new(self.action.destination())
+    }
+
+    /// Returns a mask of all of the squares whose pieces are captured
+    #[inline]
+    pub fn captured(&self) -> Mask {
+        self.captured
+    }
+
+    /// Returns the captured squares in the order they are jumped over
+    pub fn captured_squares(&self) -> impl Iterator<Item = Square> {
+        let action = self.action;
+        let mut curr = action.source();
+
+        let captured = self.captured;
+
+        // the path was already checked to stay on the board when the effects were made.
+        // every jump captures one of the squares it passes over
+        (0..action.jump_len()).map(move |i| {
+            let direction = action.jump_direction(i).unwrap();
+            let distance = action.jump_distance(i).unwrap();
+            let skipped_over = (1..distance)
+                .rev()
+                .filter_map(|d| direction.walk_from(curr, d))
+                .find(|p| captured & 1 << p != 0)
+                .unwrap();
+            curr = direction.walk_from(curr, distance).unwrap();
+            Square::new(skipped_over)
+        })
+    }
+
+    /// Returns whether the moving piece gets crowned
+    #[inline]
+    pub fn promoted(&self) -> bool {
+        self.promoted
+    }
+}
+
+/// Represents a legal action along with the board it leads to
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ActionBitboardPair {
     action: Action,
     board: Bitboard,
@@ -467,17 +1560,16 @@ impl Searchable for Bitboard {
     /// assert_eq!(board.get_game_state(), GameState::InProgress);
     /// ```
     fn get_game_state(&self) -> GameState<Bitboard> {
-        // check if somebody can't move
-        if self.turn == Black && self.get_movers(Black) == 0 && self.get_jumpers(Black) == 0 {
-            return GameState::Completed(Winner::Player(White));
-        }
-        if self.turn == White && self.get_movers(Black) == 0 && self.get_jumpers(Black) == 0 {
-            return GameState::Completed(Winner::Player(Black));
+        // check if somebody can't move. they lose, unless we are playing giveaway
+        if self.get_movers(self.turn) == 0 && self.get_jumpers(self.turn) == 0 {
+            let winner = match self.variant.rules().giveaway {
+                true => self.turn,
+                false => self.turn.opposite(),
+            };
+            return GameState::Completed(Winner::Player(winner));
         }
 
-        // need to figure out how to determine if there is a draw
-        // maybe make it so the computer can agree to a draw
-        if false {
+        if self.is_dead_draw() {
             return GameState::Completed(Winner::Draw);
         }
 
@@ -486,7 +1578,59 @@ impl Searchable for Bitboard {
     }
 
     fn evaluate(&self) -> Score {
-        GLOBAL_EVAL.eval(self)
+        evaluation::current_evaluator().eval(self)
+    }
+
+    fn evaluate_batch(states: &[Self]) -> Vec<Score> {
+        evaluation::current_evaluator().eval_batch(states)
+    }
+
+    /// Passing is only offered when the player to move has no jump to make and both
+    /// sides still have enough pieces. Checkers endgames are full of positions where
+    /// every move loses, so assuming a move is better than passing there is unsound.
+    fn null_move(&self) -> Option<(Self, u64)> {
+        let (own, opponent) = match self.turn {
+            Black => (self.blacks, self.whites),
+            White => (self.whites, self.blacks),
+        };
+
+        if self.get_jumpers(self.turn) != 0
+            || own.count_ones() < NULL_MOVE_MIN_PIECES
+            || opponent.count_ones() < NULL_MOVE_MIN_PIECES
+        {
+            return None;
+        }
+
+        Some((self.pass_turn(), zobrist::get_turn_hash()))
+    }
+
+    /// Moves that do not promote are quiet. Jumps never are, and they are forced anyway.
+    fn is_quiet(&self, pair: &ActionStatePair<Bitboard>) -> bool {
+        pair.action().action_type() == ActionType::Move
+            && pair.state().kings.count_ones() == self.kings.count_ones()
+    }
+
+    /// Captured kings count as two men, as do men crowned at the end of the action
+    fn capture_gain(&self, pair: &ActionStatePair<Bitboard>) -> u32 {
+        let (own, opponents) = match self.turn {
+            Black => (self.blacks, self.whites),
+            White => (self.whites, self.blacks),
+        };
+        let state = pair.state();
+        let (own_p, opponents_p) = match self.turn {
+            Black => (state.blacks, state.whites),
+            White => (state.whites, state.blacks),
+        };
+
+        // the side to move never loses kings of its own
+        let captured = opponents & !opponents_p;
+        let crowned = (own_p & state.kings).count_ones() - (own & self.kings).count_ones();
+        captured.count_ones() + (captured & self.kings).count_ones() + crowned
+    }
+
+    /// Actions are told apart by where they start and end
+    fn history_key(&self, action: &Action) -> Option<usize> {
+        Some(action.source() as usize * 32 + action.destination() as usize)
     }
 
     fn generate_all_actions(&self) -> Vec<ActionStatePair<Bitboard>> {
@@ -511,7 +1655,7 @@ impl Searchable for Bitboard {
             _ => ActionType::Jump,
         };
 
-        let opponent_color = self.turn.opponent();
+        let opponent_color = self.turn.opposite();
 
         let mut actions = Vec::new();
 
@@ -524,23 +1668,16 @@ impl Searchable for Bitboard {
                 while movers != 0 {
                     let mover = pop_piece(&mut movers, self.turn);
 
-                    let base_action = vec![mover];
-
-                    let move_candidates = self.next_position_possibilities(mover, action_type);
+                    let move_candidates = self.move_destinations(mover);
 
                     let starts_as_king = self.is_king(mover);
 
                     for candidate in move_candidates {
-                        let mut action = base_action.clone();
-                        action.push(candidate);
-                        let action: Vec<_> = action.iter().map(|x| (x + 1) as u8).collect();
-                        let action = Action::from_vec(action).unwrap();
-
-                        let ends_as_king = {
-                            let dest_row = candidate / 4;
-                            // will be a king if it was a king or will be in end row last
-                            starts_as_king || dest_row == 0 || dest_row == 7
-                        };
+                        let action = Action::straight_move(mover, candidate).unwrap();
+
+                        // will be a king if it was a king or will be in end row last
+                        let ends_as_king =
+                            starts_as_king || Square::new(candidate).is_promotion_row();
 
                         let mut board_p = *self;
 
@@ -570,48 +1707,80 @@ impl Searchable for Bitboard {
                 while jumpers != 0 {
                     let position = pop_piece(&mut jumpers, self.turn);
 
-                    let base_action = vec![position];
+                    let base_action = JumpChain::new(position);
 
                     // remove the zobrist hash for the piece that will move
                     let zobrist_hash =
                         zobrist::get_position_hash(position, self.turn, self.is_king(position));
 
-                    boards_in_progress.push_back((*self, base_action, zobrist_hash));
+                    boards_in_progress.push_back((*self, base_action, zobrist_hash, 0));
                 }
 
-                while let Some((board, base_action, zobrist_hash)) = boards_in_progress.pop_front()
+                let rules = self.variant.rules();
+                let crowning = rules.mid_capture_crowning;
+
+                while let Some((board, base_action, zobrist_hash, dead)) =
+                    boards_in_progress.pop_front()
                 {
                     // can only pop the piece that has been jumping [last element in action]
-                    let &jumper = base_action.last().unwrap();
+                    let jumper = base_action.last();
+                    let starts_as_king = board.is_king(jumper);
 
-                    // generate all possible new boards based on jumpers.
-                    let jump_candidates = board.next_position_possibilities(jumper, action_type);
+                    // applies a jump on the board, returning it with what it leaves behind
+                    let jump = |skipped_over: u8, candidate: u8| {
+                        let crowned = !starts_as_king && Self::crowns(candidate, board.turn);
 
-                    for candidate in jump_candidates {
-                        let mut action_vec = base_action.clone();
-                        action_vec.push(candidate);
+                        // a man crowned on the way goes on as a king unless the variant
+                        // only crowns it where its capture ends
+                        let lands_as_king = starts_as_king
+                            || crowned && crowning != MidCaptureCrowning::ContinuesAsMan;
 
-                        let action =
-                            Action::from_vec(action_vec.iter().map(|x| (x + 1) as u8).collect())
-                                .unwrap();
+                        // apply jump on piece. a piece captured by a flying king stays in
+                        // the way until the capture is over
+                        let mut board_p = board;
+                        board_p.add_piece(candidate, board.turn, lands_as_king);
+                        board_p.remove_piece(jumper);
+                        let dead_p = match rules.flying_kings {
+                            true => dead | 1 << skipped_over,
+                            false => {
+                                board_p.remove_piece(skipped_over);
+                                0
+                            }
+                        };
 
-                        let direction = Direction::between(jumper, candidate).unwrap();
+                        // check if we cannot jump anymore
+                        let stops = base_action.push(candidate).is_full()
+                            || crowned && crowning == MidCaptureCrowning::Stops
+                            || board_p
+                                .captures_from(candidate, lands_as_king, dead_p)
+                                .next()
+                                .is_none();
+                        (board_p, dead_p, crowned, stops)
+                    };
+
+                    // a flying king that can land on several squares has to pick one it
+                    // can go on capturing from, if there is any
+                    let mut continued: Mask = 0;
+                    if starts_as_king && rules.flying_kings {
+                        for (skipped_over, candidate) in board.captures_from(jumper, true, dead) {
+                            if !jump(skipped_over, candidate).3 {
+                                continued |= 1 << skipped_over;
+                            }
+                        }
+                    }
 
-                        let skipped_over = direction.relative_to(jumper).unwrap();
+                    // generate all possible new boards based on jumpers.
+                    let jump_candidates = board.captures_from(jumper, starts_as_king, dead);
 
-                        let starts_as_king = board.is_king(jumper);
+                    for (skipped_over, candidate) in jump_candidates {
+                        let (mut board_p, dead_p, crowned, stops) = jump(skipped_over, candidate);
+                        if stops && continued & 1 << skipped_over != 0 {
+                            continue;
+                        }
 
-                        let ends_as_king = {
-                            let dest_row = candidate / 4;
-                            // will be a king if it was a king or will be in end row last
-                            starts_as_king || dest_row == 0 || dest_row == 7
-                        };
+                        let action_chain = base_action.push(candidate);
 
-                        // apply jump on piece
-                        let mut board_p = board;
-                        board_p.add_piece(candidate, board.turn, ends_as_king);
-                        board_p.remove_piece(jumper);
-                        board_p.remove_piece(skipped_over);
+                        let action = Action::from_indices(action_chain.squares()).unwrap();
 
                         // remove the zobrist hash for the skipped over rpiece
                         let mut zobrist_diff = zobrist::get_position_hash(
@@ -620,10 +1789,16 @@ impl Searchable for Bitboard {
                             board.is_king(skipped_over),
                         );
 
-                        // check if we cannot jump anymore
-                        if (board_p.get_jumpers(board.turn) & (1 << candidate) == 0)
-                            | (!starts_as_king & ends_as_king)
-                        {
+                        if stops {
+                            // a man finishing on the far row is crowned whatever the rules
+                            let ends_as_king = starts_as_king || crowned;
+                            board_p.add_piece(candidate, board.turn, ends_as_king);
+
+                            // the captured pieces come off once the capture is over
+                            for captured in (0..32).filter(|&p| dead_p & 1 << p != 0) {
+                                board_p.remove_piece(captured);
+                            }
+
                             // flip the turn when it is over
                             board_p.turn = opponent_color;
 
@@ -641,12 +1816,22 @@ impl Searchable for Bitboard {
                             // other wise  it in the deque for the next move to be processed
                             boards_in_progress.push_back((
                                 board_p,
-                                action_vec,
+                                action_chain,
                                 zobrist_hash ^ zobrist_diff,
+                                dead_p,
                             ));
                         }
                     }
                 }
+
+                // some variants force the player to take a particular capture sequence
+                if self.variant.rules().capture_precedence != CapturePrecedence::Free {
+                    let best = actions
+                        .iter()
+                        .map(|p| self.capture_precedence_key(p.action()))
+                        .max();
+                    actions.retain(|p| Some(self.capture_precedence_key(p.action())) == best);
+                }
             }
         }
 
@@ -680,22 +1865,23 @@ impl Searchable for Bitboard {
     /// assert_eq!(board.validate_action(action), Err(ActionError::SinglePieceBackwardsError));
     /// ```
     fn take_action(&self, action: Action) -> Result<Bitboard, ActionError> {
+        let action = self.interpret(action);
+        let rules = self.variant.rules();
         let mut board_p = *self;
 
         let source = action.source();
         let destination = action.destination();
 
+        let effects = self.action_effects(&action)?;
+
         let starts_as_king = self.is_king(source);
 
-        let ends_as_king = {
-            let dest_row = destination / 4;
-            // will be a king if it was a king or will be in end row last
-            starts_as_king || dest_row == 0 || dest_row == 7
-        };
+        // will be a king if it was a king or will be in end row last
+        let ends_as_king = starts_as_king || effects.promoted();
 
         // sketchy way of flipping the turn color enum
         // maybe just match with the opposite color instead
-        let opponent_color = self.turn.opponent();
+        let opponent_color = self.turn.opposite();
 
         // erase color from source
         board_p.remove_piece(source);
@@ -712,8 +1898,9 @@ impl Searchable for Bitboard {
             });
         }
 
-        // ensure that destination is empty.
-        if !self.is_empty(destination) {
+        // ensure that destination is empty. a king may capture its way around a circle
+        // back to the square it left
+        if destination != source && !self.is_empty(destination) {
             return Err(ActionError::DestinationEmptyError { destination });
         }
 
@@ -726,7 +1913,25 @@ impl Searchable for Bitboard {
 
                 // ensure that it only moves backwards if source is a king
                 // reformat the next dozen or so lines
-                let move_direction = action.move_direction().unwrap();
+                let (move_direction, distance) = match Direction::towards(source, destination) {
+                    Some(step) => step,
+                    None => return Err(ActionError::NotDiagonalError),
+                };
+
+                // only a flying king moves more than a square, and only over empty ones
+                if distance > 1 {
+                    if !starts_as_king || !rules.flying_kings {
+                        return Err(ActionError::NotFlyingError);
+                    }
+                    for d in 1..distance {
+                        let passed = move_direction.walk_from(source, d).unwrap();
+                        if !self.is_empty(passed) {
+                            return Err(ActionError::DestinationEmptyError {
+                                destination: passed,
+                            });
+                        }
+                    }
+                }
 
                 if (move_direction == Direction::UpLeft || move_direction == Direction::UpRight)
                     && self.turn == Black
@@ -744,45 +1949,120 @@ impl Searchable for Bitboard {
             }
 
             ActionType::Jump => {
+                let mut is_king = starts_as_king;
                 let mut curr = source;
-                // maybe make a jump iterator. that would be super cool!
+
                 for i in 0..action.jump_len() {
                     let jump_direction = action.jump_direction(i).unwrap();
+                    let distance = action.jump_distance(i).unwrap();
 
-                    // ensure that only jump backwards if it is a king
-                    if (jump_direction == Direction::UpLeft || jump_direction == Direction::UpRight)
-                        && self.turn == Black
-                        && !starts_as_king
-                    {
-                        return Err(ActionError::SinglePieceBackwardsError);
+                    if distance != 2 && (!is_king || !rules.flying_kings) {
+                        return Err(ActionError::NotFlyingError);
                     }
 
-                    if (jump_direction == Direction::DownLeft
-                        || jump_direction == Direction::DownRight)
-                        && self.turn == White
-                        && !starts_as_king
-                    {
-                        return Err(ActionError::SinglePieceBackwardsError);
+                    // the path was already checked to stay on the board
+                    let skipped_over = self.jumped_square(curr, jump_direction, distance, source);
+
+                    // ensure that nothing else is in the way of a long jump. the piece left
+                    // its source, so a king may pass through it
+                    for d in 1..distance {
+                        let passed = jump_direction.walk_from(curr, d).unwrap();
+                        if passed != skipped_over && passed != source && !self.is_empty(passed) {
+                            return Err(ActionError::SkippedPositionError {
+                                skipped: passed,
+                                color: opponent_color,
+                            });
+                        }
                     }
+                    curr = jump_direction.walk_from(curr, distance).unwrap();
+
+                    let backwards = match self.turn {
+                        Black => matches!(jump_direction, Direction::UpLeft | Direction::UpRight),
+                        White => {
+                            matches!(jump_direction, Direction::DownLeft | Direction::DownRight)
+                        }
+                    };
 
-                    // see if we can try to use bitmasking to get this next time
-                    let skipped_over = jump_direction.relative_to(curr).unwrap();
+                    // ensure that only jump backwards if it is a king (or the variant allows it)
+                    if backwards && !is_king && !rules.men_capture_backwards {
+                        return Err(ActionError::SinglePieceBackwardsError);
+                    }
 
-                    // ensure that it actually jumps over another piece that is not its own color
-                    if !self.coloring_eq(skipped_over, opponent_color) {
+                    // ensure that it actually jumps over another piece that is not its own color.
+                    // checked against the updated board so a piece cannot be captured twice
+                    if !board_p.coloring_eq(skipped_over, opponent_color) {
                         return Err(ActionError::SkippedPositionError {
                             skipped: skipped_over,
                             color: opponent_color,
                         });
                     }
 
+                    if !is_king && !rules.men_capture_kings && self.is_king(skipped_over) {
+                        return Err(ActionError::ManCapturingKingError);
+                    }
+
                     board_p.remove_piece(skipped_over);
 
-                    curr = jump_direction.relative_jump_from(curr).unwrap();
+                    // ensure that it lands on empty squares on the way too. the piece left
+                    // its source, so a king may pass through it
+                    if i + 1 < action.jump_len() && curr != source && !self.is_empty(curr) {
+                        return Err(ActionError::DestinationEmptyError { destination: curr });
+                    }
+
+                    // ensure that a man crowned on the way stops there if the variant says so
+                    if !is_king && Self::crowns(curr, self.turn) {
+                        match rules.mid_capture_crowning {
+                            MidCaptureCrowning::Stops if i + 1 < action.jump_len() => {
+                                return Err(ActionError::CrownedMidCaptureError)
+                            }
+                            MidCaptureCrowning::ContinuesAsKing => is_king = true,
+                            _ => (),
+                        }
+                    }
+                }
+
+                // ensure that it there isnt another jump for it to do at destination, with
+                // the piece as it was while capturing. pieces captured by a flying king are
+                // still in the way
+                let stopped = action.jump_len() == MAX_JUMPS
+                    || !starts_as_king
+                        && effects.promoted()
+                        && rules.mid_capture_crowning == MidCaptureCrowning::Stops;
+                let (mut capturing, dead) = match rules.flying_kings {
+                    true => (*self, effects.captured()),
+                    false => (board_p, 0),
+                };
+                capturing.remove_piece(source);
+                capturing.remove_piece(destination);
+                capturing.add_piece(destination, self.turn, is_king);
+                if !stopped
+                    && capturing
+                        .captures_from(destination, is_king, dead)
+                        .next()
+                        .is_some()
+                {
+                    return Err(ActionError::NeedMoreJumpingError);
+                }
+
+                // ensure that no other capture sequence takes precedence over this one
+                if rules.capture_precedence != CapturePrecedence::Free {
+                    let key = self.capture_precedence_key(&action);
+                    if self
+                        .generate_all_actions()
+                        .iter()
+                        .any(|p| self.capture_precedence_key(p.action()) > key)
+                    {
+                        return Err(ActionError::CapturePrecedenceError);
+                    }
                 }
-                // ensure that it there isnt another jump for it to do at destination
-                if (board_p.get_jumpers(self.turn) & 1 << destination != 0)
-                    & !(!starts_as_king & ends_as_king)
+
+                // a flying king has to land where it can go on capturing when it can, which
+                // the generated actions already know
+                if rules.flying_kings
+                    && !self
+                        .generate_all_actions()
+                        .iter()
+                        .any(|p| *p.action() == action)
                 {
                     return Err(ActionError::NeedMoreJumpingError);
                 }
@@ -834,6 +2114,70 @@ impl Searchable for Bitboard {
     }
 }
 
+// boards are saved as their fen, which holds the variant too
+
+// boards are stored packed in base64, and read back from either that or a fen, which
+// always has a colon base64 never does
+
+impl serde::Serialize for Bitboard {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_base64())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Bitboard {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        match text.contains(':') {
+            true => Self::from_fen(&text),
+            false => Self::from_base64(&text),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serializes a board as its fen, for formats meant to be read by people and other
+/// programs
+pub(crate) fn serialize_fen<S: serde::Serializer>(
+    board: &Bitboard,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&board.fen())
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl<'a> arbitrary::Arbitrary<'a> for Bitboard {
+    /// Creates a position the rules allow from unstructured bytes: each side has at most
+    /// twelve pieces and men that would stand on their crowning row are kings
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let trim = |mut mask: Mask| {
+            while mask.count_ones() > MAX_PIECES {
+                mask &= mask - 1;
+            }
+            mask
+        };
+
+        let blacks = trim(u.arbitrary()?);
+        let whites = trim(u.arbitrary::<Mask>()? & !blacks);
+        let kings = u.arbitrary::<Mask>()? & (blacks | whites)
+            | blacks & CROWNING_ROWS[0]
+            | whites & CROWNING_ROWS[1];
+        let turn = match u.arbitrary()? {
+            true => White,
+            false => Black,
+        };
+        let variant = *u.choose(&[
+            Variant::American,
+            Variant::Italian,
+            Variant::Russian,
+            Variant::Brazilian,
+            Variant::Giveaway,
+        ])?;
+
+        Ok(Bitboard::new(blacks, whites, kings, turn).with_variant(variant))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -878,6 +2222,175 @@ mod tests {
         assert_eq!(board.fen(), "B:WK10,K15,18,24,27,28:B12,16,20,K22,K25,K29");
     }
 
+    #[test]
+    fn mirrored_test() {
+        for fen in [DEFAULT_BOARD, TEST_BOARD_1, TEST_BOARD_2, TEST_BOARD_3].iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            let mirrored = board.mirrored();
+
+            assert_eq!(mirrored.mirrored(), board);
+            assert_eq!(mirrored.turn, board.turn.opposite());
+            assert_eq!(mirrored.evaluate(), Score::from(0.) - board.evaluate());
+            assert_eq!(
+                mirrored.generate_all_actions().len(),
+                board.generate_all_actions().len()
+            );
+        }
+
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap().mirrored();
+        assert_eq!(board.fen(), "W:WK4,K8,K11,13,17,21:B5,6,9,15,K18,K23");
+    }
+
+    #[test]
+    fn board_editing_test() {
+        let square = |n| Square::from_pdn(n).unwrap();
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+
+        // a piece put on an occupied square replaces what was there
+        let edited = board.with_piece(square(22), White, false).unwrap();
+        assert_eq!(edited.whites() & square(22).mask(), square(22).mask());
+        assert_eq!(edited.blacks() & square(22).mask(), 0);
+        assert_eq!(edited.kings() & square(22).mask(), 0);
+        assert_eq!(
+            edited.without_piece(square(22)),
+            board.without_piece(square(22))
+        );
+
+        // white men are crowned on 1 to 4, so they can stand on 29 to 32
+        assert!(board.with_piece(square(30), White, false).is_ok());
+        assert_eq!(
+            board.with_piece(square(2), White, false),
+            Err(BoardError::UncrownedManError {
+                square: 2,
+                color: White
+            })
+        );
+
+        let full = Bitboard::default();
+        assert_eq!(
+            full.with_piece(square(16), Black, false),
+            Err(BoardError::TooManyPiecesError { color: Black })
+        );
+        // replacing one of the twelve is fine
+        assert!(full.with_piece(square(12), Black, true).is_ok());
+
+        let cleared = board.cleared().with_turn(White);
+        assert_eq!(cleared.fen(), "W:W:B");
+        assert_eq!(cleared.variant(), board.variant());
+    }
+
+    #[test]
+    fn mobility_test() {
+        let boards = [
+            TEST_BOARD_1,
+            TEST_BOARD_2,
+            TEST_BOARD_3,
+            TEST_BOARD_4,
+            TEST_BOARD_5,
+            TEST_BOARD_6,
+            TEST_BOARD_7,
+        ];
+        for fen in boards.iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            for &color in [Black, White].iter() {
+                // the pieces the actions start from are the ones that are counted, as
+                // jumps are forced whenever there is one
+                let (movers, jumpers) = board.mobility(color);
+                let actions = board.with_turn(color).generate_all_actions();
+                let mut sources: Vec<_> = actions.iter().map(|p| p.action().source()).collect();
+                sources.sort_unstable();
+                sources.dedup();
+                let expected = match jumpers {
+                    0 => movers,
+                    _ => jumpers,
+                };
+                assert_eq!(sources.len() as u32, expected, "{} {:?}", fen, color);
+                assert!(actions
+                    .iter()
+                    .all(|p| (p.action().action_type() == ActionType::Jump) == (jumpers > 0)));
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_key_test() {
+        for fen in [DEFAULT_BOARD, TEST_BOARD_1, TEST_BOARD_2, TEST_BOARD_3].iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            let key = board.canonical_key();
+            assert_eq!(board.mirrored().canonical_key(), key);
+            assert!(key == board.position_key() || key == board.mirrored().position_key());
+        }
+
+        // the side to move is part of the position, so passing the turn is another one
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+        assert_ne!(board.pass_turn().canonical_key(), board.canonical_key());
+        assert_ne!(
+            board.flipped_horizontal().canonical_key(),
+            board.canonical_key()
+        );
+    }
+
+    #[test]
+    fn features_test() {
+        let features = Bitboard::default().features();
+        for plane in [0, 64] {
+            assert_eq!(features[plane..plane + 32].iter().sum::<f32>(), 12.);
+        }
+        assert_eq!(features[129..], [12., 0., 12., 0., 7., 7.]);
+
+        // the features of a mirrored position swap the colors around
+        let board = Bitboard::from_fen("B:W18,K30:B14,K1").unwrap();
+        let (features, mirrored) = (board.features(), board.mirrored().features());
+        assert_eq!(features[129..131], mirrored[131..133]);
+        assert_eq!(features[133], mirrored[134]);
+        assert_ne!(features[128], mirrored[128]);
+    }
+
+    #[test]
+    fn flipped_horizontal_test() {
+        for fen in [DEFAULT_BOARD, TEST_BOARD_1, TEST_BOARD_2, TEST_BOARD_3].iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            assert_eq!(board.flipped_horizontal().flipped_horizontal(), board);
+        }
+
+        let board = Bitboard::default();
+        assert_eq!(board.flipped_horizontal(), board);
+
+        let board = Bitboard::from_fen(TEST_BOARD_2)
+            .unwrap()
+            .flipped_horizontal();
+        assert_eq!(board.fen(), "W:WK10,12,18,26,K27,31:B14,23,28,K29");
+    }
+
+    #[test]
+    fn pretty_test() {
+        let board = Bitboard::from_fen("W:WK1:B32").unwrap();
+        assert_eq!(board.pretty(), board.pretty_with(PrettyOptions::default()));
+
+        let pretty = board.pretty();
+        let lines: Vec<_> = pretty.lines().collect();
+        assert_eq!(lines[1], "|   | W |   |   |   |   |   |   |");
+        assert_eq!(lines[15], "|   |   |   |   |   |   | b |   |");
+
+        let options = PrettyOptions {
+            numbers: true,
+            turn: true,
+            flipped: true,
+            ..PrettyOptions::default()
+        };
+        let pretty = board.pretty_with(options);
+        let lines: Vec<_> = pretty.lines().collect();
+        assert_eq!(lines[1], "|   | b |   |31 |   |30 |   |29 |");
+        assert_eq!(lines[15], "| 4 |   | 3 |   | 2 |   | W |   |");
+        assert_eq!(lines[17], "White to move");
+
+        let options = PrettyOptions {
+            colors: true,
+            ..PrettyOptions::default()
+        };
+        assert!(board.pretty_with(options).contains("\x1b[1;97mW\x1b[0m"));
+    }
+
     #[test]
     fn get_movers_white_test() {
         let board = Bitboard::default();
@@ -957,6 +2470,28 @@ mod tests {
             board.get_game_state(),
             GameState::Completed(Winner::Player(Black))
         );
+
+        // white to move with black blocked in is still in progress
+        let board = Bitboard::from_fen("W:W8,11,12:B4").unwrap();
+        assert_eq!(board.get_game_state(), GameState::InProgress);
+
+        let board = Bitboard::from_fen(TEST_BOARD_4)
+            .unwrap()
+            .with_variant(Variant::Giveaway);
+        assert_eq!(
+            board.get_game_state(),
+            GameState::Completed(Winner::Player(Black))
+        );
+
+        // lone kings are drawn in opposite double corners, and not elsewhere
+        for fen in &["B:WK32:BK1", "W:WK5:BK28"] {
+            let board = Bitboard::from_fen(fen).unwrap();
+            assert_eq!(board.get_game_state(), GameState::Completed(Winner::Draw));
+        }
+        for fen in &["B:WK31:BK1", "B:WK32:BK1,2", "B:W32:BK1", "B:WK1:BK5"] {
+            let board = Bitboard::from_fen(fen).unwrap();
+            assert_eq!(board.get_game_state(), GameState::InProgress);
+        }
     }
 
     #[test]
@@ -1059,14 +2594,108 @@ mod tests {
         assert_eq!(
             board.validate_action(action),
             Err(ActionError::NeedMoreJumpingError)
-        ); // this is wrong
+        );
         let action = Action::from_movetext("8-15-22-31").unwrap();
         assert_eq!(board.validate_action(action), Ok(()));
         let action = Action::from_movetext("8-15-22-31-24").unwrap();
         assert_eq!(
             board.validate_action(action),
-            Err(ActionError::SinglePieceBackwardsError)
+            Err(ActionError::CrownedMidCaptureError)
+        );
+
+        // the squares landed on along the way have to be empty too
+        let board = Bitboard::from_fen("25:B:W9,10,12,16,19,22,23:B11,14,15,21,24").unwrap();
+        let action = Action::from_movetext("24-15-6-13").unwrap();
+        assert_eq!(
+            board.validate_action(action),
+            Err(ActionError::DestinationEmptyError { destination: 14 })
+        );
+    }
+
+    #[test]
+    fn mid_capture_crowning_test() {
+        let stop = Action::from_movetext("8-15-22-31").unwrap();
+        let go_on = Action::from_movetext("8-15-22-31-24").unwrap();
+
+        // american and italian men stop on the row they are crowned on
+        let board = Bitboard::from_fen(TEST_BOARD_7)
+            .unwrap()
+            .with_variant(Variant::Italian);
+        assert!(board.take_action(stop).unwrap().is_king(30));
+        assert_eq!(
+            board.validate_action(go_on),
+            Err(ActionError::CrownedMidCaptureError)
+        );
+
+        // russian men go on capturing as flying kings, and brazilian men go on as men and
+        // are only crowned where they stop
+        let russian = (Variant::Russian, true, 2);
+        for &(variant, crowned, count) in &[russian, (Variant::Brazilian, false, 1)] {
+            let board = Bitboard::from_fen(TEST_BOARD_7)
+                .unwrap()
+                .with_variant(variant);
+            assert_eq!(
+                board.validate_action(stop),
+                Err(ActionError::NeedMoreJumpingError)
+            );
+
+            let board_p = board.take_action(go_on).unwrap();
+            assert_eq!(board_p.is_king(23), crowned);
+            assert_eq!(board.action_effects(&go_on).unwrap().promoted(), crowned);
+
+            // a flying king may land on any square past the last piece it captures
+            let generated = board.generate_all_actions();
+            assert_eq!(generated.len(), count);
+            assert_eq!(generated[0].state(), &board_p);
+        }
+    }
+
+    #[test]
+    fn flying_kings_test() {
+        let russian = |fen| {
+            Bitboard::from_fen(fen)
+                .unwrap()
+                .with_variant(Variant::Russian)
+        };
+        let destinations = |board: Bitboard| {
+            let actions = board.generate_all_actions();
+            actions
+                .iter()
+                .map(|p| p.action().destination())
+                .collect::<Vec<_>>()
+        };
+
+        // kings fly along the diagonal up to the first piece in the way
+        let board = russian("W:WK29:B4");
+        assert_eq!(destinations(board), vec![24, 21, 17, 14, 10, 7]);
+        let action = Action::from_movetext("29-8").unwrap();
+        assert_eq!(board.take_action(action).unwrap().fen(), "25:B:WK8:B4");
+
+        // but only in the variants that have them
+        let american = Bitboard::from_fen("W:WK29:B4").unwrap();
+        assert_eq!(
+            american.validate_action(action),
+            Err(ActionError::NotFlyingError)
         );
+
+        // and capture from afar, landing anywhere past the piece they capture
+        let board = russian("W:WK29:B15");
+        assert_eq!(destinations(board), vec![10, 7, 3]);
+        let board_p = board.take_action(action).unwrap();
+        assert_eq!(board_p.fen(), "25:B:WK8:B");
+        assert_eq!(board.action_effects(&action).unwrap().captured(), 1 << 14);
+
+        // unless they can only go on capturing from some of those squares
+        let board = russian("W:WK29:B22,10");
+        let generated = board.generate_all_actions();
+        assert!(generated.iter().all(|p| p.action().positions()[1] == 15));
+        assert_eq!(
+            board.validate_action(Action::from_movetext("29-18").unwrap()),
+            Err(ActionError::NeedMoreJumpingError)
+        );
+        assert!(board
+            .validate_action(Action::from_movetext("29-15-6").unwrap())
+            .is_ok());
     }
 
     #[test]
@@ -1099,6 +2728,245 @@ mod tests {
         assert_eq!(board_p.kings, 0x40000000);
     }
 
+    #[test]
+    fn variant_test() {
+        let movetexts = |board: &Bitboard| {
+            let mut out: Vec<_> = board
+                .generate_all_actions()
+                .iter()
+                .map(|p| p.action().movetext())
+                .collect();
+            out.sort();
+            out
+        };
+
+        // single pieces capturing backwards
+        let board = Bitboard::from_fen("B:W10:B14").unwrap();
+        assert_eq!(movetexts(&board), vec!["14-17", "14-18"]);
+        let action = Action::from_movetext("14-7").unwrap();
+        assert_eq!(
+            board.validate_action(action),
+            Err(ActionError::SinglePieceBackwardsError)
+        );
+        let board = board.with_variant(Variant::Russian);
+        assert_eq!(movetexts(&board), vec!["14-7"]);
+        assert_eq!(board.validate_action(action), Ok(()));
+
+        // single pieces cannot capture kings in italian
+        let board = Bitboard::from_fen("B:WK18:B14").unwrap();
+        assert_eq!(movetexts(&board), vec!["14-23"]);
+        let board = board.with_variant(Variant::Italian);
+        assert_eq!(movetexts(&board), vec!["14-17"]);
+        let action = Action::from_movetext("14-23").unwrap();
+        assert_eq!(
+            board.validate_action(action),
+            Err(ActionError::ManCapturingKingError)
+        );
+
+        // capturing the most pieces
+        let board = Bitboard::from_fen("B:W6,15,16:B1,12").unwrap();
+        assert_eq!(movetexts(&board), vec!["1-10-19", "12-19"]);
+        let board = board.with_variant(Variant::Italian);
+        assert_eq!(movetexts(&board), vec!["1-10-19"]);
+        let action = Action::from_movetext("12-19").unwrap();
+        assert_eq!(
+            board.validate_action(action),
+            Err(ActionError::CapturePrecedenceError)
+        );
+        let board = board.with_variant(Variant::Brazilian);
+        assert_eq!(movetexts(&board), vec!["1-10-19", "12-19-10"]);
+
+        // capturing with a king in italian
+        let board = Bitboard::from_fen("B:W6,16:BK1,12").unwrap();
+        assert_eq!(movetexts(&board), vec!["1-10", "12-19"]);
+        let board = board.with_variant(Variant::Italian);
+        assert_eq!(movetexts(&board), vec!["1-10"]);
+    }
+
+    #[test]
+    fn variant_fen_test() {
+        let board = Bitboard::from_fen("25:W:W18,24:B12,16").unwrap();
+        assert_eq!(board.variant(), Variant::Russian);
+        assert_eq!(board.fen(), "25:W:W18,24:B12,16");
+
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+        assert_eq!(board.variant(), Variant::American);
+        assert_eq!(board.with_variant(Variant::Italian).fen()[..3], *"22:");
+
+        assert!(Bitboard::from_fen("23:W:W18,24:B12,16").is_err());
+    }
+
+    #[test]
+    fn action_effects_test() {
+        let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
+        let effects = board
+            .action_effects(&Action::from_movetext("16-19").unwrap())
+            .unwrap();
+        assert_eq!(effects.source(), Square::from_pdn(16).unwrap());
+        assert_eq!(effects.destination(), Square::from_pdn(19).unwrap());
+        assert_eq!(effects.captured(), 0);
+        assert!(!effects.promoted());
+
+        let board = Bitboard::from_fen(TEST_BOARD_2).unwrap();
+        let effects = board
+            .action_effects(&Action::from_movetext("11-18").unwrap())
+            .unwrap();
+        assert_eq!(effects.captured(), 0x00004000);
+        assert!(!effects.promoted()); // already a king
+
+        let board = Bitboard::from_fen(TEST_BOARD_7).unwrap();
+        let effects = board
+            .action_effects(&Action::from_movetext("8-15-22-31").unwrap())
+            .unwrap();
+        assert_eq!(effects.captured(), 0x02020400);
+        let captured: Vec<_> = effects.captured_squares().map(|s| s.pdn()).collect();
+        assert_eq!(captured, vec![11, 18, 26]);
+        assert!(effects.promoted());
+
+        // jump that wraps around the edge of the board is not even an action
+        assert!(Action::from_movetext("4-13").is_err());
+    }
+
+    #[test]
+    fn pack_test() {
+        for fen in [
+            TEST_BOARD_1,
+            TEST_BOARD_2,
+            TEST_BOARD_7,
+            "giveaway:W:WK3:B29",
+        ] {
+            let board = Bitboard::from_fen(fen).unwrap();
+            assert_eq!(Bitboard::unpack(&board.pack()).unwrap(), board);
+            assert_eq!(Bitboard::from_base64(&board.to_base64()).unwrap(), board);
+            assert_eq!(board.to_base64().len(), 18);
+
+            // boards are stored packed and read from either form
+            let json = serde_json::to_string(&board).unwrap();
+            assert_eq!(json, format!("\"{}\"", board.to_base64()));
+            let from_fen: Bitboard = serde_json::from_str(&format!("\"{}\"", fen)).unwrap();
+            assert_eq!(from_fen, board);
+        }
+
+        let mut packed = Bitboard::default().pack();
+        packed[12] = 5 << 1; // no such variant
+        assert!(matches!(
+            Bitboard::unpack(&packed),
+            Err(ParseError::PackedBoardError)
+        ));
+        packed = Bitboard::default().pack();
+        packed[4] |= 1; // a white piece on a black one
+        assert!(matches!(
+            Bitboard::unpack(&packed),
+            Err(ParseError::PackedBoardError)
+        ));
+        assert!(Bitboard::from_base64("AA8AAAAA").is_err());
+        assert!(Bitboard::from_base64("B:W18:B14").is_err());
+    }
+
+    #[test]
+    fn actions_from_test() {
+        let square = |n| Square::from_pdn(n).unwrap();
+        let movetexts = |board: &Bitboard, n| {
+            board
+                .actions_from(square(n))
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let board = Bitboard::default();
+        assert_eq!(movetexts(&board, 9), ["9-13", "9-14"]);
+        assert!(movetexts(&board, 1).is_empty()); // blocked
+        assert!(movetexts(&board, 21).is_empty()); // not black's piece
+        assert!(movetexts(&board, 15).is_empty()); // empty
+
+        // only the pieces that can jump have to
+        let board = Bitboard::from_fen(TEST_BOARD_7).unwrap();
+        assert_eq!(movetexts(&board, 8), ["8-15-22-31"]);
+
+        let total: usize = Square::all().map(|s| board.actions_from(s).len()).sum();
+        assert_eq!(total, board.generate_all_actions().len());
+    }
+
+    #[test]
+    fn continue_jump_test() {
+        let squares = |numbers: &[u8]| -> Vec<_> {
+            numbers
+                .iter()
+                .map(|&n| Square::from_pdn(n).unwrap())
+                .collect()
+        };
+        let board = Bitboard::default();
+
+        // quiet moves are entered the same way as jumps
+        assert_eq!(
+            board.continue_jump(&[]),
+            JumpContinuation::Ambiguous(squares(&[9, 10, 11, 12]))
+        );
+        assert_eq!(
+            board.continue_jump(&[9]),
+            JumpContinuation::Ambiguous(squares(&[13, 14]))
+        );
+        assert_eq!(
+            board.continue_jump(&[12]),
+            JumpContinuation::Forced(Action::from_movetext("12-16").unwrap())
+        );
+        assert_eq!(
+            board.continue_jump(&[9, 13]),
+            JumpContinuation::Complete(Action::from_movetext("9-13").unwrap())
+        );
+        assert_eq!(board.continue_jump(&[9, 15]), JumpContinuation::Invalid);
+        assert_eq!(board.continue_jump(&[9, 13, 17]), JumpContinuation::Invalid);
+
+        // the jumper has to be picked when a capture is forced
+        let board = Bitboard::from_fen(TEST_BOARD_7).unwrap();
+        assert_eq!(
+            board.continue_jump(&[8, 15]),
+            JumpContinuation::Forced(Action::from_movetext("8-15-22-31").unwrap())
+        );
+    }
+
+    #[test]
+    fn null_move_test() {
+        let board = Bitboard::default();
+        let (passed, zobrist_diff) = board.null_move().unwrap();
+        assert_eq!(passed.turn(), White);
+        assert_eq!(board.zobrist_hash() ^ zobrist_diff, passed.zobrist_hash());
+
+        // white has to jump 19 so passing is not allowed
+        let board = Bitboard::from_fen(
+            "W:W21,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,6,7,8,9,10,12,19",
+        )
+        .unwrap();
+        assert!(board.null_move().is_none());
+
+        // too few pieces for white
+        let board = Bitboard::from_fen(TEST_BOARD_7).unwrap();
+        assert!(board.null_move().is_none());
+    }
+
+    #[test]
+    fn is_quiet_test() {
+        let board = Bitboard::from_fen("B:W32:B27,K14").unwrap();
+        let quiet: Vec<_> = board
+            .generate_all_actions()
+            .iter()
+            .filter(|p| board.is_quiet(p))
+            .map(|p| p.action().movetext())
+            .collect();
+
+        // both of 27's moves crown it
+        assert_eq!(quiet.len(), 4);
+        assert!(quiet.iter().all(|m| m.starts_with("14")));
+
+        // jumps are never quiet
+        let board = Bitboard::from_fen(TEST_BOARD_6).unwrap();
+        assert!(board
+            .generate_all_actions()
+            .iter()
+            .all(|p| !board.is_quiet(p)));
+    }
+
     #[test]
     fn zobrist_hashing_test() {
         // checks that the zobrist hashing is consistent with 2 different ways of making it
@@ -1127,4 +2995,77 @@ mod tests {
         assert_valid_zobrist(TEST_BOARD_2);
         assert_valid_zobrist(TEST_BOARD_3);
     }
+
+    #[test]
+    fn random_test() {
+        let mut prng = Prng::new(11);
+
+        // a small fuzz of move generation: random positions only lead to sound ones
+        for men in 0..=MAX_PIECES {
+            let spec = RandomSpec {
+                men_per_side: men,
+                kings_per_side: (MAX_PIECES - men) % 3,
+                turn: Color::White,
+            };
+            let board = match Bitboard::random(&mut prng, spec) {
+                Some(board) => board,
+                None => continue,
+            };
+
+            assert_eq!(board.kings.count_ones(), 2 * spec.kings_per_side);
+            assert_eq!(board.blacks & !board.kings & CROWNING_ROWS[0], 0);
+            assert_eq!(board.whites & !board.kings & CROWNING_ROWS[1], 0);
+            assert_eq!(Bitboard::from_fen(&board.fen()).unwrap(), board);
+
+            let actions = board.generate_all_actions();
+            assert!(!actions.is_empty());
+            for pair in actions.iter() {
+                let next = pair.state();
+                assert_eq!(next.blacks & next.whites, 0);
+                assert_eq!(next.kings & !(next.blacks | next.whites), 0);
+                assert_eq!(board.take_action(*pair.action()), Ok(*next));
+            }
+        }
+
+        let spec = RandomSpec {
+            men_per_side: 12,
+            kings_per_side: 1,
+            turn: Color::Black,
+        };
+        assert_eq!(Bitboard::random(&mut prng, spec), None);
+    }
+
+    fn arbitrary<T: for<'a> arbitrary::Arbitrary<'a>>(bytes: &[u8]) -> T {
+        T::arbitrary(&mut arbitrary::Unstructured::new(bytes)).unwrap()
+    }
+
+    // the masks have been the source of many bugs, so move generation is checked against
+    // the rest of the board on arbitrary positions. proptest shrinks failures to the
+    // smallest position it can find
+    proptest::proptest! {
+        #[test]
+        fn generated_actions_test(bytes in proptest::collection::vec(0..=u8::MAX, 0..32)) {
+            let board: Bitboard = arbitrary(&bytes);
+
+            for pair in board.generate_all_actions().iter() {
+                proptest::prop_assert_eq!(board.validate_action(*pair.action()), Ok(()));
+                proptest::prop_assert_eq!(board.take_action(*pair.action()), Ok(*pair.state()));
+            }
+        }
+
+        #[test]
+        fn arbitrary_action_test(bytes in proptest::collection::vec(0..=u8::MAX, 0..48)) {
+            let (board, action): (Bitboard, Action) = arbitrary(&bytes);
+
+            // only generated actions are legal
+            let generated = board.generate_all_actions().iter().any(|p| *p.action() == action);
+            proptest::prop_assert_eq!(board.validate_action(action).is_ok(), generated);
+        }
+
+        #[test]
+        fn fen_round_trip_test(bytes in proptest::collection::vec(0..=u8::MAX, 0..32)) {
+            let board: Bitboard = arbitrary(&bytes);
+            proptest::prop_assert_eq!(Bitboard::from_fen(&board.fen()).unwrap(), board);
+        }
+    }
 }
@@ -0,0 +1,354 @@
+// engine interface of the CheckerBoard gui. it loads engines as dynamic libraries and
+// calls `getmove` and `enginecommand` through the c abi. boards are passed as
+// `int board[8][8]` indexed by `[x][y]`, where `x` counts columns from the right edge
+// and `y` counts rows from the top (black's back rank). every value is a combination of
+// the color and piece flags below
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_double, c_int};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+
+use crate::board::{Action, Bitboard, Color, Square};
+use crate::search::{Engine, Score, SearchConstraint, Searchable};
+
+pub const CB_EMPTY: c_int = 0;
+pub const CB_WHITE: c_int = 1;
+pub const CB_BLACK: c_int = 2;
+pub const CB_MAN: c_int = 4;
+pub const CB_KING: c_int = 8;
+
+pub const CB_DRAW: c_int = 0;
+pub const CB_WIN: c_int = 1;
+pub const CB_LOSS: c_int = 2;
+pub const CB_UNKNOWN: c_int = 3;
+
+// bit of the `info` argument telling us a new game started
+const CB_RESET: c_int = 1;
+
+const REPLY_SIZE: usize = 1024;
+
+lazy_static! {
+    static ref ENGINE: Mutex<Engine<Bitboard>> = Mutex::new(Engine::new());
+}
+
+/// Represents a square as CheckerBoard's `(x, y)` coordinates
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Coor {
+    pub x: c_int,
+    pub y: c_int,
+}
+
+impl From<Square> for Coor {
+    fn from(square: Square) -> Self {
+        Coor {
+            x: 7 - square.col() as c_int,
+            y: square.row() as c_int,
+        }
+    }
+}
+
+/// Describes a move the way CheckerBoard expects it back from `getmove`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CBMove {
+    pub jumps: c_int,
+    pub newpiece: c_int,
+    pub oldpiece: c_int,
+    pub from: Coor,
+    pub to: Coor,
+    pub path: [Coor; 12],
+    pub del: [Coor; 12],
+    pub delpiece: [c_int; 12],
+}
+
+fn piece_at(board: &Bitboard, square: Square) -> c_int {
+    let mask = square.mask();
+
+    let color = match (board.blacks() & mask != 0, board.whites() & mask != 0) {
+        (true, _) => CB_BLACK,
+        (_, true) => CB_WHITE,
+        _ => return CB_EMPTY,
+    };
+
+    match board.kings() & mask != 0 {
+        true => color | CB_KING,
+        false => color | CB_MAN,
+    }
+}
+
+/// Converts CheckerBoard's board array and side to move to a bitboard. Returns `None` if
+/// a piece sits on a light square or the side to move is unknown.
+pub fn board_from_cb(board: &[[c_int; 8]; 8], color: c_int) -> Option<Bitboard> {
+    let turn = match color {
+        CB_BLACK => Color::Black,
+        CB_WHITE => Color::White,
+        _ => return None,
+    };
+
+    let mut blacks = 0;
+    let mut whites = 0;
+    let mut kings = 0;
+
+    for (x, column) in board.iter().enumerate() {
+        for (y, &piece) in column.iter().enumerate() {
+            if piece == CB_EMPTY {
+                continue;
+            }
+
+            let mask = Square::from_coords(y as u8, 7 - x as u8)?.mask();
+
+            match piece & (CB_BLACK | CB_WHITE) {
+                CB_BLACK => blacks |= mask,
+                CB_WHITE => whites |= mask,
+                _ => return None,
+            }
+            if piece & CB_KING != 0 {
+                kings |= mask;
+            }
+        }
+    }
+
+    Some(Bitboard::new(blacks, whites, kings, turn))
+}
+
+/// Converts a bitboard to CheckerBoard's board array
+pub fn board_to_cb(board: &Bitboard) -> [[c_int; 8]; 8] {
+    let mut out = [[CB_EMPTY; 8]; 8];
+
+    for square in Square::all() {
+        let Coor { x, y } = Coor::from(square);
+        out[x as usize][y as usize] = piece_at(board, square);
+    }
+
+    out
+}
+
+fn cb_move(board: &Bitboard, action: &Action) -> CBMove {
+    let effects = board.action_effects(action).unwrap();
+    let source = effects.source();
+
+    let oldpiece = piece_at(board, source);
+    let newpiece = match effects.promoted() {
+        true => (oldpiece & !CB_MAN) | CB_KING,
+        false => oldpiece,
+    };
+
+    let mut cb = CBMove {
+        jumps: action.jump_len() as c_int,
+        newpiece,
+        oldpiece,
+        from: Coor::from(source),
+        to: Coor::from(effects.destination()),
+        ..CBMove::default()
+    };
+
+    // intermediate landing squares of the capture. the last one is the destination
+    let mut curr = action.source();
+    for i in 0..action.jump_len() {
+        curr = action
+            .jump_direction(i)
+            .unwrap()
+            .relative_jump_from(curr)
+            .unwrap();
+        cb.path[i as usize] = Coor::from(Square::new(curr));
+    }
+
+    for (i, square) in effects.captured_squares().enumerate() {
+        cb.del[i] = Coor::from(square);
+        cb.delpiece[i] = piece_at(board, square);
+    }
+
+    cb
+}
+
+/// Copies a string into a buffer CheckerBoard handed us, truncating it if needed
+unsafe fn write_reply(reply: *mut c_char, text: &str) {
+    if reply.is_null() {
+        return;
+    }
+
+    let bytes = text.as_bytes();
+    let len = bytes.len().min(REPLY_SIZE - 1);
+
+    for (i, &b) in bytes[..len].iter().enumerate() {
+        *reply.add(i) = b as c_char;
+    }
+    *reply.add(len) = 0;
+}
+
+fn game_result(turn: Color, score: Score) -> c_int {
+    let winning = match turn {
+        Color::Black => Score::INFINITY,
+        Color::White => Score::NEG_INFINITY,
+    };
+    let losing = match turn {
+        Color::Black => Score::NEG_INFINITY,
+        Color::White => Score::INFINITY,
+    };
+
+    if score == winning {
+        CB_WIN
+    } else if score == losing {
+        CB_LOSS
+    } else {
+        CB_UNKNOWN
+    }
+}
+
+/// Searches the position CheckerBoard hands us and plays the best move on its board.
+///
+/// # Safety
+///
+/// Every pointer has to be valid for the duration of the call (`str` for 1024 bytes)
+/// and `playnow` has to stay readable while we search.
+#[no_mangle]
+pub unsafe extern "system" fn getmove(
+    board: *mut [[c_int; 8]; 8],
+    color: c_int,
+    maxtime: c_double,
+    str: *mut c_char,
+    playnow: *const c_int,
+    info: c_int,
+    _moreinfo: c_int,
+    cbmove: *mut CBMove,
+) -> c_int {
+    let state = match board_from_cb(&*board, color) {
+        Some(state) => state,
+        None => {
+            write_reply(str, "invalid board");
+            return CB_UNKNOWN;
+        }
+    };
+
+    let mut engine = ENGINE.lock().unwrap();
+    if info & CB_RESET != 0 {
+        engine.reset();
+    }
+
+    let time = Duration::from_secs_f64(maxtime.max(0.));
+    let constraint = SearchConstraint::Time(time);
+
+    // the gui asks us to move right away by setting `playnow`
+    let stop = AtomicBool::new(false);
+    let done = AtomicBool::new(false);
+    let playnow = playnow as usize;
+
+    let results = thread::scope(|s| {
+        s.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                if playnow != 0 && *(playnow as *const c_int) != 0 {
+                    stop.store(true, Ordering::Relaxed);
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        let results = engine.search_with(&state, &constraint, &stop, |info| {
+            if let Some(best) = info.best() {
+                let text = format!(
+                    "depth {} best {} score {}",
+                    info.depth(),
+                    best.action(),
+                    best.score()
+                );
+                write_reply(str, &text);
+            }
+        });
+        done.store(true, Ordering::Relaxed);
+        results
+    });
+
+    let best = match results.best() {
+        Some(best) => best,
+        None => {
+            write_reply(str, "no legal moves");
+            return CB_LOSS;
+        }
+    };
+
+    let action = best.action();
+    let next = state.take_action(action).unwrap();
+
+    if !cbmove.is_null() {
+        *cbmove = cb_move(&state, &action);
+    }
+    *board = board_to_cb(&next);
+
+    game_result(state.turn(), best.score())
+}
+
+/// Answers CheckerBoard's queries and settings. Returns 1 if the command was understood.
+///
+/// # Safety
+///
+/// `str` has to be a valid C string and `reply` valid for 1024 bytes.
+#[no_mangle]
+pub unsafe extern "system" fn enginecommand(str: *const c_char, reply: *mut c_char) -> c_int {
+    let command = match str.is_null() {
+        true => "",
+        false => CStr::from_ptr(str).to_str().unwrap_or(""),
+    };
+    let words: Vec<_> = command.split_whitespace().collect();
+
+    let answer = match words.as_slice() {
+        ["name"] => format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        ["about"] => format!(
+            "{} {} by {}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_AUTHORS")
+        ),
+        ["help"] => "".to_string(),
+        ["get", "protocolversion"] => "2".to_string(),
+        ["get", "gametype"] => "21".to_string(),
+        _ => {
+            write_reply(reply, "?");
+            return 0;
+        }
+    };
+
+    write_reply(reply, &answer);
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn board_conversion_test() {
+        let board = Bitboard::from_fen("W:W9,K11,19,K26,27,30:B15,22,25,K32").unwrap();
+        let cb = board_to_cb(&board);
+
+        // square 1 is on the top row next to the right corner
+        assert_eq!(
+            Coor::from(Square::from_pdn(1).unwrap()),
+            Coor { x: 6, y: 0 }
+        );
+        assert_eq!(cb[6][2], CB_WHITE | CB_MAN); // square 9
+        assert_eq!(cb[1][7], CB_BLACK | CB_KING); // square 32
+
+        assert_eq!(board_from_cb(&cb, CB_WHITE), Some(board));
+        assert_eq!(board_from_cb(&cb, 0), None);
+    }
+
+    #[test]
+    fn cb_move_test() {
+        let board = Bitboard::from_fen("B:W11,18,26,27:B8").unwrap();
+        let action = Action::from_movetext("8-15-22-31").unwrap();
+        let cb = cb_move(&board, &action);
+
+        assert_eq!(cb.jumps, 3);
+        assert_eq!(cb.oldpiece, CB_BLACK | CB_MAN);
+        assert_eq!(cb.newpiece, CB_BLACK | CB_KING);
+        assert_eq!(cb.to, Coor::from(Square::from_pdn(31).unwrap()));
+        assert_eq!(cb.path[2], cb.to);
+        assert_eq!(cb.del[0], Coor::from(Square::from_pdn(11).unwrap()));
+    }
+}
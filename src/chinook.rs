@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::board::{ActionType, Bitboard, Color, Variant};
+use crate::search::{EndgameTable, Searchable};
+
+pub use crate::search::Wdl;
+
+// the endgame databases of chinook, which cake and checkerboard read as well, hold the
+// result of every position of american checkers with up to eight pieces. the positions
+// of `n` pieces are kept in a pair of files, `DBn` and its index `DBn.idx`.
+//
+// positions are grouped into subdatabases by the men and kings of each side, the rank of
+// the leading man of each side, counted from its own back rank, and the side to move.
+// the index is text, an entry per subdatabase:
+//
+//   BASE<bm>,<bk>,<wm>,<wk>,<bmrank>,<wmrank>,<b|w>:
+//
+// followed by `+`, `=` or `-` if every position of the subdatabase is won, drawn or lost
+// for the side to move, or by the block of the data file the subdatabase starts in and
+// the byte of the block it starts at, as `block/byte`, and then the index of the first
+// position of every further block it takes up.
+//
+// the data file is made of blocks of 1024 bytes, which are read on their own. a byte
+// below 81 holds the values of four positions in base 3, the first in the lowest digit,
+// where 0 is a win, 1 a loss and 2 a draw. a byte from 81 up is a run of a single value:
+// `81 + 58 * value + n` stands for `4 * (n + 1)` positions.
+//
+// the positions of a subdatabase are numbered by the men of black, then the men of
+// white, then the kings of black and then the kings of white. the men of a side are
+// numbered by the square of the leading man within its rank and then by the squares of
+// the others behind it, both counted from the side's own back rank. the kings are
+// numbered by the squares they stand on among the ones the men leave empty.
+//
+// positions where the side to move can capture are left out, their values are whatever
+// compresses best, so they are searched instead of looked up.
+
+/// The most pieces of any database
+pub const MAX_PIECES: usize = 8;
+
+const BLOCK_SIZE: usize = 1024;
+// the first byte that stands for a run, and the number of run lengths
+const RUN: u8 = 81;
+const RUN_LENGTHS: u8 = 58;
+
+const BINOMIALS: [[usize; MAX_PIECES + 1]; 33] = binomials();
+
+const fn binomials() -> [[usize; MAX_PIECES + 1]; 33] {
+    let mut table = [[0; MAX_PIECES + 1]; 33];
+    let mut n = 0;
+    while n < 33 {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= MAX_PIECES && n > 0 {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+// the index of a set of squares among every set of as many squares
+fn rank_of(mut mask: u32) -> usize {
+    let mut rank = 0;
+    let mut i = 1;
+    while mask != 0 {
+        rank += BINOMIALS[mask.trailing_zeros() as usize][i];
+        mask &= mask - 1;
+        i += 1;
+    }
+    rank
+}
+
+// the number of ways to place `count` men of a side with the leading one on `rank`
+fn men_placements(count: u8, rank: u8) -> usize {
+    match count {
+        0 => 1,
+        _ => (4 * rank..4 * rank + 4)
+            .map(|square| BINOMIALS[usize::from(square)][usize::from(count - 1)])
+            .sum(),
+    }
+}
+
+// the index of the men of a side, with the squares counted from its own back rank
+fn men_index(men: u32) -> usize {
+    if men == 0 {
+        return 0;
+    }
+    let leader = 31 - men.leading_zeros();
+    let count = men.count_ones() as usize;
+    let before: usize = (leader & !3..leader)
+        .map(|square| BINOMIALS[square as usize][count - 1])
+        .sum();
+    before + rank_of(men & !(1 << leader))
+}
+
+// the index of the kings of a side among the squares of `free`
+fn kings_index(kings: u32, free: u32) -> usize {
+    let mut squeezed = 0;
+    let mut rest = kings;
+    while rest != 0 {
+        let square = rest.trailing_zeros();
+        squeezed |= 1 << (free & ((1 << square) - 1)).count_ones();
+        rest &= rest - 1;
+    }
+    rank_of(squeezed)
+}
+
+/// Represents a subdatabase: the men and kings of each side, the ranks of their leading
+/// men and the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Key {
+    counts: [u8; 4], // black men, black kings, white men and white kings
+    ranks: [u8; 2],  // the ranks of the leading men of black and white
+    turn: Color,
+}
+
+impl Key {
+    // the men of each side, with the squares counted from its own back rank
+    fn men(board: &Bitboard) -> [u32; 2] {
+        let men = !board.kings();
+        [board.blacks() & men, (board.whites() & men).reverse_bits()]
+    }
+
+    fn of(board: &Bitboard) -> Self {
+        let men = Self::men(board);
+        let rank = |men: u32| match men {
+            0 => 0,
+            _ => ((31 - men.leading_zeros()) / 4) as u8,
+        };
+        let count = |mask: u32| mask.count_ones() as u8;
+        Key {
+            counts: [
+                count(men[0]),
+                count(board.blacks() & board.kings()),
+                count(men[1]),
+                count(board.whites() & board.kings()),
+            ],
+            ranks: [rank(men[0]), rank(men[1])],
+            turn: board.turn(),
+        }
+    }
+
+    fn pieces(&self) -> usize {
+        self.counts.iter().map(|&count| usize::from(count)).sum()
+    }
+
+    /// Returns the number of positions of the subdatabase
+    fn positions(&self) -> usize {
+        let [black_men, black_kings, white_men, white_kings] = self.counts;
+        let free = 32 - usize::from(black_men + white_men);
+        men_placements(black_men, self.ranks[0])
+            * men_placements(white_men, self.ranks[1])
+            * BINOMIALS[free][usize::from(black_kings)]
+            * BINOMIALS[free - usize::from(black_kings)][usize::from(white_kings)]
+    }
+
+    /// Returns the index of a board of the subdatabase
+    fn index(&self, board: &Bitboard) -> usize {
+        let [black_men, black_kings, white_men, white_kings] = self.counts;
+        let men = Self::men(board);
+        let black = board.blacks() & board.kings();
+        let empty = !(board.blacks() | board.whites()) | board.kings();
+        let black_index = kings_index(black, empty);
+        let white_index = kings_index(board.whites() & board.kings(), empty & !black);
+
+        let free = 32 - usize::from(black_men + white_men);
+        let mut index = men_index(men[0]);
+        index = index * men_placements(white_men, self.ranks[1]) + men_index(men[1]);
+        index = index * BINOMIALS[free][usize::from(black_kings)] + black_index;
+        index * BINOMIALS[free - usize::from(black_kings)][usize::from(white_kings)] + white_index
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    // every position of the subdatabase has the same value
+    Uniform(Wdl),
+    Blocks {
+        file: usize,
+        block: usize,
+        byte: usize,
+        firsts: Vec<usize>, // the first position of every further block
+    },
+}
+
+/// Represents the endgame databases of Chinook, as read by Cake and CheckerBoard, mapped
+/// into memory
+///
+/// # Examples
+///
+/// ```no_run
+/// use muskox::board::Bitboard;
+/// use muskox::chinook::{Database, Wdl};
+/// use std::path::Path;
+///
+/// let database = Database::open(Path::new("db")).unwrap();
+/// let board = Bitboard::from_fen("B:WK3:BK14").unwrap();
+/// assert_eq!(database.probe(&board), Some(Wdl::Win));
+/// ```
+#[derive(Debug)]
+pub struct Database {
+    entries: HashMap<Key, Entry>,
+    files: Vec<Mmap>,
+    pieces: usize, // the most pieces of any database
+}
+
+impl Database {
+    /// Maps the databases of 2 pieces and up in the directory at `dir` into memory, up to
+    /// the first number of pieces whose files are missing
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        let mut database = Database {
+            entries: HashMap::new(),
+            files: Vec::new(),
+            pieces: 0,
+        };
+        for pieces in 2..=MAX_PIECES {
+            let path = dir.join(format!("DB{}", pieces));
+            let index = match fs::read_to_string(path.with_extension("idx")) {
+                Ok(index) => index,
+                Err(err) if err.kind() == io::ErrorKind::NotFound && pieces > 2 => break,
+                Err(err) => return Err(err),
+            };
+
+            // database files are only ever replaced whole
+            let map = unsafe { Mmap::map(&File::open(&path)?)? };
+            let entries =
+                parse_index(&index, database.files.len(), map.len()).map_err(|message| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}.idx: {}", path.display(), message),
+                    )
+                })?;
+            database.entries.extend(entries);
+            database.files.push(map);
+            database.pieces = pieces;
+        }
+        Ok(database)
+    }
+
+    /// Returns the number of pieces of the largest database
+    pub fn pieces(&self) -> usize {
+        self.pieces
+    }
+
+    /// Returns the result of a board of american checkers for the side to move, or `None`
+    /// if the databases do not hold it. Positions where the side to move can capture are
+    /// never held.
+    pub fn probe(&self, board: &Bitboard) -> Option<Wdl> {
+        let pieces = (board.blacks() | board.whites()).count_ones() as usize;
+        if board.variant() != Variant::American || pieces > self.pieces || pieces < 2 {
+            return None;
+        }
+        let capture = board.successors().first()?.action().action_type() == ActionType::Jump;
+        if capture {
+            return None;
+        }
+
+        // a subdatabase may only be kept for one of a position and its mirror
+        [*board, board.mirrored()].iter().find_map(|board| {
+            let key = Key::of(board);
+            let entry = self.entries.get(&key)?;
+            self.value(entry, key.index(board))
+        })
+    }
+
+    fn value(&self, entry: &Entry, index: usize) -> Option<Wdl> {
+        let (file, block, byte, firsts) = match entry {
+            Entry::Uniform(wdl) => return Some(*wdl),
+            Entry::Blocks {
+                file,
+                block,
+                byte,
+                firsts,
+            } => (*file, *block, *byte, firsts),
+        };
+
+        // find the block the position is in, and the first position of the block
+        let further = firsts.partition_point(|&first| first <= index);
+        let (start, mut position) = match further {
+            0 => ((block * BLOCK_SIZE + byte), 0),
+            _ => ((block + further) * BLOCK_SIZE, firsts[further - 1]),
+        };
+        let end = ((start / BLOCK_SIZE + 1) * BLOCK_SIZE).min(self.files[file].len());
+
+        for &byte in &self.files[file][start..end] {
+            let (digit, length) = match byte {
+                byte if byte < RUN => {
+                    let offset = (index - position) as u32;
+                    (byte / 3u8.pow(offset.min(3)) % 3, 4)
+                }
+                byte => {
+                    let code = byte - RUN;
+                    (
+                        code / RUN_LENGTHS,
+                        4 * (usize::from(code % RUN_LENGTHS) + 1),
+                    )
+                }
+            };
+            if index < position + length {
+                return match digit {
+                    0 => Some(Wdl::Win),
+                    1 => Some(Wdl::Loss),
+                    2 => Some(Wdl::Draw),
+                    _ => None,
+                };
+            }
+            position += length;
+        }
+        None
+    }
+}
+
+impl EndgameTable<Bitboard> for Database {
+    fn probe(&self, board: &Bitboard) -> Option<Wdl> {
+        Database::probe(self, board)
+    }
+}
+
+// the entries of the index of the data file `file`, which is `size` bytes long
+fn parse_index(index: &str, file: usize, size: usize) -> Result<Vec<(Key, Entry)>, String> {
+    let mut entries: Vec<(Key, Option<Entry>)> = Vec::new();
+    for word in index.split_whitespace() {
+        if let Some(base) = word.strip_prefix("BASE") {
+            entries.push((parse_key(base)?, None));
+            continue;
+        }
+        let (key, entry) = entries
+            .last_mut()
+            .ok_or_else(|| format!("{} before any subdatabase", word))?;
+
+        let parse = |text: &str| text.parse::<usize>().ok();
+        match (entry.as_mut(), word) {
+            (None, "+") => *entry = Some(Entry::Uniform(Wdl::Win)),
+            (None, "=") => *entry = Some(Entry::Uniform(Wdl::Draw)),
+            (None, "-") => *entry = Some(Entry::Uniform(Wdl::Loss)),
+            (None, start) => {
+                let (block, byte) = start
+                    .split_once('/')
+                    .and_then(|(block, byte)| Some((parse(block)?, parse(byte)?)))
+                    .filter(|&(block, byte)| byte < BLOCK_SIZE && block * BLOCK_SIZE < size)
+                    .ok_or_else(|| format!("bad start {}", start))?;
+                *entry = Some(Entry::Blocks {
+                    file,
+                    block,
+                    byte,
+                    firsts: Vec::new(),
+                });
+            }
+            (Some(Entry::Blocks { block, firsts, .. }), first) => {
+                // every further block starts further on, and lies within the file
+                let last = firsts.last().copied().unwrap_or(0);
+                let next = (*block + firsts.len() + 1) * BLOCK_SIZE;
+                let first = parse(first)
+                    .filter(|&first| last < first && first < key.positions() && next < size)
+                    .ok_or_else(|| format!("bad block start {}", first))?;
+                firsts.push(first);
+            }
+            (Some(Entry::Uniform(_)), word) => return Err(format!("{} after a value", word)),
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|(key, entry)| match entry {
+            Some(entry) => Ok((key, entry)),
+            None => Err(format!(
+                "no value or start for a subdatabase of {:?}",
+                key.counts
+            )),
+        })
+        .collect()
+}
+
+// a key written as `bm,bk,wm,wk,bmrank,wmrank,color:`
+fn parse_key(text: &str) -> Result<Key, String> {
+    let bad = || format!("bad subdatabase BASE{}", text);
+    let fields: Vec<&str> = text.strip_suffix(':').ok_or_else(bad)?.split(',').collect();
+    let numbers = fields[..fields.len() - 1]
+        .iter()
+        .map(|field| field.parse::<u8>().ok().filter(|&number| number < 8))
+        .collect::<Option<Vec<u8>>>()
+        .filter(|numbers| numbers.len() == 6)
+        .ok_or_else(bad)?;
+    let turn = match fields[fields.len() - 1] {
+        "b" => Color::Black,
+        "w" => Color::White,
+        _ => return Err(bad()),
+    };
+
+    let key = Key {
+        counts: [numbers[0], numbers[1], numbers[2], numbers[3]],
+        ranks: [numbers[4], numbers[5]],
+        turn,
+    };
+    match key.pieces() <= MAX_PIECES {
+        true => Ok(key),
+        false => Err(bad()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::egtb::{Material, Tablebase};
+    use crate::search::{Engine, Score, SearchConstraint};
+
+    // writes the databases of up to `pieces` pieces into `dir`, taking the values from a
+    // tablebase, and keeps only one of every subdatabase and its mirror
+    fn write(dir: &Path, tablebase: &Tablebase, pieces: usize) {
+        let mut subdatabases: HashMap<Key, Vec<Wdl>> = HashMap::new();
+        for material in Material::up_to(pieces) {
+            for board in (0..material.positions()).filter_map(|index| material.board(index)) {
+                let key = Key::of(&board);
+                if key.counts[..2] < key.counts[2..] {
+                    continue;
+                }
+                let values = subdatabases
+                    .entry(key)
+                    .or_insert_with(|| vec![Wdl::Draw; key.positions()]);
+                values[key.index(&board)] = tablebase.probe(&board).unwrap();
+            }
+        }
+
+        for pieces in 2..=pieces {
+            let mut keys: Vec<&Key> = subdatabases
+                .keys()
+                .filter(|key| key.pieces() == pieces)
+                .collect();
+            keys.sort_by_key(|key| (key.counts, key.ranks, key.turn == Color::White));
+
+            let (mut index, mut data) = (String::new(), Vec::new());
+            for key in keys {
+                let [bm, bk, wm, wk] = key.counts;
+                let turn = if key.turn == Color::Black { 'b' } else { 'w' };
+                index += &format!(
+                    "BASE{},{},{},{},{},{},{}:",
+                    bm, bk, wm, wk, key.ranks[0], key.ranks[1], turn
+                );
+                let values = &subdatabases[key];
+                if values.iter().all(|&value| value == values[0]) {
+                    let symbol = ["+", "-", "="][digit(values[0]) as usize];
+                    index += &format!(" {}\n", symbol);
+                    continue;
+                }
+
+                index += &format!(" {}/{}", data.len() / BLOCK_SIZE, data.len() % BLOCK_SIZE);
+                let mut position = 0;
+                while position < values.len() {
+                    if data.len() % BLOCK_SIZE == 0 && position > 0 {
+                        index += &format!(" {}", position);
+                    }
+                    let value = values[position];
+                    let run = values[position..]
+                        .iter()
+                        .take(4 * usize::from(RUN_LENGTHS))
+                        .take_while(|&&other| other == value)
+                        .count();
+                    if run >= 8 {
+                        let n = run / 4 - 1;
+                        data.push(RUN + RUN_LENGTHS * digit(value) + n as u8);
+                        position += 4 * (n + 1);
+                    } else {
+                        let byte = (0..4).rev().fold(0, |byte, i| {
+                            let value = values.get(position + i).copied();
+                            byte * 3 + value.map_or(0, digit)
+                        });
+                        data.push(byte);
+                        position += 4;
+                    }
+                }
+                index += "\n";
+            }
+            fs::write(dir.join(format!("DB{}", pieces)), data).unwrap();
+            fs::write(dir.join(format!("DB{}.idx", pieces)), index).unwrap();
+        }
+    }
+
+    fn digit(value: Wdl) -> u8 {
+        match value {
+            Wdl::Win => 0,
+            Wdl::Loss => 1,
+            Wdl::Draw => 2,
+        }
+    }
+
+    #[test]
+    fn chinook_test() {
+        let dir = std::env::temp_dir().join("muskox_chinook_test");
+        let _ = fs::remove_dir_all(&dir);
+        let tablebase = Tablebase::build(3, &dir.join("egtb"), |_| ()).unwrap();
+        write(&dir, &tablebase, 3);
+        let database = Database::open(&dir).unwrap();
+        assert_eq!(database.pieces(), 3);
+        // the subdatabases of three pieces span blocks
+        assert!(database.entries.values().any(|entry| match entry {
+            Entry::Blocks { firsts, .. } => !firsts.is_empty(),
+            _ => false,
+        }));
+
+        // every position agrees with the tablebase, except for captures and positions
+        // without moves, which are not held
+        for material in Material::up_to(3) {
+            for board in (0..material.positions()).filter_map(|index| material.board(index)) {
+                let expected = match board.successors().first() {
+                    Some(pair) if pair.action().action_type() == ActionType::Move => {
+                        tablebase.probe(&board)
+                    }
+                    _ => None,
+                };
+                assert_eq!(database.probe(&board), expected, "{}", board.fen());
+            }
+        }
+        assert_eq!(database.probe(&Bitboard::default()), None);
+
+        // wins are scored in the band of tablebase wins
+        let engine = Engine::<Bitboard>::builder()
+            .threads(1)
+            .tablebase(Database::open(&dir).unwrap())
+            .build()
+            .unwrap();
+        let board = Bitboard::from_fen("B:WK3:BK14").unwrap();
+        let result = engine.search(&board, &SearchConstraint::Depth(2));
+        let score = result.best().unwrap().score();
+        assert!(score.is_tablebase() && score > Score::DRAW);
+
+        // damaged indices are refused, as are starts past the end of the data file
+        fs::write(dir.join("DB3.idx"), "BASE1,0,1,0,9,0,b: +").unwrap();
+        assert!(Database::open(&dir).is_err());
+        fs::write(dir.join("DB3.idx"), "BASE1,0,1,0,0,0,b: 12/0").unwrap();
+        assert!(Database::open(&dir).is_err());
+        fs::write(dir.join("DB3.idx"), "BASE1,0,1,0,0,0,b:").unwrap();
+        assert!(Database::open(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::AnnotatedGame;
+use crate::bitboard::serialize_fen;
+use crate::board::{Bitboard, Color, Prng, PACKED_SIZE};
+use crate::search::{Score, Searchable, Winner};
+
+// training data for tuning the evaluation and for training models. every position of a
+// game becomes a sample holding the score a search gave it and how the game ended, and
+// samples are written as csv or json lines for inspecting them, or in a compact binary
+// format for the millions of positions training takes. the binary format is a header
+// followed by records of RECORD_SIZE bytes:
+//
+//   board                   the 13 bytes of Bitboard::pack: three u32 masks, little
+//                           endian, then 1 when white is to move plus the variant
+//                           shifted by one
+//   score                   f32, little endian, in pieces from black's point of view
+//   result                  u8, 0 when white won, 1 for a draw and 2 when black won
+
+const MAGIC: &[u8; 4] = b"MKDS";
+/// The version of the binary format this build reads and writes
+pub const BINARY_VERSION: u8 = 1;
+const RECORD_SIZE: usize = 18;
+
+/// Represents a position along with its score and the result of the game it was played in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    #[serde(rename = "fen", serialize_with = "serialize_fen")]
+    board: Bitboard,
+    score: Score,
+    result: f32,
+}
+
+impl Sample {
+    /// Creates a sample of `board`, which `score` was given to in a game `winner` won
+    pub fn new(board: Bitboard, score: Score, winner: &Winner<Bitboard>) -> Self {
+        let result = match winner {
+            Winner::Player(Color::Black) => 1.,
+            Winner::Player(Color::White) => 0.,
+            Winner::Draw => 0.5,
+        };
+        Sample {
+            board,
+            score,
+            result,
+        }
+    }
+
+    #[inline]
+    pub fn board(&self) -> Bitboard {
+        self.board
+    }
+
+    /// Returns the score of the position from black's point of view
+    #[inline]
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Returns the result of the game from black's point of view: 1 if black won, 0.5 for
+    /// a draw and 0 if white won
+    #[inline]
+    pub fn result(&self) -> f32 {
+        self.result
+    }
+}
+
+/// Returns a sample of every position of an annotated game before each of its moves,
+/// scored with the best move of the position. The game was won by `winner`, which games
+/// that ended by resignation or adjudication cannot tell themselves.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::analysis;
+/// use muskox::dataset;
+/// use muskox::pdn;
+/// use muskox::search::{SearchConstraint, Winner};
+///
+/// let game = &pdn::parse_games("1. 11-15 23-19 2. 8-11 *").unwrap()[0];
+/// let annotated = analysis::annotate(game, &SearchConstraint::Depth(2));
+/// let samples = dataset::samples(&annotated, &Winner::Draw);
+/// assert_eq!(samples.len(), 3);
+/// assert_eq!(samples[0].board(), game.start());
+/// assert_eq!(samples[0].result(), 0.5);
+/// ```
+pub fn samples(annotated: &AnnotatedGame, winner: &Winner<Bitboard>) -> Vec<Sample> {
+    let game = annotated.game();
+    let mut board = game.start();
+
+    annotated
+        .annotations()
+        .iter()
+        .map(|annotation| {
+            let sample = Sample::new(board, annotation.best(), winner);
+            board = board.take_action(annotation.action()).unwrap();
+            sample
+        })
+        .collect()
+}
+
+/// Writes samples as csv with a header, one `fen,score,result` line per sample
+pub fn write_csv<W: Write>(samples: &[Sample], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "fen,score,result")?;
+    for sample in samples {
+        writeln!(
+            writer,
+            "{},{},{}",
+            sample.board.fen(),
+            sample.score,
+            sample.result
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes samples as json lines, one object with a `fen`, a `score` and a `result` per
+/// line. Decisive scores are written as `"inf"` and `"-inf"`.
+pub fn write_jsonl<W: Write>(samples: &[Sample], mut writer: W) -> io::Result<()> {
+    for sample in samples {
+        serde_json::to_writer(&mut writer, sample)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Writes samples in the binary format
+pub fn write_binary<W: Write>(samples: &[Sample], mut writer: W) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[BINARY_VERSION])?;
+
+    for sample in samples {
+        let mut record = [0; RECORD_SIZE];
+        record[..PACKED_SIZE].copy_from_slice(&sample.board.pack());
+        record[13..17].copy_from_slice(&f32::from(sample.score).to_le_bytes());
+        record[17] = (sample.result * 2.) as u8;
+        writer.write_all(&record)?;
+    }
+    Ok(())
+}
+
+/// Reads every sample of the binary format. Fails with `InvalidData` if the data is not
+/// in the format, or in another version of it.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Bitboard;
+/// use muskox::dataset::{self, Sample};
+/// use muskox::search::{Score, Winner};
+///
+/// let samples = [Sample::new(Bitboard::default(), Score::from(0.25), &Winner::Draw)];
+/// let mut data = Vec::new();
+/// dataset::write_binary(&samples, &mut data).unwrap();
+/// assert_eq!(dataset::read_binary(&data[..]).unwrap(), samples);
+/// ```
+pub fn read_binary<R: Read>(mut reader: R) -> io::Result<Vec<Sample>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut header = [0; 5];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != MAGIC {
+        return Err(invalid("not a muskox dataset"));
+    }
+    if header[4] != BINARY_VERSION {
+        return Err(invalid("unsupported dataset version"));
+    }
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if data.len() % RECORD_SIZE != 0 {
+        return Err(invalid("truncated record"));
+    }
+
+    data.chunks(RECORD_SIZE)
+        .map(|record| {
+            let packed = record[..PACKED_SIZE].try_into().unwrap();
+            let board = Bitboard::unpack(&packed).map_err(|_| invalid("invalid record"))?;
+            let result = record[17];
+            if result > 2 {
+                return Err(invalid("invalid record"));
+            }
+
+            let score = [record[13], record[14], record[15], record[16]];
+            Ok(Sample {
+                board,
+                score: Score::from(f32::from_le_bytes(score)),
+                result: f32::from(result) / 2.,
+            })
+        })
+        .collect()
+}
+
+/// Shuffles samples in place, so that positions of the same game do not follow each other
+pub fn shuffle(samples: &mut [Sample], prng: &mut Prng) {
+    for i in (1..samples.len()).rev() {
+        let j = (prng.rand64() % (i as u64 + 1)) as usize;
+        samples.swap(i, j);
+    }
+}
+
+/// Splits samples into a training set and a validation set of about `validation` (from 0
+/// to 1) of them. The validation set is taken from the end, so samples are best shuffled
+/// first.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Bitboard;
+/// use muskox::dataset::{self, Sample};
+/// use muskox::search::{Score, Winner};
+///
+/// let samples = vec![Sample::new(Bitboard::default(), Score::DRAW, &Winner::Draw); 10];
+/// let (training, validation) = dataset::split(&samples, 0.2);
+/// assert_eq!((training.len(), validation.len()), (8, 2));
+/// ```
+pub fn split(samples: &[Sample], validation: f64) -> (&[Sample], &[Sample]) {
+    let validation = (samples.len() as f64 * validation.clamp(0., 1.)).round() as usize;
+    samples.split_at(samples.len() - validation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dataset_test() {
+        let samples = vec![
+            Sample::new(Bitboard::default(), Score::from(0.5), &Winner::Draw),
+            Sample::new(
+                Bitboard::from_fen("W:WK18,30:B14,K1").unwrap(),
+                Score::INFINITY,
+                &Winner::Player(Color::Black),
+            ),
+            Sample::new(
+                Bitboard::from_fen("russian:B:W18:B14").unwrap(),
+                Score::from(-1.),
+                &Winner::Player(Color::White),
+            ),
+        ];
+
+        let mut data = Vec::new();
+        write_binary(&samples, &mut data).unwrap();
+        assert_eq!(data.len(), 5 + samples.len() * RECORD_SIZE);
+        assert_eq!(read_binary(&data[..]).unwrap(), samples);
+        assert!(read_binary(&data[..data.len() - 1]).is_err());
+        data[4] = BINARY_VERSION + 1;
+        assert!(read_binary(&data[..]).is_err());
+
+        let mut csv = Vec::new();
+        write_csv(&samples, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], "fen,score,result");
+        assert_eq!(lines[2], "W:WK18,30:BK1,14,inf,1");
+
+        let mut jsonl = Vec::new();
+        write_jsonl(&samples, &mut jsonl).unwrap();
+        let read: Vec<Sample> = String::from_utf8(jsonl)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(read, samples);
+
+        // shuffling keeps every sample
+        let mut shuffled = samples.clone();
+        shuffle(&mut shuffled, &mut Prng::new(3));
+        for sample in &samples {
+            assert!(shuffled.contains(sample));
+        }
+        let (training, validation) = split(&shuffled, 0.5);
+        assert_eq!((training.len(), validation.len()), (1, 2));
+    }
+}
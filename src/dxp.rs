@@ -0,0 +1,500 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Instant;
+
+use crate::board::{Action, Bitboard, Color, Square};
+use crate::error::ParseError;
+use crate::parse;
+use crate::players::{self, Clock, EnginePlayer, Player};
+use crate::search::{Engine, SearchConstraint, Searchable, Winner};
+
+// implements the DamExchange protocol (dxp) used by draughts programs to play each other
+// over tcp. messages are fixed width ascii records terminated by a null character. dxp
+// was made for the 10x10 board, so on our board squares are numbered 1 to 32 and
+// positions have 32 squares
+
+const VERSION: u8 = 1;
+const NAME_WIDTH: usize = 32;
+
+/// Represents why a game ended, from the point of view of the player sending it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EndReason {
+    Unknown,
+    Loss,
+    Draw,
+    Win,
+}
+
+impl EndReason {
+    fn code(&self) -> u8 {
+        match self {
+            EndReason::Unknown => 0,
+            EndReason::Loss => 1,
+            EndReason::Draw => 2,
+            EndReason::Win => 3,
+        }
+    }
+}
+
+/// Represents a single DamExchange message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Asks the other side for a game. The follower is the color the receiver plays.
+    GameRequest {
+        name: String,
+        follower: Color,
+        minutes: u16,
+        moves: u16,
+        position: Option<Bitboard>,
+    },
+    /// Answers a game request. A code of 0 accepts the game.
+    GameAccept {
+        name: String,
+        code: u8,
+    },
+    /// A move along with the seconds spent thinking about it
+    Move {
+        seconds: u16,
+        source: Square,
+        destination: Square,
+        captured: Vec<Square>,
+    },
+    /// Ends the game. `stop` asks not to play another game.
+    GameEnd {
+        reason: EndReason,
+        stop: bool,
+    },
+    Chat(String),
+    /// Asks to take back moves up to a move number
+    BackRequest {
+        move_number: u16,
+        turn: Color,
+    },
+    /// Answers a take back request. A code of 0 accepts it.
+    BackAccept {
+        code: u8,
+    },
+}
+
+impl Message {
+    /// Parses a message without its terminating null character
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::dxp::Message;
+    /// use muskox::board::Square;
+    ///
+    /// let message = Message::parse("M001209150111").unwrap();
+    /// let square = |n| Square::from_pdn(n).unwrap();
+    /// assert_eq!(message, Message::Move {
+    ///     seconds: 12,
+    ///     source: square(9),
+    ///     destination: square(15),
+    ///     captured: vec![square(11)],
+    /// });
+    /// ```
+    pub fn parse(message: &str) -> Result<Self, ParseError> {
+        Ok(parse::dxp_message_primary(message)?.1)
+    }
+}
+
+fn dxp_color(color: Color) -> char {
+    match color {
+        Color::White => 'W',
+        Color::Black => 'Z',
+    }
+}
+
+fn dxp_position(board: &Bitboard) -> String {
+    Square::all()
+        .map(|s| {
+            let mask = s.mask();
+            let king = board.kings() & mask != 0;
+            match (board.blacks() & mask != 0, board.whites() & mask != 0, king) {
+                (true, _, false) => 'z',
+                (true, _, true) => 'Z',
+                (_, true, false) => 'w',
+                (_, true, true) => 'W',
+                _ => 'e',
+            }
+        })
+        .collect()
+}
+
+impl fmt::Display for Message {
+    /// Writes the message as it is sent over the wire, without the null character
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::GameRequest {
+                name,
+                follower,
+                minutes,
+                moves,
+                position,
+            } => {
+                write!(
+                    f,
+                    "R{:02}{:<width$.width$}{}{:03}{:03}",
+                    VERSION,
+                    name,
+                    dxp_color(*follower),
+                    minutes,
+                    moves,
+                    width = NAME_WIDTH
+                )?;
+                match position {
+                    Some(board) => write!(f, "B{}{}", dxp_color(board.turn()), dxp_position(board)),
+                    None => write!(f, "A"),
+                }
+            }
+            Message::GameAccept { name, code } => {
+                write!(f, "A{:<width$.width$}{}", name, code, width = NAME_WIDTH)
+            }
+            Message::Move {
+                seconds,
+                source,
+                destination,
+                captured,
+            } => {
+                write!(
+                    f,
+                    "M{:04}{:02}{:02}{:02}",
+                    seconds,
+                    source.pdn(),
+                    destination.pdn(),
+                    captured.len()
+                )?;
+                captured
+                    .iter()
+                    .try_for_each(|s| write!(f, "{:02}", s.pdn()))
+            }
+            Message::GameEnd { reason, stop } => write!(f, "E{}{}", reason.code(), *stop as u8),
+            Message::Chat(text) => write!(f, "C{}", text),
+            Message::BackRequest { move_number, turn } => {
+                write!(f, "B{:03}{}", move_number, dxp_color(*turn))
+            }
+            Message::BackAccept { code } => write!(f, "K{}", code),
+        }
+    }
+}
+
+/// Plays games against another program over a DamExchange connection
+pub struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    engine: Engine<Bitboard>,
+    name: String,
+}
+
+impl Client {
+    /// Connects to a DamExchange server such as `localhost:27531`
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let writer = TcpStream::connect(address)?;
+        let reader = BufReader::new(writer.try_clone()?);
+
+        Ok(Client {
+            reader,
+            writer,
+            engine: Engine::new(),
+            name: env!("CARGO_PKG_NAME").to_string(),
+        })
+    }
+
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        write!(self.writer, "{}\0", message)?;
+        self.writer.flush()
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        let mut buffer = Vec::new();
+        if self.reader.read_until(0, &mut buffer)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buffer.pop(); // the null character
+
+        let text = String::from_utf8_lossy(&buffer);
+        Message::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    /// Asks for a game from `position`, or from the start, where we play `color`. The
+    /// moves are then played through a `RemotePlayer`.
+    pub fn request_game(
+        &mut self,
+        color: Color,
+        minutes: u16,
+        moves: u16,
+        position: Option<Bitboard>,
+    ) -> io::Result<()> {
+        let request = Message::GameRequest {
+            name: self.name.clone(),
+            follower: color.opposite(),
+            minutes,
+            moves,
+            position,
+        };
+        self.send(&request)?;
+
+        match self.receive()? {
+            Message::GameAccept { code: 0, .. } => Ok(()),
+            Message::GameAccept { code, .. } => {
+                let error = format!("game request refused (code {})", code);
+                Err(io::Error::new(io::ErrorKind::ConnectionRefused, error))
+            }
+            other => {
+                let error = format!("expected a game accept, got {}", other);
+                Err(io::Error::new(io::ErrorKind::InvalidData, error))
+            }
+        }
+    }
+
+    /// Asks for a game where we play `color` and plays it out, returning who won.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - The color muskox plays
+    /// * `minutes` - The thinking time each side gets for `moves` moves
+    /// * `moves` - The number of moves the thinking time is for
+    pub fn play(&mut self, color: Color, minutes: u16, moves: u16) -> io::Result<Winner<Bitboard>> {
+        self.request_game(color, minutes, moves, None)?;
+        self.engine.reset();
+
+        // spread the thinking time evenly over the moves, within what the engine allows
+        let budget = (u32::from(minutes) * 60_000 / u32::from(moves.max(1))).max(1);
+        let constraint = SearchConstraint::time(budget)
+            .unwrap_or_else(|_| SearchConstraint::time(300_000).unwrap());
+
+        // the server keeps the time, so as far as we are concerned there is no clock
+        let mut engine = EnginePlayer::new(self.engine.clone(), constraint);
+        let mut remote = RemotePlayer::new(self);
+        let (black, white): (&mut dyn Player<Bitboard>, &mut dyn Player<Bitboard>) = match color {
+            Color::Black => (&mut engine, &mut remote),
+            Color::White => (&mut remote, &mut engine),
+        };
+        let game = players::play_game(
+            Bitboard::default(),
+            black,
+            white,
+            &mut Clock::unlimited(),
+            None,
+        );
+
+        remote.finish(color, game.winner(), true)
+    }
+}
+
+/// The program at the other end of a DamExchange connection, as a player. Its moves are
+/// read from the connection and the moves of its opponent are sent over it. It gives the
+/// game up when the other side ends the game, when the connection fails or when it
+/// receives an illegal move.
+pub struct RemotePlayer<'a> {
+    client: &'a mut Client,
+    since: Instant, // when the opponent started thinking
+    ended: Option<EndReason>,
+    error: Option<io::Error>,
+}
+
+impl<'a> RemotePlayer<'a> {
+    /// Creates a player from a connection a game was accepted on
+    pub fn new(client: &'a mut Client) -> Self {
+        RemotePlayer {
+            client,
+            since: Instant::now(),
+            ended: None,
+            error: None,
+        }
+    }
+
+    /// Returns why the other side ended the game from its point of view, if it did
+    #[inline]
+    pub fn ended(&self) -> Option<EndReason> {
+        self.ended
+    }
+
+    /// Returns the error that made the player give up, if there was one
+    #[inline]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Ends the game on the connection once it is over, where `color` is the color played
+    /// on our side and `winner` is who won on the board. Returns who won, which is what
+    /// the other side said if it ended the game itself. `stop` asks the other side not to
+    /// play another game.
+    pub fn finish(
+        mut self,
+        color: Color,
+        winner: &Winner<Bitboard>,
+        stop: bool,
+    ) -> io::Result<Winner<Bitboard>> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+
+        if let Some(reason) = self.ended {
+            self.client.send(&Message::GameEnd {
+                reason: EndReason::Unknown,
+                stop,
+            })?;
+            // their reason is from their point of view
+            let winner = match reason {
+                EndReason::Win => Winner::Player(color.opposite()),
+                EndReason::Loss => Winner::Player(color),
+                _ => Winner::Draw,
+            };
+            return Ok(winner);
+        }
+
+        // the game ended on the board, or our side resigned it
+        let winner = match winner {
+            Winner::Player(c) => Winner::Player(*c),
+            Winner::Draw => Winner::Draw,
+        };
+        let reason = match &winner {
+            Winner::Player(c) if *c == color => EndReason::Win,
+            Winner::Player(_) => EndReason::Loss,
+            Winner::Draw => EndReason::Draw,
+        };
+        self.client.send(&Message::GameEnd { reason, stop })?;
+        // the other side confirms with its own game end
+        let _ = self.client.receive();
+        Ok(winner)
+    }
+
+    fn receive_action(&mut self, board: &Bitboard) -> io::Result<Option<Action>> {
+        loop {
+            match self.client.receive()? {
+                Message::Move {
+                    source,
+                    destination,
+                    captured,
+                    ..
+                } => {
+                    let action = board
+                        .find_action(source, destination, &captured)
+                        .ok_or_else(|| {
+                            let error = format!("illegal move {}-{}", source, destination);
+                            io::Error::new(io::ErrorKind::InvalidData, error)
+                        })?;
+                    return Ok(Some(action));
+                }
+                Message::GameEnd { reason, .. } => {
+                    self.ended = Some(reason);
+                    return Ok(None);
+                }
+                Message::Chat(text) => println!("{}", text),
+                // we do not support taking moves back
+                Message::BackRequest { .. } => {
+                    self.client.send(&Message::BackAccept { code: 1 })?
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Player<Bitboard> for RemotePlayer<'_> {
+    fn choose(&mut self, state: &Bitboard, _: &Clock) -> Option<Action> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        let action = self.receive_action(state).unwrap_or_else(|err| {
+            self.error = Some(err);
+            None
+        });
+        self.since = Instant::now();
+        action
+    }
+
+    fn observe(&mut self, state: &Bitboard, action: Action) {
+        let effects = state.action_effects(&action).unwrap();
+        let message = Message::Move {
+            seconds: self.since.elapsed().as_secs().min(9999) as u16,
+            source: effects.source(),
+            destination: effects.destination(),
+            captured: effects.captured_squares().collect(),
+        };
+
+        if let Err(err) = self.client.send(&message) {
+            self.error.get_or_insert(err);
+        }
+    }
+}
+
+/// Connects to a DamExchange server and plays a single game as black
+pub fn run(address: &str) -> io::Result<()> {
+    let mut client = Client::connect(address)?;
+    let winner = client.play(Color::Black, 5, 75)?;
+
+    let result = match winner {
+        Winner::Player(color) => format!("{:?} won", color),
+        Winner::Draw => "Draw".to_string(),
+    };
+    println!("{}", result);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_round_trip_test() {
+        let square = |n| Square::from_pdn(n).unwrap();
+
+        let messages = vec![
+            Message::GameRequest {
+                name: "muskox".to_string(),
+                follower: Color::White,
+                minutes: 5,
+                moves: 75,
+                position: None,
+            },
+            Message::GameRequest {
+                name: "muskox".to_string(),
+                follower: Color::Black,
+                minutes: 10,
+                moves: 50,
+                position: Some(Bitboard::from_fen("W:W9,K11,19:B15,22,K32").unwrap()),
+            },
+            Message::GameAccept {
+                name: "other".to_string(),
+                code: 0,
+            },
+            Message::Move {
+                seconds: 3,
+                source: square(8),
+                destination: square(31),
+                captured: vec![square(11), square(18), square(26)],
+            },
+            Message::GameEnd {
+                reason: EndReason::Win,
+                stop: false,
+            },
+            Message::Chat("good game".to_string()),
+            Message::BackRequest {
+                move_number: 12,
+                turn: Color::Black,
+            },
+            Message::BackAccept { code: 1 },
+        ];
+
+        for message in messages {
+            assert_eq!(Message::parse(&message.to_string()).unwrap(), message);
+        }
+
+        assert_eq!(
+            Message::GameEnd {
+                reason: EndReason::Draw,
+                stop: true
+            }
+            .to_string(),
+            "E21"
+        );
+        assert!(Message::parse("X").is_err());
+    }
+}
@@ -0,0 +1,638 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use crate::bitboard::CROWNING_ROWS;
+use crate::board::{Bitboard, Color, Variant};
+use crate::search::{EndgameTable, GameState, Searchable, Winner};
+
+pub use crate::search::Wdl;
+
+// endgame tablebases hold the result with perfect play of every position of american
+// checkers with few enough pieces. positions are grouped into slices by how many men and
+// kings each side has. captures lead into slices with fewer pieces and crownings into
+// slices with fewer men, so slices are built from the fewest pieces and men up, and
+// every move out of a slice lands in one built before it. a slice is solved by going
+// over its positions until none changes: a position is won if a move leads to a lost
+// one and lost if every move leads to a won one, and whatever is left is drawn.
+//
+// a position and its mirror, with the colors swapped and the board turned around, have
+// the same result for the side to move. only one of every pair of slices that mirror each
+// other is built, the one where black has more men, or as many men and more kings, and
+// positions of the other are looked up mirrored.
+//
+// every slice is written to a file of its own as soon as it is solved. the files are
+// the checkpoints of a build: a build that is stopped picks up from the slices already
+// on disk, and a checksum at the end of every file catches files cut short or damaged,
+// whose slices are built again. a file is laid out as
+//
+//   magic, version          b"MKTB" and a byte
+//   material                four bytes, as in `Material`
+//   values                  a byte per position, in the order of `Material::index`
+//   checksum                u64, little endian, fnv-1a of everything before it
+
+/// The most pieces a tablebase can be built for
+pub const MAX_PIECES: usize = 6;
+
+const MAGIC: &[u8; 4] = b"MKTB";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 9;
+
+// the value of a position for the side to move. positions that are never resolved are
+// drawn, and squares overlapping or men standing on the row they are crowned on make for
+// positions that cannot occur
+const DRAW: u8 = 0;
+const WIN: u8 = 1;
+const LOSS: u8 = 2;
+const INVALID: u8 = 3;
+
+const BINOMIALS: [[usize; MAX_PIECES + 1]; 33] = binomials();
+
+const fn binomials() -> [[usize; MAX_PIECES + 1]; 33] {
+    let mut table = [[0; MAX_PIECES + 1]; 33];
+    let mut n = 0;
+    while n < 33 {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= MAX_PIECES && n > 0 {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+// the index of a set of squares among every set of as many squares
+fn rank(mut mask: u32) -> usize {
+    let mut rank = 0;
+    let mut i = 1;
+    while mask != 0 {
+        rank += BINOMIALS[mask.trailing_zeros() as usize][i];
+        mask &= mask - 1;
+        i += 1;
+    }
+    rank
+}
+
+fn unrank(mut rank: usize, count: usize) -> u32 {
+    let mut mask = 0;
+    let mut square = 32;
+    for i in (1..=count).rev() {
+        square -= 1;
+        while BINOMIALS[square][i] > rank {
+            square -= 1;
+        }
+        rank -= BINOMIALS[square][i];
+        mask |= 1 << square;
+    }
+    mask
+}
+
+/// Represents the pieces of a slice of a tablebase: the men and kings of each side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Material {
+    counts: [u8; 4], // black men, black kings, white men and white kings
+}
+
+impl Material {
+    /// Returns the material on a board
+    pub fn of(board: &Bitboard) -> Self {
+        let men = !board.kings();
+        let count = |mask: u32| mask.count_ones() as u8;
+        Material {
+            counts: [
+                count(board.blacks() & men),
+                count(board.blacks() & board.kings()),
+                count(board.whites() & men),
+                count(board.whites() & board.kings()),
+            ],
+        }
+    }
+
+    /// Returns every slice of a tablebase of up to `pieces` pieces with at least one piece
+    /// on each side, in the order they are built
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::egtb::Material;
+    ///
+    /// let slices = Material::up_to(3);
+    /// assert_eq!(slices.len(), 4 + 12);
+    /// assert_eq!(slices[0].to_string(), "01v01");
+    /// ```
+    pub fn up_to(pieces: usize) -> Vec<Material> {
+        let mut slices = Vec::new();
+        for total in 2..=pieces.min(MAX_PIECES) {
+            let mut these = Vec::new();
+            for black in 1..total {
+                for black_men in 0..=black {
+                    for white_men in 0..=total - black {
+                        these.push(Material {
+                            counts: [
+                                black_men as u8,
+                                (black - black_men) as u8,
+                                white_men as u8,
+                                (total - black - white_men) as u8,
+                            ],
+                        });
+                    }
+                }
+            }
+            these.sort_by_key(|material| material.counts[0] + material.counts[2]);
+            slices.extend(these);
+        }
+        slices
+    }
+
+    /// Returns the material with the colors swapped
+    pub fn mirrored(&self) -> Self {
+        let [black_men, black_kings, white_men, white_kings] = self.counts;
+        Material {
+            counts: [white_men, white_kings, black_men, black_kings],
+        }
+    }
+
+    /// Returns whether the slice is the one of its mirrored pair the tablebase holds
+    pub fn is_canonical(&self) -> bool {
+        self.counts[..2] >= self.counts[2..]
+    }
+
+    /// Returns the number of pieces on the board
+    pub fn pieces(&self) -> usize {
+        self.counts.iter().map(|&count| usize::from(count)).sum()
+    }
+
+    /// Returns the number of positions of the slice, with either side to move, including
+    /// those that cannot occur
+    pub fn positions(&self) -> usize {
+        2 * self
+            .counts
+            .iter()
+            .map(|&count| BINOMIALS[32][usize::from(count)])
+            .product::<usize>()
+    }
+
+    // the squares of every kind of piece, in the order of `counts`
+    fn masks(board: &Bitboard) -> [u32; 4] {
+        let men = !board.kings();
+        [
+            board.blacks() & men,
+            board.blacks() & board.kings(),
+            board.whites() & men,
+            board.whites() & board.kings(),
+        ]
+    }
+
+    /// Returns the index of a board with this material within the slice
+    pub fn index(&self, board: &Bitboard) -> usize {
+        let masks = Self::masks(board);
+        let mut index = 0;
+        for (mask, &count) in masks.iter().zip(&self.counts) {
+            index = index * BINOMIALS[32][usize::from(count)] + rank(*mask);
+        }
+        2 * index + (board.turn() == Color::White) as usize
+    }
+
+    /// Returns the board at an index of the slice, or `None` if the position cannot occur
+    pub fn board(&self, index: usize) -> Option<Bitboard> {
+        let turn = match index % 2 {
+            0 => Color::Black,
+            _ => Color::White,
+        };
+
+        let mut rest = index / 2;
+        let mut masks = [0; 4];
+        for (mask, &count) in masks.iter_mut().zip(&self.counts).rev() {
+            let count = usize::from(count);
+            *mask = unrank(rest % BINOMIALS[32][count], count);
+            rest /= BINOMIALS[32][count];
+        }
+
+        let [black_men, black_kings, white_men, white_kings] = masks;
+        let occupied = masks.iter().fold(0, |all, mask| all | mask);
+        let valid = occupied.count_ones() as usize == self.pieces()
+            && black_men & CROWNING_ROWS[0] == 0
+            && white_men & CROWNING_ROWS[1] == 0;
+        valid.then(|| {
+            Bitboard::new(
+                black_men | black_kings,
+                white_men | white_kings,
+                black_kings | white_kings,
+                turn,
+            )
+        })
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}.mktb", self)
+    }
+}
+
+impl fmt::Display for Material {
+    /// Writes the men and kings of black, then of white, such as `21v02` for two black
+    /// men and a king against two white kings
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [black_men, black_kings, white_men, white_kings] = self.counts;
+        write!(
+            f,
+            "{}{}v{}{}",
+            black_men, black_kings, white_men, white_kings
+        )
+    }
+}
+
+/// Represents where a slice of a build came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceOrigin {
+    /// The slice was solved
+    Built,
+    /// The slice was read from the file a previous build wrote
+    Resumed,
+    /// The file of the slice failed its checksum, so the slice was solved again
+    Rebuilt,
+}
+
+/// Represents a slice a build went through
+#[derive(Debug, Clone)]
+pub struct SliceReport {
+    material: Material,
+    origin: SliceOrigin,
+    elapsed: Duration,
+    memory: usize,
+}
+
+impl SliceReport {
+    #[inline]
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    #[inline]
+    pub fn origin(&self) -> SliceOrigin {
+        self.origin
+    }
+
+    /// Returns the time it took to solve or read the slice
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the bytes of slices held in memory once the slice was done
+    #[inline]
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+}
+
+impl fmt::Display for SliceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let origin = match self.origin {
+            SliceOrigin::Built => "built",
+            SliceOrigin::Resumed => "resumed",
+            SliceOrigin::Rebuilt => "rebuilt (bad checksum)",
+        };
+        write!(
+            f,
+            "{} {}: {} positions in {:.2}s, {:.1} MB in memory",
+            origin,
+            self.material,
+            self.material.positions(),
+            self.elapsed.as_secs_f64(),
+            self.memory as f64 / (1 << 20) as f64
+        )
+    }
+}
+
+/// Represents the slices of an endgame tablebase held in memory
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Bitboard;
+/// use muskox::egtb::{Tablebase, Wdl};
+///
+/// let dir = std::env::temp_dir().join("muskox_egtb_doc");
+/// let tablebase = Tablebase::build(2, &dir, |_| ()).unwrap();
+///
+/// // the king catches the man before it is crowned
+/// let board = Bitboard::from_fen("B:W27:BK14").unwrap();
+/// assert_eq!(tablebase.probe(&board), Some(Wdl::Win));
+/// // positions with more pieces are not in the tablebase
+/// assert_eq!(tablebase.probe(&Bitboard::default()), None);
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct Tablebase {
+    slices: HashMap<Material, Vec<u8>>,
+    pieces: usize, // the most pieces of any slice
+}
+
+impl Tablebase {
+    /// Builds every slice of up to `pieces` pieces into the directory at `dir`, resuming
+    /// from the slices a previous build left there. `progress` is told about every slice
+    /// as it is done.
+    pub fn build<F>(pieces: usize, dir: &Path, mut progress: F) -> io::Result<Self>
+    where
+        F: FnMut(&SliceReport),
+    {
+        fs::create_dir_all(dir)?;
+
+        let mut tablebase = Tablebase {
+            pieces,
+            ..Tablebase::default()
+        };
+        for material in canonical_slices(pieces) {
+            let start = Instant::now();
+            let path = dir.join(material.file_name());
+
+            let origin = match read_slice(&path, material) {
+                Ok(values) => {
+                    tablebase.slices.insert(material, values);
+                    SliceOrigin::Resumed
+                }
+                Err(err) => {
+                    let values = tablebase.solve(material);
+                    write_slice(&path, material, &values)?;
+                    tablebase.slices.insert(material, values);
+                    match err.kind() {
+                        io::ErrorKind::NotFound => SliceOrigin::Built,
+                        _ => SliceOrigin::Rebuilt,
+                    }
+                }
+            };
+
+            progress(&SliceReport {
+                material,
+                origin,
+                elapsed: start.elapsed(),
+                memory: tablebase.memory(),
+            });
+        }
+        Ok(tablebase)
+    }
+
+    /// Reads every slice of up to `pieces` pieces from the directory at `dir`, failing if
+    /// any is missing or damaged
+    pub fn load(pieces: usize, dir: &Path) -> io::Result<Self> {
+        let mut tablebase = Tablebase {
+            pieces,
+            ..Tablebase::default()
+        };
+        for material in canonical_slices(pieces) {
+            let values = read_slice(&dir.join(material.file_name()), material)?;
+            tablebase.slices.insert(material, values);
+        }
+        Ok(tablebase)
+    }
+
+    /// Returns the bytes of slices held in memory
+    pub fn memory(&self) -> usize {
+        self.slices.values().map(Vec::len).sum()
+    }
+
+    /// Returns the result of a board of american checkers for the side to move, or `None`
+    /// if the tablebase does not hold its material
+    pub fn probe(&self, board: &Bitboard) -> Option<Wdl> {
+        let pieces = (board.blacks() | board.whites()).count_ones() as usize;
+        if board.variant() != Variant::American || pieces > self.pieces {
+            return None;
+        }
+
+        let (board, material) = canonical(board);
+        let values = self.slices.get(&material)?;
+        match values[material.index(&board)] {
+            WIN => Some(Wdl::Win),
+            LOSS => Some(Wdl::Loss),
+            _ => Some(Wdl::Draw),
+        }
+    }
+
+    // the value of a board for the side to move, looked up in `values` if it is of the
+    // slice being solved and in the slices built before otherwise
+    fn value(&self, board: &Bitboard, material: Material, values: &[u8]) -> u8 {
+        let own = match board.turn() {
+            Color::Black => board.blacks(),
+            Color::White => board.whites(),
+        };
+        if own == 0 {
+            return LOSS;
+        }
+
+        let (board, of) = canonical(board);
+        match of == material {
+            true => values[material.index(&board)],
+            false => self.slices[&of][of.index(&board)],
+        }
+    }
+
+    // the value a board gets from the values of the boards its moves lead to, if any yet
+    fn resolve(&self, board: &Bitboard, material: Material, values: &[u8]) -> Option<u8> {
+        // the rules call lone kings in opposite double corners a draw, moves or not
+        if board.get_game_state() == GameState::Completed(Winner::Draw) {
+            return None;
+        }
+
+        let mut all_won = true;
+        for pair in board.generate_all_actions() {
+            match self.value(pair.state(), material, values) {
+                LOSS => return Some(WIN),
+                WIN => (),
+                _ => all_won = false,
+            }
+        }
+        all_won.then_some(LOSS)
+    }
+
+    fn solve(&self, material: Material) -> Vec<u8> {
+        let mut values: Vec<u8> = (0..material.positions())
+            .into_par_iter()
+            .map(|index| match material.board(index) {
+                Some(_) => DRAW,
+                None => INVALID,
+            })
+            .collect();
+
+        // every pass resolves the positions one move further from the end
+        loop {
+            let resolved: Vec<(usize, u8)> = (0..values.len())
+                .into_par_iter()
+                .filter(|&index| values[index] == DRAW)
+                .filter_map(|index| {
+                    let board = material.board(index).unwrap();
+                    self.resolve(&board, material, &values)
+                        .map(|value| (index, value))
+                })
+                .collect();
+
+            if resolved.is_empty() {
+                return values;
+            }
+            for (index, value) in resolved {
+                values[index] = value;
+            }
+        }
+    }
+}
+
+// the slices of up to `pieces` pieces a tablebase holds, in the order they are built
+fn canonical_slices(pieces: usize) -> impl Iterator<Item = Material> {
+    Material::up_to(pieces)
+        .into_iter()
+        .filter(Material::is_canonical)
+}
+
+// a board is looked up as itself or mirrored, whichever has the material of a held slice
+fn canonical(board: &Bitboard) -> (Bitboard, Material) {
+    let material = Material::of(board);
+    match material.is_canonical() {
+        true => (*board, material),
+        false => (board.mirrored(), material.mirrored()),
+    }
+}
+
+impl EndgameTable<Bitboard> for Tablebase {
+    fn probe(&self, board: &Bitboard) -> Option<Wdl> {
+        Tablebase::probe(self, board)
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn write_slice(path: &Path, material: Material, values: &[u8]) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + values.len() + 8);
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&material.counts);
+    bytes.extend_from_slice(values);
+    let sum = checksum(&bytes);
+    bytes.extend_from_slice(&sum.to_le_bytes());
+
+    // a slice only ever appears under its own name once it is whole
+    let partial = path.with_extension("partial");
+    fs::write(&partial, bytes)?;
+    fs::rename(partial, path)
+}
+
+fn read_slice(path: &Path, material: Material) -> io::Result<Vec<u8>> {
+    let invalid = |message: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), message),
+        )
+    };
+
+    let mut bytes = fs::read(path)?;
+    if bytes.len() != HEADER_SIZE + material.positions() + 8 {
+        return Err(invalid("wrong size"));
+    }
+    let sum = bytes.split_off(bytes.len() - 8);
+    if checksum(&bytes).to_le_bytes()[..] != sum[..] {
+        return Err(invalid("bad checksum"));
+    }
+    if &bytes[..4] != MAGIC || bytes[4] != VERSION || bytes[5..HEADER_SIZE] != material.counts {
+        return Err(invalid("not a slice of this tablebase"));
+    }
+
+    Ok(bytes.split_off(HEADER_SIZE))
+}
+
+/// Builds a tablebase of up to `pieces` pieces into the directory at `dir`, resuming from
+/// the slices already there and reporting every slice and the memory held on stderr
+pub fn run(pieces: usize, dir: &str) -> io::Result<()> {
+    if !(2..=MAX_PIECES).contains(&pieces) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("tablebases have 2 to {} pieces", MAX_PIECES),
+        ));
+    }
+
+    let dir = PathBuf::from(dir);
+    let tablebase = Tablebase::build(pieces, &dir, |report| eprintln!("{}", report))?;
+    eprintln!(
+        "tablebase of up to {} pieces in {}, {} slices",
+        pieces,
+        dir.display(),
+        tablebase.slices.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn egtb_test() {
+        // indices go both ways
+        let material = Material {
+            counts: [1, 1, 2, 0],
+        };
+        let board = Bitboard::from_fen("W:W18,30:B14,K1").unwrap();
+        assert_eq!(Material::of(&board), material);
+        assert_eq!(material.board(material.index(&board)), Some(board));
+        assert_eq!(Material::of(&Bitboard::default()).pieces(), 24);
+
+        let dir = std::env::temp_dir().join("muskox_egtb_test");
+        let _ = fs::remove_dir_all(&dir);
+        let mut origins = Vec::new();
+        let tablebase = Tablebase::build(2, &dir, |report| origins.push(report.origin())).unwrap();
+        // of the slices of a man against a king only one is built
+        assert_eq!(origins, [SliceOrigin::Built; 3]);
+        assert_eq!(tablebase.memory(), 2 * (32 * 32) * 3);
+        assert!(!material.is_canonical());
+        assert!(material.mirrored().is_canonical());
+        let (mirrored, of) = canonical(&board);
+        assert_eq!((mirrored, of), (board.mirrored(), material.mirrored()));
+
+        // a king catches a man before it is crowned, or traps a king by the single corner
+        let probe = |fen: &str| tablebase.probe(&Bitboard::from_fen(fen).unwrap());
+        assert_eq!(probe("W:W28:BK19"), Some(Wdl::Loss));
+        assert_eq!(probe("B:WK3:BK14"), Some(Wdl::Win));
+        // mirrored positions are looked up in the slice of their twin
+        for fen in ["W:W28:BK19", "B:WK3:BK14", "B:W18:B14", "W:WK14:B5"].iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            assert_eq!(tablebase.probe(&board.mirrored()), probe(fen));
+        }
+        // kings in opposite double corners are drawn by the rules
+        assert_eq!(probe("W:WK5:BK28"), Some(Wdl::Draw));
+        assert_eq!(probe("B:WK5:BK24"), Some(Wdl::Draw));
+        // whoever moves first captures
+        assert_eq!(probe("B:W18:B14"), Some(Wdl::Win));
+
+        // builds pick up from the slices on disk, and build slices again whose files were
+        // damaged
+        let path = dir.join(Material::up_to(2)[2].file_name());
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[HEADER_SIZE] ^= 1;
+        fs::write(&path, bytes).unwrap();
+        origins.clear();
+        Tablebase::build(2, &dir, |report| origins.push(report.origin())).unwrap();
+        assert_eq!(
+            origins,
+            [
+                SliceOrigin::Resumed,
+                SliceOrigin::Rebuilt,
+                SliceOrigin::Resumed
+            ]
+        );
+        let loaded = Tablebase::load(2, &dir).unwrap();
+        assert_eq!(loaded.probe(&board), None);
+        assert_eq!(
+            loaded.probe(&Bitboard::from_fen("W:W28:BK19").unwrap()),
+            Some(Wdl::Loss)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
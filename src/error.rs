@@ -1,9 +1,12 @@
+use std::io;
+
 use nom::error::{VerboseError, VerboseErrorKind::Context};
 use snafu::Snafu;
 
 use crate::board::Color;
 
 #[derive(Debug, PartialEq, Snafu)]
+#[non_exhaustive]
 pub enum ActionError {
     #[snafu(display(
         "Source position {} must be in possession of mover {:?}",
@@ -30,9 +33,37 @@ pub enum ActionError {
 
     #[snafu(display("More jumping required!"))]
     NeedMoreJumpingError,
+
+    #[snafu(display(
+        "A single piece crowned during a capture has to stop there in this variant!"
+    ))]
+    CrownedMidCaptureError,
+
+    #[snafu(display("Pieces can only move diagonally to a neighboring square!"))]
+    NotDiagonalError,
+
+    #[snafu(display("Only kings fly across more than one square, and only in this variant!"))]
+    NotFlyingError,
+
+    #[snafu(display("Single pieces cannot capture kings in this variant!"))]
+    ManCapturingKingError,
+
+    #[snafu(display("Another capture takes precedence in this variant!"))]
+    CapturePrecedenceError,
+}
+
+#[derive(Debug, PartialEq, Snafu)]
+#[non_exhaustive]
+pub enum BoardError {
+    #[snafu(display("A {:?} man on {} would already have been crowned!", color, square))]
+    UncrownedManError { square: u8, color: Color },
+
+    #[snafu(display("{:?} cannot have more than 12 pieces!", color))]
+    TooManyPiecesError { color: Color },
 }
 
 #[derive(Debug, Snafu)]
+#[non_exhaustive]
 pub enum ParseError {
     // for board below
     #[snafu(display("Invalid color letter (W and B are valid)!"))]
@@ -44,9 +75,17 @@ pub enum ParseError {
     #[snafu(display("{} is not a valid position 1 - 32", position))]
     PositionError { position: String },
 
+    #[snafu(display("{} is not a dark square on the board (a1 - h8)", square))]
+    SquareError { square: String },
+
     #[snafu(display("Couldn't parse board!"))]
     InvalidBoard,
 
+    #[snafu(display(
+        "Unknown variant (american, italian, russian, brazilian and giveaway are valid)!"
+    ))]
+    VariantError,
+
     // for actions only...
     #[snafu(display("Can only have up to eight positions in any given movetext!"))]
     MoveQuantityError,
@@ -72,6 +111,134 @@ pub enum ParseError {
 
     #[snafu(display("Invalid command!"))]
     InvalidCommand,
+
+    #[snafu(display("Unknown command {}!{}", name, hint))]
+    UnknownCommand { name: String, hint: String },
+
+    // for engine protocols
+    #[snafu(display("Invalid protocol message!"))]
+    MessageError,
+
+    #[snafu(display("Invalid protocol position (side to move then one letter per square)!"))]
+    ProtocolPositionError,
+
+    #[snafu(display("Invalid packed board!"))]
+    PackedBoardError,
+
+    // for games in portable draughts notation
+    #[snafu(display("Couldn't parse PDN!"))]
+    PdnError,
+
+    #[snafu(display("Illegal move {} in game {}!", movetext, game))]
+    PdnMoveError { game: usize, movetext: String },
+
+    // for test suites
+    #[snafu(display(
+        "Couldn't parse test position on line {} (expected FEN bm MOVES)!",
+        line
+    ))]
+    SuiteError { line: usize },
+
+    #[snafu(display("Illegal best move {} on line {}!", movetext, line))]
+    SuiteMoveError { line: usize, movetext: String },
+}
+
+#[derive(Debug, PartialEq, Snafu)]
+#[non_exhaustive]
+pub enum OptionError {
+    #[snafu(display("Unknown option {}!", name))]
+    UnknownOption { name: String },
+
+    #[snafu(display("Invalid value {} for option {} (expected {})!", value, name, expected))]
+    OptionValueError {
+        name: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+#[derive(Debug, PartialEq, Snafu)]
+#[non_exhaustive]
+pub enum TicTacToeError {
+    #[snafu(display("Square {} is not on the board (0 - 8)", square))]
+    SquareRangeError { square: u8 },
+
+    #[snafu(display("Square {} is already taken", square))]
+    SquareTakenError { square: u8 },
+
+    #[snafu(display("The game is already over!"))]
+    GameOverError,
+}
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum BuildError {
+    #[snafu(display("{} threads is not between 1 and 64!", threads))]
+    ThreadsError { threads: usize },
+
+    #[snafu(display("{} megabytes is not a table size between 1 and 4096!", size))]
+    HashSizeError { size: usize },
+
+    #[snafu(display("{} is not a depth between 1 and 1000!", depth))]
+    MaxDepthError { depth: u32 },
+
+    #[snafu(display("Deterministic searches use a single thread, not {}!", threads))]
+    DeterministicThreadsError { threads: usize },
+
+    #[snafu(display("Could not load the opening book {}: {}", path, message))]
+    BookError { path: String, message: String },
+}
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum ModelError {
+    #[snafu(display("Could not load the model {}: {}", path, message))]
+    ModelLoadError { path: String, message: String },
+}
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum SessionError {
+    #[snafu(display("Could not access the session file {}: {}", path, source))]
+    SessionIoError { path: String, source: io::Error },
+
+    #[snafu(display("Couldn't parse the session file: {}", message))]
+    SessionFormatError { message: String },
+
+    #[snafu(display(
+        "Session files of version {} are not supported (expected {})!",
+        version,
+        expected
+    ))]
+    SessionVersionError { version: u32, expected: u32 },
+
+    #[snafu(display("Illegal move {} in the session!", movetext))]
+    SessionMoveError { movetext: String },
+
+    #[snafu(display("{}", source))]
+    SessionOptionError { source: OptionError },
+}
+
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum TournamentError {
+    #[snafu(display("Couldn't parse the tournament: {}", message))]
+    TournamentFormatError { message: String },
+
+    #[snafu(display("A tournament needs at least two engines!"))]
+    TournamentSizeError,
+
+    #[snafu(display("Invalid option for engine {}: {}", name, source))]
+    TournamentOptionError { name: String, source: OptionError },
+
+    #[snafu(display("Lost the connection to engine {}: {}", name, source))]
+    TournamentEngineError { name: String, source: io::Error },
+
+    #[snafu(display("Could not write the games to {}: {}", path, source))]
+    TournamentIoError { path: String, source: io::Error },
+
+    #[snafu(display("Invalid sprt argument: {}", message))]
+    SprtArgumentError { message: String },
 }
 
 impl<T> From<nom::Err<VerboseError<T>>> for ParseError {
@@ -86,12 +253,17 @@ impl<T> From<nom::Err<VerboseError<T>>> for ParseError {
             match kind {
                 Context("color") => return ParseError::ColorError,
                 Context("king") => return ParseError::PieceError,
+                Context("variant") => return ParseError::VariantError,
                 Context("digit") => return ParseError::PieceError,
                 Context("position") => return ParseError::PositionValueError,
                 Context("delimiter") => return ParseError::InvalidDelimiter,
                 Context("no command") => return ParseError::NoCommandError,
+                Context("command") => return ParseError::InvalidCommand,
                 Context("constraint option") => return ParseError::ConstraintOptionError,
                 Context("constraint value") => return ParseError::ConstraintValueError,
+                Context("message") => return ParseError::MessageError,
+                Context("protocol position") => return ParseError::ProtocolPositionError,
+                Context("pdn") => return ParseError::PdnError,
                 _ => (),
             }
         }
@@ -1,10 +1,20 @@
+use std::cmp;
 use std::default;
+use std::fmt;
 use std::ops::Fn;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
 
 use lazy_static::lazy_static;
 
+use crate::bitboard::DOUBLE_CORNERS;
 use crate::board::{Bitboard, Color};
+#[cfg(feature = "onnx")]
+use crate::error::ModelError;
+#[cfg(feature = "onnx")]
+use crate::onnx::OnnxModel;
 use crate::search::{GameState, Score, Searchable, Winner};
+use crate::square::Square;
 
 // honestly not 100% sure what to do with this module as there are many different approaches
 // each with their own benefits. when i get a better sense with what i want out of this module
@@ -12,54 +22,614 @@ use crate::search::{GameState, Score, Searchable, Winner};
 
 lazy_static! {
     pub static ref GLOBAL_EVAL: BoardEvaluator = BoardEvaluator::default();
+
+    // the eight squares in the middle of the board, where kings reach the most
+    static ref CENTER: u32 = [10, 11, 14, 15, 18, 19, 22, 23]
+        .iter()
+        .map(|&n| Square::from_pdn(n).unwrap().mask())
+        .fold(0, |mask, square| mask | square);
+}
+
+// the squares of the structures the evaluation knows, as masks where square n is bit n - 1.
+// a bridge is a pair of men on the back rank guarding it against kings being crowned, here
+// on 1 and 3 for black and 30 and 32 for white
+const BRIDGES: [u32; 2] = [1 << 0 | 1 << 2, 1 << 29 | 1 << 31];
+// a man in a dog-hole, 28 for black and 5 for white, cannot move while the other side holds
+// the square in front of it, 32 or 1, in place. both squares are given for every side
+const DOG_HOLES: [(u32, u32); 2] = [(1 << 27, 1 << 31), (1 << 4, 1 << 0)];
+// a side with a man on 7 or 26 while the other side has none there, before any kings, holds
+// the apex of a weak triangle that gets picked apart
+const APEX: u32 = 1 << 6 | 1 << 25;
+// the system of each side, the files b, d, f and h for black and the others for white. the
+// side to move has the move, or the opposition, when its system holds an odd number of pieces
+const SYSTEMS: [u32; 2] = [0x0f0f0f0f, 0xf0f0f0f0];
+
+// the phase of a game follows the number of pieces left. it goes from the middlegame to the
+// endgame between these two counts, and the weights of the evaluation blend along with it
+const MIDGAME_PIECES: u32 = 24;
+const ENDGAME_PIECES: u32 = 8;
+// games with at least this many pieces left are still in their opening
+const OPENING_PIECES: u32 = 20;
+
+// the number of terms the classical evaluation is made of
+const CLASSICAL_TERMS: usize = 10;
+
+/// Represents the stages a game goes through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Opening,
+    Midgame,
+    Endgame,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Stage::Opening => "opening",
+            Stage::Midgame => "midgame",
+            Stage::Endgame => "endgame",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Represents how far along a game is, judged by the material left on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Phase {
+    pieces: u32,
+}
+
+impl Phase {
+    /// Creates the phase of a board with `pieces` pieces on it, kings included
+    pub fn new(pieces: u32) -> Self {
+        Phase { pieces }
+    }
+
+    #[inline]
+    pub fn pieces(&self) -> u32 {
+        self.pieces
+    }
+
+    pub fn stage(&self) -> Stage {
+        match self.pieces {
+            p if p >= OPENING_PIECES => Stage::Opening,
+            p if p > ENDGAME_PIECES => Stage::Midgame,
+            _ => Stage::Endgame,
+        }
+    }
+
+    /// Returns how far the game has gone from the middlegame toward the endgame, from 0
+    /// with every piece on the board to 1 once few enough are left
+    pub fn endgame_weight(&self) -> f32 {
+        let pieces = self.pieces.clamp(ENDGAME_PIECES, MIDGAME_PIECES);
+        (MIDGAME_PIECES - pieces) as f32 / (MIDGAME_PIECES - ENDGAME_PIECES) as f32
+    }
+}
+
+/// Represents what the classical evaluation makes of the pieces at one point of a game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalWeights {
+    man: f32,
+    king: f32,
+    center: f32,
+    tempo: f32,
+    turn: f32,
+    bridge: f32,
+    dog_hole: f32,
+    apex: f32,
+    opposition: f32,
+    king_mobility: f32,
+}
+
+impl EvalWeights {
+    /// Creates weights for the pieces alone, with nothing for tempo or for having the move
+    pub fn new(man: f32, king: f32, center: f32) -> Self {
+        EvalWeights {
+            man,
+            king,
+            center,
+            tempo: 0.,
+            turn: 0.,
+            bridge: 0.,
+            dog_hole: 0.,
+            apex: 0.,
+            opposition: 0.,
+            king_mobility: 0.,
+        }
+    }
+
+    /// Returns the weights with `tempo` for every row a man has advanced
+    pub fn with_tempo(self, tempo: f32) -> Self {
+        EvalWeights { tempo, ..self }
+    }
+
+    /// Returns the weights with `turn` for the side to move
+    pub fn with_turn(self, turn: f32) -> Self {
+        EvalWeights { turn, ..self }
+    }
+
+    /// Returns the weights with `bridge` for an intact bridge on the back rank
+    pub fn with_bridge(self, bridge: f32) -> Self {
+        EvalWeights { bridge, ..self }
+    }
+
+    /// Returns the weights with `dog_hole` against a man stuck in a dog-hole
+    pub fn with_dog_hole(self, dog_hole: f32) -> Self {
+        EvalWeights { dog_hole, ..self }
+    }
+
+    /// Returns the weights with `apex` against holding the apex squares alone
+    pub fn with_apex(self, apex: f32) -> Self {
+        EvalWeights { apex, ..self }
+    }
+
+    /// Returns the weights with `opposition` for the side that has the move
+    pub fn with_opposition(self, opposition: f32) -> Self {
+        EvalWeights { opposition, ..self }
+    }
+
+    /// Returns the weights with `king_mobility` for every step the kings can take
+    pub fn with_king_mobility(self, king_mobility: f32) -> Self {
+        EvalWeights {
+            king_mobility,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn man(&self) -> f32 {
+        self.man
+    }
+
+    #[inline]
+    pub fn king(&self) -> f32 {
+        self.king
+    }
+
+    /// Returns what a king on one of the eight middle squares adds to its worth
+    #[inline]
+    pub fn center(&self) -> f32 {
+        self.center
+    }
+
+    /// Returns what every row a man has come from its own back row adds to its worth
+    #[inline]
+    pub fn tempo(&self) -> f32 {
+        self.tempo
+    }
+
+    /// Returns what having the move is worth
+    #[inline]
+    pub fn turn(&self) -> f32 {
+        self.turn
+    }
+
+    #[inline]
+    pub fn bridge(&self) -> f32 {
+        self.bridge
+    }
+
+    /// Returns what a man stuck in a dog-hole costs its side
+    #[inline]
+    pub fn dog_hole(&self) -> f32 {
+        self.dog_hole
+    }
+
+    /// Returns what holding the apex squares alone costs a side
+    #[inline]
+    pub fn apex(&self) -> f32 {
+        self.apex
+    }
+
+    #[inline]
+    pub fn opposition(&self) -> f32 {
+        self.opposition
+    }
+
+    #[inline]
+    pub fn king_mobility(&self) -> f32 {
+        self.king_mobility
+    }
+}
+
+/// Represents the weights of the classical evaluation, which blend from a middlegame set
+/// into an endgame set as pieces come off the board. Late in a game men, being close to
+/// crowning, are worth more next to kings, and kings need the middle of the board more.
+/// Advancing men counts for more too, while the side to move gets a small bonus all game
+/// long so that positions alike for both sides do not all score the same. The back rank
+/// bridge and the apex matter while there are men to crown and to trade, and fade along
+/// with them, while the opposition and how freely the kings move only count once the
+/// board has emptied out.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::{Bitboard, EvalParams, EvalWeights};
+///
+/// let params = EvalParams::default();
+/// let start = params.weights(Bitboard::default().phase());
+/// assert_eq!(start, params.middlegame());
+///
+/// let board = Bitboard::from_fen("B:WK18:B1,2").unwrap();
+/// let late = params.weights(board.phase());
+/// assert_eq!(late, params.endgame());
+/// assert!(late.king() / late.man() < start.king() / start.man());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalParams {
+    middlegame: EvalWeights,
+    endgame: EvalWeights,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams::new(
+            EvalWeights::new(1., 2., 0.125)
+                .with_tempo(0.015625)
+                .with_turn(0.0625)
+                .with_bridge(0.125)
+                .with_dog_hole(0.125)
+                .with_apex(0.0625),
+            EvalWeights::new(1., 1.5, 0.25)
+                .with_tempo(0.0625)
+                .with_turn(0.0625)
+                .with_bridge(0.03125)
+                .with_dog_hole(0.125)
+                .with_opposition(0.125)
+                .with_king_mobility(0.03125),
+        )
+    }
+}
+
+impl EvalParams {
+    pub fn new(middlegame: EvalWeights, endgame: EvalWeights) -> Self {
+        EvalParams {
+            middlegame,
+            endgame,
+        }
+    }
+
+    #[inline]
+    pub fn middlegame(&self) -> EvalWeights {
+        self.middlegame
+    }
+
+    #[inline]
+    pub fn endgame(&self) -> EvalWeights {
+        self.endgame
+    }
+
+    /// Returns the weights for a game at `phase`
+    pub fn weights(&self, phase: Phase) -> EvalWeights {
+        let t = phase.endgame_weight();
+        let blend = |middlegame: f32, endgame: f32| middlegame * (1. - t) + endgame * t;
+        EvalWeights {
+            man: blend(self.middlegame.man, self.endgame.man),
+            king: blend(self.middlegame.king, self.endgame.king),
+            center: blend(self.middlegame.center, self.endgame.center),
+            tempo: blend(self.middlegame.tempo, self.endgame.tempo),
+            turn: blend(self.middlegame.turn, self.endgame.turn),
+            bridge: blend(self.middlegame.bridge, self.endgame.bridge),
+            dog_hole: blend(self.middlegame.dog_hole, self.endgame.dog_hole),
+            apex: blend(self.middlegame.apex, self.endgame.apex),
+            opposition: blend(self.middlegame.opposition, self.endgame.opposition),
+            king_mobility: blend(self.middlegame.king_mobility, self.endgame.king_mobility),
+        }
+    }
+}
+
+// the evaluator installed in place of the global one, if any. installed evaluators are
+// leaked, as searches running at the time may still be using the one they replace
+static INSTALLED: AtomicPtr<BoardEvaluator> = AtomicPtr::new(ptr::null_mut());
+
+/// Returns the evaluator boards are evaluated with
+#[inline]
+pub fn current_evaluator() -> &'static BoardEvaluator {
+    let installed = INSTALLED.load(Ordering::Acquire);
+    match installed.is_null() {
+        true => &GLOBAL_EVAL,
+        // installed evaluators are never freed
+        false => unsafe { &*installed },
+    }
+}
+
+/// Makes boards be evaluated with `evaluator` from now on. The evaluator it replaces is
+/// never freed, so this is meant for setting up the program rather than for calling over
+/// and over.
+pub fn install_evaluator(evaluator: BoardEvaluator) {
+    INSTALLED.store(Box::into_raw(Box::new(evaluator)), Ordering::Release);
 }
 
 #[allow(dead_code)]
+#[non_exhaustive]
 pub enum BoardEvaluator {
-    Classical(Box<dyn Fn(&Bitboard) -> Score + Send + Sync>),
+    Classical(Box<dyn Fn(&Bitboard) -> Score + Send + Sync>, EvalParams),
     Nnue,
+    /// Evaluates boards with a model in the onnx format. Finished games are scored by
+    /// their result without asking the model.
+    #[cfg(feature = "onnx")]
+    Onnx(Box<OnnxModel>),
 }
 use BoardEvaluator::*;
 
 impl BoardEvaluator {
+    /// Creates the classical evaluator weighing the pieces with `params`
+    pub fn classical(params: EvalParams) -> Self {
+        Classical(
+            Box::new(move |board: &Bitboard| {
+                let (terms, len) = classical_terms(board, &params);
+                sum(&terms[..len])
+            }),
+            params,
+        )
+    }
+
+    /// Creates an evaluator running the onnx model at `path`
+    #[cfg(feature = "onnx")]
+    pub fn onnx(path: &str) -> Result<Self, ModelError> {
+        OnnxModel::load(path).map(|model| Onnx(Box::new(model)))
+    }
+
     #[inline]
     pub fn eval(&self, board: &Bitboard) -> Score {
         match self {
-            Classical(f) => f(board),
+            Classical(f, _) => f(board),
+            Nnue => panic!("Cannot use NNUE evaluation yet!"),
+            #[cfg(feature = "onnx")]
+            Onnx(model) => game_result(board).unwrap_or_else(|| model.eval(board)),
+        }
+    }
+
+    /// Evaluates many boards at once, returning their scores in the same order. NNUE is
+    /// far faster fed batches than boards one at a time.
+    pub fn eval_batch(&self, boards: &[Bitboard]) -> Vec<Score> {
+        match self {
+            Classical(f, _) => boards.iter().map(f).collect(),
             Nnue => panic!("Cannot use NNUE evaluation yet!"),
+            #[cfg(feature = "onnx")]
+            Onnx(model) => {
+                let results: Vec<_> = boards.iter().map(game_result).collect();
+                let going_on: Vec<_> = boards
+                    .iter()
+                    .zip(&results)
+                    .filter(|(_, result)| result.is_none())
+                    .map(|(board, _)| *board)
+                    .collect();
+                let mut scores = model.eval_batch(&going_on).into_iter();
+
+                results
+                    .into_iter()
+                    .map(|result| result.or_else(|| scores.next()).unwrap())
+                    .collect()
+            }
+        }
+    }
+
+    /// Splits the evaluation of a board into the terms it is made of
+    pub fn explain(&self, board: &Bitboard) -> EvalBreakdown {
+        match self {
+            Classical(_, params) => {
+                let (terms, len) = classical_terms(board, params);
+                EvalBreakdown::new(terms[..len].to_vec())
+            }
+            Nnue => panic!("Cannot use NNUE evaluation yet!"),
+            #[cfg(feature = "onnx")]
+            Onnx(model) => EvalBreakdown {
+                terms: match game_result(board) {
+                    Some(result) => vec![("result", result)],
+                    None => vec![("model", model.eval(board))],
+                },
+            },
         }
     }
 }
 
-impl default::Default for BoardEvaluator {
-    fn default() -> Self {
-        Classical(Box::new(|board: &Bitboard| {
-            if let GameState::Completed(winner) = board.get_game_state() {
-                match winner {
-                    Winner::Player(Color::Black) => return Score::from(f32::INFINITY),
-                    Winner::Player(Color::White) => return Score::from(f32::NEG_INFINITY),
-                    Winner::Draw => return Score::from(0.),
-                }
+/// Lists the terms of an evaluation with what each of them adds to it, from black's point
+/// of view. The terms add up to the total.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalBreakdown {
+    terms: Vec<(&'static str, Score)>,
+}
+
+impl EvalBreakdown {
+    pub(crate) fn new(terms: Vec<(&'static str, Score)>) -> Self {
+        EvalBreakdown { terms }
+    }
+
+    /// Returns the name and the contribution of every term
+    #[inline]
+    pub fn terms(&self) -> &[(&'static str, Score)] {
+        &self.terms
+    }
+
+    /// Returns the evaluation the terms add up to
+    pub fn total(&self) -> Score {
+        sum(&self.terms)
+    }
+}
+
+impl fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, score) in &self.terms {
+            writeln!(f, "{:<10}{}", name, score)?;
+        }
+        write!(f, "{:<10}{}", "total", self.total())
+    }
+}
+
+/// Returns what `terms` add up to
+fn sum(terms: &[(&'static str, Score)]) -> Score {
+    terms.iter().fold(Score::DRAW, |total, (_, score)| {
+        total.saturating_add(*score)
+    })
+}
+
+/// Returns the score of a finished game, or `None` while the game goes on
+fn game_result(board: &Bitboard) -> Option<Score> {
+    match board.get_game_state() {
+        GameState::Completed(Winner::Player(Color::Black)) => Some(Score::INFINITY),
+        GameState::Completed(Winner::Player(Color::White)) => Some(Score::NEG_INFINITY),
+        GameState::Completed(Winner::Draw) => Some(Score::DRAW),
+        GameState::InProgress => None,
+    }
+}
+
+/// Returns whether the game is drawn however it is played on, though it is not over yet.
+/// Only endings of kings alone with nothing to capture are recognized: a king against a
+/// king anywhere on the board, and as many kings on each side holding opposite double
+/// corners. The game itself only ends for lone kings that are both in double corners.
+fn dead_draw(board: &Bitboard) -> bool {
+    let (blacks, whites) = (board.blacks(), board.whites());
+    if board.variant().rules().giveaway || (blacks | whites) & !board.kings() != 0 {
+        return false;
+    }
+    if board.mobility(Color::Black).1 > 0 || board.mobility(Color::White).1 > 0 {
+        return false;
+    }
+    if blacks.count_ones() != whites.count_ones() {
+        return false;
+    }
+
+    let [first, second] = DOUBLE_CORNERS;
+    let holds = |kings: u32, corner: u32| kings & !corner == 0;
+    blacks.count_ones() == 1
+        || (holds(blacks, first) && holds(whites, second))
+        || (holds(blacks, second) && holds(whites, first))
+}
+
+/// Returns how many rows in all the men of `color` have come from their own back row
+fn advancement(men: u32, color: Color) -> Score {
+    let rows = (0..8).map(|row| (row, (men >> (4 * row) & 0xf).count_ones()));
+    let total: u32 = match color {
+        Color::Black => rows.map(|(row, count)| row * count).sum(),
+        Color::White => rows.map(|(row, count)| (7 - row) * count).sum(),
+    };
+    Score::from(total as f32)
+}
+
+// the classical evaluation and its breakdown are both built from these terms so the two can
+// never disagree. they go in an array, with how many of them are used, as the evaluation of
+// every leaf of a search has them and cannot afford to allocate
+fn classical_terms(
+    board: &Bitboard,
+    params: &EvalParams,
+) -> ([(&'static str, Score); CLASSICAL_TERMS], usize) {
+    let only = |term: (&'static str, Score)| {
+        let mut terms = [("", Score::DRAW); CLASSICAL_TERMS];
+        terms[0] = term;
+        (terms, 1)
+    };
+    if let Some(result) = game_result(board) {
+        return only(("result", result));
+    }
+    // a draw scores exactly even, so the search stops shuffling kings around for the
+    // sake of a better square
+    if dead_draw(board) {
+        return only(("drawn", Score::DRAW));
+    }
+
+    // reaccess this as mask
+    let count_ones = |mut mask: u32| {
+        let mut count = Score::from(0.);
+        while mask != 0 {
+            if mask & 1 == 1 {
+                count += Score::from(1.);
             }
+            mask >>= 1;
+        }
+        count
+    };
+
+    let black_kings = board.blacks() & board.kings();
+    let white_kings = board.whites() & board.kings();
+    let black_men = board.blacks() & !board.kings();
+    let white_men = board.whites() & !board.kings();
+    let weights = params.weights(board.phase());
+    let weight = |weight: f32| Score::from(weight);
 
-            // reaccess this as mask
-            let count_ones = |mut mask: u32| {
-                let mut count = Score::from(0.);
-                while mask != 0 {
-                    if mask & 1 == 1 {
-                        count += Score::from(1.);
-                    }
-                    mask >>= 1;
-                }
-                count
-            };
+    // every piece is worth a man, and kings are worth what they have over men
+    let material = weight(weights.man) * (count_ones(board.blacks()) - count_ones(board.whites()));
+    let kings =
+        weight(weights.king - weights.man) * (count_ones(black_kings) - count_ones(white_kings));
+    let center = weight(weights.center)
+        * (count_ones(black_kings & *CENTER) - count_ones(white_kings & *CENTER));
+    let tempo = weight(weights.tempo)
+        * (advancement(black_men, Color::Black) - advancement(white_men, Color::White));
+    // the structures are there or not, and count one for black or against it
+    let one = |there: bool| Score::from(there as u8 as f32);
+    let bridge = weight(weights.bridge)
+        * (one(black_men & BRIDGES[0] == BRIDGES[0]) - one(white_men & BRIDGES[1] == BRIDGES[1]));
+    // a man stuck in the dog-hole of its side is good for the other side
+    let stuck = |men: u32, others: u32, (hole, blocker): (u32, u32)| {
+        one(men & hole != 0 && others & blocker != 0)
+    };
+    let dog_hole = weight(weights.dog_hole)
+        * (stuck(white_men, board.blacks(), DOG_HOLES[1])
+            - stuck(black_men, board.whites(), DOG_HOLES[0]));
+    let alone = |men: u32, others: u32| one(men & APEX != 0 && others & APEX == 0);
+    let apex = match board.kings() {
+        0 => weight(weights.apex) * (alone(white_men, black_men) - alone(black_men, white_men)),
+        _ => Score::DRAW,
+    };
+    // the side to move keeps the move with an odd count in its system, and hands it to the
+    // other side otherwise
+    let system = match board.turn() {
+        Color::Black => SYSTEMS[0],
+        Color::White => SYSTEMS[1],
+    };
+    let has_move = match ((board.blacks() | board.whites()) & system).count_ones() % 2 {
+        1 => board.turn(),
+        _ => board.turn().opposite(),
+    };
+    let opposition = match has_move {
+        Color::Black => weight(weights.opposition),
+        Color::White => Score::DRAW - weight(weights.opposition),
+    };
+    // kings hemmed in are the ones that lose. a side that is ahead only looks at how little
+    // room the kings of the other have left, as its own kings have to give up some of
+    // theirs to close in, which is how endings of a king or two up are won without a
+    // tablebase
+    let room = |color: Color| Score::from(board.king_mobility(color) as f32);
+    let king_mobility = weight(weights.king_mobility)
+        * match board
+            .blacks()
+            .count_ones()
+            .cmp(&board.whites().count_ones())
+        {
+            cmp::Ordering::Greater => Score::DRAW - room(Color::White),
+            cmp::Ordering::Less => room(Color::Black),
+            cmp::Ordering::Equal => room(Color::Black) - room(Color::White),
+        };
+    let turn = match board.turn() {
+        Color::Black => weight(weights.turn),
+        Color::White => Score::DRAW - weight(weights.turn),
+    };
 
-            let black_kings = board.blacks() & board.kings();
-            let white_kings = board.whites() & board.kings();
+    // in giveaway checkers having fewer pieces is what is good. having the move is
+    // worth the same either way
+    let sign = |score: Score| match board.variant().rules().giveaway {
+        true => Score::from(0.) - score,
+        false => score,
+    };
 
-            count_ones(board.blacks()) - count_ones(board.whites()) + count_ones(black_kings)
-                - count_ones(white_kings)
-        }))
+    let terms = [
+        ("material", sign(material)),
+        ("kings", sign(kings)),
+        ("center", sign(center)),
+        ("tempo", sign(tempo)),
+        ("turn", turn),
+        ("bridge", sign(bridge)),
+        ("dog-hole", sign(dog_hole)),
+        ("apex", sign(apex)),
+        ("opposition", sign(opposition)),
+        ("mobility", sign(king_mobility)),
+    ];
+    (terms, CLASSICAL_TERMS)
+}
+
+impl default::Default for BoardEvaluator {
+    fn default() -> Self {
+        BoardEvaluator::classical(EvalParams::default())
     }
 }
 
@@ -77,16 +647,197 @@ mod tests {
     fn default_evaluator_test() {
         use crate::search::{Score, Searchable};
 
+        // even but for black having the move
         let board = Bitboard::from_fen(DEFAULT_BOARD).unwrap();
-        assert_eq!(board.evaluate(), Score::from(0.));
+        assert_eq!(board.evaluate(), Score::from(0.0625));
 
+        // black is a king up, but white has two kings in the middle to black's one
         let board = Bitboard::from_fen(TEST_BOARD_1).unwrap();
-        assert_eq!(board.evaluate(), Score::from(1.));
+        assert_eq!(board.evaluate(), Score::from(0.640625));
 
         let board = Bitboard::from_fen(TEST_BOARD_2).unwrap();
-        assert_eq!(board.evaluate(), Score::from(-3.));
+        assert_eq!(board.evaluate(), Score::from(-2.439_453_1));
 
         let board = Bitboard::from_fen(TEST_BOARD_3).unwrap();
-        assert_eq!(board.evaluate(), Score::from(1.));
+        assert_eq!(board.evaluate(), Score::from(1.435_546_9));
+
+        let board =
+            Bitboard::from_fen("giveaway:B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29").unwrap();
+        assert_eq!(board.evaluate(), Score::from(-0.515625));
+    }
+
+    #[test]
+    fn tapered_evaluation_test() {
+        let board = Bitboard::from_fen(DEFAULT_BOARD).unwrap();
+        assert_eq!(board.phase().pieces(), 24);
+        assert_eq!(board.phase().stage(), Stage::Opening);
+        assert_eq!(Phase::new(16).stage(), Stage::Midgame);
+        assert_eq!(Phase::new(16).endgame_weight(), 0.5);
+        assert_eq!(Phase::new(8).stage(), Stage::Endgame);
+        assert_eq!(Phase::new(2).endgame_weight(), 1.);
+
+        let params = EvalParams::new(EvalWeights::new(1., 3., 0.), EvalWeights::new(2., 3., 1.));
+        assert_eq!(
+            params.weights(Phase::new(16)),
+            EvalWeights::new(1.5, 3., 0.5)
+        );
+
+        // one king in the middle against none, late in the game
+        let board = Bitboard::from_fen("B:WK3,K4:BK15,K16").unwrap();
+        let breakdown = BoardEvaluator::classical(params).explain(&board);
+        assert_eq!(
+            breakdown.terms(),
+            &[
+                ("material", Score::from(0.)),
+                ("kings", Score::from(0.)),
+                ("center", Score::from(1.)),
+                ("tempo", Score::from(0.)),
+                ("turn", Score::from(0.)),
+                ("bridge", Score::from(0.)),
+                ("dog-hole", Score::from(0.)),
+                ("apex", Score::from(0.)),
+                ("opposition", Score::from(0.)),
+                ("mobility", Score::from(0.))
+            ]
+        );
+    }
+
+    #[test]
+    fn tempo_test() {
+        // the side to move is ahead in a position that is the same for both sides
+        let board = Bitboard::from_fen(DEFAULT_BOARD).unwrap();
+        let turn = board.evaluate();
+        assert!(turn > Score::DRAW);
+        assert_eq!(board.with_turn(Color::White).evaluate(), Score::DRAW - turn);
+
+        // and a position swapped around scores the other way
+        for fen in [TEST_BOARD_1, TEST_BOARD_2, TEST_BOARD_3].iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            assert_eq!(board.mirrored().evaluate(), Score::DRAW - board.evaluate());
+        }
+
+        // the men further up the board are the better placed ones
+        let back = Bitboard::from_fen("B:W25,26:B1,2").unwrap();
+        let ahead = Bitboard::from_fen("B:W25,26:B9,10").unwrap();
+        assert!(ahead.evaluate() > back.evaluate());
+        let tempo = |board: &Bitboard| board.explain().terms()[3];
+        assert_eq!(tempo(&back), ("tempo", Score::from(-0.125)));
+        assert_eq!(tempo(&ahead), ("tempo", Score::from(0.125)));
+
+        // without a weight for either there are no ties to break
+        let params = EvalParams::new(EvalWeights::new(1., 2., 0.), EvalWeights::new(1., 2., 0.));
+        assert_eq!(BoardEvaluator::classical(params).eval(&board), Score::DRAW);
+    }
+
+    #[test]
+    fn structure_test() {
+        let term = |fen: &str, name: &str| {
+            let board = Bitboard::from_fen(fen).unwrap();
+            let breakdown = board.explain();
+            assert_eq!(breakdown.total(), board.evaluate());
+            breakdown
+                .terms()
+                .iter()
+                .find(|(term, _)| *term == name)
+                .map(|(_, score)| f32::from(*score))
+                .unwrap()
+        };
+
+        // black keeps its bridge on 1 and 3 while white has broken its own
+        assert!(term("B:W21,22,30:B1,3,10", "bridge") > 0.);
+        assert_eq!(term("B:W21,30,32:B1,3,10", "bridge"), 0.);
+        // with so few pieces left it is worth the endgame weight
+        assert_eq!(term("B:W21,30,32:B1,2,10", "bridge"), -0.03125);
+
+        // the white man on 5 cannot move past the black one on 1, and the other way around
+        assert!(term("W:W5,20:B1,13", "dog-hole") > 0.);
+        assert!(term("B:W32,20:B28,13", "dog-hole") < 0.);
+        assert_eq!(term("W:W5,20:B2,13", "dog-hole"), 0.);
+
+        // black alone on the apex squares, which stops mattering once there are kings
+        let men = "B:W17,18,19,20,21,22,23,24:B5,6,7,8,9,10,11,12";
+        assert!(term(men, "apex") < 0.);
+        assert_eq!(term(&men.replace("W17", "W26"), "apex"), 0.);
+        assert_eq!(term(&men.replace("W17", "WK17"), "apex"), 0.);
+    }
+
+    #[test]
+    fn king_endgame_test() {
+        let term = |fen: &str, name: &str| {
+            let board = Bitboard::from_fen(fen).unwrap();
+            let (_, score) = *board
+                .explain()
+                .terms()
+                .iter()
+                .find(|(term, _)| *term == name)
+                .unwrap();
+            f32::from(score)
+        };
+
+        // kings facing each other on the h file with a square between them, and another
+        // pair out of the way. the side to move has to give way
+        assert_eq!(term("B:WK20,K29:BK12,K5", "opposition"), -0.125);
+        assert_eq!(term("W:WK20,K29:BK12,K5", "opposition"), 0.125);
+        // none of it matters with every piece still on the board
+        assert_eq!(term(DEFAULT_BOARD, "opposition"), 0.);
+
+        // two kings against one are better off with the one stuck in a corner, whatever
+        // room their own kings have
+        assert_eq!(term("B:WK1:BK6,K10", "mobility"), -0.03125);
+        assert_eq!(term("B:WK14:BK1,K3", "mobility"), -0.125);
+        // with even material the room of both sides counts, and here they have as much
+        assert_eq!(term("B:WK14,K1:BK6,K10", "mobility"), 0.);
+    }
+
+    #[test]
+    fn dead_draw_test() {
+        let drawn = ["B:WK4:BK15", "W:WK28,K32:BK1,K5", "B:WK2:BK31"];
+        for fen in drawn.iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            assert!(dead_draw(&board), "{}", fen);
+            assert_eq!(board.explain().terms(), &[("drawn", Score::DRAW)]);
+        }
+
+        let not_drawn = [
+            // black can capture
+            "B:WK18:BK14",
+            // white has a man left that can crown
+            "B:WK4,20:BK15,K32",
+            // the kings are not in the double corners
+            "W:WK28,K32:BK1,K14",
+            "W:WK27,K32:BK14,K15,K22",
+            "giveaway:B:WK4:BK15",
+        ];
+        for fen in not_drawn.iter() {
+            let board = Bitboard::from_fen(fen).unwrap();
+            assert!(!dead_draw(&board), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn explain_test() {
+        let board = Bitboard::from_fen(TEST_BOARD_2).unwrap();
+        let breakdown = GLOBAL_EVAL.explain(&board);
+        assert_eq!(
+            breakdown.terms(),
+            &[
+                ("material", Score::from(-2.)),
+                ("kings", Score::from(-0.5625)),
+                ("center", Score::from(-0.234375)),
+                ("tempo", Score::from(0.283_203_13)),
+                ("turn", Score::from(-0.0625)),
+                ("bridge", Score::from(0.)),
+                ("dog-hole", Score::from(0.)),
+                ("apex", Score::from(0.)),
+                ("opposition", Score::from(0.109375)),
+                ("mobility", Score::from(0.02734375))
+            ]
+        );
+        assert_eq!(breakdown.total(), board.evaluate());
+
+        let board = Bitboard::from_fen("W:W:B1").unwrap();
+        let breakdown = GLOBAL_EVAL.explain(&board);
+        assert_eq!(breakdown.total(), Score::INFINITY);
+        assert_eq!(breakdown.to_string(), "result    inf\ntotal     inf");
     }
 }
@@ -0,0 +1,211 @@
+// c interface for embedding the engine in other programs. the functions are declared in
+// include/muskox.h, which has to be kept in step with this file. engines are opaque
+// handles, positions are passed as fen strings and actions as movetext. strings
+// returned to the caller are owned by it and have to be given back to
+// `muskox_string_free`. a panic must not unwind into the caller, so every function
+// catches them and fails the way it does on bad input instead
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+use crate::board::Bitboard;
+use crate::search::{Engine, SearchConstraint, Searchable};
+
+pub const MUSKOX_OK: c_int = 0;
+pub const MUSKOX_ERROR: c_int = -1;
+
+/// Represents an engine along with the position it searches
+pub struct MuskoxEngine {
+    engine: Engine<Bitboard>,
+    board: Bitboard,
+}
+
+fn into_c_string(text: String) -> *mut c_char {
+    CString::new(text).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+// runs the body of an exported function, returning `failed` if it panics
+fn guard<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(failed)
+}
+
+/// Creates an engine with the default settings, set up at the starting position. It has
+/// to be given back to `muskox_engine_free`. Returns null if it cannot be created.
+#[no_mangle]
+pub extern "C" fn muskox_engine_new() -> *mut MuskoxEngine {
+    guard(ptr::null_mut(), || {
+        let engine = MuskoxEngine {
+            engine: Engine::new(),
+            board: Bitboard::default(),
+        };
+        Box::into_raw(Box::new(engine))
+    })
+}
+
+/// Destroys an engine. Null pointers are ignored.
+///
+/// # Safety
+///
+/// `engine` has to come from `muskox_engine_new` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn muskox_engine_free(engine: *mut MuskoxEngine) {
+    if !engine.is_null() {
+        guard((), || drop(Box::from_raw(engine)));
+    }
+}
+
+/// Sets the position the engine searches from a fen string. Returns `MUSKOX_OK`, or
+/// `MUSKOX_ERROR` if the fen is invalid, in which case the position is unchanged.
+///
+/// # Safety
+///
+/// `engine` has to be a live engine and `fen` a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn muskox_set_position(
+    engine: *mut MuskoxEngine,
+    fen: *const c_char,
+) -> c_int {
+    if engine.is_null() || fen.is_null() {
+        return MUSKOX_ERROR;
+    }
+
+    guard(MUSKOX_ERROR, || {
+        let board = CStr::from_ptr(fen)
+            .to_str()
+            .ok()
+            .and_then(|fen| Bitboard::from_fen(fen).ok());
+
+        match board {
+            Some(board) => {
+                (*engine).board = board;
+                MUSKOX_OK
+            }
+            None => MUSKOX_ERROR,
+        }
+    })
+}
+
+/// Returns the legal actions in the engine's position as movetext separated by spaces,
+/// or null if `engine` is null or they cannot be listed. The string is empty once the
+/// game is over.
+///
+/// # Safety
+///
+/// `engine` has to be a live engine.
+#[no_mangle]
+pub unsafe extern "C" fn muskox_legal_moves(engine: *const MuskoxEngine) -> *mut c_char {
+    if engine.is_null() {
+        return ptr::null_mut();
+    }
+
+    guard(ptr::null_mut(), || {
+        let actions: Vec<_> = (*engine)
+            .board
+            .generate_all_actions()
+            .iter()
+            .map(|p| p.action().to_string())
+            .collect();
+        into_c_string(actions.join(" "))
+    })
+}
+
+/// Searches the engine's position for at most `milliseconds` and returns the movetext of
+/// the best action, or null if the game is over or the search failed. The call blocks
+/// until the search ends.
+///
+/// # Safety
+///
+/// `engine` has to be a live engine.
+#[no_mangle]
+pub unsafe extern "C" fn muskox_search(
+    engine: *const MuskoxEngine,
+    milliseconds: u32,
+) -> *mut c_char {
+    if engine.is_null() {
+        return ptr::null_mut();
+    }
+
+    guard(ptr::null_mut(), || {
+        let MuskoxEngine { engine, board } = &*engine;
+        let constraint = SearchConstraint::Time(Duration::from_millis(milliseconds.into()));
+
+        match engine.search(board, &constraint).best() {
+            Some(best) => into_c_string(best.action().to_string()),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// Frees a string returned by the library. Null pointers are ignored.
+///
+/// # Safety
+///
+/// `string` has to come from this library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn muskox_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        guard((), || drop(CString::from_raw(string)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn take_string(string: *mut c_char) -> String {
+        let owned = CStr::from_ptr(string).to_str().unwrap().to_string();
+        muskox_string_free(string);
+        owned
+    }
+
+    #[test]
+    fn ffi_test() {
+        unsafe {
+            let engine = muskox_engine_new();
+            assert_eq!(
+                take_string(muskox_legal_moves(engine)).split(' ').count(),
+                7
+            );
+
+            let fen = CString::new("B:W18:B14").unwrap();
+            assert_eq!(muskox_set_position(engine, fen.as_ptr()), MUSKOX_OK);
+            assert_eq!(take_string(muskox_legal_moves(engine)), "14-23");
+            assert_eq!(take_string(muskox_search(engine, 100)), "14-23");
+
+            // a bad fen leaves the position alone
+            let fen = CString::new("B:W18:B99").unwrap();
+            assert_eq!(muskox_set_position(engine, fen.as_ptr()), MUSKOX_ERROR);
+            assert_eq!(take_string(muskox_legal_moves(engine)), "14-23");
+
+            let fen = CString::new("W:W:B1").unwrap();
+            assert_eq!(muskox_set_position(engine, fen.as_ptr()), MUSKOX_OK);
+            assert_eq!(take_string(muskox_legal_moves(engine)), "");
+            assert!(muskox_search(engine, 100).is_null());
+
+            muskox_engine_free(engine);
+        }
+
+        // panics come back as failures
+        assert_eq!(
+            guard(MUSKOX_ERROR, || panic!("search failed")),
+            MUSKOX_ERROR
+        );
+        assert!(into_c_string("14\0-23".to_string()).is_null());
+
+        // every function is declared in the header
+        let header = include_str!("../include/muskox.h");
+        for name in &[
+            "muskox_engine_new",
+            "muskox_engine_free",
+            "muskox_set_position",
+            "muskox_legal_moves",
+            "muskox_search",
+            "muskox_string_free",
+        ] {
+            assert!(header.contains(&format!("{}(", name)), "{}", name);
+        }
+    }
+}
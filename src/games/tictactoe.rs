@@ -0,0 +1,306 @@
+use std::fmt;
+
+use lazy_static::lazy_static;
+
+use crate::error::TicTacToeError;
+use crate::search::{ActionStatePair, GameState, Optim, Score, Searchable, Side, Winner};
+use crate::zobrist::Prng;
+
+// tic-tac-toe is small enough to search to the end, so it pins down what the engine
+// expects from a game without any checkers specific shortcuts getting in the way
+
+const SEED: u64 = 8512295108372;
+
+// rows, columns and diagonals of squares numbered 0 to 8 left to right, top to bottom
+const LINES: [u16; 8] = [
+    0b000_000_111,
+    0b000_111_000,
+    0b111_000_000,
+    0b001_001_001,
+    0b010_010_010,
+    0b100_100_100,
+    0b100_010_001,
+    0b001_010_100,
+];
+const FULL: u16 = 0b111_111_111;
+
+lazy_static! {
+    // a key for every square and player, then one for o to move
+    static ref ZOBRIST_TABLE: [u64; 19] = {
+        let mut table = [0; 19];
+        let mut prng = Prng::new(SEED);
+
+        for entry in table.iter_mut() {
+            *entry = prng.rand64()
+        }
+
+        table
+    };
+}
+
+/// Represents one of the two players of tic-tac-toe. X goes first and is the maximizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Player {
+    X,
+    O,
+}
+
+impl Side for Player {
+    fn optim(&self) -> Optim {
+        match self {
+            Player::X => Optim::Max,
+            Player::O => Optim::Min,
+        }
+    }
+
+    fn opposite(&self) -> Player {
+        match self {
+            Player::X => Player::O,
+            Player::O => Player::X,
+        }
+    }
+}
+
+/// Represents a mark on one of the squares, numbered 0 to 8 from the top left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mark(u8);
+
+impl Mark {
+    pub fn new(square: u8) -> Result<Self, TicTacToeError> {
+        match square {
+            0..=8 => Ok(Mark(square)),
+            _ => Err(TicTacToeError::SquareRangeError { square }),
+        }
+    }
+
+    #[inline]
+    pub fn square(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Represents a game of tic-tac-toe, a second game for the search to run on
+///
+/// # Examples
+///
+/// ```
+/// use muskox::games::tictactoe::{Mark, TicTacToe};
+/// use muskox::search::{Engine, SearchConstraint, Searchable};
+///
+/// // x has taken two corners of the top row and o has to block the middle
+/// let mut board = TicTacToe::default();
+/// for square in [0, 4, 2] {
+///     board = board.take_action(Mark::new(square).unwrap()).unwrap();
+/// }
+///
+/// let engine = Engine::new();
+/// let result = engine.search(&board, &SearchConstraint::Depth(6));
+/// assert_eq!(result.best().unwrap().action(), Mark::new(1).unwrap());
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TicTacToe {
+    xs: u16,
+    os: u16,
+}
+
+impl TicTacToe {
+    fn winner(&self) -> Option<Player> {
+        let complete = |marks: u16| LINES.iter().any(|&line| line & !marks == 0);
+
+        match (complete(self.xs), complete(self.os)) {
+            (true, _) => Some(Player::X),
+            (_, true) => Some(Player::O),
+            _ => None,
+        }
+    }
+
+    fn square_hash(square: u8, player: Player) -> u64 {
+        match player {
+            Player::X => ZOBRIST_TABLE[square as usize],
+            Player::O => ZOBRIST_TABLE[9 + square as usize],
+        }
+    }
+}
+
+impl Searchable for TicTacToe {
+    type Action = Mark;
+    type Side = Player;
+    type ActionError = TicTacToeError;
+
+    fn generate_all_actions(&self) -> Vec<ActionStatePair<Self>> {
+        if let GameState::Completed(_) = self.get_game_state() {
+            return Vec::new();
+        }
+
+        let turn = self.turn();
+        let empty = FULL & !(self.xs | self.os);
+
+        (0..9)
+            .filter(|square| empty & (1 << square) != 0)
+            .map(|square| {
+                let mark = Mark(square);
+                let board = self.take_action(mark).unwrap();
+                let zobrist_diff = Self::square_hash(square, turn) ^ ZOBRIST_TABLE[18];
+                ActionStatePair::new(mark, board, zobrist_diff)
+            })
+            .collect()
+    }
+
+    fn take_action(&self, mark: Mark) -> Result<Self, TicTacToeError> {
+        let square = mark.square();
+
+        if let GameState::Completed(_) = self.get_game_state() {
+            return Err(TicTacToeError::GameOverError);
+        }
+        if (self.xs | self.os) & (1 << square) != 0 {
+            return Err(TicTacToeError::SquareTakenError { square });
+        }
+
+        let mut board = *self;
+        match self.turn() {
+            Player::X => board.xs |= 1 << square,
+            Player::O => board.os |= 1 << square,
+        }
+        Ok(board)
+    }
+
+    fn get_game_state(&self) -> GameState<Self> {
+        match self.winner() {
+            Some(player) => GameState::Completed(Winner::Player(player)),
+            None if self.xs | self.os == FULL => GameState::Completed(Winner::Draw),
+            None => GameState::InProgress,
+        }
+    }
+
+    /// Returns whose turn it is. X moves whenever both players have as many marks.
+    fn turn(&self) -> Player {
+        match self.xs.count_ones() == self.os.count_ones() {
+            true => Player::X,
+            false => Player::O,
+        }
+    }
+
+    /// Scores won games only, from x's point of view. Every other position is even
+    /// until the search reaches the end of the game.
+    fn evaluate(&self) -> Score {
+        match self.winner() {
+            Some(Player::X) => Score::from(1.),
+            Some(Player::O) => Score::from(-1.),
+            None => Score::from(0.),
+        }
+    }
+
+    fn zobrist_hash(&self) -> u64 {
+        let mut zobrist_hash = 0;
+
+        for square in 0..9 {
+            if self.xs & (1 << square) != 0 {
+                zobrist_hash ^= Self::square_hash(square, Player::X);
+            }
+            if self.os & (1 << square) != 0 {
+                zobrist_hash ^= Self::square_hash(square, Player::O);
+            }
+        }
+
+        if self.turn() == Player::O {
+            zobrist_hash ^= ZOBRIST_TABLE[18];
+        }
+
+        zobrist_hash
+    }
+}
+
+impl fmt::Display for TicTacToe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rows: Vec<String> = (0..3)
+            .map(|row| {
+                (0..3)
+                    .map(|col| {
+                        let mask = 1 << (3 * row + col);
+                        match (self.xs & mask != 0, self.os & mask != 0) {
+                            (true, _) => 'x',
+                            (_, true) => 'o',
+                            _ => '.',
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{Engine, MctsEngine, SearchConstraint};
+
+    fn play(squares: &[u8]) -> TicTacToe {
+        squares.iter().fold(TicTacToe::default(), |board, &square| {
+            board.take_action(Mark::new(square).unwrap()).unwrap()
+        })
+    }
+
+    #[test]
+    fn rules_test() {
+        let board = play(&[0, 3, 1, 4, 2]);
+        assert_eq!(board.to_string(), "xxx\noo.\n...");
+        assert_eq!(
+            board.get_game_state(),
+            GameState::Completed(Winner::Player(Player::X))
+        );
+        assert!(board.generate_all_actions().is_empty());
+        assert_eq!(
+            board.take_action(Mark(5)),
+            Err(TicTacToeError::GameOverError)
+        );
+
+        let board = play(&[0, 4, 8, 1, 7, 6, 2, 5, 3]);
+        assert_eq!(board.get_game_state(), GameState::Completed(Winner::Draw));
+
+        assert_eq!(
+            play(&[4]).take_action(Mark(4)),
+            Err(TicTacToeError::SquareTakenError { square: 4 })
+        );
+        assert!(Mark::new(9).is_err());
+    }
+
+    #[test]
+    fn zobrist_test() {
+        // the diff of every action has to agree with hashing the new state from scratch
+        for board in [play(&[]), play(&[4]), play(&[4, 0, 8])] {
+            for pair in board.generate_all_actions() {
+                assert_eq!(
+                    board.zobrist_hash() ^ pair.zobrist_diff(),
+                    pair.state().zobrist_hash()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn search_test() {
+        let engine = Engine::new();
+
+        // perfect play from the start is a draw
+        let result = engine.search(&TicTacToe::default(), &SearchConstraint::Depth(9));
+        assert_eq!(result.best().unwrap().score(), Score::from(0.));
+
+        // each side takes its own win, with the score from x's point of view
+        let result = engine.search(&play(&[0, 3, 1, 4]), &SearchConstraint::Depth(2));
+        assert_eq!(result.best().unwrap().action(), Mark(2));
+        assert_eq!(result.best().unwrap().score(), Score::from(1.));
+
+        // blocking at 2 wins for o as well, just later
+        let result = engine.search(&play(&[0, 3, 1, 4, 8]), &SearchConstraint::Depth(2));
+        assert_eq!(result.best().unwrap().score(), Score::from(-1.));
+        let pair = result.results().iter().find(|p| p.action() == Mark(5));
+        assert_eq!(pair.unwrap().score(), Score::from(-1.));
+
+        // the tree search knows nothing but the rules and still finds the win
+        let mcts = MctsEngine::new();
+        let result = mcts.search(&play(&[0, 3, 1, 4]), &SearchConstraint::Depth(1));
+        assert_eq!(result.best().unwrap().action(), Mark(2));
+    }
+}
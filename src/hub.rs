@@ -0,0 +1,398 @@
+use std::io::{self, BufRead};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::board::{Action, Bitboard, Color, Square, Variant};
+use crate::error::ParseError;
+use crate::parse;
+use crate::search::{Engine, Score, SearchConstraint, SearchInfo, Searchable};
+
+// implements the hub protocol that draughts guis use to talk to engines. the gui sends
+// one message per line and we answer on stdout. searches run on their own thread so
+// that `stop` can still be read while we think
+
+// how many moves we assume are left in the game when the gui does not tell us
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// Represents how much time or depth the gui gave us to think
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Level {
+    Depth(u32),
+    MoveTime(Duration),
+    Clock {
+        time: Duration,
+        increment: Duration,
+        moves: Option<u64>,
+    },
+    Infinite,
+}
+
+impl Level {
+    fn constraint(&self) -> SearchConstraint {
+        match *self {
+            Level::Depth(depth) => SearchConstraint::Depth(depth),
+            Level::MoveTime(time) => SearchConstraint::Time(time),
+            Level::Clock {
+                time,
+                increment,
+                moves,
+            } => {
+                // spread the clock evenly over the remaining moves and keep most of the
+                // increment as a safety margin
+                let moves = moves.unwrap_or(DEFAULT_MOVES_TO_GO).max(1) as u32;
+                let budget = time / moves + increment / 2;
+                SearchConstraint::Time(budget.min(time / 2))
+            }
+            Level::Infinite => SearchConstraint::Time(Duration::MAX),
+        }
+    }
+}
+
+struct Search {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Engine<Bitboard>>,
+}
+
+struct Hub {
+    board: Bitboard,
+    variant: Variant,
+    level: Level,
+    engine: Option<Engine<Bitboard>>,
+    search: Option<Search>,
+}
+
+impl Hub {
+    fn new() -> Self {
+        Hub {
+            board: Bitboard::default(),
+            variant: Variant::default(),
+            level: Level::MoveTime(Duration::from_secs(1)),
+            engine: Some(Engine::new()),
+            search: None,
+        }
+    }
+
+    fn execute(&mut self, line: &str) -> Result<(), String> {
+        let (_, (name, arguments)) =
+            parse::message_primary(line).map_err(|e| ParseError::from(e).to_string())?;
+
+        let argument = |key: &str| {
+            arguments
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.unwrap_or(""))
+        };
+
+        match name {
+            "hub" => self.handshake(),
+            "init" => println!("ready"),
+            "ping" => println!("pong"),
+            "set-param" => {
+                let name = argument("name").ok_or("missing parameter name")?;
+                let value = argument("value").ok_or("missing parameter value")?;
+                self.set_param(name, value)?
+            }
+            "new-game" => {
+                self.finish_search();
+                self.board = Bitboard::default().with_variant(self.variant);
+                self.engine_mut().reset();
+            }
+            "pos" => {
+                let pos = argument("pos");
+                let moves = argument("moves").unwrap_or("");
+                self.set_position(pos, moves)?
+            }
+            "level" => self.set_level(&argument)?,
+            "go" => {
+                let infinite = arguments
+                    .iter()
+                    .any(|(k, _)| *k == "ponder" || *k == "analyze");
+                self.go(infinite)
+            }
+            // we do not keep track of the time spent pondering, so we simply move now
+            "stop" | "ponder-hit" => self.finish_search(),
+            "quit" => {
+                self.finish_search();
+                process::exit(0)
+            }
+            _ => return Err(format!("unknown message {}", name)),
+        }
+
+        Ok(())
+    }
+
+    fn handshake(&self) {
+        println!(
+            "id name={} version={} author=\"{}\"",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_AUTHORS")
+        );
+        println!(
+            "param name=variant value={} type=enum values=\"american italian russian brazilian giveaway\"",
+            self.variant
+        );
+        println!("wait");
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "variant" => {
+                self.variant = value.parse().map_err(|e: ParseError| e.to_string())?;
+                self.board = self.board.with_variant(self.variant);
+                Ok(())
+            }
+            _ => Err(format!("unknown parameter {}", name)),
+        }
+    }
+
+    fn set_position(&mut self, pos: Option<&str>, moves: &str) -> Result<(), String> {
+        self.finish_search();
+
+        let mut board = match pos {
+            Some(pos) => parse::protocol_position_primary(pos)
+                .map_err(|e| ParseError::from(e).to_string())?
+                .1
+                .with_variant(self.variant),
+            None => Bitboard::default().with_variant(self.variant),
+        };
+
+        for text in moves.split_whitespace() {
+            let action = parse_move(&board, text)?;
+            board = board.take_action(action).map_err(|e| e.to_string())?;
+        }
+
+        self.board = board;
+        Ok(())
+    }
+
+    fn set_level<'a, F>(&mut self, argument: &F) -> Result<(), String>
+    where
+        F: Fn(&str) -> Option<&'a str>,
+    {
+        let number = |key: &str| -> Result<Option<f64>, String> {
+            argument(key)
+                .map(|v| {
+                    v.parse::<f64>()
+                        .map_err(|_| format!("invalid {} value", key))
+                })
+                .transpose()
+        };
+        let seconds = |s: f64| Duration::from_secs_f64(s.max(0.));
+
+        self.level = if argument("infinite").is_some() {
+            Level::Infinite
+        } else if let Some(depth) = number("depth")? {
+            let depth = (depth as u32).max(1);
+            SearchConstraint::depth(depth)?;
+            Level::Depth(depth)
+        } else if let Some(time) = number("move-time")? {
+            Level::MoveTime(seconds(time))
+        } else if let Some(time) = number("time")? {
+            Level::Clock {
+                time: seconds(time),
+                increment: seconds(number("inc")?.unwrap_or(0.)),
+                moves: number("moves")?.map(|m| m as u64),
+            }
+        } else {
+            return Err("unknown level".to_string());
+        };
+
+        Ok(())
+    }
+
+    fn go(&mut self, infinite: bool) {
+        self.finish_search();
+
+        let engine = self.engine.take().unwrap();
+        let board = self.board;
+        let constraint = match infinite {
+            true => Level::Infinite.constraint(),
+            false => self.level.constraint(),
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+
+        let handle = thread::spawn(move || {
+            let results =
+                engine.search_with(&board, &constraint, &flag, |info| print_info(&board, info));
+
+            match results.best() {
+                Some(p) => println!("done move={}", format_move(&board, &p.action())),
+                None => println!("done"),
+            }
+
+            engine
+        });
+
+        self.search = Some(Search { stop, handle });
+    }
+
+    /// Stops the running search (if any) and waits for it to report its move
+    fn finish_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            self.engine = Some(search.handle.join().expect("search thread panicked"));
+        }
+    }
+
+    fn engine_mut(&mut self) -> &mut Engine<Bitboard> {
+        self.engine.as_mut().unwrap()
+    }
+}
+
+fn print_info(board: &Bitboard, info: &SearchInfo<Bitboard>) {
+    let best = match info.best() {
+        Some(best) => best,
+        None => return,
+    };
+
+    println!(
+        "info depth={} score={} time={:.3} pv=\"{}\"",
+        info.depth(),
+        format_score(board.turn(), best.score()),
+        info.elapsed().as_secs_f64(),
+        format_move(board, &best.action())
+    );
+}
+
+/// Formats a score from the point of view of the side to move like the protocol expects
+fn format_score(turn: Color, score: Score) -> String {
+    // our scores are always from black's point of view
+    let score = match turn {
+        Color::Black => score,
+        Color::White => Score::from(0.) - score,
+    };
+
+    if score == Score::INFINITY {
+        return "1000".to_string();
+    }
+    if score == Score::NEG_INFINITY {
+        return "-1000".to_string();
+    }
+
+    format!("{:.2}", score)
+}
+
+/// Writes a move in hub notation. Captures list the source, destination and then every
+/// captured square, separated by `x`.
+fn format_move(board: &Bitboard, action: &Action) -> String {
+    let effects = match board.action_effects(action) {
+        Ok(effects) => effects,
+        Err(_) => return action.movetext(),
+    };
+
+    let source = effects.source();
+    let destination = effects.destination();
+
+    if effects.captured() == 0 {
+        return format!("{}-{}", source, destination);
+    }
+
+    let mut out = format!("{}x{}", source, destination);
+    for square in effects.captured_squares() {
+        out.push_str(&format!("x{}", square));
+    }
+    out
+}
+
+/// Finds the legal action a move in hub notation refers to. Our own movetext listing
+/// every landing square is understood as well.
+fn parse_move(board: &Bitboard, text: &str) -> Result<Action, String> {
+    let invalid = || format!("invalid move {}", text);
+
+    let squares = text
+        .split(['-', 'x'])
+        .map(|s| {
+            s.parse::<u8>()
+                .ok()
+                .and_then(Square::from_pdn)
+                .ok_or_else(invalid)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if squares.len() < 2 {
+        return Err(invalid());
+    }
+
+    // movetext listing the landing squares rather than the captured ones
+    if !text.contains('x') {
+        if let Ok(action) = Action::from_movetext(text) {
+            if board.validate_action(action).is_ok() {
+                return Ok(action);
+            }
+        }
+    }
+
+    board
+        .find_action(squares[0], squares[1], &squares[2..])
+        .ok_or_else(invalid)
+}
+
+/// Runs the hub protocol on stdin and stdout until the gui sends `quit`
+pub fn run() -> ! {
+    let mut hub = Hub::new();
+
+    for line in io::stdin().lock().lines() {
+        let line = line.expect("Error with your standard input!");
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Err(message) = hub.execute(line) {
+            println!("error message=\"{}\"", message);
+        }
+    }
+
+    // the gui went away without saying goodbye
+    hub.finish_search();
+    process::exit(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_test() {
+        let (_, (name, arguments)) =
+            parse::message_primary("pos pos=Wbbbb moves=\"9-13 22-18\" start").unwrap();
+        assert_eq!(name, "pos");
+        assert_eq!(
+            arguments,
+            vec![
+                ("pos", Some("Wbbbb")),
+                ("moves", Some("9-13 22-18")),
+                ("start", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn position_test() {
+        let (_, board) =
+            parse::protocol_position_primary("Bbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww").unwrap();
+        assert_eq!(board, Bitboard::default());
+
+        assert!(parse::protocol_position_primary("Bbbbbbbbbbbbbeeeeeeeewwwwwwwwwwwx").is_err());
+    }
+
+    #[test]
+    fn move_notation_test() {
+        let board = Bitboard::from_fen("B:W11,18,26,27:B8").unwrap();
+
+        let action = Action::from_movetext("8-15-22-31").unwrap();
+        assert_eq!(format_move(&board, &action), "8x31x11x18x26");
+
+        assert_eq!(parse_move(&board, "8x31x11x18x26"), Ok(action));
+        assert_eq!(parse_move(&board, "8x31"), Ok(action));
+        assert_eq!(parse_move(&board, "8-15-22-31"), Ok(action));
+        assert!(parse_move(&board, "8-12").is_err());
+    }
+}
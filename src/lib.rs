@@ -1,24 +1,116 @@
+pub mod analysis;
 pub mod app;
+pub mod bench;
+#[cfg(feature = "cdylib")]
+pub mod cb;
+pub mod chinook;
+pub mod dataset;
+pub mod dxp;
+pub mod egtb;
 pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod hub;
+#[cfg(feature = "onnx")]
+pub mod onnx;
+pub mod options;
+pub mod pdn;
+pub mod perft;
+pub mod play;
+pub mod players;
+pub mod render;
+pub mod searchlog;
+pub mod selfplay;
+pub mod serve;
+pub mod session;
+pub mod sprt;
+pub mod symmetry;
+pub mod testsuite;
+pub mod tournament;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 mod action;
+mod base64;
 mod bitboard;
 mod evaluation;
 mod parse;
+mod square;
+mod variant;
 mod zobrist;
 
 pub mod search {
+    mod book;
+    mod depth;
     mod engine;
+    mod future;
+    mod mcts;
+    mod observer;
     mod score;
     mod searchable;
+    mod tablebase;
+    mod tree;
     mod tt;
 
+    pub use book::*;
+    pub use depth::Depth;
     pub use engine::*;
+    pub use future::*;
+    pub use mcts::*;
+    pub use observer::*;
     pub use score::*;
     pub use searchable::*;
+    pub use tablebase::*;
+    pub use tree::{NodeExit, SearchTree, TreeNode};
+}
+
+pub mod openings {
+    pub mod ballot;
+    pub mod bookfile;
+    mod names;
+
+    pub use names::*;
+}
+
+pub mod games {
+    pub mod tictactoe;
+}
+
+/// The types most programs need to play and search games of checkers, in one place.
+/// Everything here keeps its name and path across minor versions.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::prelude::*;
+///
+/// let engine: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+/// let board = Bitboard::default();
+/// let result = engine.search(&board, &SearchConstraint::Depth(4));
+/// let best: Action = result.best().unwrap().action();
+///
+/// let board = board.take_action(best).unwrap();
+/// assert_eq!(board.turn(), Color::White);
+/// assert_eq!(board.get_game_state(), GameState::InProgress);
+/// assert!(result.best().unwrap().score() < Score::MAX_EVAL);
+/// ```
+pub mod prelude {
+    pub use crate::board::{Action, Bitboard, Color};
+    pub use crate::search::{
+        Engine, GameState, Score, SearchConstraint, SearchOutcome, SearchResult, Searchable, Winner,
+    };
 }
 
 pub mod board {
     pub use super::action::*;
     pub use super::bitboard::*;
+    pub use super::evaluation::{
+        current_evaluator, install_evaluator, BoardEvaluator, EvalBreakdown, EvalParams,
+        EvalWeights, Phase, Stage,
+    };
+    pub use super::square::*;
+    pub use super::variant::*;
+    pub use super::zobrist::{Prng, ZobristKeys};
 }
@@ -1,5 +1,181 @@
-use muskox::app;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::process;
+
+use muskox::app::{self, OutputMode};
+use muskox::board::Color;
+#[cfg(feature = "onnx")]
+use muskox::board::{self, BoardEvaluator};
+use muskox::openings::bookfile;
+use muskox::{dxp, egtb, hub, play, selfplay, serve, sprt, tournament};
+
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_PLAY_PORT: u16 = 8081;
 
 fn main() {
-    app::run();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let mode = match args.iter().any(|a| a == "--json") {
+        true => OutputMode::Json,
+        false => OutputMode::Text,
+    };
+    let mut args: Vec<&str> = args
+        .iter()
+        .map(|s| s.as_str())
+        .filter(|s| *s != "--json")
+        .collect();
+
+    if let Some(i) = args.iter().position(|a| *a == "--log-level") {
+        init_logging(args.get(i + 1).copied());
+        args.drain(i..args.len().min(i + 2));
+    }
+
+    if let Some(i) = args.iter().position(|a| *a == "--model") {
+        load_model(args.get(i + 1).copied());
+        args.drain(i..args.len().min(i + 2));
+    }
+
+    match args.as_slice() {
+        ["--hub"] => hub::run(),
+        ["exec"] | ["exec", "-"] => {
+            let stdin = io::stdin();
+            exit_with(app::exec(stdin.lock(), io::stdout(), mode))
+        }
+        ["exec", path] => match File::open(path) {
+            Ok(file) => exit_with(app::exec(BufReader::new(file), io::stdout(), mode)),
+            Err(err) => exit_with(Err(err)),
+        },
+        // runs the same as the command of the interactive prompt
+        ["testsuite", ..] | ["bench"] => {
+            exit_with(app::exec(args.join(" ").as_bytes(), io::stdout(), mode))
+        }
+        ["dxp", "--connect", address] => exit_with(dxp::run(address).map(|_| 0)),
+        ["play"] => exit_with(play::run(DEFAULT_PLAY_PORT).map(|_| 0)),
+        ["play", "--port", port] => match port.parse() {
+            Ok(port) => exit_with(play::run(port).map(|_| 0)),
+            Err(_) => {
+                eprintln!("Error: invalid port {}", port);
+                process::exit(1);
+            }
+        },
+        ["book", "make", input, output] => {
+            exit_with(report_book(bookfile::make(input, output), output))
+        }
+        ["book", "merge", output, inputs @ ..] if !inputs.is_empty() => {
+            exit_with(report_book(bookfile::merge(inputs, output), output))
+        }
+        ["egtb", "build", "--pieces", pieces, "--out", dir] => match pieces.parse() {
+            Ok(pieces) => exit_with(egtb::run(pieces, dir).map(|_| 0)),
+            Err(_) => {
+                eprintln!("Error: invalid number of pieces {}", pieces);
+                process::exit(1);
+            }
+        },
+        ["selfplay", games, depth, path] => match (games.parse(), depth.parse()) {
+            (Ok(games), Ok(depth)) => exit_with(selfplay::run(games, depth, path).map(|_| 0)),
+            _ => {
+                eprintln!(
+                    "Error: invalid number of games {} or depth {}",
+                    games, depth
+                );
+                process::exit(1);
+            }
+        },
+        ["sprt", flags @ ..] => match sprt::run(flags) {
+            Ok(()) => process::exit(0),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                process::exit(1);
+            }
+        },
+        ["tournament", config] => match tournament::run(config) {
+            Ok(()) => process::exit(0),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                process::exit(1);
+            }
+        },
+        ["serve"] => exit_with(serve::run(DEFAULT_PORT).map(|_| 0)),
+        ["serve", "--port", port] => match port.parse() {
+            Ok(port) => exit_with(serve::run(port).map(|_| 0)),
+            Err(_) => {
+                eprintln!("Error: invalid port {}", port);
+                process::exit(1);
+            }
+        },
+        ["tui"] => run_tui(Color::Black),
+        ["tui", "--white"] => run_tui(Color::White),
+        _ => app::run_with(mode),
+    }
+}
+
+/// Plays a game against the engine in the terminal interface
+#[cfg(feature = "tui")]
+fn run_tui(human: Color) {
+    exit_with(muskox::tui::run(human).map(|_| 0))
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_: Color) {
+    eprintln!("Error: muskox was built without the tui feature");
+    process::exit(1);
+}
+
+/// Sends what searches do to stderr at the given level (error, warn, info, debug or trace)
+#[cfg(feature = "tracing")]
+fn init_logging(level: Option<&str>) {
+    match level.and_then(|level| level.parse::<tracing::Level>().ok()) {
+        Some(level) => tracing_subscriber::fmt()
+            .with_max_level(level)
+            .with_writer(io::stderr)
+            .init(),
+        None => {
+            eprintln!("Error: the log level has to be error, warn, info, debug or trace");
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+fn init_logging(_: Option<&str>) {
+    eprintln!("Warning: muskox was built without the tracing feature, so nothing is logged");
+}
+
+/// Evaluates boards with the onnx model at `path` from now on
+#[cfg(feature = "onnx")]
+fn load_model(path: Option<&str>) {
+    let path = path.unwrap_or_else(|| {
+        eprintln!("Error: --model needs the path of an onnx model");
+        process::exit(1);
+    });
+    match BoardEvaluator::onnx(path) {
+        Ok(evaluator) => board::install_evaluator(evaluator),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "onnx"))]
+fn load_model(_: Option<&str>) {
+    eprintln!("Error: muskox was built without the onnx feature, so it cannot load models");
+    process::exit(1);
+}
+
+fn report_book(written: io::Result<usize>, path: &str) -> io::Result<i32> {
+    let entries = written?;
+    eprintln!("wrote {} entries to {}", entries, path);
+    Ok(0)
+}
+
+fn exit_with(result: io::Result<i32>) -> ! {
+    match result {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use std::fmt;
+
+use tract_onnx::prelude::*;
+use tract_onnx::tract_core::internal::{dims, ensure};
+
+use crate::board::{Bitboard, FEATURE_COUNT};
+use crate::error::ModelError;
+use crate::search::Score;
+
+// evaluation by a model in the onnx format, so that evaluations trained in python or
+// anywhere else can be searched with without writing any rust. a board is fed to the
+// model as a row of its features, laid out as `Bitboard::features` documents, and the
+// model answers with the score of each row. rows are batched, which is how models are
+// fast
+
+type Plan = TypedSimplePlan<TypedModel>;
+
+/// Represents a model that evaluates boards. It takes a `[N, 135]` tensor of `f32`
+/// features, as laid out by `Bitboard::features`, and gives back `N` scores in pieces from black's
+/// point of view, as a `[N]` or `[N, 1]` tensor.
+pub struct OnnxModel {
+    path: String,
+    plan: Plan,
+}
+
+impl fmt::Debug for OnnxModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OnnxModel({})", self.path)
+    }
+}
+
+impl OnnxModel {
+    /// Loads the model at `path` and checks that it scores a board
+    pub fn load(path: &str) -> Result<Self, ModelError> {
+        let error = |err: TractError| ModelError::ModelLoadError {
+            path: path.to_string(),
+            message: err.to_string(),
+        };
+
+        let mut model = tract_onnx::onnx().model_for_path(path).map_err(error)?;
+        let batch = model.symbol_table.sym("N");
+        model
+            .set_input_fact(0, f32::fact(dims!(batch, FEATURE_COUNT)).into())
+            .map_err(error)?;
+        let plan = model
+            .into_optimized()
+            .and_then(|model| model.into_runnable())
+            .map_err(error)?;
+
+        let model = OnnxModel {
+            path: path.to_string(),
+            plan,
+        };
+        model.run(&[Bitboard::default()]).map_err(error)?;
+        Ok(model)
+    }
+
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn run(&self, boards: &[Bitboard]) -> TractResult<Vec<Score>> {
+        let features: Vec<f32> = boards.iter().flat_map(|board| board.features()).collect();
+        let input = tract_ndarray::Array2::from_shape_vec((boards.len(), FEATURE_COUNT), features)?;
+
+        let outputs = self.plan.run(tvec!(Tensor::from(input).into()))?;
+        let scores = outputs[0].as_slice::<f32>()?;
+        ensure!(
+            scores.len() == boards.len(),
+            "the model gave {} scores for {} boards",
+            scores.len(),
+            boards.len()
+        );
+
+        Ok(scores.iter().map(|&score| Score::from(score)).collect())
+    }
+
+    /// Scores boards whose games go on, from black's point of view, in a single run of
+    /// the model
+    ///
+    /// # Panics
+    ///
+    /// Panics if the model fails to run, which loading checks it does not
+    pub fn eval_batch(&self, boards: &[Bitboard]) -> Vec<Score> {
+        match boards.is_empty() {
+            true => Vec::new(),
+            false => self.run(boards).expect("the model failed to run"),
+        }
+    }
+
+    /// Scores a board whose game goes on, from black's point of view
+    pub fn eval(&self, board: &Bitboard) -> Score {
+        self.eval_batch(&[*board])[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn onnx_test() {
+        let err = OnnxModel::load("no_such_model.onnx").unwrap_err();
+        assert!(err.to_string().contains("no_such_model.onnx"));
+    }
+}
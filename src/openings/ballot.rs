@@ -0,0 +1,145 @@
+use std::fmt;
+
+use lazy_static::lazy_static;
+
+use crate::board::{Action, Bitboard};
+use crate::search::Searchable;
+use crate::zobrist::Prng;
+
+// the official deck bars a number of openings that are considered lost for one side.
+// we do not have that list yet so every legal three move opening is in the deck. the
+// numbering is stable: ballots are ordered by the squares of their first, second and
+// third moves
+
+lazy_static! {
+    static ref DECK: Vec<Ballot> = {
+        let mut deck = Vec::new();
+        let board = Bitboard::default();
+
+        for first in sorted_actions(&board) {
+            let board = board.take_action(first).unwrap();
+            for second in sorted_actions(&board) {
+                let board = board.take_action(second).unwrap();
+                for third in sorted_actions(&board) {
+                    deck.push(Ballot {
+                        actions: [first, second, third],
+                    });
+                }
+            }
+        }
+
+        deck
+    };
+}
+
+fn sorted_actions(board: &Bitboard) -> Vec<Action> {
+    let mut actions: Vec<_> = board
+        .generate_all_actions()
+        .iter()
+        .map(|p| *p.action())
+        .collect();
+    actions.sort_by_key(|a| (a.source(), a.destination(), a.movetext()));
+    actions
+}
+
+/// Represents a three move opening from the ballot deck used in tournament american
+/// checkers. Games start after both sides have played the moves of the ballot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ballot {
+    actions: [Action; 3],
+}
+
+impl Ballot {
+    /// Returns the three moves of the opening in the order they are played
+    #[inline]
+    pub fn actions(&self) -> &[Action; 3] {
+        &self.actions
+    }
+
+    /// Returns the board after the opening has been played from the starting position
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::openings::ballot;
+    /// use muskox::board::Color;
+    /// use muskox::search::Searchable;
+    ///
+    /// let board = ballot::get(1).unwrap().board();
+    /// assert_eq!(board.turn(), Color::White);
+    /// ```
+    pub fn board(&self) -> Bitboard {
+        self.actions
+            .iter()
+            .fold(Bitboard::default(), |board, action| {
+                board.take_action(*action).unwrap()
+            })
+    }
+}
+
+impl fmt::Display for Ballot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.actions[0], self.actions[1], self.actions[2]
+        )
+    }
+}
+
+/// Returns the whole deck of openings. Ballot number `n` is at index `n - 1`.
+pub fn deck() -> &'static [Ballot] {
+    &DECK
+}
+
+/// Returns the ballot with a certain number, counting from 1.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::openings::ballot;
+///
+/// assert_eq!(ballot::get(1).unwrap().to_string(), "9-13 21-17 5-9");
+/// assert!(ballot::get(0).is_none());
+/// ```
+pub fn get(number: usize) -> Option<&'static Ballot> {
+    match number {
+        0 => None,
+        _ => DECK.get(number - 1),
+    }
+}
+
+/// Applies the ballot with a certain number to a fresh board. Returns `None` if there is
+/// no ballot with that number.
+pub fn board(number: usize) -> Option<Bitboard> {
+    get(number).map(|b| b.board())
+}
+
+/// Draws a ballot from the deck, returning its number along with it
+pub fn random() -> (usize, &'static Ballot) {
+    let index = (Prng::from_time().rand64() % DECK.len() as u64) as usize;
+    (index + 1, &DECK[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deck_test() {
+        // every opening is legal and distinct
+        for (i, ballot) in deck().iter().enumerate() {
+            let _ = ballot.board();
+            assert!(deck()[i + 1..].iter().all(|b| b != ballot));
+        }
+
+        assert_eq!(
+            board(1).unwrap().fen(),
+            "W:W17,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,6,7,8,9,10,11,12,13"
+        );
+        assert!(board(deck().len() + 1).is_none());
+
+        let (number, ballot) = random();
+        assert_eq!(get(number), Some(ballot));
+    }
+}
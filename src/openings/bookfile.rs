@@ -0,0 +1,409 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::TryInto;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::board::{Action, Bitboard};
+use crate::pdn;
+use crate::search::{Book, OpeningBook, Searchable};
+
+// a book file holds the actions of an opening book sorted by the zobrist hash of the
+// position they are played in, so that the actions of a position are found by binary
+// search through the file mapped into memory instead of reading it in first. books
+// built from millions of games are far too large to load before every game. the file is
+// laid out as
+//
+//   magic, version, padding   b"MKBK", a byte and three zero bytes
+//   count                     u64, little endian, the number of entries
+//   entries                   ENTRY_SIZE bytes each, sorted by key and then action
+//
+// and every entry, all little endian, as
+//
+//   key                       u64, the zobrist hash of the position
+//   action                    u64, the action as the engine packs it
+//   weight                    u32, how often the action was played
+//   learn                     i32, the wins minus the losses of the engine playing it
+
+const MAGIC: &[u8; 4] = b"MKBK";
+const VERSION: u8 = 2;
+const HEADER_SIZE: usize = 16;
+const ENTRY_SIZE: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    key: u64,
+    action: u64,
+    weight: u32,
+    learn: i32,
+}
+
+impl Entry {
+    fn read(bytes: &[u8]) -> Self {
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        Entry {
+            key: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            action: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            weight: u32_at(16),
+            learn: u32_at(20) as i32,
+        }
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.key.to_le_bytes())?;
+        writer.write_all(&self.action.to_le_bytes())?;
+        writer.write_all(&self.weight.to_le_bytes())?;
+        writer.write_all(&self.learn.to_le_bytes())
+    }
+}
+
+fn invalid(path: &str, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, message))
+}
+
+// checks the header of a mapped book file, returning its number of entries
+fn check_header(path: &str, bytes: &[u8]) -> io::Result<usize> {
+    if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+        return Err(invalid(path, "not a book file"));
+    }
+    if bytes[4] != VERSION {
+        return Err(invalid(path, "unsupported book version"));
+    }
+
+    let count = u64::from_le_bytes(bytes[8..HEADER_SIZE].try_into().unwrap()) as usize;
+    match bytes.len() == HEADER_SIZE + count * ENTRY_SIZE {
+        true => Ok(count),
+        false => Err(invalid(path, "truncated book file")),
+    }
+}
+
+// the range of entries of a key, found by binary search
+fn find(bytes: &[u8], count: usize, key: u64) -> (usize, usize) {
+    let key_at = |i: usize| {
+        let offset = HEADER_SIZE + i * ENTRY_SIZE;
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    };
+    let partition = |pred: &dyn Fn(u64) -> bool| {
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match pred(key_at(mid)) {
+                true => low = mid + 1,
+                false => high = mid,
+            }
+        }
+        low
+    };
+    (partition(&|k| k < key), partition(&|k| k <= key))
+}
+
+/// Represents an action of a book file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookMove {
+    action: Action,
+    weight: u32,
+    learn: i32,
+}
+
+impl BookMove {
+    #[inline]
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    /// Returns how often the action was played in the games the book was made from
+    #[inline]
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// Returns how many more games the engine won than lost playing the action
+    #[inline]
+    pub fn learn(&self) -> i32 {
+        self.learn
+    }
+}
+
+/// Represents a book file mapped into memory. Only the pages of the file that searches
+/// look at are ever read.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::{Action, Bitboard};
+/// use muskox::openings::bookfile::{self, BookFile};
+/// use muskox::pdn;
+/// use muskox::search::Book;
+///
+/// let games = pdn::parse_games("1. 11-15 23-19 *\n\n1. 11-15 22-18 *\n\n1. 9-13 *").unwrap();
+/// let path = std::env::temp_dir().join("muskox_bookfile_doc.bin");
+/// let path = path.to_str().unwrap();
+/// bookfile::write(&Book::from_games(&games), path).unwrap();
+///
+/// let book = BookFile::open(path).unwrap();
+/// let moves = book.moves(&Bitboard::default());
+/// assert_eq!(moves[0].action(), Action::from_movetext("11-15").unwrap());
+/// assert_eq!(moves[0].weight(), 2);
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub struct BookFile {
+    path: String,
+    map: Mmap,
+    count: usize,
+}
+
+impl BookFile {
+    /// Maps the book file at `path` into memory, checking its header
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // book files are only ever written whole, by `write` and `merge`, into new files,
+        // or changed in place a counter at a time by `learn`
+        let map = unsafe { Mmap::map(&file)? };
+        let count = check_header(path, &map)?;
+
+        Ok(BookFile {
+            path: path.to_string(),
+            map,
+            count,
+        })
+    }
+
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the number of entries, one for every action of every position
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn entry(&self, i: usize) -> Entry {
+        let offset = HEADER_SIZE + i * ENTRY_SIZE;
+        Entry::read(&self.map[offset..offset + ENTRY_SIZE])
+    }
+
+    /// Returns the legal actions the book has for `board`, the most played first.
+    /// Actions played equally often come in the order the file has them in.
+    pub fn moves(&self, board: &Bitboard) -> Vec<BookMove> {
+        let (start, end) = find(&self.map, self.count, board.zobrist_hash());
+        if start == end {
+            return Vec::new();
+        }
+
+        // keys can collide, and files can be damaged
+        let legal: Vec<_> = board
+            .generate_all_actions()
+            .iter()
+            .map(|pair| *pair.action())
+            .collect();
+        let mut moves: Vec<_> = (start..end)
+            .map(|i| self.entry(i))
+            .map(|entry| BookMove {
+                action: Action::from_bits(entry.action),
+                weight: entry.weight,
+                learn: entry.learn,
+            })
+            .filter(|m| legal.contains(&m.action))
+            .collect();
+        moves.sort_by_key(|m| Reverse(m.weight));
+        moves
+    }
+
+    /// Adds `delta` to the learn counter of `action` in `board` in the book file at
+    /// `path`, such as 1 after the engine won a game playing it and -1 after it lost one.
+    /// Returns false if the book does not have the action.
+    pub fn learn(path: &str, board: &Bitboard, action: Action, delta: i32) -> io::Result<bool> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        // see `open`
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        let count = check_header(path, &map)?;
+
+        let (start, end) = find(&map, count, board.zobrist_hash());
+        for i in start..end {
+            let offset = HEADER_SIZE + i * ENTRY_SIZE;
+            let mut entry = Entry::read(&map[offset..offset + ENTRY_SIZE]);
+            if entry.action == action.to_bits() {
+                entry.learn = entry.learn.saturating_add(delta);
+                entry.write(&mut &mut map[offset..offset + ENTRY_SIZE])?;
+                map.flush()?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl OpeningBook<Bitboard> for BookFile {
+    /// Returns the actions of the book with their weights, leaving out the ones the
+    /// engine lost more games with than it won
+    fn actions(&self, state: &Bitboard) -> Vec<(Action, u32)> {
+        self.moves(state)
+            .into_iter()
+            .filter(|m| m.learn >= 0)
+            .map(|m| (m.action, m.weight))
+            .collect()
+    }
+}
+
+/// Returns whether the file at `path` is a book file rather than games in PDN
+pub fn is_book_file(path: &str) -> bool {
+    let mut magic = [0; 4];
+    File::open(path)
+        .and_then(|mut file| io::Read::read_exact(&mut file, &mut magic))
+        .is_ok()
+        && &magic == MAGIC
+}
+
+// writes entries sorted by key and then action to a new file at `path`, adding up the
+// weights and learn counters of entries of the same action. returns the number written
+fn write_sorted<I>(entries: I, path: &str) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Entry>,
+{
+    // the count is only known at the end
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION, 0, 0, 0])?;
+    writer.write_all(&0u64.to_le_bytes())?;
+
+    let mut count = 0u64;
+    let mut pending: Option<Entry> = None;
+    for entry in entries {
+        match &mut pending {
+            Some(last) if (last.key, last.action) == (entry.key, entry.action) => {
+                last.weight = last.weight.saturating_add(entry.weight);
+                last.learn = last.learn.saturating_add(entry.learn);
+            }
+            _ => {
+                if let Some(last) = pending.replace(entry) {
+                    last.write(&mut writer)?;
+                    count += 1;
+                }
+            }
+        }
+    }
+    if let Some(last) = pending {
+        last.write(&mut writer)?;
+        count += 1;
+    }
+
+    let mut file = writer.into_inner().map_err(|err| err.into_error())?;
+    file.seek(SeekFrom::Start(8))?;
+    file.write_all(&count.to_le_bytes())?;
+    Ok(count as usize)
+}
+
+/// Writes an opening book to a book file at `path`. Returns the number of entries
+/// written.
+pub fn write(book: &Book<Bitboard>, path: &str) -> io::Result<usize> {
+    let mut entries: Vec<_> = book
+        .entries()
+        .flat_map(|(key, actions)| {
+            actions.iter().map(move |(action, weight)| Entry {
+                key,
+                action: action.to_bits(),
+                weight: *weight,
+                learn: 0,
+            })
+        })
+        .collect();
+    entries.sort_unstable();
+    write_sorted(entries, path)
+}
+
+/// Makes a book file at `output` from the first moves of every game of the PDN file at
+/// `input`. Returns the number of entries written.
+pub fn make(input: &str, output: &str) -> io::Result<usize> {
+    let text = fs::read_to_string(input)?;
+    let games = pdn::parse_games(&text).map_err(|err| invalid(input, &err.to_string()))?;
+    write(&Book::from_games(&games), output)
+}
+
+/// Merges book files into a new one at `output`, adding up the weights and learn
+/// counters of the actions they share. The files are read through once each, side by
+/// side, so books larger than memory merge as well. Returns the number of entries
+/// written.
+pub fn merge(inputs: &[&str], output: &str) -> io::Result<usize> {
+    let books = inputs
+        .iter()
+        .map(|path| BookFile::open(path))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // the next entry of every book, smallest first
+    let mut heap: BinaryHeap<_> = books
+        .iter()
+        .enumerate()
+        .filter(|(_, book)| !book.is_empty())
+        .map(|(i, book)| Reverse((book.entry(0), i, 0)))
+        .collect();
+    let entries = std::iter::from_fn(|| {
+        let Reverse((entry, i, position)) = heap.pop()?;
+        if position + 1 < books[i].len() {
+            heap.push(Reverse((books[i].entry(position + 1), i, position + 1)));
+        }
+        Some(entry)
+    });
+
+    write_sorted(entries, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{Engine, SearchConstraint};
+
+    #[test]
+    fn bookfile_test() {
+        let dir = std::env::temp_dir();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        let (first, second, merged) = (
+            path("muskox_bookfile_1.bin"),
+            path("muskox_bookfile_2.bin"),
+            path("muskox_bookfile_merged.bin"),
+        );
+
+        let games = pdn::parse_games("1. 11-15 23-19 2. 8-11 *\n\n1. 11-15 22-18 *").unwrap();
+        assert_eq!(write(&Book::from_games(&games), &first).unwrap(), 4);
+        let games = pdn::parse_games("1. 9-13 22-18 *\n\n1. 11-15 23-19 *").unwrap();
+        write(&Book::from_games(&games), &second).unwrap();
+
+        // shared actions add up
+        assert_eq!(merge(&[&first, &second], &merged).unwrap(), 6);
+        let book = BookFile::open(&merged).unwrap();
+        let start = Bitboard::default();
+        let weights: Vec<_> = book
+            .moves(&start)
+            .iter()
+            .map(|m| (m.action().to_string(), m.weight()))
+            .collect();
+        assert_eq!(weights, [("11-15".to_string(), 3), ("9-13".to_string(), 1)]);
+        let board = start
+            .take_action(Action::from_movetext("11-15").unwrap())
+            .unwrap();
+        assert_eq!(book.moves(&board)[0].action().to_string(), "23-19");
+
+        // the engine leaves out actions it lost with
+        let eleven = Action::from_movetext("11-15").unwrap();
+        assert!(BookFile::learn(&merged, &start, eleven, -1).unwrap());
+        let book = BookFile::open(&merged).unwrap();
+        assert_eq!(book.moves(&start)[0].learn(), -1);
+        let engine = Engine::builder().threads(1).book(book).build().unwrap();
+        let result = engine.search(&start, &SearchConstraint::Depth(4));
+        assert_eq!(result.best().unwrap().action().to_string(), "9-13");
+
+        assert!(is_book_file(&merged));
+        assert!(BookFile::open("Cargo.toml").is_err());
+        for path in [first, second, merged] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+use lazy_static::lazy_static;
+
+use crate::board::{Action, Bitboard};
+use crate::pdn;
+use crate::search::Searchable;
+
+// the traditional names of american checkers openings. the seven first moves of black
+// have names of their own, and the popular replies and lines after them have more
+// specific ones. a game is named after the longest line it starts with
+
+const NAMES: [(&str, &str); 25] = [
+    ("9-13", "Edinburgh"),
+    ("9-14", "Double Corner"),
+    ("10-14", "Denny"),
+    ("10-15", "Kelso"),
+    ("11-15", "Old Faithful"),
+    ("11-16", "Bristol"),
+    ("12-16", "Dundee"),
+    ("11-15 21-17", "Switcher"),
+    ("11-15 22-18", "Single Corner"),
+    ("11-15 23-18", "Cross"),
+    ("11-15 24-19", "Second Double Corner"),
+    ("11-15 24-20", "Ayrshire Lassie"),
+    ("11-16 23-18", "Bristol Cross"),
+    ("11-16 24-19", "Paisley"),
+    ("11-15 22-17 15-19", "Dyke"),
+    ("11-15 23-19 9-13", "Will o' the Wisp"),
+    ("11-15 23-19 9-14 27-23", "Defiance"),
+    ("11-15 22-17 8-11 17-13 15-18", "Maid of the Mill"),
+    ("11-15 23-19 8-11 22-17 3-8", "Alma"),
+    ("11-15 23-19 8-11 22-17 4-8", "Old Fourteenth"),
+    ("11-15 23-19 8-11 22-17 9-13", "Laird and Lady"),
+    ("11-15 23-19 8-11 22-17 11-16", "Glasgow"),
+    ("11-15 23-19 9-14 22-17 5-9", "Fife"),
+    ("11-15 23-19 9-14 22-17 6-9", "Souter"),
+    ("11-15 23-19 9-14 22-17 7-11", "Whilter"),
+];
+
+lazy_static! {
+    static ref OPENINGS: Vec<OpeningName> = NAMES
+        .iter()
+        .map(|(line, name)| {
+            let mut board = Bitboard::default();
+            let actions = line
+                .split_whitespace()
+                .map(|movetext| {
+                    let action = pdn::find_action(&board, movetext).unwrap();
+                    board = board.take_action(action).unwrap();
+                    action
+                })
+                .collect();
+
+            OpeningName { name, actions }
+        })
+        .collect();
+}
+
+/// Represents a named opening along with the moves that define it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpeningName {
+    name: &'static str,
+    actions: Vec<Action>,
+}
+
+impl OpeningName {
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the moves of the opening from the starting position, in order
+    #[inline]
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+}
+
+impl fmt::Display for OpeningName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Names the opening of a game of american checkers played from the starting position,
+/// given its moves so far. The most specific opening the game follows is picked. Returns
+/// `None` if the game does not start with a named line.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Action;
+/// use muskox::openings;
+///
+/// let history: Vec<_> = ["11-15", "22-18", "15-22"]
+///     .iter()
+///     .map(|m| Action::from_movetext(m).unwrap())
+///     .collect();
+/// assert_eq!(openings::classify(&history).unwrap().name(), "Single Corner");
+/// assert_eq!(openings::classify(&history[..1]).unwrap().name(), "Old Faithful");
+/// assert!(openings::classify(&[]).is_none());
+/// ```
+pub fn classify(history: &[Action]) -> Option<OpeningName> {
+    OPENINGS
+        .iter()
+        .filter(|opening| history.starts_with(&opening.actions))
+        .max_by_key(|opening| opening.actions.len())
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_test() {
+        // every line is legal and named once
+        assert_eq!(OPENINGS.len(), NAMES.len());
+        for (i, opening) in OPENINGS.iter().enumerate() {
+            assert!(OPENINGS[i + 1..]
+                .iter()
+                .all(|o| o.actions != opening.actions));
+            assert_eq!(classify(opening.actions()).as_ref(), Some(opening));
+        }
+
+        let history: Vec<_> = ["11-15", "23-19", "8-11", "22-17", "4-8", "17-13"]
+            .iter()
+            .map(|m| Action::from_movetext(m).unwrap())
+            .collect();
+        assert_eq!(classify(&history).unwrap().to_string(), "Old Fourteenth");
+        assert_eq!(classify(&history[..4]).unwrap().name(), "Old Faithful");
+    }
+}
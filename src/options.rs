@@ -0,0 +1,451 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::board::{Bitboard, Variant};
+use crate::error::OptionError;
+use crate::search::{
+    default_threads, EasyMove, Engine, Score, SearchObserver, DEFAULT_DRAW_THRESHOLD,
+    DEFAULT_HASH_SIZE, DEFAULT_RESIGN_THRESHOLD, MAX_DEPTH, MAX_HASH_SIZE, MAX_THREADS,
+};
+use crate::searchlog::SearchLog;
+
+pub use crate::search::Evaluator;
+
+const MAX_CONTEMPT: f32 = 10.;
+// a side has twelve men to begin with
+const MAX_RESIGN_THRESHOLD: f32 = 12.;
+
+/// Represents the algorithm used to search positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Searcher {
+    AlphaBeta,
+    Mcts,
+}
+
+impl fmt::Display for Searcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Searcher::AlphaBeta => write!(f, "alphabeta"),
+            Searcher::Mcts => write!(f, "mcts"),
+        }
+    }
+}
+
+/// Represents the settings of the engine that can be changed while it runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineOptions {
+    threads: usize,
+    hash_size: usize,
+    evaluator: Evaluator,
+    searcher: Searcher,
+    depth_cap: Option<u32>,
+    deterministic: bool,
+    contempt: Score,
+    variant: Variant,
+    resign_threshold: Option<Score>,
+    draw_threshold: Option<Score>,
+    win_probability: bool,
+    tt_check: bool,
+    easy_move: Option<Score>,
+    search_log: Option<Arc<SearchLog>>,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            threads: default_threads(),
+            hash_size: DEFAULT_HASH_SIZE,
+            evaluator: Evaluator::Classical,
+            searcher: Searcher::AlphaBeta,
+            depth_cap: None,
+            deterministic: false,
+            contempt: Score::from(0.),
+            variant: Variant::default(),
+            resign_threshold: Some(Score::from(DEFAULT_RESIGN_THRESHOLD)),
+            draw_threshold: Some(Score::from(DEFAULT_DRAW_THRESHOLD)),
+            win_probability: false,
+            tt_check: false,
+            easy_move: None,
+            search_log: None,
+        }
+    }
+}
+
+impl EngineOptions {
+    /// The names of the options in the order they are listed
+    pub const NAMES: [&'static str; 14] = [
+        "threads",
+        "hash",
+        "evaluator",
+        "searcher",
+        "depth",
+        "deterministic",
+        "contempt",
+        "variant",
+        "resign",
+        "draw",
+        "winprob",
+        "ttcheck",
+        "easymove",
+        "searchlog",
+    ];
+
+    /// Returns the number of threads the engine searches with
+    #[inline]
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Returns the size of the transposition table in megabytes
+    #[inline]
+    pub fn hash_size(&self) -> usize {
+        self.hash_size
+    }
+
+    #[inline]
+    pub fn evaluator(&self) -> Evaluator {
+        self.evaluator
+    }
+
+    #[inline]
+    pub fn searcher(&self) -> Searcher {
+        self.searcher
+    }
+
+    /// Returns the deepest iteration a search may run, if there is a limit
+    #[inline]
+    pub fn depth_cap(&self) -> Option<u32> {
+        self.depth_cap
+    }
+
+    /// Returns whether searches always give the same results for the same position
+    #[inline]
+    pub fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Returns how much worse than even a draw is for the engine
+    #[inline]
+    pub fn contempt(&self) -> Score {
+        self.contempt
+    }
+
+    #[inline]
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Returns how many pieces behind the engine has to be to resign, if it ever does
+    #[inline]
+    pub fn resign_threshold(&self) -> Option<Score> {
+        self.resign_threshold
+    }
+
+    /// Returns how many pieces ahead the engine may be and still accept a draw, if it
+    /// accepts any
+    #[inline]
+    pub fn draw_threshold(&self) -> Option<Score> {
+        self.draw_threshold
+    }
+
+    /// Returns whether scores are shown along with the chance of winning they stand for
+    #[inline]
+    pub fn win_probability(&self) -> bool {
+        self.win_probability
+    }
+
+    /// Returns whether the transposition table checks every hit, for debugging
+    #[inline]
+    pub fn tt_check(&self) -> bool {
+        self.tt_check
+    }
+
+    /// Returns how far ahead of the rest an action has to stay for a timed search to
+    /// stop early on it, if timed searches ever do
+    #[inline]
+    pub fn easy_move(&self) -> Option<Score> {
+        self.easy_move
+    }
+
+    /// Returns the CSV file every search is appended to, if there is one
+    #[inline]
+    pub fn search_log(&self) -> Option<&SearchLog> {
+        self.search_log.as_deref()
+    }
+
+    #[inline]
+    pub(crate) fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Sets an option from its name and the text of its value. The options are left
+    /// untouched if the value is not valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::options::EngineOptions;
+    ///
+    /// let mut options = EngineOptions::default();
+    /// options.set("threads", "4").unwrap();
+    /// options.set("depth", "none").unwrap();
+    /// assert_eq!(options.threads(), 4);
+    /// assert_eq!(options.depth_cap(), None);
+    ///
+    /// assert!(options.set("threads", "0").is_err());
+    /// assert!(options.set("colour", "black").is_err());
+    /// ```
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), OptionError> {
+        let name =
+            Self::NAMES
+                .iter()
+                .find(|n| **n == name)
+                .ok_or_else(|| OptionError::UnknownOption {
+                    name: name.to_string(),
+                })?;
+
+        let invalid = |expected| OptionError::OptionValueError {
+            name,
+            value: value.to_string(),
+            expected,
+        };
+
+        match *name {
+            "threads" => {
+                self.threads = value
+                    .parse()
+                    .ok()
+                    .filter(|t| (1..=MAX_THREADS).contains(t))
+                    .ok_or_else(|| invalid("a number of threads from 1 to 64"))?
+            }
+            "hash" => {
+                self.hash_size = value
+                    .parse()
+                    .ok()
+                    .filter(|s| (1..=MAX_HASH_SIZE).contains(s))
+                    .ok_or_else(|| invalid("megabytes from 1 to 4096"))?
+            }
+            "evaluator" => {
+                self.evaluator = match value {
+                    "classical" => Evaluator::Classical,
+                    _ => return Err(invalid("classical")),
+                }
+            }
+            "searcher" => {
+                self.searcher = match value {
+                    "alphabeta" => Searcher::AlphaBeta,
+                    "mcts" => Searcher::Mcts,
+                    _ => return Err(invalid("alphabeta or mcts")),
+                }
+            }
+            "depth" => {
+                self.depth_cap = match value {
+                    "none" => None,
+                    _ => Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|d| (1..=MAX_DEPTH).contains(d))
+                            .ok_or_else(|| invalid("none or a depth from 1 to 1000"))?,
+                    ),
+                }
+            }
+            "deterministic" => {
+                self.deterministic = value.parse().map_err(|_| invalid("true or false"))?
+            }
+            "contempt" => {
+                self.contempt = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|c| (-MAX_CONTEMPT..=MAX_CONTEMPT).contains(c))
+                    .map(Score::from)
+                    .ok_or_else(|| invalid("pieces from -10 to 10"))?
+            }
+            "variant" => self.variant = value.parse().map_err(|_| invalid("a variant name"))?,
+            "resign" => {
+                self.resign_threshold = match value {
+                    "none" => None,
+                    _ => Some(
+                        value
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|t| *t > 0. && *t <= MAX_RESIGN_THRESHOLD)
+                            .map(Score::from)
+                            .ok_or_else(|| invalid("none or pieces from 0 to 12"))?,
+                    ),
+                }
+            }
+            "draw" => {
+                self.draw_threshold = match value {
+                    "none" => None,
+                    _ => Some(
+                        value
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|t| (-MAX_CONTEMPT..=MAX_CONTEMPT).contains(t))
+                            .map(Score::from)
+                            .ok_or_else(|| invalid("none or pieces from -10 to 10"))?,
+                    ),
+                }
+            }
+            "winprob" => {
+                self.win_probability = value.parse().map_err(|_| invalid("true or false"))?
+            }
+            "ttcheck" => self.tt_check = value.parse().map_err(|_| invalid("true or false"))?,
+            "easymove" => {
+                self.easy_move = match value {
+                    "none" => None,
+                    _ => Some(
+                        value
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|m| *m > 0. && *m <= MAX_RESIGN_THRESHOLD)
+                            .map(Score::from)
+                            .ok_or_else(|| invalid("none or pieces from 0 to 12"))?,
+                    ),
+                }
+            }
+            "searchlog" => {
+                self.search_log = match value {
+                    "none" => None,
+                    _ => {
+                        Some(Arc::new(SearchLog::open(value).map_err(|_| {
+                            invalid("none or a file that can be written to")
+                        })?))
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Returns every option's name along with its value written out
+    pub fn values(&self) -> Vec<(&'static str, String)> {
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+
+        let values = vec![
+            self.threads.to_string(),
+            self.hash_size.to_string(),
+            self.evaluator.to_string(),
+            self.searcher.to_string(),
+            or_none(self.depth_cap.map(|d| d.to_string())),
+            self.deterministic.to_string(),
+            self.contempt.to_string(),
+            self.variant.to_string(),
+            or_none(self.resign_threshold.map(|t| t.to_string())),
+            or_none(self.draw_threshold.map(|t| t.to_string())),
+            self.win_probability.to_string(),
+            self.tt_check.to_string(),
+            or_none(self.easy_move.map(|m| m.to_string())),
+            or_none(
+                self.search_log
+                    .as_ref()
+                    .map(|log| log.path().display().to_string()),
+            ),
+        ];
+
+        Self::NAMES.iter().copied().zip(values).collect()
+    }
+
+    /// Configures an engine with the options. Only settings that changed are rebuilt.
+    pub fn apply(&self, engine: &mut Engine<Bitboard>) {
+        engine.set_threads(self.threads);
+        engine.set_hash_size(self.hash_size);
+        engine.set_depth_cap(self.depth_cap);
+        engine.set_deterministic(self.deterministic);
+        engine.set_contempt(self.contempt);
+        engine.set_resign_threshold(self.resign_threshold);
+        engine.set_draw_threshold(self.draw_threshold);
+        engine.set_tt_self_check(self.tt_check);
+        engine.set_easy_move(
+            self.easy_move
+                .map(|margin| EasyMove::new(margin, EasyMove::default().iterations())),
+        );
+        engine.set_observer(
+            self.search_log
+                .clone()
+                .map(|log| log as Arc<dyn SearchObserver<Bitboard>>),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_option_test() {
+        let mut options = EngineOptions::default();
+
+        options.set("hash", "64").unwrap();
+        options.set("depth", "12").unwrap();
+        options.set("contempt", "-0.5").unwrap();
+        options.set("variant", "italian").unwrap();
+        assert_eq!(options.hash_size(), 64);
+        assert_eq!(options.depth_cap(), Some(12));
+        assert_eq!(options.contempt(), Score::from(-0.5));
+        assert_eq!(options.variant(), Variant::Italian);
+
+        let before = options.clone();
+        assert!(options.set("hash", "0").is_err());
+        assert!(options.set("depth", "1001").is_err());
+        assert!(options.set("contempt", "NaN").is_err());
+        assert!(options.set("evaluator", "nnue").is_err());
+        assert_eq!(
+            options.set("variant", "chess"),
+            Err(OptionError::OptionValueError {
+                name: "variant",
+                value: "chess".to_string(),
+                expected: "a variant name",
+            })
+        );
+        assert_eq!(options, before);
+
+        let values = options.values();
+        assert_eq!(values[4], ("depth", "12".to_string()));
+        assert_eq!(values[7], ("variant", "italian".to_string()));
+
+        options.set("depth", "300").unwrap();
+        assert_eq!(options.depth_cap(), Some(300));
+        options.set("deterministic", "true").unwrap();
+        assert!(options.deterministic());
+        assert!(options.set("deterministic", "yes").is_err());
+
+        options.set("searcher", "mcts").unwrap();
+        assert_eq!(options.searcher(), Searcher::Mcts);
+
+        options.set("resign", "none").unwrap();
+        options.set("draw", "-1").unwrap();
+        assert_eq!(options.resign_threshold(), None);
+        assert_eq!(options.draw_threshold(), Some(Score::from(-1.)));
+        assert!(options.set("resign", "0").is_err());
+        assert_eq!(options.values()[8], ("resign", "none".to_string()));
+
+        options.set("winprob", "true").unwrap();
+        assert!(options.win_probability());
+        assert!(options.set("winprob", "73%").is_err());
+
+        options.set("ttcheck", "true").unwrap();
+        assert!(options.tt_check());
+        assert_eq!(options.values()[11], ("ttcheck", "true".to_string()));
+
+        options.set("easymove", "1.5").unwrap();
+        assert_eq!(options.easy_move(), Some(Score::from(1.5)));
+        assert!(options.set("easymove", "0").is_err());
+        assert_eq!(options.values()[12], ("easymove", "1.5".to_string()));
+
+        let path = std::env::temp_dir().join("muskox_option_search_log.csv");
+        options.set("searchlog", path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            options.search_log().map(|log| log.path()),
+            Some(path.as_path())
+        );
+        assert!(options.set("searchlog", "/no/such/dir/log.csv").is_err());
+        options.set("searchlog", "none").unwrap();
+        assert_eq!(options.values()[13], ("searchlog", "none".to_string()));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
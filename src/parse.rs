@@ -1,18 +1,20 @@
 use nom::{
-    bytes::complete::{tag, take, take_while},
-    character::complete::digit1,
-    combinator::{map, map_res},
+    branch::alt,
+    bytes::complete::{tag, take, take_while, take_while1},
+    character::complete::{alpha1, digit1, multispace0, multispace1, one_of, satisfy},
+    combinator::{eof, map, map_res, not, opt, peek, recognize, value},
     error::{context, VerboseError},
-    multi::separated_list1,
-    sequence::tuple,
+    multi::{count, many0, many1, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
     IResult,
 };
 use num_traits::PrimInt;
 
-use crate::app::Command;
-use crate::board::{Action, Bitboard, Color};
+use crate::app::{Command, Command::*, OutputMode};
+use crate::board::{Action, Bitboard, Color, PrettyOptions, Square, Variant};
+use crate::dxp::{EndReason, Message as DxpMessage};
 use crate::error::ParseError;
-use crate::search::SearchConstraint;
+use crate::search::{RootActions, SearchConstraint};
 
 // try to condense these functions except for stuff taht is too large or reused..
 // convert the match statements to nom's switch macro
@@ -25,24 +27,28 @@ fn from_decimal<T: PrimInt>(input: &str) -> Result<T, T::FromStrRadixErr> {
 
 // everything below is for parsing the action
 
-fn position_primary(input: &str) -> Res<&str, u8> {
-    let (input, position) = context("position", map_res(digit1, from_decimal::<u8>))(input)?;
+fn match_position<T: PrimInt>(input: &str) -> Result<T, ParseError> {
+    let position = from_decimal::<u8>(input).map_err(|_| ParseError::PositionValueError)?;
 
-    if !(1..=32).contains(&position) {
-        panic!("another unhandled error!");
+    match position {
+        1..=32 => Ok(T::from(position).unwrap()),
+        _ => Err(ParseError::PositionValueError),
     }
+}
 
-    Ok((input, position))
+fn match_positions(input: Vec<&str>) -> Result<Vec<u8>, ParseError> {
+    input.into_iter().map(match_position).collect()
 }
 
 pub(crate) fn action_primary(input: &str) -> Res<&str, Action> {
-    context(
-        "delimiter",
-        map_res(
-            separated_list1(tag("-"), position_primary),
-            Action::from_vec,
-        ),
-    )(input)
+    // read every number before checking them so that a bad position is not mistaken
+    // for the end of the movetext
+    let positions = context(
+        "position",
+        map_res(separated_list1(tag("-"), digit1), match_positions),
+    );
+
+    context("delimiter", map_res(positions, Action::from_vec))(input)
 }
 
 // everything below is for parsing the pick constraint
@@ -55,45 +61,608 @@ fn search_constraint_primary(input: &str) -> Res<&str, SearchConstraint> {
     let (input, constraint_name) = take_while(|c: char| c.is_ascii_alphabetic())(input)?;
     let (input, _) = take_while(is_space)(input)?;
 
+    let value = |f: fn(u32) -> Result<SearchConstraint, &'static str>| {
+        context(
+            "constraint value",
+            map_res(map_res(digit1, from_decimal), f),
+        )
+    };
+
     // would be better to use the switch macro
-    let constraint = match constraint_name {
-        "" => SearchConstraint::none(),
-        "timed" => map_res(map_res(digit1, from_decimal), SearchConstraint::time)(input)?.1,
-        "depth" => map_res(map_res(digit1, from_decimal), SearchConstraint::depth)(input)?.1,
-        _ => panic!("bad!"),
+    match constraint_name {
+        "" => Ok((input, SearchConstraint::none())),
+        "timed" => value(SearchConstraint::time)(input),
+        "depth" => value(SearchConstraint::depth)(input),
+        _ => context(
+            "constraint option",
+            map_res(take(0_usize), |_| Err(ParseError::ConstraintOptionError)),
+        )(input),
+    }
+}
+
+/// Parses which root actions a search looks at, as `only` or `except` followed by
+/// movetext, or nothing for all of them
+fn root_actions_primary(input: &str) -> Res<&str, RootActions<Action>> {
+    let (input, _) = take_while(is_space)(input)?;
+    let actions = |input| {
+        preceded(
+            multispace1,
+            context("root actions", separated_list1(multispace1, action_primary)),
+        )(input)
     };
 
-    Ok((input, constraint))
+    alt((
+        map(preceded(tag("only"), actions), RootActions::Only),
+        map(preceded(tag("except"), actions), RootActions::Excluding),
+        map(eof, |_| RootActions::All),
+    ))(input)
 }
 
-// everything below is for parsing commands in app
+// everything below is for parsing commands in app. every command is described once in
+// COMMANDS, which both the parser and the help read, so that the two cannot drift apart
 
-pub(crate) fn command_primary(input: &str) -> Res<&str, Command> {
-    use Command::*;
+/// Describes a command of the prompt: the names it goes by, how it is written and what
+/// it does, along with the parser for whatever follows the name
+pub(crate) struct CommandSpec {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    usage: &'static str,
+    description: &'static str,
+    parse: fn(&str) -> Res<&str, Command>,
+}
 
-    let (input, cmd_name) = take_while(|c: char| c.is_ascii_alphabetic())(input)?;
-    let (input, _) = take_while(is_space)(input)?;
+impl CommandSpec {
+    #[inline]
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
 
-    let wrap_fn = |cmd| Ok(("", cmd));
+    #[inline]
+    pub(crate) fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+
+    #[inline]
+    pub(crate) fn usage(&self) -> &'static str {
+        self.usage
+    }
+
+    #[inline]
+    pub(crate) fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// Returns whether the command goes by `name`
+    fn answers_to(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+}
 
-    match cmd_name {
-        "fen" => match input {
-            "" => wrap_fn(PrintFen),
+fn done(command: Command) -> Res<&'static str, Command> {
+    Ok(("", command))
+}
+
+/// Every command of the prompt, in the order the help lists them
+pub(crate) static COMMANDS: [CommandSpec; 38] = [
+    CommandSpec {
+        name: "help",
+        aliases: &["h"],
+        usage: "help [COMMAND]",
+        description: "list the commands, or describe one of them",
+        parse: |input| match input {
+            "" => done(Help(None)),
+            _ => map(
+                context(
+                    "command",
+                    map_res(alpha1, |name| {
+                        command_spec(name)
+                            .map(|spec| spec.name)
+                            .ok_or(ParseError::InvalidCommand)
+                    }),
+                ),
+                |name| Help(Some(name)),
+            )(input),
+        },
+    },
+    CommandSpec {
+        name: "fen",
+        aliases: &[],
+        usage: "fen [FEN]",
+        description: "set up the board from a FEN string, or print the board's FEN",
+        parse: |input| match input {
+            "" => done(PrintFen),
             _ => map(board_fen_primary, SetFen)(input),
         },
-        "validate" => map(action_primary, ValidateAction)(input),
-        "take" => map(action_primary, TakeAction)(input),
-        "search" => map(search_constraint_primary, Search)(input),
-        "best" => map(search_constraint_primary, PickAction)(input),
-        "evaluate" => map(search_constraint_primary, EvaluateBoard)(input),
-        "gamestate" => wrap_fn(GetGameState),
-        "generate" => wrap_fn(GenerateAllActions),
-        "turn" => wrap_fn(GetTurn),
-        "print" => wrap_fn(Print),
-        "history" => wrap_fn(GetMoveHistory),
-        "clear" => wrap_fn(Clear),
-        "exit" => wrap_fn(Exit),
-        _ => panic!("return error here when it implements properly!!"),
+    },
+    CommandSpec {
+        name: "validate",
+        aliases: &[],
+        usage: "validate MOVE",
+        description: "tell whether a move is legal and why not if it is not",
+        parse: |input| map(action_primary, ValidateAction)(input),
+    },
+    CommandSpec {
+        name: "take",
+        aliases: &["t"],
+        usage: "take MOVE",
+        description: "play a move on the board",
+        parse: |input| map(action_primary, TakeAction)(input),
+    },
+    CommandSpec {
+        name: "search",
+        aliases: &["s"],
+        usage: "search [timed MS | depth N] [only MOVES | except MOVES]",
+        description: "score every move, or only some of them",
+        parse: |input| {
+            // the constraint can be left out in front of the root actions too
+            let (input, constraint) = match input.starts_with("only") || input.starts_with("except")
+            {
+                true => (input, SearchConstraint::none()),
+                false => search_constraint_primary(input)?,
+            };
+            let (input, root) = root_actions_primary(input)?;
+            Ok((input, Search(constraint, root)))
+        },
+    },
+    CommandSpec {
+        name: "best",
+        aliases: &["b"],
+        usage: "best [timed MS | depth N]",
+        description: "search for the best move",
+        parse: |input| map(search_constraint_primary, PickAction)(input),
+    },
+    CommandSpec {
+        name: "evaluate",
+        aliases: &["e"],
+        usage: "evaluate [timed MS | depth N]",
+        description: "search for the score of the board, positive when black is better",
+        parse: |input| map(search_constraint_primary, EvaluateBoard)(input),
+    },
+    CommandSpec {
+        name: "explain",
+        aliases: &[],
+        usage: "explain",
+        description: "break the evaluation of the board down into its terms",
+        parse: |_| done(ExplainEvaluation),
+    },
+    CommandSpec {
+        name: "tree",
+        aliases: &[],
+        usage: "tree DEPTH",
+        description: "search and print every node visited, as DOT for graphviz or as JSON",
+        parse: |input| {
+            map(
+                context("tree depth", map_res(digit1, from_decimal::<u32>)),
+                DebugTree,
+            )(input)
+        },
+    },
+    CommandSpec {
+        name: "gamestate",
+        aliases: &[],
+        usage: "gamestate",
+        description: "tell whether the game is over and who won",
+        parse: |_| done(GetGameState),
+    },
+    CommandSpec {
+        name: "generate",
+        aliases: &["g"],
+        usage: "generate",
+        description: "list the legal moves",
+        parse: |_| done(GenerateAllActions),
+    },
+    CommandSpec {
+        name: "turn",
+        aliases: &[],
+        usage: "turn",
+        description: "print the color to move",
+        parse: |_| done(GetTurn),
+    },
+    CommandSpec {
+        name: "print",
+        aliases: &["p"],
+        usage: "print [numbers] [color] [turn] [flipped]",
+        description: "draw the board",
+        parse: |input| map(print_options_primary, Print)(input),
+    },
+    CommandSpec {
+        name: "history",
+        aliases: &[],
+        usage: "history",
+        description: "print the moves of the game so far",
+        parse: |_| done(GetMoveHistory),
+    },
+    CommandSpec {
+        name: "save",
+        aliases: &[],
+        usage: "save FILE",
+        description: "write the game to a PDN file",
+        parse: |input| match input.trim() {
+            "" => invalid_command(input),
+            path => done(Save(path.to_string())),
+        },
+    },
+    CommandSpec {
+        name: "load",
+        aliases: &[],
+        usage: "load FILE [N]",
+        description: "replay the N'th game of a PDN file onto the board",
+        parse: |input| game_file_primary(Load)(input),
+    },
+    CommandSpec {
+        name: "annotate",
+        aliases: &[],
+        usage: "annotate FILE [N]",
+        description: "score every move of the N'th game of a PDN file",
+        parse: |input| game_file_primary(Annotate)(input),
+    },
+    CommandSpec {
+        name: "session",
+        aliases: &[],
+        usage: "session save FILE [tt] | session load FILE",
+        description: "keep the analysis in a file, or pick it up again",
+        parse: |input| match input.trim().split_once(' ') {
+            Some(("save", rest)) => match rest.trim().rsplit_once(' ') {
+                Some((path, "tt")) => done(SaveSession(path.trim().to_string(), true)),
+                _ => done(SaveSession(rest.trim().to_string(), false)),
+            },
+            Some(("load", path)) => done(LoadSession(path.trim().to_string())),
+            _ => invalid_command(input),
+        },
+    },
+    CommandSpec {
+        name: "testsuite",
+        aliases: &[],
+        usage: "testsuite FILE [timed MS | depth N]",
+        description: "search every position of a test suite and report the solved ones",
+        parse: |input| {
+            let (input, path) = take_while1(|c: char| !is_space(c))(input)?;
+            let (input, _) = take_while(is_space)(input)?;
+            let (input, constraint) = search_constraint_primary(input)?;
+            Ok((input, TestSuite(path.to_string(), constraint)))
+        },
+    },
+    CommandSpec {
+        name: "bench",
+        aliases: &[],
+        usage: "bench",
+        description: "search a fixed set of positions and print the nodes per second",
+        parse: |_| done(Bench),
+    },
+    CommandSpec {
+        name: "next",
+        aliases: &["n"],
+        usage: "next",
+        description: "play the next move of the loaded game",
+        parse: |_| done(Next),
+    },
+    CommandSpec {
+        name: "prev",
+        aliases: &[],
+        usage: "prev",
+        description: "take back the last move",
+        parse: |_| done(Prev),
+    },
+    CommandSpec {
+        name: "ballot",
+        aliases: &[],
+        usage: "ballot N | ballot random",
+        description: "start from a three move opening of the ballot deck",
+        parse: |input| match input {
+            "random" => done(RandomBallot),
+            _ => map(
+                context("ballot", map_res(digit1, |s: &str| s.parse::<usize>())),
+                Ballot,
+            )(input),
+        },
+    },
+    CommandSpec {
+        name: "random",
+        aliases: &[],
+        usage: "random MEN KINGS",
+        description: "start from a random position with that many men and kings a side",
+        parse: |input| {
+            map(
+                context(
+                    "random",
+                    separated_pair(
+                        map_res(digit1, from_decimal::<u32>),
+                        multispace1,
+                        map_res(digit1, from_decimal::<u32>),
+                    ),
+                ),
+                |(men, kings)| Random(men, kings),
+            )(input)
+        },
+    },
+    CommandSpec {
+        name: "put",
+        aliases: &[],
+        usage: "put SQUARE w|b [king]",
+        description: "put a white or black man, or king, on a square of the board",
+        parse: |input| {
+            let (input, square) = square_primary(input)?;
+            let (input, _) = take_while1(is_space)(input)?;
+            let (input, color) = color_letter_primary(input)?;
+            let (input, king) = opt(preceded(take_while1(is_space), tag("king")))(input)?;
+            Ok((input, Put(square, color, king.is_some())))
+        },
+    },
+    CommandSpec {
+        name: "remove",
+        aliases: &[],
+        usage: "remove SQUARE",
+        description: "take the piece on a square off the board",
+        parse: |input| map(square_primary, Remove)(input),
+    },
+    CommandSpec {
+        name: "setturn",
+        aliases: &[],
+        usage: "setturn w|b",
+        description: "give the move to white or black",
+        parse: |input| map(color_letter_primary, SetTurn)(input),
+    },
+    CommandSpec {
+        name: "clearboard",
+        aliases: &[],
+        usage: "clearboard",
+        description: "take every piece off the board, to set up a position",
+        parse: |_| done(ClearBoard),
+    },
+    CommandSpec {
+        name: "mode",
+        aliases: &[],
+        usage: "mode text | mode json",
+        description: "print replies as text or as a JSON object a line",
+        parse: |input| match input {
+            "text" => done(SetMode(OutputMode::Text)),
+            "json" => done(SetMode(OutputMode::Json)),
+            _ => invalid_command(input),
+        },
+    },
+    CommandSpec {
+        name: "variant",
+        aliases: &[],
+        usage: "variant [NAME]",
+        description: "play under the rules of another variant, or print the current one",
+        parse: |input| match input {
+            "" => done(PrintVariant),
+            _ => map(
+                context("variant", map_res(alpha1, match_variant_name)),
+                SetVariant,
+            )(input),
+        },
+    },
+    CommandSpec {
+        name: "set",
+        aliases: &[],
+        usage: "set OPTION VALUE",
+        description: "change an engine option",
+        parse: |input| {
+            map(option_primary, |(name, value)| {
+                SetOption(name.to_string(), value.to_string())
+            })(input)
+        },
+    },
+    CommandSpec {
+        name: "show",
+        aliases: &[],
+        usage: "show options",
+        description: "print the engine options",
+        parse: |input| match input {
+            "options" => done(ShowOptions),
+            _ => invalid_command(input),
+        },
+    },
+    CommandSpec {
+        name: "analyze",
+        aliases: &["a"],
+        usage: "analyze",
+        description: "search in the background until the next command",
+        parse: |_| done(Analyze),
+    },
+    CommandSpec {
+        name: "stop",
+        aliases: &[],
+        usage: "stop",
+        description: "stop the analysis and print the best move it found",
+        parse: |_| done(Stop),
+    },
+    CommandSpec {
+        name: "hint",
+        aliases: &[],
+        usage: "hint [N]",
+        description: "suggest the N best moves with the lines expected after them",
+        parse: |input| match input {
+            "" => done(Hint(None)),
+            _ => map(
+                context(
+                    "command",
+                    map_res(digit1, |s: &str| match s.parse::<usize>() {
+                        Ok(0) | Err(_) => Err(ParseError::InvalidCommand),
+                        Ok(n) => Ok(n),
+                    }),
+                ),
+                |n| Hint(Some(n)),
+            )(input),
+        },
+    },
+    CommandSpec {
+        name: "clear",
+        aliases: &[],
+        usage: "clear",
+        description: "start a new game and forget what the engine learned",
+        parse: |_| done(Clear),
+    },
+    CommandSpec {
+        name: "complete",
+        aliases: &[],
+        usage: "complete TEXT",
+        description: "list the ways the last word of a command could go on",
+        parse: |input| done(Complete(input.to_string())),
+    },
+    CommandSpec {
+        name: "exit",
+        aliases: &["quit", "q"],
+        usage: "exit",
+        description: "leave the prompt",
+        parse: |_| done(Exit),
+    },
+];
+
+/// Returns the command that goes by `name` or has it as an alias
+pub(crate) fn command_spec(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.answers_to(name))
+}
+
+/// Returns the number of single character edits needed to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Suggests the command that the mistyped `name` was most likely meant to be
+pub(crate) fn suggest_command(name: &str) -> Option<&'static str> {
+    COMMANDS
+        .iter()
+        .map(|spec| (edit_distance(name, spec.name), spec.name))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// Parses a whole command, naming the closest command when the name is not known
+pub(crate) fn command(input: &str) -> Result<Command, ParseError> {
+    let name: String = input
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+
+    if !name.is_empty() && command_spec(&name).is_none() {
+        let hint = match suggest_command(&name) {
+            Some(suggestion) => format!(" Did you mean `{}`?", suggestion),
+            None => String::new(),
+        };
+        return Err(ParseError::UnknownCommand { name, hint });
+    }
+
+    Ok(command_primary(input)?.1)
+}
+
+/// Parses the name of an option followed by its value
+fn option_primary(input: &str) -> Res<&str, (&str, &str)> {
+    let name = take_while1(|c: char| c.is_ascii_alphanumeric() || c == '-');
+    let (input, name) = context("command", name)(input)?;
+    let (input, _) = take_while1(is_space)(input)?;
+    let (input, value) = context("command", take_while1(|c: char| !is_space(c)))(input)?;
+
+    Ok((input, (name, value)))
+}
+
+/// Parses a square given by its PDN number or its algebraic name, like `14` or `d6`
+fn square_primary(input: &str) -> Res<&str, Square> {
+    let number = map_res(digit1, |s: &str| {
+        from_decimal::<u8>(s)
+            .ok()
+            .and_then(Square::from_pdn)
+            .ok_or(ParseError::PositionValueError)
+    });
+    let name = map_res(recognize(pair(alpha1, digit1)), Square::from_algebraic);
+    context("square", alt((number, name)))(input)
+}
+
+/// Parses a color written as `w` or `b`
+fn color_letter_primary(input: &str) -> Res<&str, Color> {
+    let side = |input: &str| match input {
+        "w" => Ok(Color::White),
+        "b" => Ok(Color::Black),
+        _ => Err(ParseError::ColorError),
+    };
+    context("color", map_res(alpha1, side))(input)
+}
+
+/// Parses the flags of the print command, which may come in any order
+fn print_options_primary(input: &str) -> Res<&str, PrettyOptions> {
+    let flags = separated_list0(take_while1(is_space), alpha1);
+
+    context(
+        "command",
+        map_res(flags, |flags: Vec<&str>| {
+            flags
+                .into_iter()
+                .try_fold(PrettyOptions::default(), |options, flag| match flag {
+                    "numbers" => Ok(PrettyOptions {
+                        numbers: true,
+                        ..options
+                    }),
+                    "color" | "colors" => Ok(PrettyOptions {
+                        colors: true,
+                        ..options
+                    }),
+                    "turn" => Ok(PrettyOptions {
+                        turn: true,
+                        ..options
+                    }),
+                    "flipped" => Ok(PrettyOptions {
+                        flipped: true,
+                        ..options
+                    }),
+                    _ => Err(ParseError::InvalidCommand),
+                })
+        }),
+    )(input)
+}
+
+/// Parses the path of a PDN file followed by the number of a game in it, which is the
+/// first if left out
+fn game_file_primary(
+    command: impl Fn(String, usize) -> Command,
+) -> impl Fn(&str) -> Res<&str, Command> {
+    move |input| match input.trim().rsplit_once(' ') {
+        Some((path, number)) if number.bytes().all(|b| b.is_ascii_digit()) => {
+            match number.parse() {
+                Ok(number) => Ok(("", command(path.trim().to_string(), number))),
+                Err(_) => invalid_command(input),
+            }
+        }
+        _ => match input.trim() {
+            "" => invalid_command(input),
+            path => Ok(("", command(path.to_string(), 1))),
+        },
+    }
+}
+
+fn invalid_command(input: &str) -> Res<&str, Command> {
+    context(
+        "command",
+        map_res(take(0_usize), |_| Err(ParseError::InvalidCommand)),
+    )(input)
+}
+
+pub(crate) fn command_primary(input: &str) -> Res<&str, Command> {
+    let (input, name) = take_while(|c: char| c.is_ascii_alphabetic())(input)?;
+    let (input, _) = take_while(is_space)(input)?;
+
+    match (name, command_spec(name)) {
+        ("", _) => context(
+            "no command",
+            map_res(take(0_usize), |_| Err(ParseError::NoCommandError)),
+        )(input),
+        (_, Some(spec)) => (spec.parse)(input),
+        (_, None) => invalid_command(input),
     }
 }
 
@@ -131,9 +700,8 @@ fn piece_primary(input: &str) -> Res<&str, (u32, bool)> {
         return Ok((input, (0, false)));
     }
 
-    let (input, position) = context("digit", map_res(digit1, from_decimal::<u32>))(input)?;
+    let (input, position) = context("position", map_res(digit1, match_position::<u32>))(input)?;
 
-    // need to make sure the position is less than 32
     let mask = 1 << (position - 1);
 
     Ok((input, (mask, is_king)))
@@ -164,7 +732,36 @@ fn side_primary(input: &str) -> Res<&str, (Color, u32, u32)> {
     Ok((input, (side, pieces, kings)))
 }
 
+fn match_game_type(input: &str) -> Result<Variant, ParseError> {
+    let game_type = from_decimal::<u8>(input).map_err(|_| ParseError::VariantError)?;
+    Variant::from_game_type(game_type).ok_or(ParseError::VariantError)
+}
+
+fn match_variant_name(input: &str) -> Result<Variant, ParseError> {
+    input.parse()
+}
+
+fn variant_primary(input: &str) -> Res<&str, Variant> {
+    context(
+        "variant",
+        terminated(
+            alt((
+                map_res(digit1, match_game_type),
+                map_res(alpha1, match_variant_name),
+            )),
+            tag(":"),
+        ),
+    )(input)
+}
+
 pub(crate) fn board_fen_primary(input: &str) -> Res<&str, Bitboard> {
+    // read the optional variant hint (anything longer than a color letter before the
+    // first colon). without it we play american checkers
+    let (input, variant) = match input.find(':') {
+        Some(i) if i > 1 => variant_primary(input)?,
+        _ => (input, Variant::American),
+    };
+
     // read the color of the turn
     let (input, turn) = color_primary(input)?;
 
@@ -179,7 +776,372 @@ pub(crate) fn board_fen_primary(input: &str) -> Res<&str, Bitboard> {
         Color::White => (s2_pieces, s1_pieces),
     };
 
-    let board = Bitboard::new(blacks, whites, kings, turn);
+    let board = Bitboard::new(blacks, whites, kings, turn).with_variant(variant);
 
     Ok((input, board))
 }
+
+// everything below is for parsing engine protocol messages
+
+fn message_value_primary(input: &str) -> Res<&str, &str> {
+    alt((
+        delimited(tag("\""), take_while(|c: char| c != '"'), tag("\"")),
+        take_while1(|c: char| c != ' '),
+    ))(input)
+}
+
+fn message_argument_primary(input: &str) -> Res<&str, (&str, Option<&str>)> {
+    tuple((
+        take_while1(|c: char| c != ' ' && c != '='),
+        opt(preceded(tag("="), message_value_primary)),
+    ))(input)
+}
+
+/// A protocol message name along with its (optionally valued) arguments
+pub(crate) type Message<'a> = (&'a str, Vec<(&'a str, Option<&'a str>)>);
+
+/// Parses a line of the form `name key=value key="quoted value" flag`
+pub(crate) fn message_primary(input: &str) -> Res<&str, Message<'_>> {
+    let (input, name) = context("message", take_while1(|c: char| c != ' '))(input)?;
+    let (input, _) = take_while(is_space)(input)?;
+    let (input, arguments) =
+        separated_list0(take_while1(is_space), message_argument_primary)(input)?;
+
+    Ok((input, (name, arguments)))
+}
+
+fn match_protocol_squares(input: &str) -> Result<(u32, u32, u32), ParseError> {
+    input
+        .chars()
+        .enumerate()
+        .try_fold((0, 0, 0), |(blacks, whites, kings), (i, c)| {
+            let mask = 1 << i;
+            match c {
+                // dxp writes black (zwart) pieces as z
+                'b' | 'z' => Ok((blacks | mask, whites, kings)),
+                'w' => Ok((blacks, whites | mask, kings)),
+                'B' | 'Z' => Ok((blacks | mask, whites, kings | mask)),
+                'W' => Ok((blacks, whites | mask, kings | mask)),
+                'e' => Ok((blacks, whites, kings)),
+                _ => Err(ParseError::ProtocolPositionError),
+            }
+        })
+}
+
+/// Parses a position as the side to move followed by a letter for each of the 32
+/// squares: `b`/`w` for men, `B`/`W` for kings and `e` for empty squares
+pub(crate) fn protocol_position_primary(input: &str) -> Res<&str, Bitboard> {
+    let (input, turn) = context("protocol position", map_res(take(1_usize), match_color))(input)?;
+    let (input, (blacks, whites, kings)) = context(
+        "protocol position",
+        map_res(take(32_usize), match_protocol_squares),
+    )(input)?;
+
+    Ok((input, Bitboard::new(blacks, whites, kings, turn)))
+}
+
+// everything below is for parsing DamExchange messages. fields have fixed widths
+
+fn dxp_number<'a, T: PrimInt>(width: usize) -> impl FnMut(&'a str) -> Res<&'a str, T> {
+    context(
+        "message",
+        map_res(take(width), |s: &str| from_decimal::<T>(s.trim())),
+    )
+}
+
+fn dxp_square(input: &str) -> Res<&str, Square> {
+    context(
+        "message",
+        map_res(dxp_number::<u8>(2), |n| {
+            Square::from_pdn(n).ok_or(ParseError::PositionValueError)
+        }),
+    )(input)
+}
+
+fn match_dxp_color(input: &str) -> Result<Color, ParseError> {
+    // black is zwart in dutch
+    match input {
+        "W" => Ok(Color::White),
+        "Z" => Ok(Color::Black),
+        _ => Err(ParseError::ColorError),
+    }
+}
+
+fn dxp_color(input: &str) -> Res<&str, Color> {
+    context("color", map_res(take(1_usize), match_dxp_color))(input)
+}
+
+fn dxp_name(input: &str) -> Res<&str, String> {
+    map(take(32_usize), |s: &str| s.trim_end().to_string())(input)
+}
+
+fn dxp_position(input: &str) -> Res<&str, Option<Bitboard>> {
+    let (input, start) = context("message", take(1_usize))(input)?;
+
+    match start {
+        "A" => Ok((input, None)),
+        _ => {
+            let (input, turn) = dxp_color(input)?;
+            let (input, (blacks, whites, kings)) = context(
+                "protocol position",
+                map_res(take(32_usize), match_protocol_squares),
+            )(input)?;
+
+            Ok((input, Some(Bitboard::new(blacks, whites, kings, turn))))
+        }
+    }
+}
+
+fn match_end_reason(input: &str) -> Result<EndReason, ParseError> {
+    match input {
+        "0" => Ok(EndReason::Unknown),
+        "1" => Ok(EndReason::Loss),
+        "2" => Ok(EndReason::Draw),
+        "3" => Ok(EndReason::Win),
+        _ => Err(ParseError::MessageError),
+    }
+}
+
+/// Parses a DamExchange message without its terminating null character
+pub(crate) fn dxp_message_primary(input: &str) -> Res<&str, DxpMessage> {
+    let (input, kind) = context("message", take(1_usize))(input)?;
+
+    match kind {
+        "R" => {
+            let (input, _version) = dxp_number::<u8>(2)(input)?;
+            let (input, name) = dxp_name(input)?;
+            let (input, follower) = dxp_color(input)?;
+            let (input, minutes) = dxp_number(3)(input)?;
+            let (input, moves) = dxp_number(3)(input)?;
+            let (input, position) = dxp_position(input)?;
+
+            let message = DxpMessage::GameRequest {
+                name,
+                follower,
+                minutes,
+                moves,
+                position,
+            };
+            Ok((input, message))
+        }
+        "A" => {
+            let (input, name) = dxp_name(input)?;
+            let (input, code) = dxp_number(1)(input)?;
+            Ok((input, DxpMessage::GameAccept { name, code }))
+        }
+        "M" => {
+            let (input, seconds) = dxp_number(4)(input)?;
+            let (input, source) = dxp_square(input)?;
+            let (input, destination) = dxp_square(input)?;
+            let (input, n_captured) = dxp_number::<usize>(2)(input)?;
+            let (input, captured) = count(dxp_square, n_captured)(input)?;
+
+            let message = DxpMessage::Move {
+                seconds,
+                source,
+                destination,
+                captured,
+            };
+            Ok((input, message))
+        }
+        "E" => {
+            let (input, reason) =
+                context("message", map_res(take(1_usize), match_end_reason))(input)?;
+            let (input, stop) = dxp_number::<u8>(1)(input)?;
+            let message = DxpMessage::GameEnd {
+                reason,
+                stop: stop == 1,
+            };
+            Ok((input, message))
+        }
+        "C" => Ok(("", DxpMessage::Chat(input.to_string()))),
+        "B" => {
+            let (input, move_number) = dxp_number(3)(input)?;
+            let (input, turn) = dxp_color(input)?;
+            Ok((input, DxpMessage::BackRequest { move_number, turn }))
+        }
+        "K" => {
+            let (input, code) = dxp_number(1)(input)?;
+            Ok((input, DxpMessage::BackAccept { code }))
+        }
+        _ => context(
+            "message",
+            map_res(take(0_usize), |_| Err(ParseError::MessageError)),
+        )(input),
+    }
+}
+
+// everything below is for parsing games in portable draughts notation (pdn)
+
+/// Represents the parts of PDN text needed to replay a game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PdnToken<'a> {
+    Tag(&'a str, &'a str),
+    Move(&'a str),
+    Result(&'a str),
+    Skip, // move numbers, comments, variations and annotations
+}
+
+fn pdn_tag(input: &str) -> Res<&str, PdnToken<'_>> {
+    let name = take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    let tag_value = delimited(tag("\""), take_while(|c| c != '"'), tag("\""));
+
+    map(
+        delimited(
+            pair(tag("["), multispace0),
+            separated_pair(name, multispace1, tag_value),
+            pair(multispace0, tag("]")),
+        ),
+        |(name, tag_value)| PdnToken::Tag(name, tag_value),
+    )(input)
+}
+
+fn pdn_variation(input: &str) -> Res<&str, ()> {
+    // variations can hold variations of their own
+    let inside = alt((
+        value((), take_while1(|c| c != '(' && c != ')')),
+        pdn_variation,
+    ));
+    value((), delimited(tag("("), many0(inside), tag(")")))(input)
+}
+
+fn pdn_skip(input: &str) -> Res<&str, PdnToken<'_>> {
+    let number = pair(digit1, many1(tag(".")));
+    let comment = delimited(tag("{"), take_while(|c| c != '}'), tag("}"));
+    let line_comment = pair(alt((tag(";"), tag("%"))), take_while(|c| c != '\n'));
+    let annotation = alt((
+        recognize(pair(tag("$"), digit1)),
+        take_while1(|c| c == '!' || c == '?'),
+    ));
+
+    value(
+        PdnToken::Skip,
+        alt((
+            value((), number),
+            value((), comment),
+            value((), line_comment),
+            value((), annotation),
+            pdn_variation,
+        )),
+    )(input)
+}
+
+fn pdn_result(input: &str) -> Res<&str, PdnToken<'_>> {
+    let result = alt((
+        tag("1/2-1/2"),
+        tag("1-0"),
+        tag("0-1"),
+        tag("2-0"),
+        tag("0-2"),
+        tag("1-1"),
+        tag("0-0"),
+        tag("*"),
+    ));
+
+    // so that the move 1-05 is not read as a result
+    let end = peek(not(satisfy(|c: char| c.is_ascii_alphanumeric())));
+    map(terminated(result, end), PdnToken::Result)(input)
+}
+
+fn pdn_move(input: &str) -> Res<&str, PdnToken<'_>> {
+    let squares = pair(digit1, many1(pair(one_of("-x"), digit1)));
+    map(recognize(squares), PdnToken::Move)(input)
+}
+
+/// Parses PDN text holding any number of games into the tokens needed to replay them
+pub(crate) fn pdn_primary(input: &str) -> Res<&str, Vec<PdnToken<'_>>> {
+    let token = alt((pdn_tag, pdn_skip, pdn_result, pdn_move));
+    let tokens = many0(preceded(multispace0, token));
+
+    context("pdn", terminated(tokens, pair(multispace0, eof)))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_error_test() {
+        assert!(matches!(command(""), Err(ParseError::NoCommandError)));
+        assert!(matches!(
+            command("take 9-33"),
+            Err(ParseError::PositionValueError)
+        ));
+        assert!(matches!(
+            command("best fast"),
+            Err(ParseError::ConstraintOptionError)
+        ));
+        assert!(matches!(
+            command("best depth 1001"),
+            Err(ParseError::ConstraintValueError)
+        ));
+        assert!(matches!(
+            command("best timed soon"),
+            Err(ParseError::ConstraintValueError)
+        ));
+        assert!(matches!(
+            command("mode xml"),
+            Err(ParseError::InvalidCommand)
+        ));
+        assert!(matches!(
+            command("fen B:W33:B1"),
+            Err(ParseError::PositionValueError)
+        ));
+
+        assert!(command("best depth 3").is_ok());
+        assert!(command("take 9-13").is_ok());
+    }
+
+    #[test]
+    fn suggestion_test() {
+        match command("tkae 9-13") {
+            Err(err @ ParseError::UnknownCommand { .. }) => {
+                assert_eq!(
+                    err.to_string(),
+                    "Unknown command tkae! Did you mean `take`?"
+                )
+            }
+            _ => panic!("expected an unknown command"),
+        }
+
+        assert_eq!(suggest_command("histroy"), Some("history"));
+        assert_eq!(suggest_command("gamestat"), Some("gamestate"));
+        assert_eq!(suggest_command("xyzzy"), None);
+    }
+
+    #[test]
+    fn command_table_test() {
+        assert!(matches!(command("g"), Ok(GenerateAllActions)));
+        assert!(matches!(command("p numbers"), Ok(Print(o)) if o.numbers));
+        assert!(matches!(command("help"), Ok(Help(None))));
+        assert!(matches!(command("help t"), Ok(Help(Some("take")))));
+        let square = |n| Square::from_pdn(n).unwrap();
+        assert!(matches!(
+            command("put 14 b king"),
+            Ok(Put(s, Color::Black, true)) if s == square(14)
+        ));
+        assert!(matches!(
+            command("put b6 w"),
+            Ok(Put(s, Color::White, false)) if s == Square::from_algebraic("b6").unwrap()
+        ));
+        assert!(matches!(command("remove 32"), Ok(Remove(s)) if s == square(32)));
+        assert!(matches!(command("setturn w"), Ok(SetTurn(Color::White))));
+        assert!(matches!(command("clearboard"), Ok(ClearBoard)));
+        assert!(command("put 33 b").is_err());
+        assert!(command("setturn red").is_err());
+        assert!(matches!(
+            command("help xyzzy"),
+            Err(ParseError::InvalidCommand)
+        ));
+
+        // no name stands for two commands
+        let mut names: Vec<_> = COMMANDS
+            .iter()
+            .flat_map(|spec| spec.aliases().iter().chain([&spec.name]))
+            .collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+}
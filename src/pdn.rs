@@ -0,0 +1,635 @@
+use std::fmt;
+use std::fs;
+use std::iter;
+use std::sync::Arc;
+
+use crate::board::{Action, Bitboard, Color, Variant};
+use crate::error::{ActionError, BuildError, ParseError};
+use crate::openings::bookfile::{self, BookFile};
+use crate::openings::{self, OpeningName};
+use crate::parse::{self, PdnToken};
+use crate::search::{Book, EngineBuilder, GameState, OpeningBook, Searchable, Winner};
+
+// portable draughts notation (pdn) is how checkers games are stored and shared. a game
+// is a few tag pairs followed by numbered movetext and the result. black moves first,
+// so a result of 1-0 is a win for black
+
+// how many moves are written on a single line of movetext
+const MOVES_PER_LINE: usize = 6;
+// how many actions from the start of every game go into an opening book
+const BOOK_PLIES: usize = 16;
+
+/// Represents a game as the position it started from and the actions taken since. Games
+/// are equal when all of that is, while the positions they reach are compared as boards
+/// or by their position keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    start: Bitboard,
+    actions: Vec<Action>,
+    board: Bitboard,
+    move_limit: Option<usize>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(Bitboard::default())
+    }
+}
+
+impl Game {
+    /// Creates a game without any actions taken from a starting position
+    pub fn new(start: Bitboard) -> Self {
+        Game {
+            start,
+            actions: Vec::new(),
+            board: start,
+            move_limit: None,
+        }
+    }
+
+    /// Returns the most moves the game may last, counting the moves of both sides
+    #[inline]
+    pub fn move_limit(&self) -> Option<usize> {
+        self.move_limit
+    }
+
+    /// Sets the most moves the game may last, counting the moves of both sides. The game
+    /// is drawn once it reaches them, which keeps games between engines from going on
+    /// forever. `None` removes the limit.
+    pub fn set_move_limit(&mut self, limit: Option<usize>) {
+        self.move_limit = limit;
+    }
+
+    /// Returns the position the game started from
+    #[inline]
+    pub fn start(&self) -> Bitboard {
+        self.start
+    }
+
+    /// Returns the current position of the game
+    #[inline]
+    pub fn board(&self) -> Bitboard {
+        self.board
+    }
+
+    /// Returns every action taken since the start, in order
+    #[inline]
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Returns every position the game went through in order, the current one included
+    fn boards(&self) -> impl Iterator<Item = Bitboard> + '_ {
+        let mut board = self.start;
+        let following = self.actions.iter().map(move |&action| {
+            board = board.take_action(action).unwrap();
+            board
+        });
+
+        iter::once(self.start).chain(following)
+    }
+
+    /// Returns the position keys of every position the game went through, the current
+    /// one included. Searches are given these to find repetitions.
+    pub fn hashes(&self) -> Vec<u64> {
+        self.boards().map(|board| board.position_key()).collect()
+    }
+
+    /// Returns how many times the current position occurred earlier in the game. The
+    /// positions are compared in full, so keys that collide cannot fake a repetition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Action, Bitboard};
+    /// use muskox::pdn::Game;
+    ///
+    /// let mut game = Game::new(Bitboard::from_fen("B:WK32:BK1").unwrap());
+    /// for movetext in &["1-6", "32-27", "6-1", "27-32"] {
+    ///     game.take_action(Action::from_movetext(movetext).unwrap()).unwrap();
+    /// }
+    /// assert_eq!(game.repetitions(), 1);
+    /// ```
+    pub fn repetitions(&self) -> usize {
+        self.boards()
+            .take(self.actions.len())
+            .filter(|board| *board == self.board)
+            .count()
+    }
+
+    /// Takes an action on the current position and records it. Illegal actions leave the
+    /// game as it was.
+    pub fn take_action(&mut self, action: Action) -> Result<(), ActionError> {
+        self.board = self.board.take_action(action)?;
+        self.actions.push(action);
+        Ok(())
+    }
+
+    /// Takes back the last action, returning it if there was one
+    pub fn undo(&mut self) -> Option<Action> {
+        let action = self.actions.pop()?;
+
+        self.board = self.start;
+        for &action in &self.actions {
+            self.board = self.board.take_action(action).unwrap();
+        }
+
+        Some(action)
+    }
+
+    /// Returns the state of the game: the state of its board, or a draw once the game
+    /// reached its move limit
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Action;
+    /// use muskox::pdn::Game;
+    /// use muskox::search::{GameState, Winner};
+    ///
+    /// let mut game = Game::default();
+    /// game.set_move_limit(Some(1));
+    /// assert_eq!(game.state(), GameState::InProgress);
+    ///
+    /// game.take_action(Action::from_movetext("11-15").unwrap()).unwrap();
+    /// assert_eq!(game.state(), GameState::Completed(Winner::Draw));
+    /// ```
+    pub fn state(&self) -> GameState<Bitboard> {
+        let limited = matches!(self.move_limit, Some(limit) if self.actions.len() >= limit);
+
+        match self.board.get_game_state() {
+            GameState::InProgress if limited => GameState::Completed(Winner::Draw),
+            state => state,
+        }
+    }
+
+    /// Returns the name of the opening the game follows, if it started from the initial
+    /// position of american checkers with a named line
+    pub fn opening(&self) -> Option<OpeningName> {
+        match self.start == Bitboard::default() {
+            true => openings::classify(&self.actions),
+            false => None,
+        }
+    }
+
+    /// Returns the PDN result of the game: `1-0` if black won, `0-1` if white won,
+    /// `1/2-1/2` for a draw and `*` while the game is still going
+    pub fn result(&self) -> &'static str {
+        match self.state() {
+            GameState::Completed(Winner::Player(Color::Black)) => "1-0",
+            GameState::Completed(Winner::Player(Color::White)) => "0-1",
+            GameState::Completed(Winner::Draw) => "1/2-1/2",
+            GameState::InProgress => "*",
+        }
+    }
+
+    /// Returns the numbered movetext of the game followed by its result, without any
+    /// tag pairs
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Action;
+    /// use muskox::pdn::Game;
+    ///
+    /// let mut game = Game::default();
+    /// for movetext in &["11-15", "23-19", "8-11"] {
+    ///     game.take_action(Action::from_movetext(movetext).unwrap()).unwrap();
+    /// }
+    /// assert_eq!(game.movetext(), "1. 11-15 23-19 2. 8-11 *");
+    /// ```
+    pub fn movetext(&self) -> String {
+        self.movetext_with(&[])
+    }
+
+    /// Returns the movetext with the annotation of every action (NAGs and comments) written
+    /// after it. Actions without an annotation are left bare.
+    pub(crate) fn movetext_with(&self, notes: &[String]) -> String {
+        self.movetext_ending(notes, self.result())
+    }
+
+    // the movetext ending in `result`, which games that were resigned or lost on time
+    // cannot tell from their moves
+    fn movetext_ending(&self, notes: &[String], result: &str) -> String {
+        let mut moves = Vec::new();
+        let mut turn = self.start.turn();
+        let mut number = 1;
+
+        // a game that starts with white to move skips black's half of the first move
+        if turn == Color::White {
+            moves.push(vec![format!("{}...", number)]);
+        }
+
+        for (i, action) in self.actions.iter().enumerate() {
+            let text = match notes.get(i) {
+                Some(note) if !note.is_empty() => format!("{} {}", action.pdn_movetext(), note),
+                _ => action.pdn_movetext(),
+            };
+            match turn {
+                Color::Black => moves.push(vec![format!("{}.", number), text]),
+                Color::White => {
+                    match moves.last_mut() {
+                        Some(last) => last.push(text),
+                        None => moves.push(vec![text]),
+                    }
+                    number += 1;
+                }
+            }
+            turn = turn.opposite();
+        }
+
+        let mut lines: Vec<_> = moves
+            .chunks(MOVES_PER_LINE)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|m| m.join(" "))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+
+        match lines.last_mut() {
+            Some(last) => {
+                last.push(' ');
+                last.push_str(result);
+            }
+            None => lines.push(result.to_string()),
+        }
+
+        lines.join("\n")
+    }
+
+    /// Returns the whole game in PDN, with a `GameType` tag for variants other than
+    /// american checkers, a `FEN` tag when the game did not start from the initial
+    /// position and an `Opening` tag when it follows a named opening
+    pub fn pdn(&self) -> String {
+        self.pdn_with(&[])
+    }
+
+    /// Returns the whole game in PDN like `pdn`, with annotations like `movetext_with`
+    pub(crate) fn pdn_with(&self, notes: &[String]) -> String {
+        self.pdn_ending(&[], notes, self.result())
+    }
+
+    /// Returns the whole game in PDN like `pdn`, led by the given tag pairs and ending in
+    /// `result` rather than the result on the board. Used for games that ended off the
+    /// board, by resignation or on time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::pdn::Game;
+    ///
+    /// let game = Game::default();
+    /// let pdn = game.pdn_tagged(&[("Event", "match"), ("Black", "muskox")], "0-1");
+    /// assert!(pdn.starts_with("[Event \"match\"]\n[Black \"muskox\"]\n"));
+    /// assert!(pdn.ends_with("[Result \"0-1\"]\n\n0-1\n"));
+    /// ```
+    pub fn pdn_tagged(&self, tags: &[(&str, &str)], result: &str) -> String {
+        self.pdn_ending(tags, &[], result)
+    }
+
+    /// Returns the whole game in PDN like `pdn_tagged`, with annotations like
+    /// `movetext_with`
+    pub(crate) fn pdn_tagged_with(
+        &self,
+        tags: &[(&str, &str)],
+        notes: &[String],
+        result: &str,
+    ) -> String {
+        self.pdn_ending(tags, notes, result)
+    }
+
+    fn pdn_ending(&self, tags: &[(&str, &str)], notes: &[String], result: &str) -> String {
+        let mut out = String::new();
+        for (name, value) in tags {
+            out.push_str(&format!("[{} \"{}\"]\n", name, value.replace('"', "'")));
+        }
+        let variant = self.start.variant();
+
+        if variant != Variant::American {
+            let game_type = match variant.game_type() {
+                Some(game_type) => game_type.to_string(),
+                None => variant.name().to_string(),
+            };
+            out.push_str(&format!("[GameType \"{}\"]\n", game_type));
+        }
+
+        if self.start != Bitboard::default().with_variant(variant) {
+            let fen = self.start.with_variant(Variant::American).fen();
+            out.push_str("[SetUp \"1\"]\n");
+            out.push_str(&format!("[FEN \"{}\"]\n", fen));
+        }
+
+        if let Some(opening) = self.opening() {
+            out.push_str(&format!("[Opening \"{}\"]\n", opening));
+        }
+
+        out.push_str(&format!("[Result \"{}\"]\n\n", result));
+        out.push_str(&self.movetext_ending(notes, result));
+        out.push('\n');
+
+        out
+    }
+}
+
+/// Parses every game in a piece of PDN text and replays its moves. Moves may list every
+/// landing square of a capture (`9x18x27`) or only where it starts and ends (`9x27`).
+/// Comments, variations and annotations are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::pdn;
+///
+/// let text = "[Event \"?\"]\n1. 11-15 {the old fourteenth} 23-19 2. 8-11 22-17 1/2-1/2";
+/// let games = pdn::parse_games(text).unwrap();
+/// assert_eq!(games.len(), 1);
+/// assert_eq!(games[0].actions().len(), 4);
+/// ```
+pub fn parse_games(text: &str) -> Result<Vec<Game>, ParseError> {
+    let (_, tokens) = parse::pdn_primary(text)?;
+
+    let mut games = Vec::new();
+    let mut tags = Vec::new();
+    let mut moves = Vec::new();
+
+    for token in tokens {
+        match token {
+            // tags after moves belong to the next game when the result was left out
+            PdnToken::Tag(name, value) => {
+                if !moves.is_empty() {
+                    games.push(replay(games.len() + 1, &tags, &moves)?);
+                    tags.clear();
+                    moves.clear();
+                }
+                tags.push((name, value));
+            }
+            PdnToken::Move(movetext) => moves.push(movetext),
+            PdnToken::Result(_) => {
+                games.push(replay(games.len() + 1, &tags, &moves)?);
+                tags.clear();
+                moves.clear();
+            }
+            PdnToken::Skip => (),
+        }
+    }
+
+    if !tags.is_empty() || !moves.is_empty() {
+        games.push(replay(games.len() + 1, &tags, &moves)?);
+    }
+
+    Ok(games)
+}
+
+fn replay(number: usize, tags: &[(&str, &str)], moves: &[&str]) -> Result<Game, ParseError> {
+    let tag = |name: &str| tags.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+
+    let variant = match tag("GameType") {
+        // the game type may carry extra fields after a comma
+        Some(game_type) => {
+            let game_type = game_type.split(',').next().unwrap_or("");
+            match game_type.parse() {
+                Ok(number) => Variant::from_game_type(number).ok_or(ParseError::VariantError)?,
+                Err(_) => game_type.parse()?,
+            }
+        }
+        None => Variant::American,
+    };
+
+    let start = match tag("FEN") {
+        Some(fen) => Bitboard::from_fen(fen)?,
+        None => Bitboard::default(),
+    };
+
+    let mut game = Game::new(start.with_variant(variant));
+    for movetext in moves {
+        let illegal = || ParseError::PdnMoveError {
+            game: number,
+            movetext: movetext.to_string(),
+        };
+        let action = find_action(&game.board(), movetext).ok_or_else(illegal)?;
+        game.take_action(action).map_err(|_| illegal())?;
+    }
+
+    Ok(game)
+}
+
+/// Finds the legal action that PDN movetext refers to
+pub(crate) fn find_action(board: &Bitboard, movetext: &str) -> Option<Action> {
+    let squares: Vec<_> = movetext.split(['-', 'x']).collect();
+
+    board
+        .generate_all_actions()
+        .iter()
+        .map(|p| *p.action())
+        .find(|action| {
+            let text = action.movetext();
+            let path: Vec<_> = text.split('-').collect();
+            match squares.len() {
+                2 => path.first() == squares.first() && path.last() == squares.last(),
+                _ => path == squares,
+            }
+        })
+}
+
+impl Book<Bitboard> {
+    /// Creates an opening book from the first moves of every game
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Action, Bitboard};
+    /// use muskox::pdn;
+    /// use muskox::search::Book;
+    ///
+    /// let games = pdn::parse_games("1. 11-15 23-19 *\n\n1. 11-15 22-18 *\n\n1. 9-13 *").unwrap();
+    /// let book = Book::from_games(&games);
+    ///
+    /// let actions = book.actions(&Bitboard::default());
+    /// assert_eq!(actions[0], (Action::from_movetext("11-15").unwrap(), 2));
+    /// ```
+    pub fn from_games(games: &[Game]) -> Self {
+        let mut book = Book::new();
+
+        for game in games {
+            let mut board = game.start();
+            for action in game.actions().iter().take(BOOK_PLIES) {
+                book.add(&board, *action);
+                board = board.take_action(*action).unwrap();
+            }
+        }
+
+        book
+    }
+}
+
+impl EngineBuilder<Bitboard> {
+    /// Sets the opening book: a book file, or a PDN file whose games make up the book.
+    /// The file is read, or mapped into memory if it is a book file, when the engine is
+    /// built.
+    pub fn use_book(mut self, path: &str) -> Self {
+        let path = path.to_string();
+        self.book = Some(Box::new(move || {
+            let error = |message: String| BuildError::BookError {
+                path: path.clone(),
+                message,
+            };
+
+            let book: Arc<dyn OpeningBook<Bitboard>> = match bookfile::is_book_file(&path) {
+                true => Arc::new(BookFile::open(&path).map_err(|err| error(err.to_string()))?),
+                false => {
+                    let text = fs::read_to_string(&path).map_err(|err| error(err.to_string()))?;
+                    let games = parse_games(&text).map_err(|err| error(err.to_string()))?;
+                    Arc::new(Book::from_games(&games))
+                }
+            };
+            Ok(book)
+        }));
+        self
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pdn())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{Engine, SearchConstraint};
+
+    fn play(start: Bitboard, movetexts: &[&str]) -> Game {
+        let mut game = Game::new(start);
+        for movetext in movetexts {
+            let action = Action::from_movetext(movetext).unwrap();
+            game.take_action(action).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn movetext_test() {
+        let game = play(Bitboard::default(), &[]);
+        assert_eq!(game.movetext(), "*");
+        assert_eq!(game.pdn(), "[Result \"*\"]\n\n*\n");
+
+        let mut game = play(Bitboard::default(), &["9-13", "22-18", "13-17", "21-14"]);
+        assert_eq!(game.movetext(), "1. 9-13 22-18 2. 13-17 21x14 *");
+        assert!(game.pdn().starts_with("[Opening \"Edinburgh\"]\n[Result"));
+        assert!(game
+            .take_action(Action::from_movetext("1-5").unwrap())
+            .is_err());
+        assert_eq!(game.actions().len(), 4);
+
+        let start = Bitboard::from_fen("W:W9,K11,19:B15,22,K32").unwrap();
+        let game = play(start, &["19-10", "32-27"]);
+        assert_eq!(game.movetext(), "1... 19x10 2. 32-27 *");
+        assert!(game.pdn().contains("[FEN \"W:W9,K11,19:B15,22,K32\"]"));
+
+        let start = Bitboard::from_fen("W:W14:B1").unwrap();
+        let game = play(start, &["14-9", "1-5", "9-6", "5-9", "6-2"]);
+        assert!(game.pdn().starts_with("[SetUp \"1\"]"));
+        assert!(game.movetext().ends_with("3. 5-9 6-2 *"));
+    }
+
+    #[test]
+    fn parse_games_test() {
+        let text = "[Event \"first\"]
+[Result \"*\"]
+
+1. 9-13 22-18 2. 13-17 21x14! (2. 21x14 $1 {forced} (2. 18-14?)) 10x17 *
+
+[Event \"second\"]
+[SetUp \"1\"]
+[FEN \"B:W11,18,26,27:B8\"]
+1. 8x31 ; the triple jump
+0-1";
+        let games = parse_games(text).unwrap();
+        assert_eq!(games.len(), 2);
+
+        assert_eq!(
+            games[0].movetext(),
+            "1. 9-13 22-18 2. 13-17 21x14 3. 10x17 *"
+        );
+        assert_eq!(games[1].actions()[0].movetext(), "8-15-22-31");
+        assert_eq!(games[1].board().get_game_state(), GameState::InProgress);
+
+        // a game we write can be read back
+        let pdn = games[1].pdn();
+        assert_eq!(parse_games(&pdn).unwrap(), vec![games[1].clone()]);
+
+        let text = "[GameType \"22\"]\n1. 9-13 *";
+        assert_eq!(
+            parse_games(text).unwrap()[0].board().variant(),
+            Variant::Italian
+        );
+
+        assert!(matches!(
+            parse_games("1. 9-13 22-17 2. 9-14 *"),
+            Err(ParseError::PdnMoveError { game: 1, .. })
+        ));
+        assert!(matches!(
+            parse_games("1. 9-13 [Event"),
+            Err(ParseError::PdnError)
+        ));
+    }
+
+    #[test]
+    fn undo_test() {
+        let mut game = play(Bitboard::default(), &["9-13", "22-18"]);
+        assert_eq!(game.undo(), Some(Action::from_movetext("22-18").unwrap()));
+        assert_eq!(game, play(Bitboard::default(), &["9-13"]));
+        game.undo();
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.board(), Bitboard::default());
+    }
+
+    #[test]
+    fn result_test() {
+        let start = Bitboard::from_fen("W:W9,K11,19:B15").unwrap();
+        let game = play(start, &["19-10"]);
+        assert_eq!(game.result(), "0-1");
+        assert!(game.pdn().contains("[Result \"0-1\"]"));
+        assert!(game.movetext().ends_with("19x10 0-1"));
+
+        let start = Bitboard::from_fen("giveaway:W:W9,K11,19:B15").unwrap();
+        let game = play(start, &["19-10"]);
+        assert_eq!(game.result(), "1-0");
+        assert!(game.pdn().starts_with("[GameType \"giveaway\"]"));
+    }
+
+    #[test]
+    fn book_test() {
+        let path = std::env::temp_dir().join("muskox_book_test.pdn");
+        fs::write(&path, "1. 11-15 23-19 2. 8-11 *\n\n1. 11-15 22-18 *\n").unwrap();
+
+        let engine = Engine::builder()
+            .use_book(path.to_str().unwrap())
+            .build()
+            .unwrap();
+        let board = Bitboard::default();
+        let result = engine.search(&board, &SearchConstraint::Depth(6));
+        assert_eq!(result.nodes(), 0);
+        assert_eq!(result.results().len(), 1);
+        assert_eq!(
+            result.best().unwrap().action(),
+            Action::from_movetext("11-15").unwrap()
+        );
+
+        // positions the book does not know are searched
+        let board = board
+            .take_action(Action::from_movetext("9-13").unwrap())
+            .unwrap();
+        assert!(engine.search(&board, &SearchConstraint::Depth(2)).nodes() > 0);
+
+        let missing = path.with_extension("missing");
+        let engine: Result<Engine<Bitboard>, _> = Engine::builder()
+            .use_book(missing.to_str().unwrap())
+            .build();
+        assert!(matches!(engine, Err(BuildError::BookError { .. })));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::search::Searchable;
+
+// perft counts the positions at the leaves of the full game tree to some depth. the
+// counts of well known positions are published, so comparing against them checks move
+// generation far more thoroughly than any handful of hand written cases. positions are
+// expanded whether or not the game is over, like every published count does
+
+/// Returns the number of positions `depth` moves away from `state`
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Bitboard;
+/// use muskox::perft;
+///
+/// assert_eq!(perft::perft(&Bitboard::default(), 3), 302);
+/// ```
+pub fn perft<S: Searchable>(state: &S, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let pairs = state.generate_all_actions();
+    match depth {
+        // the children need not be expanded to be counted
+        1 => pairs.len() as u64,
+        _ => pairs.iter().map(|p| perft(p.state(), depth - 1)).sum(),
+    }
+}
+
+/// Returns the perft count below each action of `state`, which narrows a wrong count
+/// down to the moves that cause it
+pub fn divide<S: Searchable>(state: &S, depth: u32) -> Vec<(S::Action, u64)> {
+    state
+        .generate_all_actions()
+        .iter()
+        .map(|p| (*p.action(), perft(p.state(), depth.saturating_sub(1))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Bitboard;
+
+    // the published counts of the starting position
+    const START: [u64; 8] = [1, 7, 49, 302, 1469, 7361, 36768, 179740];
+
+    // positions where jumps are easy to get wrong, each with its counts from depth 1. the
+    // moves at depth 1 were checked by hand and the deeper counts pin the generator down
+    const CORPUS: [(&str, &[u64]); 6] = [
+        // a man crowned by a jump stops there, though a king could jump on
+        ("B:W26,27:B22", &[1, 2, 4, 8, 32, 56]),
+        // kings with many chains, some of them ending where they started
+        (
+            "B:W6,7,14,15,22,23,24:BK1,K3",
+            &[8, 32, 96, 300, 1164, 4752],
+        ),
+        // any capture may be chosen in american checkers
+        ("B:W14,15,22,23,30:B10,11", &[4, 5, 7, 37, 60, 234]),
+        // brazilian men capture backwards, and the most pieces have to be taken
+        ("26:B:W14,15,22,23,30:B10,11", &[2, 4, 16, 32, 112, 168]),
+        // italian captures take the most pieces and then the earliest king
+        ("22:W:WK14,30:B10,11,18,K19,27", &[1, 2, 12, 23, 132, 416]),
+        ("W:WK14,30:B10,11,18,K19,27", &[4, 14, 64, 236, 938, 4566]),
+    ];
+
+    #[test]
+    fn start_test() {
+        let board = Bitboard::default();
+        for (depth, &nodes) in START.iter().enumerate() {
+            assert_eq!(perft(&board, depth as u32), nodes, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    fn corpus_test() {
+        for (fen, counts) in &CORPUS {
+            let board = Bitboard::from_fen(fen).unwrap();
+            for (i, &nodes) in counts.iter().enumerate() {
+                assert_eq!(
+                    perft(&board, i as u32 + 1),
+                    nodes,
+                    "{} depth {}",
+                    fen,
+                    i + 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn divide_test() {
+        let board = Bitboard::default();
+        let divided = divide(&board, 4);
+        assert_eq!(divided.len(), 7);
+        assert_eq!(divided.iter().map(|(_, n)| n).sum::<u64>(), START[4]);
+    }
+}
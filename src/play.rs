@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::app::{color_name, json_actions};
+use crate::board::{Action, Bitboard, Color};
+use crate::pdn::Game;
+use crate::players::Clock;
+use crate::search::{Engine, GameState, Score, SearchConstraint, Searchable};
+
+// a websocket server for playing against the engine from a browser. every message is a
+// json object with a `type`. clients create games and submit moves, and the server
+// answers with the state of the game after the engine replied, so the frontend needs no
+// engine logic at all. games are kept by id and can be played from any connection
+//
+// client messages:
+//   {"type": "new", "color": "white", "seconds": 300, "fen": "..."}
+//       starts a game with the client playing `color` (black by default), `seconds` of
+//       thinking time for each side (300 by default) from `fen` (the start by default)
+//   {"type": "move", "game": 1, "move": "23-19"}
+//   {"type": "draw", "game": 1}
+//       offers the engine a draw, which it accepts unless it thinks it is ahead
+//   {"type": "state", "game": 1}
+//
+// server messages:
+//   {"type": "state", "game": 1, "fen": "...", "turn": "black", "moves": [...],
+//    "engine_move": "11-15", "clock": {"black": 299500, "white": 298000},
+//    "result": "*"}
+//   {"type": "error", "message": "..."}
+
+const DEFAULT_SECONDS: u64 = 300;
+// the engine plans its time as if this many moves were left in the game
+const MOVES_TO_GO: u32 = 30;
+
+/// Represents a game between a client and the engine
+struct Session {
+    game: Game,
+    human: Color,
+    clock: Clock,
+    flagged: Option<Color>,  // the side that ran out of time, if one did
+    resigned: Option<Color>, // the side that resigned, which can only be the engine
+    drawn: bool,             // whether the engine accepted a draw
+    engine_move: Option<Action>,
+    engine_scores: Vec<Score>, // the score of every search the engine made, in order
+}
+
+impl Session {
+    fn over(&self) -> bool {
+        self.flagged.is_some()
+            || self.resigned.is_some()
+            || self.drawn
+            || !matches!(self.game.state(), GameState::InProgress)
+    }
+
+    fn result(&self) -> &'static str {
+        match self.flagged.or(self.resigned) {
+            Some(Color::Black) => "0-1",
+            Some(Color::White) => "1-0",
+            None if self.drawn => "1/2-1/2",
+            None => self.game.result(),
+        }
+    }
+
+    fn state(&self, id: u64) -> Value {
+        let board = self.game.board();
+        let millis = |color| self.clock.remaining(&color).as_millis() as u64;
+
+        json!({
+            "type": "state",
+            "game": id,
+            "fen": board.fen(),
+            "turn": color_name(board.turn()),
+            "moves": json_actions(self.game.actions()),
+            "engine_move": self.engine_move.map(|a| a.to_string()),
+            "clock": { "black": millis(Color::Black), "white": millis(Color::White) },
+            "result": self.result(),
+        })
+    }
+
+    /// Lets the engine move if it is its turn
+    fn engine_reply(&mut self, engine: &Engine<Bitboard>) {
+        let board = self.game.board();
+        if self.over() || board.turn() == self.human {
+            return;
+        }
+
+        let budget = self.clock.remaining(&board.turn()) / MOVES_TO_GO;
+        let mut engine = engine.clone();
+        engine.set_history(&self.game.hashes());
+        let results = engine.search(&board, &SearchConstraint::Time(budget));
+
+        if !self.clock.punch(&board.turn()) {
+            self.flagged = Some(board.turn());
+            return;
+        }
+
+        // the game is in progress so there is an action
+        let best = results.best().unwrap();
+        self.engine_scores.push(best.score());
+        if engine.should_resign(&self.engine_scores, &board.turn()) {
+            self.resigned = Some(board.turn());
+            return;
+        }
+
+        let action = best.action();
+        self.game.take_action(action).unwrap();
+        self.engine_move = Some(action);
+    }
+}
+
+fn error(message: &str) -> Value {
+    json!({ "type": "error", "message": message })
+}
+
+/// Plays games against the engine over websockets. See the comment at the top of the
+/// module for the messages it understands.
+pub struct PlayServer {
+    listener: TcpListener,
+    shared: Arc<Shared>,
+}
+
+impl PlayServer {
+    /// Listens on `address`
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(PlayServer {
+            listener: TcpListener::bind(address)?,
+            shared: Arc::new(Shared {
+                engine: Engine::new(),
+                sessions: Mutex::new(HashMap::new()),
+                next_id: AtomicU64::new(1),
+            }),
+        })
+    }
+
+    /// Returns the address the server listens on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections until accepting one fails, serving each on a thread of its own
+    pub fn run(self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let shared = self.shared.clone();
+
+            // one bad client is no reason to stop serving the others
+            thread::spawn(move || {
+                let _ = shared.serve(stream);
+            });
+        }
+        Ok(())
+    }
+}
+
+fn io_error(err: impl fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Holds what every connection of a server shares: the engine and the games
+struct Shared {
+    engine: Engine<Bitboard>,
+    sessions: Mutex<HashMap<u64, Arc<Mutex<Session>>>>,
+    next_id: AtomicU64,
+}
+
+impl Shared {
+    fn serve(&self, stream: TcpStream) -> io::Result<()> {
+        let mut socket: WebSocket<TcpStream> = tungstenite::accept(stream).map_err(io_error)?;
+
+        loop {
+            let reply = match socket.read().map_err(io_error)? {
+                Message::Text(text) => match serde_json::from_str(&text) {
+                    Ok(request) => self.answer(&request),
+                    Err(_) => error("message is not json"),
+                },
+                Message::Close(_) => return Ok(()),
+                _ => continue,
+            };
+            socket
+                .send(Message::Text(reply.to_string()))
+                .map_err(io_error)?;
+        }
+    }
+
+    fn answer(&self, request: &Value) -> Value {
+        match request["type"].as_str() {
+            Some("new") => self.new_game(request),
+            Some("move") => self.with_session(request, |session| {
+                let movetext = match request["move"].as_str() {
+                    Some(movetext) => movetext,
+                    None => return Err("missing move"),
+                };
+                if session.over() {
+                    return Err("the game is over");
+                }
+                if session.game.board().turn() != session.human {
+                    return Err("it is not your turn");
+                }
+
+                let action = Action::from_movetext(movetext).map_err(|_| "invalid movetext")?;
+                session
+                    .game
+                    .take_action(action)
+                    .map_err(|_| "illegal move")?;
+
+                session.engine_move = None;
+                match session.clock.punch(&session.human) {
+                    true => session.engine_reply(&self.engine),
+                    false => session.flagged = Some(session.human),
+                }
+                Ok(())
+            }),
+            Some("draw") => self.with_session(request, |session| {
+                if session.over() {
+                    return Err("the game is over");
+                }
+                let side = session.human.opposite();
+                match self
+                    .engine
+                    .should_accept_draw(&session.engine_scores, &side)
+                {
+                    true => {
+                        session.drawn = true;
+                        Ok(())
+                    }
+                    false => Err("the engine declined the draw"),
+                }
+            }),
+            Some("state") => self.with_session(request, |_| Ok(())),
+            _ => error("unknown message type"),
+        }
+    }
+
+    fn new_game(&self, request: &Value) -> Value {
+        let human = match request["color"].as_str() {
+            None | Some("black") => Color::Black,
+            Some("white") => Color::White,
+            Some(_) => return error("color has to be black or white"),
+        };
+        let seconds = match &request["seconds"] {
+            Value::Null => DEFAULT_SECONDS,
+            value => match value.as_u64() {
+                Some(seconds) if seconds > 0 => seconds,
+                _ => return error("invalid seconds"),
+            },
+        };
+        let start = match request["fen"].as_str() {
+            None => Bitboard::default(),
+            Some(fen) => match Bitboard::from_fen(fen) {
+                Ok(board) => board,
+                Err(err) => return error(&err.to_string()),
+            },
+        };
+
+        let mut session = Session {
+            game: Game::new(start),
+            human,
+            clock: Clock::new(Duration::from_secs(seconds)),
+            flagged: None,
+            resigned: None,
+            drawn: false,
+            engine_move: None,
+            engine_scores: Vec::new(),
+        };
+        session.engine_reply(&self.engine);
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let state = session.state(id);
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id, Arc::new(Mutex::new(session)));
+        state
+    }
+
+    /// Runs `f` on the session the request names and answers with its state
+    fn with_session<F>(&self, request: &Value, f: F) -> Value
+    where
+        F: FnOnce(&mut Session) -> Result<(), &'static str>,
+    {
+        let id = match request["game"].as_u64() {
+            Some(id) => id,
+            None => return error("missing game"),
+        };
+        // only hold on to the map long enough to find the game
+        let session = match self.sessions.lock().unwrap().get(&id) {
+            Some(session) => session.clone(),
+            None => return error("unknown game"),
+        };
+
+        let mut session = session.lock().unwrap();
+        match f(&mut session) {
+            Ok(()) => session.state(id),
+            Err(message) => error(message),
+        }
+    }
+}
+
+/// Serves games against the engine over websockets on `port` until the process is killed
+pub fn run(port: u16) -> io::Result<()> {
+    let server = PlayServer::bind(("0.0.0.0", port))?;
+    eprintln!("listening on {}", server.local_addr()?);
+    server.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_server_test() {
+        let server = PlayServer::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+
+        let stream = TcpStream::connect(address).unwrap();
+        let url = format!("ws://{}/", address);
+        let (mut socket, _) = tungstenite::client(url, stream).unwrap();
+
+        let mut exchange = |request: Value| {
+            socket.send(Message::Text(request.to_string())).unwrap();
+            match socket.read().unwrap() {
+                Message::Text(text) => serde_json::from_str::<Value>(&text).unwrap(),
+                other => panic!("unexpected message {:?}", other),
+            }
+        };
+
+        // the engine plays black and moves first
+        let state = exchange(json!({ "type": "new", "color": "white", "seconds": 3 }));
+        assert_eq!(state["type"], "state");
+        assert_eq!(state["turn"], "white");
+        assert_eq!(state["moves"].as_array().unwrap().len(), 1);
+        assert!(state["clock"]["black"].as_u64().unwrap() < 3000);
+        assert_eq!(state["clock"]["white"], 3000);
+        let game = state["game"].clone();
+
+        let reply = exchange(json!({ "type": "move", "game": game, "move": "1-5" }));
+        assert_eq!(reply["type"], "error");
+
+        let legal = Bitboard::from_fen(state["fen"].as_str().unwrap())
+            .unwrap()
+            .generate_all_actions()[0]
+            .action()
+            .to_string();
+        let state = exchange(json!({ "type": "move", "game": game, "move": legal }));
+        assert_eq!(state["turn"], "white");
+        assert_eq!(state["moves"][1], legal);
+        assert_eq!(state["moves"][2], state["engine_move"]);
+        assert_eq!(state["result"], "*");
+
+        let reply = exchange(json!({ "type": "state", "game": 99 }));
+        assert_eq!(reply["message"], "unknown game");
+
+        // the engine has not searched anything to judge a draw by yet
+        let state = exchange(json!({ "type": "new" }));
+        let reply = exchange(json!({ "type": "draw", "game": state["game"] }));
+        assert_eq!(reply["message"], "the engine declined the draw");
+    }
+}
@@ -0,0 +1,537 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::board::Prng;
+use crate::search::{Engine, GameState, Optim, Score, SearchConstraint, Searchable, Side, Winner};
+
+// players pick the actions of one side of a game, whether they are the engine, someone
+// at the keyboard or a program at the other end of a connection. `play_game` plays any
+// two of them against each other, so everything that runs games only has to set the
+// players up. besides the engine there are two weak baselines, one moving at random and
+// one grabbing whatever looks best a single move ahead. matches against them show
+// whether a change to the engine made it stronger at all, long before it is worth
+// playing the engine against itself. every player can tell how it came to its last
+// action, and games keep that for every move so a surprising one can be played again
+
+// the engine plans its time as if this many moves were left in the game
+const MOVES_TO_GO: u32 = 30;
+
+/// Represents anything that can pick actions for one side of a game
+pub trait Player<S: Searchable> {
+    /// Returns the action to take in `state`, with the time both sides have left on
+    /// `clock`. Returns `None` if the player gives the game up, which is also how
+    /// players that lost their input or their connection leave.
+    fn choose(&mut self, state: &S, clock: &Clock) -> Option<S::Action>;
+
+    /// Lets the player know the opponent took `action` in `state`. Players that keep
+    /// their own copy of the game, such as remote ones, pass it on.
+    fn observe(&mut self, _state: &S, _action: S::Action) {}
+
+    /// Returns how the player came to the action it chose last, if it can tell
+    fn record(&self) -> Option<MoveRecord> {
+        None
+    }
+}
+
+/// Describes how a player came to an action, with what it takes to choose it again: the
+/// search that found it and the seed of the random numbers it drew. A timed search is
+/// repeated by searching to the depth it reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MoveRecord {
+    constraint: Option<SearchConstraint>,
+    depth: Option<u32>,
+    seed: Option<u64>,
+}
+
+impl MoveRecord {
+    /// Creates the record of a search within `constraint` that reached `depth`
+    pub fn search(constraint: SearchConstraint, depth: u32) -> Self {
+        MoveRecord {
+            constraint: Some(constraint),
+            depth: Some(depth),
+            seed: None,
+        }
+    }
+
+    /// Creates the record of a choice made with random numbers drawn from `seed`
+    pub fn seeded(seed: u64) -> Self {
+        MoveRecord {
+            seed: Some(seed),
+            ..MoveRecord::default()
+        }
+    }
+
+    /// Returns what the search was limited to, if the player searched
+    #[inline]
+    pub fn constraint(&self) -> Option<SearchConstraint> {
+        self.constraint
+    }
+
+    /// Returns the deepest iteration the search finished, if the player searched
+    #[inline]
+    pub fn depth(&self) -> Option<u32> {
+        self.depth
+    }
+
+    /// Returns the seed of the random numbers the player drew, if it drew any
+    #[inline]
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
+/// Writes the record out the way it is kept in PDN comments, as in
+/// `time 0.500s, depth 9` or `seed 12345`
+impl fmt::Display for MoveRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        match self.constraint {
+            Some(SearchConstraint::Depth(depth)) => parts.push(format!("limit {}", depth)),
+            Some(SearchConstraint::Time(time)) => {
+                parts.push(format!("time {:.3}s", time.as_secs_f64()))
+            }
+            Some(_) => parts.push("unlimited".to_string()),
+            None => {}
+        }
+        if let Some(depth) = self.depth {
+            parts.push(format!("depth {}", depth));
+        }
+        if let Some(seed) = self.seed {
+            parts.push(format!("seed {}", seed));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Represents the thinking time each side has left. The clock runs for the side to move
+/// from the moment it is started or punched.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use muskox::board::Color;
+/// use muskox::players::Clock;
+///
+/// let mut clock = Clock::new(Duration::from_secs(60));
+/// assert!(clock.punch(&Color::Black));
+/// assert!(clock.remaining(&Color::Black) < Duration::from_secs(60));
+/// assert_eq!(clock.remaining(&Color::White), Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Clock {
+    remaining: [Duration; 2], // the maximizer's, then the minimizer's
+    since: Instant,           // when the side to move started thinking
+}
+
+impl Clock {
+    /// Creates a clock giving each side `time`
+    pub fn new(time: Duration) -> Self {
+        Clock {
+            remaining: [time; 2],
+            since: Instant::now(),
+        }
+    }
+
+    /// Creates a clock that never runs out
+    pub fn unlimited() -> Self {
+        Self::new(Duration::MAX)
+    }
+
+    fn index<Sd: Side>(side: &Sd) -> usize {
+        match side.optim() {
+            Optim::Max => 0,
+            Optim::Min => 1,
+        }
+    }
+
+    /// Returns whether the clock never runs out
+    pub fn is_unlimited(&self) -> bool {
+        self.remaining.iter().all(|&r| r == Duration::MAX)
+    }
+
+    /// Returns the time `side` has left
+    pub fn remaining<Sd: Side>(&self, side: &Sd) -> Duration {
+        self.remaining[Self::index(side)]
+    }
+
+    /// Starts the clock of the side to move over from now, without charging anybody
+    pub fn restart(&mut self) {
+        self.since = Instant::now();
+    }
+
+    /// Returns the time the side to move has been thinking, which is not charged yet
+    pub fn thinking(&self) -> Duration {
+        self.since.elapsed()
+    }
+
+    /// Charges the time since the last move to `side`. Returns false if it ran out.
+    pub fn punch<Sd: Side>(&mut self, side: &Sd) -> bool {
+        let now = Instant::now();
+        let left = &mut self.remaining[Self::index(side)];
+        let spent = now - self.since;
+
+        self.since = now;
+        match left.checked_sub(spent) {
+            Some(rest) if rest > Duration::from_secs(0) => {
+                *left = rest;
+                true
+            }
+            _ => {
+                *left = Duration::from_secs(0);
+                false
+            }
+        }
+    }
+}
+
+/// Represents how a game played by `play_game` came to an end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Ending {
+    /// The rules ended the game
+    Completed,
+    /// The side to move gave the game up
+    Resigned,
+    /// The side to move ran out of time
+    Flagged,
+    /// The side to move chose an illegal action
+    Forfeited,
+    /// The game went on for too long and was called a draw
+    MoveLimit,
+}
+
+/// Represents a game played out between two players
+#[derive(Debug)]
+pub struct PlayedGame<S: Searchable> {
+    actions: Vec<S::Action>,
+    records: Vec<Option<MoveRecord>>, // how every action came about, if its player told
+    state: S,
+    winner: Winner<S>,
+    ending: Ending,
+}
+
+impl<S: Searchable> PlayedGame<S> {
+    /// Returns every action taken, in order
+    #[inline]
+    pub fn actions(&self) -> &[S::Action] {
+        &self.actions
+    }
+
+    /// Returns how every action came about, in order, for the players that could tell
+    #[inline]
+    pub fn records(&self) -> &[Option<MoveRecord>] {
+        &self.records
+    }
+
+    /// Returns the state the game ended in
+    #[inline]
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    #[inline]
+    pub fn winner(&self) -> &Winner<S> {
+        &self.winner
+    }
+
+    #[inline]
+    pub fn ending(&self) -> Ending {
+        self.ending
+    }
+}
+
+/// Plays a game from `state` between `max`, who plays the maximizing side, and `min`
+/// until it ends. A player who resigns, runs out of time on `clock` or chooses an
+/// illegal action loses. After `max_actions` actions, if there is a limit, the game is
+/// a draw.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Bitboard;
+/// use muskox::players::{self, Clock, Ending, GreedyPlayer, RandomPlayer};
+///
+/// let game = players::play_game(
+///     Bitboard::default(),
+///     &mut RandomPlayer::with_seed(5),
+///     &mut GreedyPlayer,
+///     &mut Clock::unlimited(),
+///     Some(300),
+/// );
+/// assert_ne!(game.ending(), Ending::Forfeited);
+/// ```
+pub fn play_game<'a, S: Searchable>(
+    mut state: S,
+    max: &mut (dyn Player<S> + 'a),
+    min: &mut (dyn Player<S> + 'a),
+    clock: &mut Clock,
+    max_actions: Option<usize>,
+) -> PlayedGame<S> {
+    let mut actions = Vec::new();
+    let mut records = Vec::new();
+    clock.restart();
+
+    let (winner, ending) = loop {
+        if let GameState::Completed(winner) = state.get_game_state() {
+            break (winner, Ending::Completed);
+        }
+        if matches!(max_actions, Some(limit) if actions.len() >= limit) {
+            break (Winner::Draw, Ending::MoveLimit);
+        }
+
+        let side = state.turn();
+        let (player, opponent) = match side.optim() {
+            Optim::Max => (&mut *max, &mut *min),
+            Optim::Min => (&mut *min, &mut *max),
+        };
+
+        let action = player.choose(&state, clock);
+        if !clock.punch(&side) {
+            break (Winner::Player(side.opposite()), Ending::Flagged);
+        }
+        let action = match action {
+            Some(action) => action,
+            None => break (Winner::Player(side.opposite()), Ending::Resigned),
+        };
+        let next = match state.take_action(action) {
+            Ok(next) => next,
+            Err(_) => break (Winner::Player(side.opposite()), Ending::Forfeited),
+        };
+
+        opponent.observe(&state, action);
+        actions.push(action);
+        records.push(player.record());
+        state = next;
+    };
+
+    PlayedGame {
+        actions,
+        records,
+        state,
+        winner,
+        ending,
+    }
+}
+
+/// A player that takes any legal action with the same chance
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Bitboard;
+/// use muskox::players::{Clock, Player, RandomPlayer};
+///
+/// let board = Bitboard::default();
+/// let clock = Clock::unlimited();
+/// let action = RandomPlayer::with_seed(7).choose(&board, &clock).unwrap();
+/// assert!(board.validate_action(action).is_ok());
+/// assert_eq!(Some(action), RandomPlayer::with_seed(7).choose(&board, &clock));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RandomPlayer {
+    prng: Prng,
+    seed: u64, // the seed the last action was drawn with
+}
+
+impl Default for RandomPlayer {
+    /// Returns a player seeded by the clock
+    fn default() -> Self {
+        let prng = Prng::from_time();
+        RandomPlayer {
+            seed: prng.seed(),
+            prng,
+        }
+    }
+}
+
+impl RandomPlayer {
+    /// Creates a player that always takes the same actions for the same seed, which must
+    /// not be zero
+    pub fn with_seed(seed: u64) -> Self {
+        RandomPlayer {
+            prng: Prng::new(seed),
+            seed,
+        }
+    }
+}
+
+impl<S: Searchable> Player<S> for RandomPlayer {
+    fn choose(&mut self, state: &S, _: &Clock) -> Option<S::Action> {
+        let pairs = state.generate_all_actions();
+        self.seed = self.prng.seed();
+        match pairs.len() {
+            0 => None,
+            len => Some(*pairs[(self.prng.rand64() % len as u64) as usize].action()),
+        }
+    }
+
+    // a player seeded with the seed of an action takes it again
+    fn record(&self) -> Option<MoveRecord> {
+        Some(MoveRecord::seeded(self.seed))
+    }
+}
+
+/// A player that takes the action leading to the best evaluation, without looking any
+/// further. With the classical evaluation it grabs material whenever it can. Of actions
+/// that are equally good it takes the first one generated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GreedyPlayer;
+
+impl<S: Searchable> Player<S> for GreedyPlayer {
+    fn choose(&mut self, state: &S, _: &Clock) -> Option<S::Action> {
+        // scores are from the maximizer's point of view
+        let advantage = |score: Score| match state.turn().optim() {
+            Optim::Max => score,
+            Optim::Min => Score::DRAW - score,
+        };
+
+        let pairs = state.generate_all_actions();
+        let mut best = pairs.first()?;
+        let mut best_score = advantage(best.state().evaluate());
+        for pair in &pairs[1..] {
+            let score = advantage(pair.state().evaluate());
+            if score > best_score {
+                best = pair;
+                best_score = score;
+            }
+        }
+        Some(*best.action())
+    }
+}
+
+/// A player that searches for its actions with an engine. It resigns when the engine
+/// would.
+pub struct EnginePlayer<S: Searchable> {
+    engine: Engine<S>,
+    constraint: Option<SearchConstraint>,
+    scores: Vec<Score>,         // the score of every search, in order
+    record: Option<MoveRecord>, // how the last action was found
+}
+
+impl<S: Searchable> EnginePlayer<S> {
+    /// Creates a player that takes the best action `engine` finds within `constraint`
+    pub fn new(engine: Engine<S>, constraint: SearchConstraint) -> Self {
+        EnginePlayer {
+            engine,
+            constraint: Some(constraint),
+            scores: Vec::new(),
+            record: None,
+        }
+    }
+
+    /// Creates a player that spreads the time it has left on the clock over the rest of
+    /// the game. On a clock that never runs out it searches to the default depth.
+    pub fn timed(engine: Engine<S>) -> Self {
+        EnginePlayer {
+            engine,
+            constraint: None,
+            scores: Vec::new(),
+            record: None,
+        }
+    }
+
+    #[inline]
+    pub fn engine(&self) -> &Engine<S> {
+        &self.engine
+    }
+
+    /// Returns the scores of the searches made so far, in order
+    #[inline]
+    pub fn scores(&self) -> &[Score] {
+        &self.scores
+    }
+}
+
+impl<S: Searchable> Player<S> for EnginePlayer<S> {
+    fn choose(&mut self, state: &S, clock: &Clock) -> Option<S::Action> {
+        let constraint = match self.constraint {
+            Some(constraint) => constraint,
+            None if clock.is_unlimited() => SearchConstraint::None,
+            None => SearchConstraint::Time(clock.remaining(&state.turn()) / MOVES_TO_GO),
+        };
+
+        let results = self.engine.search(state, &constraint);
+        self.record = Some(MoveRecord::search(constraint, results.depth()));
+        let best = results.best()?;
+        self.scores.push(best.score());
+        match self.engine.should_resign(&self.scores, &state.turn()) {
+            true => None,
+            false => Some(best.action()),
+        }
+    }
+
+    fn record(&self) -> Option<MoveRecord> {
+        self.record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Bitboard, Color};
+
+    #[test]
+    fn players_test() {
+        let clock = Clock::unlimited();
+
+        // a greedy player takes the king rather than the man
+        let board = Bitboard::from_fen("B:W17,K18,30:B14,1").unwrap();
+        let action = GreedyPlayer.choose(&board, &clock).unwrap();
+        assert_eq!(action.to_string(), "14-23");
+
+        let play = |black: u64, white: u64| {
+            play_game(
+                Bitboard::default(),
+                &mut RandomPlayer::with_seed(black),
+                &mut RandomPlayer::with_seed(white),
+                &mut Clock::unlimited(),
+                Some(200),
+            )
+        };
+        let game = play(1, 2);
+        assert_eq!(game.actions(), play(1, 2).actions());
+        assert_eq!(
+            game.actions().len() == 200,
+            game.ending() == Ending::MoveLimit
+        );
+
+        // the engine should never lose to a player moving at random
+        let engine = Engine::builder().threads(1).build().unwrap();
+        let game = play_game(
+            Bitboard::default(),
+            &mut RandomPlayer::with_seed(3),
+            &mut EnginePlayer::new(engine, SearchConstraint::Depth(4)),
+            &mut Clock::unlimited(),
+            Some(200),
+        );
+        assert_ne!(game.winner(), &Winner::Player(Color::Black));
+
+        // every move can be made again from its record
+        assert_eq!(game.records().len(), game.actions().len());
+        let mut board = Bitboard::default();
+        for (action, record) in game.actions().iter().zip(game.records()) {
+            let record = record.unwrap();
+            match record.seed() {
+                Some(seed) => assert_eq!(
+                    RandomPlayer::with_seed(seed).choose(&board, &clock),
+                    Some(*action)
+                ),
+                None => assert_eq!(record.to_string(), "limit 4, depth 4"),
+            }
+            board = board.take_action(*action).unwrap();
+        }
+
+        // players who run out of time lose
+        let game = play_game(
+            Bitboard::default(),
+            &mut GreedyPlayer,
+            &mut GreedyPlayer,
+            &mut Clock::new(Duration::from_secs(0)),
+            None,
+        );
+        assert_eq!(game.ending(), Ending::Flagged);
+        assert_eq!(game.winner(), &Winner::Player(Color::White));
+        assert!(game.actions().is_empty());
+    }
+}
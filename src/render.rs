@@ -0,0 +1,214 @@
+use std::fmt::Write;
+
+use crate::board::{Bitboard, Square};
+
+// draws boards for places a terminal's ascii art does not fit, such as documentation,
+// web frontends and bug reports. both renderers draw black's pieces at the top like
+// `pretty` does unless the board is flipped
+
+const LIGHT_SQUARE: &str = "#f0d9b5";
+const DARK_SQUARE: &str = "#b58863";
+const BLACK_PIECE: &str = "#222222";
+const WHITE_PIECE: &str = "#f8f8f8";
+const CROWN: &str = "#d4af37";
+
+/// Represents how a board is drawn as SVG
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SvgOptions {
+    /// The width and height of a single square in pixels
+    pub square_size: u32,
+    /// Writes the PDN number in the corner of every dark square
+    pub numbers: bool,
+    /// Draws the board from black's side, with black's pieces at the bottom
+    pub flipped: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            square_size: 60,
+            numbers: false,
+            flipped: false,
+        }
+    }
+}
+
+/// Returns where a square is drawn as `(row, col)` from the top left corner
+fn position(square: Square, flipped: bool) -> (u8, u8) {
+    match flipped {
+        true => (7 - square.row(), 7 - square.col()),
+        false => (square.row(), square.col()),
+    }
+}
+
+impl Bitboard {
+    /// Returns the board as a standalone SVG image
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    /// use muskox::render::SvgOptions;
+    ///
+    /// let svg = Bitboard::default().to_svg(&SvgOptions::default());
+    /// assert!(svg.starts_with("<svg"));
+    /// assert_eq!(svg.matches("<circle").count(), 24);
+    /// ```
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let size = options.square_size;
+        let mut out = String::with_capacity(8192);
+
+        // writing to a string cannot fail
+        let _ = writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">",
+            8 * size
+        );
+        let _ = writeln!(
+            out,
+            "<rect width=\"{0}\" height=\"{0}\" fill=\"{1}\"/>",
+            8 * size,
+            LIGHT_SQUARE
+        );
+
+        for square in Square::all() {
+            let (row, col) = position(square, options.flipped);
+            let (x, y) = (u32::from(col) * size, u32::from(row) * size);
+
+            let _ = writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\"/>",
+                x, y, size, DARK_SQUARE
+            );
+
+            if options.numbers {
+                let _ = writeln!(
+                    out,
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"sans-serif\" fill=\"{}\">{}</text>",
+                    x + size / 20,
+                    y + size / 4,
+                    size / 5,
+                    LIGHT_SQUARE,
+                    square.pdn()
+                );
+            }
+
+            let mask = square.mask();
+            let fill = match (self.blacks() & mask != 0, self.whites() & mask != 0) {
+                (true, _) => BLACK_PIECE,
+                (_, true) => WHITE_PIECE,
+                _ => continue,
+            };
+
+            let (cx, cy) = (x + size / 2, y + size / 2);
+            let _ = writeln!(
+                out,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"#000000\" stroke-width=\"{}\"/>",
+                cx,
+                cy,
+                size * 2 / 5,
+                fill,
+                (size / 30).max(1)
+            );
+
+            // kings get a ring drawn inside the piece
+            if self.kings() & mask != 0 {
+                let _ = writeln!(
+                    out,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+                    cx,
+                    cy,
+                    size / 5,
+                    CROWN,
+                    (size / 15).max(1)
+                );
+            }
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Returns a compact drawing of the board with a character per square, using the
+    /// unicode draughts symbols for the pieces and `·` for empty dark squares
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    ///
+    /// let board = Bitboard::from_fen("B:WK10:B1").unwrap();
+    /// let unicode = board.to_unicode(false);
+    /// assert_eq!(unicode.lines().next(), Some("  ⛂   ·   ·   · "));
+    /// ```
+    pub fn to_unicode(&self, flipped: bool) -> String {
+        let mut rows = [[' '; 8]; 8];
+
+        for square in Square::all() {
+            let mask = square.mask();
+            let king = self.kings() & mask != 0;
+
+            let c = match (self.blacks() & mask != 0, self.whites() & mask != 0, king) {
+                (true, _, false) => '⛂',
+                (true, _, true) => '⛃',
+                (_, true, false) => '⛀',
+                (_, true, true) => '⛁',
+                _ => '·',
+            };
+
+            let (row, col) = position(square, flipped);
+            rows[row as usize][col as usize] = c;
+        }
+
+        rows.iter()
+            .map(|row| row.iter().map(|c| format!("{} ", c)).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_test() {
+        let board = Bitboard::from_fen("W:WK1:B32").unwrap();
+        let options = SvgOptions {
+            square_size: 10,
+            numbers: true,
+            flipped: false,
+        };
+
+        let svg = board.to_svg(&options);
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<text").count(), 32);
+        // the white king on square 1 sits on the top row with a crown
+        assert!(svg.contains("<circle cx=\"15\" cy=\"5\" r=\"4\" fill=\"#f8f8f8\""));
+        assert_eq!(svg.matches(CROWN).count(), 1);
+
+        let options = SvgOptions {
+            flipped: true,
+            ..options
+        };
+        assert!(board
+            .to_svg(&options)
+            .contains("<circle cx=\"65\" cy=\"75\" r=\"4\" fill=\"#f8f8f8\""));
+    }
+
+    #[test]
+    fn unicode_test() {
+        let board = Bitboard::default();
+        let unicode = board.to_unicode(false);
+        let lines: Vec<_> = unicode.lines().collect();
+
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "  ⛂   ⛂   ⛂   ⛂ ");
+        assert_eq!(lines[3], "·   ·   ·   ·   ");
+        assert_eq!(lines[7], "⛀   ⛀   ⛀   ⛀   ");
+
+        // seen from black's side white's men are on top
+        let flipped = board.to_unicode(true);
+        assert_eq!(flipped.lines().next(), Some("  ⛀   ⛀   ⛀   ⛀ "));
+    }
+}
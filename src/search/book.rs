@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use super::Searchable;
+
+/// Represents anything the engine can look actions up in before searching: a `Book` held
+/// in memory, or a book file mapped from disk such as
+/// [`BookFile`](../openings/bookfile/struct.BookFile.html)
+pub trait OpeningBook<S: Searchable>: Send + Sync {
+    /// Returns the actions of `state` with their weights, the heaviest first
+    fn actions(&self, state: &S) -> Vec<(S::Action, u32)>;
+}
+
+/// Represents an opening book: the actions known to be good in positions from the
+/// start of the game, along with how often each was played
+pub struct Book<S: Searchable> {
+    entries: HashMap<u64, Vec<(S::Action, u32)>>, // keyed by the zobrist hash
+}
+
+impl<S: Searchable> Default for Book<S> {
+    fn default() -> Self {
+        Book::new()
+    }
+}
+
+impl<S: Searchable> Book<S> {
+    pub fn new() -> Self {
+        Book {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Records that `action` was played in `state`
+    pub fn add(&mut self, state: &S, action: S::Action) {
+        let actions = self.entries.entry(state.zobrist_hash()).or_default();
+
+        match actions.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, count)) => *count += 1,
+            None => actions.push((action, 1)),
+        }
+    }
+
+    /// Returns the actions of `state` with how often they were played, the most played
+    /// first. Actions played equally often keep the order they were added in.
+    pub fn actions(&self, state: &S) -> Vec<(S::Action, u32)> {
+        let mut actions = self
+            .entries
+            .get(&state.zobrist_hash())
+            .cloned()
+            .unwrap_or_default();
+        actions.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        actions
+    }
+
+    /// Returns every position of the book by its zobrist hash, with its actions and how
+    /// often each was played, in no particular order
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (u64, &[(S::Action, u32)])> {
+        self.entries
+            .iter()
+            .map(|(key, actions)| (*key, actions.as_slice()))
+    }
+
+    /// Returns the number of positions in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<S: Searchable> OpeningBook<S> for Book<S> {
+    fn actions(&self, state: &S) -> Vec<(S::Action, u32)> {
+        Book::actions(self, state)
+    }
+}
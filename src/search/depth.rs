@@ -0,0 +1,92 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// depths count plies. the search works with them in full, while the transposition table
+// keeps them in a byte with one value set aside for empty entries. a depth that does not
+// fit is stored as the deepest one that does, which only ever understates how deep an
+// entry was searched, and a probe deeper than that never hits
+
+/// The deepest depth the transposition table can tell apart
+pub(crate) const MAX_STORED_DEPTH: u8 = u8::MAX - 1;
+
+/// Represents how many plies a search looks ahead
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Depth(u32);
+
+impl Depth {
+    pub const ZERO: Depth = Depth(0);
+
+    #[inline]
+    pub const fn new(plies: u32) -> Self {
+        Depth(plies)
+    }
+
+    #[inline]
+    pub const fn plies(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the depth `plies` shallower, or zero
+    #[inline]
+    pub fn reduced(self, plies: u32) -> Self {
+        Depth(self.0.saturating_sub(plies))
+    }
+
+    /// Returns the depth `plies` deeper
+    #[inline]
+    pub fn extended(self, plies: u32) -> Self {
+        Depth(self.0.saturating_add(plies))
+    }
+
+    /// Returns the depth as the transposition table keeps it
+    #[inline]
+    pub(crate) fn stored(self) -> u8 {
+        self.0.min(u32::from(MAX_STORED_DEPTH)) as u8
+    }
+
+    /// Returns whether an entry stored at `stored` was searched at least this deep
+    #[inline]
+    pub(crate) fn covered_by(self, stored: u8) -> bool {
+        self.0 <= u32::from(stored)
+    }
+}
+
+impl From<u32> for Depth {
+    fn from(plies: u32) -> Self {
+        Depth(plies)
+    }
+}
+
+impl From<Depth> for u32 {
+    fn from(depth: Depth) -> Self {
+        depth.0
+    }
+}
+
+impl fmt::Display for Depth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_test() {
+        assert_eq!(Depth::new(3).reduced(5), Depth::ZERO);
+        assert_eq!(Depth::new(3).reduced(1).extended(2), Depth::new(4));
+        assert_eq!(Depth::new(u32::MAX).extended(1).plies(), u32::MAX);
+
+        // depths too deep for a byte are stored as the deepest one that fits
+        assert_eq!(Depth::new(20).stored(), 20);
+        assert_eq!(Depth::new(300).stored(), MAX_STORED_DEPTH);
+        assert!(Depth::new(200).covered_by(Depth::new(300).stored()));
+        assert!(!Depth::new(300).covered_by(Depth::new(300).stored()));
+        assert!(!Depth::new(21).covered_by(Depth::new(20).stored()));
+    }
+}
@@ -1,21 +1,311 @@
 use std::cmp::{self, Reverse};
 use std::default::Default;
-use std::sync::{mpsc, Arc};
+use std::fmt;
+use std::iter;
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+use rayon::prelude::*;
 use rayon::{ThreadPool, ThreadPoolBuilder};
 
-use super::{tt::TranspositionTable, GameState, Optim, Score, Searchable, Side};
+use crate::error::BuildError;
+
+use super::{
+    tree::{NodeExit, TreeRecorder},
+    tt::{Bound, TranspositionTable},
+    ActionStatePair, Depth, EndgameTable, GameState, OpeningBook, Optim, Score, SearchObserver,
+    SearchTree, Searchable, Side, Wdl, Winner, HISTORY_SIZE,
+};
 
-const MAX_DEPTH: u32 = 25;
+// the deepest a search may be asked to go. the depth cap of an engine keeps it shallower
+pub const MAX_DEPTH: u32 = 1000;
 const MAX_TIME: u32 = 300000;
-const NUM_THREADS: usize = 8;
+pub const MAX_THREADS: usize = 64;
+pub const DEFAULT_HASH_SIZE: usize = 256;
+pub const MAX_HASH_SIZE: usize = 4096;
+// how deep searches without a constraint go
+const DEFAULT_DEPTH: u32 = 13;
+// the deepest search `debug_tree` records, as trees grow far too big to read beyond it,
+// and the size of the table it searches with
+pub const MAX_TREE_DEPTH: u32 = 8;
+const TREE_HASH_SIZE: usize = 16;
+// searches recurse a ply at a time, so the threads of the pool get room for the deepest
+const SEARCH_STACK_SIZE: usize = 64 * 1024 * 1024;
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+// how many nodes a search without a pool visits between looking at the clock
+const CLOCK_INTERVAL: u64 = 1024;
+// how much shallower the search after a null move is
+const NULL_MOVE_REDUCTION: u32 = 2;
+// the reduced search has to look at least one move ahead to verify anything
+const NULL_MOVE_MIN_DEPTH: Depth = Depth::new(NULL_MOVE_REDUCTION + 2);
+// quiet actions after this many in the ordering are searched a ply shallower first
+const LATE_MOVE_INDEX: usize = 3;
+const LATE_MOVE_MIN_DEPTH: Depth = Depth::new(3);
+// how many plies a line may be extended by for single replies
+const MAX_EXTENSIONS: u32 = 8;
+// how many pieces behind the engine has to be before it resigns, and for how many moves
+pub const DEFAULT_RESIGN_THRESHOLD: f32 = 3.;
+const RESIGN_MOVES: usize = 3;
+// the engine takes draws unless it is ahead by more than this many pieces
+pub const DEFAULT_DRAW_THRESHOLD: f32 = 0.25;
+// how far ahead of the rest an action has to be, and for how many iterations in a row,
+// before a timed search settles on it
+const EASY_MOVE_MARGIN: f32 = 2.;
+const EASY_MOVE_ITERATIONS: u32 = 4;
+// how much shallower the other actions are searched to make sure they are that far behind
+const EASY_MOVE_REDUCTION: u32 = 2;
+// how wide the windows are that only test which side of a bound a score is on. a search
+// between equal bounds cuts off after its first action, so the window needs some width,
+// and it is far too narrow for any score worth telling apart to fit in
+const NULL_WINDOW: f32 = 1e-3;
+
+/// Returns the null window that ends at `bound`. A search in it fails high if the score
+/// is at least `bound` and below that otherwise.
+fn null_window_below(bound: Score) -> (Score, Score) {
+    (bound.saturating_sub(Score::from(NULL_WINDOW)), bound)
+}
+
+/// Returns the null window that starts at `bound`. A search in it fails low if the score
+/// is at most `bound` and above that otherwise.
+fn null_window_above(bound: Score) -> (Score, Score) {
+    (bound, bound.saturating_add(Score::from(NULL_WINDOW)))
+}
+
+// events of the search go to the tracing crate with the `tracing` feature and compile
+// to nothing without it
+macro_rules! search_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)*);
+    };
+}
+
+// the compact-tt feature keeps a second hash of every state in the table instead of the
+// state itself
+#[cfg(not(feature = "compact-tt"))]
+type Table<S> = TranspositionTable<S>;
+#[cfg(feature = "compact-tt")]
+type Table<S> = TranspositionTable<S, super::tt::VerificationHash>;
+
+// the table deterministic searches empty and use, along with its size in megabytes. it
+// is out while a search runs
+type KeptTable<S> = Arc<Mutex<Option<(usize, Table<S>)>>>;
+
+/// Represents an entry of the transposition table, as saved by `Engine::table_entries`
+#[cfg(not(feature = "compact-tt"))]
+pub type TableEntry<S> = super::tt::SavedEntry<S, super::tt::FullState<S>>;
+/// Represents an entry of the transposition table, as saved by `Engine::table_entries`
+#[cfg(feature = "compact-tt")]
+pub type TableEntry<S> = super::tt::SavedEntry<S, super::tt::VerificationHash>;
+
+/// Represents the line of play from the root that a search is on
+struct Line {
+    hashes: Vec<u64>, // zobrist hashes of the positions, used to find repetitions
+    passed: bool,     // whether the last move of the line was a null move
+    extensions: u32,  // how many plies were added to the line for single replies
+    deadline: Option<Instant>, // when to abort, for searches that have no thread watching the clock
+}
+
+/// Represents the results of an iteration, along with whether its best action was far
+/// enough ahead of the others to be an easy move
+struct Iteration<S: Searchable> {
+    results: Vec<ActionScorePair<S>>,
+    easy: bool,
+}
+
+/// Represents when a timed search may stop before its time is up: once the best action
+/// has been ahead of every other one by `margin` for `iterations` iterations in a row
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Bitboard;
+/// use muskox::search::{EasyMove, Engine, Score, SearchConstraint};
+/// use std::time::Duration;
+///
+/// let engine: Engine<Bitboard> = Engine::builder()
+///     .threads(1)
+///     .easy_move(EasyMove::new(Score::from(1.), 3))
+///     .build()
+///     .unwrap();
+///
+/// // white has to jump, so there is nothing to think about
+/// let board = Bitboard::from_fen("W:W18,32:B14,K23").unwrap();
+/// let constraint = SearchConstraint::Time(Duration::from_secs(60));
+/// let result = engine.search(&board, &constraint);
+/// assert!(result.stopped_early());
+/// assert!(result.elapsed() < Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EasyMove {
+    margin: Score,
+    iterations: u32,
+}
+
+impl Default for EasyMove {
+    fn default() -> Self {
+        EasyMove::new(Score::from(EASY_MOVE_MARGIN), EASY_MOVE_ITERATIONS)
+    }
+}
+
+impl EasyMove {
+    pub fn new(margin: Score, iterations: u32) -> Self {
+        EasyMove { margin, iterations }
+    }
+
+    /// Returns how far ahead of every other action the best one has to be
+    #[inline]
+    pub fn margin(&self) -> Score {
+        self.margin
+    }
+
+    /// Returns for how many iterations in a row the best action has to stay that far
+    /// ahead
+    #[inline]
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+}
+
+/// Represents the function used to score positions. NNUE will join once it works.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Evaluator {
+    Classical,
+}
 
+impl fmt::Display for Evaluator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Evaluator::Classical => write!(f, "classical"),
+        }
+    }
+}
+
+/// Returns the number of threads searches use unless told otherwise: one for every core
+/// of the machine
+pub fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_THREADS)
+}
+
+/// Counts the work of a search across all of the threads taking part in it
+#[derive(Default)]
+struct Counters {
+    nodes: AtomicU64,
+    tt_probes: AtomicU64,
+    tt_hits: AtomicU64,
+    tb_hits: AtomicU64,
+    max_ply: AtomicU32,
+}
+
+/// Remembers how often quiet actions caused cutoffs during a search, for each side, so
+/// they can be tried early in other positions. Games key actions by their squares, which
+/// makes a butterfly table of every start and end for each side. Scores are halved
+/// between iterations so the cutoffs of the deepest ones weigh the most.
+struct HistoryTable {
+    scores: Vec<AtomicU32>, // the maximizer's keys, then the minimizer's
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        HistoryTable {
+            scores: (0..2 * HISTORY_SIZE).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+}
+
+impl HistoryTable {
+    fn index(optim: &Optim, key: usize) -> usize {
+        match optim {
+            Optim::Max => key,
+            Optim::Min => HISTORY_SIZE + key,
+        }
+    }
+
+    fn score(&self, optim: &Optim, key: Option<usize>) -> u32 {
+        key.map_or(0, |key| {
+            self.scores[Self::index(optim, key)].load(Ordering::Relaxed)
+        })
+    }
+
+    /// Rewards an action for a cutoff. Cutoffs far from the leaves prune more, so they
+    /// count for more.
+    fn reward(&self, optim: &Optim, key: Option<usize>, depth: u32) {
+        if let Some(key) = key {
+            self.scores[Self::index(optim, key)].fetch_add(depth * depth, Ordering::Relaxed);
+        }
+    }
+
+    /// Halves every score, so that what earlier iterations learned fades
+    fn age(&self) {
+        for score in &self.scores {
+            score.store(score.load(Ordering::Relaxed) / 2, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Searches games with minmax and alpha-beta pruning.
+///
+/// Searching only needs `&self`, so one engine can be shared across threads with an
+/// `Arc`, and searches running at the same time share what they learn through the
+/// transposition table. Clones are cheap and share the table as well.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use muskox::board::Bitboard;
+/// use muskox::search::{Engine, SearchConstraint};
+///
+/// let engine: Arc<Engine<Bitboard>> = Arc::new(Engine::new());
+///
+/// let shared = engine.clone();
+/// let handle = thread::spawn(move || {
+///     shared.search(&Bitboard::default(), &SearchConstraint::Depth(4))
+/// });
+///
+/// let result = engine.search(&Bitboard::default(), &SearchConstraint::Depth(4));
+/// let other = handle.join().unwrap();
+/// assert_eq!(result.best().unwrap().score(), other.best().unwrap().score());
+/// ```
 #[derive(Clone)]
 pub struct Engine<S: Searchable> {
-    tt: TranspositionTable<S>,
-    pool: Arc<ThreadPool>,
+    tt: Table<S>,
+    pool: Option<Arc<ThreadPool>>, // none where threads cannot be spawned
+    abort: Arc<AtomicBool>,
+    threads: usize,
+    hash_size: usize,
+    evaluator: Evaluator,
+    depth_cap: Option<u32>,
+    deterministic: bool,
+    deterministic_tt: KeptTable<S>,
+    contempt: Score,
+    resign_threshold: Option<Score>,
+    draw_threshold: Option<Score>,
+    draw_score: Score, // the score of a drawn root action from black's point of view
+    history: Arc<[u64]>,
+    book: Option<Arc<dyn OpeningBook<S>>>,
+    tablebase: Option<Arc<dyn EndgameTable<S>>>,
+    probe_root: bool, // whether the actions of the root are looked up in the tablebase
+    tree: Option<Arc<Mutex<TreeRecorder<S>>>>, // records every node, for `debug_tree`
+    easy_move: Option<EasyMove>,
+    observer: Option<Arc<dyn SearchObserver<S>>>,
+    counters: Arc<Counters>,
+    history_table: Arc<HistoryTable>, // of the search running on this clone
+    last_history_table: Arc<Mutex<Arc<HistoryTable>>>, // of the last search to finish
 }
 
 impl<S: Searchable> Default for Engine<S> {
@@ -25,39 +315,511 @@ impl<S: Searchable> Default for Engine<S> {
 }
 
 impl<S: Searchable> Engine<S> {
+    /// Creates an engine with the default settings: a thread for every core and a
+    /// transposition table of 256 megabytes. Use `builder` to pick other ones.
     pub fn new() -> Self {
-        let tt = TranspositionTable::new(256);
-        let pool = Arc::new(
-            ThreadPoolBuilder::new()
-                .num_threads(NUM_THREADS - 1)
-                .build()
-                .unwrap(),
-        );
+        Self::with_sizes(default_threads(), DEFAULT_HASH_SIZE)
+    }
+
+    fn with_sizes(threads: usize, hash_size: usize) -> Self {
+        let tt = TranspositionTable::new(hash_size);
+        let pool = Self::build_pool(threads);
+
+        let abort = Arc::new(AtomicBool::new(false));
+
+        Engine {
+            tt,
+            pool,
+            abort,
+            threads,
+            hash_size,
+            evaluator: Evaluator::Classical,
+            depth_cap: None,
+            deterministic: false,
+            deterministic_tt: Arc::new(Mutex::new(None)),
+            contempt: Score::from(0.),
+            resign_threshold: Some(Score::from(DEFAULT_RESIGN_THRESHOLD)),
+            draw_threshold: Some(Score::from(DEFAULT_DRAW_THRESHOLD)),
+            draw_score: Score::from(0.),
+            history: Arc::from(Vec::new()),
+            book: None,
+            tablebase: None,
+            probe_root: true,
+            tree: None,
+            easy_move: None,
+            observer: None,
+            counters: Arc::new(Counters::default()),
+            history_table: Arc::new(HistoryTable::default()),
+            last_history_table: Arc::new(Mutex::new(Arc::new(HistoryTable::default()))),
+        }
+    }
+
+    /// Returns a builder to configure an engine before creating it
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    /// use muskox::search::{Engine, Score};
+    ///
+    /// // a positive contempt makes the engine play on rather than settle for a draw
+    /// let engine: Engine<Bitboard> = Engine::builder()
+    ///     .threads(2)
+    ///     .tt_size_mb(16)
+    ///     .max_depth(10)
+    ///     .contempt(Score::from(0.25))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(engine.threads(), 2);
+    ///
+    /// // deterministic searches only ever use one thread
+    /// let engine: Result<Engine<Bitboard>, _> =
+    ///     Engine::builder().threads(2).deterministic(true).build();
+    /// assert!(engine.is_err());
+    ///
+    /// // the depth cap goes up to MAX_DEPTH
+    /// assert!(Engine::<Bitboard>::builder().max_depth(300).build().is_ok());
+    /// assert!(Engine::<Bitboard>::builder().max_depth(1001).build().is_err());
+    /// ```
+    pub fn builder() -> EngineBuilder<S> {
+        EngineBuilder {
+            threads: None,
+            tt_size_mb: DEFAULT_HASH_SIZE,
+            evaluator: Evaluator::Classical,
+            max_depth: None,
+            deterministic: false,
+            contempt: Score::from(0.),
+            book: None,
+            tablebase: None,
+            probe_root: true,
+            tt_self_check: false,
+            easy_move: None,
+        }
+    }
+
+    /// Returns the number of threads the engine searches with
+    #[inline]
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Returns the size of the transposition table in megabytes
+    #[inline]
+    pub fn hash_size(&self) -> usize {
+        self.hash_size
+    }
+
+    #[inline]
+    pub fn evaluator(&self) -> Evaluator {
+        self.evaluator
+    }
+
+    fn build_pool(threads: usize) -> Option<Arc<ThreadPool>> {
+        // there are no threads on the web, so searches run on the caller instead
+        if cfg!(target_arch = "wasm32") {
+            return None;
+        }
+
+        // the caller only waits on the results, so every thread searches
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .stack_size(SEARCH_STACK_SIZE)
+            .build()
+            .unwrap();
+        Some(Arc::new(pool))
+    }
+
+    /// Sets the number of threads the engine searches with. The actions at the root are
+    /// searched side by side, sharing the transposition table.
+    pub fn set_threads(&mut self, threads: usize) {
+        if threads != self.threads {
+            self.pool = Self::build_pool(threads);
+            self.threads = threads;
+        }
+    }
+
+    /// Sets the size of the transposition table in megabytes, clearing it if it changes
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        if size_mb != self.hash_size {
+            self.tt.resize(size_mb);
+            self.hash_size = size_mb;
+            *self.deterministic_tt.lock().unwrap() = None;
+        }
+    }
+
+    /// Makes the transposition table check every hit against the state it was saved
+    /// for, a debugging aid for tables that keep less than whole states. Hits of other
+    /// states are counted in `SearchResult::tt_collisions`, logged with the `tracing`
+    /// feature and never used.
+    pub fn set_tt_self_check(&mut self, check: bool) {
+        self.tt.set_self_check(check);
+    }
+
+    /// Lets timed searches stop early when their best action is far ahead of the rest,
+    /// or makes them always use their time with `None`
+    pub fn set_easy_move(&mut self, easy_move: Option<EasyMove>) {
+        self.easy_move = easy_move;
+    }
+
+    /// Reports every search the engine finishes to `observer` from now on, or to nothing
+    /// with `None`
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn SearchObserver<S>>>) {
+        self.observer = observer;
+    }
+
+    /// Returns whether the transposition table checks every hit
+    #[inline]
+    pub fn tt_self_check(&self) -> bool {
+        self.tt.self_check()
+    }
+
+    /// Sets the deepest iteration a search may run. `None` lets timed searches deepen
+    /// until they run out of time.
+    pub fn set_depth_cap(&mut self, depth_cap: Option<u32>) {
+        self.depth_cap = depth_cap;
+    }
+
+    /// Makes every search reproducible: the same position and constraint always give
+    /// the same actions, scores and node counts. Each search starts from an empty
+    /// transposition table of its own, which goes into the engine's once the search is
+    /// done, and timed searches ignore the clock, deepening to the depth
+    /// cap (or the default depth without one) instead. Only setting `stop` can still
+    /// cut a search short.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// Sets how much worse than even a draw is for the side to move. A positive
+    /// contempt makes the engine avoid draws. It is only given to the root actions that
+    /// score a draw, so the transposition table never holds it and searches with another
+    /// contempt or from the other side can share the table.
+    pub fn set_contempt(&mut self, contempt: Score) {
+        self.contempt = contempt;
+    }
+
+    /// Sets how many pieces behind the engine has to be for a few moves in a row before
+    /// it resigns. `None` makes it play every game out.
+    pub fn set_resign_threshold(&mut self, threshold: Option<Score>) {
+        self.resign_threshold = threshold;
+    }
+
+    /// Sets how many pieces ahead the engine may be and still accept a draw. `None` makes
+    /// it refuse every draw offer.
+    pub fn set_draw_threshold(&mut self, threshold: Option<Score>) {
+        self.draw_threshold = threshold;
+    }
+
+    /// Returns whether the side playing `side` should resign, given the scores of the
+    /// searches it made for its moves so far. It resigns once the last few of them were
+    /// all at least the resign threshold behind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Color};
+    /// use muskox::search::{Engine, Score};
+    ///
+    /// let engine: Engine<Bitboard> = Engine::new();
+    /// let scores: Vec<_> = [-1., -4., -5., -6.].iter().map(|&s| Score::from(s)).collect();
+    /// assert!(engine.should_resign(&scores, &Color::Black));
+    /// assert!(!engine.should_resign(&scores[..3], &Color::Black));
+    /// assert!(!engine.should_resign(&scores, &Color::White));
+    /// ```
+    pub fn should_resign(&self, scores: &[Score], side: &S::Side) -> bool {
+        let threshold = match self.resign_threshold {
+            Some(threshold) => f32::from(threshold),
+            None => return false,
+        };
+
+        scores.len() >= RESIGN_MOVES
+            && scores[scores.len() - RESIGN_MOVES..]
+                .iter()
+                .all(|&score| Self::advantage(score, side) <= -threshold)
+    }
+
+    /// Returns whether the side playing `side` should accept a draw offer, given the
+    /// scores of the searches it made for its moves so far. It accepts unless its last
+    /// score is more than the draw threshold ahead.
+    pub fn should_accept_draw(&self, scores: &[Score], side: &S::Side) -> bool {
+        match (self.draw_threshold, scores.last()) {
+            (Some(threshold), Some(&score)) => Self::advantage(score, side) <= f32::from(threshold),
+            _ => false,
+        }
+    }
+
+    /// Returns how many pieces ahead `side` is by a score, which is from the maximizer's
+    /// point of view
+    fn advantage(score: Score, side: &S::Side) -> f32 {
+        match side.optim() {
+            Optim::Max => f32::from(score),
+            Optim::Min => -f32::from(score),
+        }
+    }
+
+    /// Sets the zobrist hashes of the positions the game went through before the one
+    /// that is searched next. Searches score lines that return to any of them as draws.
+    pub fn set_history(&mut self, history: &[u64]) {
+        self.history = Arc::from(history);
+    }
+
+    pub fn search(&self, state: &S, constraint: &SearchConstraint) -> SearchResult<S> {
+        self.search_with(state, constraint, &AtomicBool::new(false), |_| ())
+    }
+
+    /// Searches for the best actions like `search`, but can be cancelled from another
+    /// thread and reports on its progress.
+    ///
+    /// The search stops early once `stop` is set, returning the results of the deepest
+    /// finished iteration. `info` is called every time an iteration finishes. Searches
+    /// with a depth (or no) constraint only run a single iteration.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The state to search from
+    /// * `constraint` - How long or how deep to search
+    /// * `stop` - A flag that cancels the search when set
+    /// * `info` - A callback receiving information about every finished iteration
+    pub fn search_with<F>(
+        &self,
+        state: &S,
+        constraint: &SearchConstraint,
+        stop: &AtomicBool,
+        info: F,
+    ) -> SearchResult<S>
+    where
+        F: FnMut(&SearchInfo<S>),
+    {
+        if let GameState::Completed(winner) = state.get_game_state() {
+            return SearchResult::game_over(winner);
+        }
+        if let Some(result) = self.book_helper(state) {
+            return result;
+        }
+
+        self.search_helper(state, constraint, stop, info, None)
+    }
+
+    /// Searches for the best actions like `search_with`, but only among the root actions
+    /// `root` allows. The opening book is not used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::atomic::AtomicBool;
+    /// use muskox::board::{Action, Bitboard};
+    /// use muskox::search::{Engine, RootActions, SearchConstraint};
+    ///
+    /// let engine: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+    /// let board = Bitboard::default();
+    /// let excluded = Action::from_movetext("11-15").unwrap();
+    /// let result = engine.search_among(
+    ///     &board,
+    ///     &SearchConstraint::Depth(4),
+    ///     &RootActions::Excluding(vec![excluded]),
+    ///     &AtomicBool::new(false),
+    ///     |_| (),
+    /// );
+    /// assert_eq!(result.results().len(), 6);
+    /// assert!(result.results().iter().all(|p| p.action() != excluded));
+    /// ```
+    pub fn search_among<F>(
+        &self,
+        state: &S,
+        constraint: &SearchConstraint,
+        root: &RootActions<S::Action>,
+        stop: &AtomicBool,
+        info: F,
+    ) -> SearchResult<S>
+    where
+        F: FnMut(&SearchInfo<S>),
+    {
+        let actions: Vec<_> = state
+            .generate_all_actions()
+            .iter()
+            .map(|p| *p.action())
+            .filter(|action| root.allows(action))
+            .collect();
+
+        self.search_helper(state, constraint, stop, info, Some(&actions))
+    }
+
+    /// Searches a single action of `state` as deep as `search` would search it, and
+    /// returns its score. It is quicker than searching every action when only one of them
+    /// matters, like the move a player is thinking of making. The opening book is not
+    /// used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Action, Bitboard};
+    /// use muskox::search::{Engine, SearchConstraint};
+    ///
+    /// let engine: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+    /// let board = Bitboard::from_fen("B:W18,32:B14,1").unwrap();
+    /// let constraint = SearchConstraint::Depth(4);
+    ///
+    /// let action = Action::from_movetext("14-23").unwrap();
+    /// let score = engine.evaluate_action(&board, action, &constraint).unwrap();
+    /// assert_eq!(score, engine.search(&board, &constraint).best().unwrap().score());
+    ///
+    /// let action = Action::from_movetext("1-6").unwrap();
+    /// assert!(engine.evaluate_action(&board, action, &constraint).is_err());
+    /// ```
+    pub fn evaluate_action(
+        &self,
+        state: &S,
+        action: S::Action,
+        constraint: &SearchConstraint,
+    ) -> Result<Score, S::ActionError> {
+        let child = state.take_action(action)?;
 
-        Engine { tt, pool }
+        let result = self.search_helper(
+            state,
+            constraint,
+            &AtomicBool::new(false),
+            |_| (),
+            Some(&[action]),
+        );
+        // the action is legal so it was searched, unless no iteration got to finish
+        Ok(match result.best() {
+            Some(best) => best.score(),
+            None => child.evaluate(),
+        })
     }
 
-    pub fn search(&mut self, state: &S, constraint: &SearchConstraint) -> Vec<ActionScorePair<S>> {
-        self.tt.new_search(); // increment the generation
+    /// Searches `state` like `search_with`, only looking at the root actions in `actions`
+    /// if they are given
+    fn search_helper<F>(
+        &self,
+        state: &S,
+        constraint: &SearchConstraint,
+        stop: &AtomicBool,
+        info: F,
+        actions: Option<&[S::Action]>,
+    ) -> SearchResult<S>
+    where
+        F: FnMut(&SearchInfo<S>),
+    {
+        if let GameState::Completed(winner) = state.get_game_state() {
+            return SearchResult::game_over(winner);
+        }
+
+        // every search gets its own abort flag so stale work from an earlier search can
+        // never be cut short (or kept alive) by a later one
+        let mut me = self.clone();
+        me.abort = Arc::new(AtomicBool::new(false));
+        let abort = me.abort.clone();
+        me.counters = Arc::new(Counters::default());
+        let counters = me.counters.clone();
+        // searches running at the same time on clones of the engine must not wipe each
+        // other's history, so every search starts a table of its own
+        me.history_table = Arc::new(HistoryTable::default());
+        let history_table = me.history_table.clone();
+
+        match self.deterministic {
+            // what earlier searches left behind would change the results, and the shared
+            // table may be in use, so the search empties a table of its own. the engine
+            // keeps one for the next search, and a search running beside it on a clone
+            // makes another
+            true => {
+                let kept = self.deterministic_tt.lock().unwrap().take();
+                me.tt = match kept {
+                    Some((size, tt)) if size == self.hash_size => {
+                        tt.clear();
+                        tt
+                    }
+                    _ => Table::new(self.hash_size),
+                };
+                me.tt.set_self_check(self.tt.self_check());
+            }
+            false => self.tt.new_search(), // increment the generation
+        }
+        let table = me.tt.clone();
+
+        // contempt is from the point of view of the side to move at the root. the table is
+        // shared with searches from the other side, so the tree scores draws as even and
+        // only the root actions get the contempt
+        me.draw_score = match state.turn().optim() {
+            Optim::Max => Score::DRAW - self.contempt,
+            Optim::Min => self.contempt,
+        };
 
-        let me = self.clone();
         let state = *state;
 
         // set the initial zobrist hash
         let zobrist_hash = state.zobrist_hash(); // this is relatively expensive function to call
 
+        let start = Instant::now();
+
+        let cap = self.depth_cap.unwrap_or(u32::MAX);
+        let (depths, deadline): (Box<dyn Iterator<Item = u32> + Send>, _) = match constraint {
+            // have iterative deepening for None as well..
+            SearchConstraint::None => (Box::new(iter::once(cmp::min(DEFAULT_DEPTH, cap))), None),
+            SearchConstraint::Depth(dep) => (Box::new(iter::once(cmp::min(*dep, cap))), None),
+            // how far a search gets in some time depends on the machine
+            SearchConstraint::Time(_) if self.deterministic => {
+                let cap = self.depth_cap.unwrap_or(DEFAULT_DEPTH);
+                (Box::new(1..=cap), None)
+            }
+            // a duration too long to represent means we search until stopped
+            SearchConstraint::Time(dur) => (Box::new(1..=cap), start.checked_add(*dur)),
+        };
+
+        // without a pool the search runs on the caller, so it has to watch the clock itself.
+        // the first iteration always finishes so there is something to return
+        let clock = match self.pool {
+            Some(_) => None,
+            None => deadline,
+        };
+
+        // easy moves only make sense when there is time to save
+        let easy_move = self.easy_move.filter(|_| deadline.is_some());
+
+        // the iterations may run on the pool, so they need their own copy
+        let actions = actions.map(|actions| actions.to_vec());
         let compute_at_depth = move |depth| {
-            let action_states = state.generate_all_actions();
-            let evals: Vec<_> = action_states
-                .iter()
-                .map(|p| {
-                    me.minmax_helper(
-                        p.state(),
-                        depth,
-                        Score::NEG_INFINITY,
-                        Score::INFINITY,
-                        zobrist_hash ^ p.zobrist_diff(),
-                    )
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("iteration", depth).entered();
+
+            me.history_table.age();
+            let mut action_states = state.generate_all_actions();
+            if let Some(actions) = &actions {
+                action_states.retain(|p| actions.contains(p.action()));
+            }
+            let new_line = || Line {
+                hashes: vec![zobrist_hash],
+                passed: false,
+                extensions: 0,
+                deadline: clock.filter(|_| depth > 1),
+            };
+            let evals: Vec<_> = match depth {
+                // nothing is searched below the children, so they are evaluated together
+                0 => me.leaf_helper(&action_states, zobrist_hash, &new_line()),
+                _ => {
+                    let search = |p: &ActionStatePair<S>| {
+                        me.minmax_helper(
+                            p.state(),
+                            Depth::new(depth),
+                            Score::NEG_INFINITY,
+                            Score::INFINITY,
+                            zobrist_hash ^ *p.zobrist_diff(),
+                            &mut new_line(),
+                        )
+                    };
+                    // every action at the root is searched with the whole window, so they
+                    // can be searched at the same time on the threads of the pool
+                    match me.parallel() {
+                        true => action_states.par_iter().map(search).collect(),
+                        false => action_states.iter().map(search).collect(),
+                    }
+                }
+            };
+            // draws are even in the tree, and worth the contempt at the root
+            let evals: Vec<_> = evals
+                .into_iter()
+                .map(|eval| match eval == Score::DRAW {
+                    true => me.draw_score,
+                    false => eval,
                 })
                 .collect();
             let mut results: Vec<_> = action_states
@@ -70,27 +832,235 @@ impl<S: Searchable> Engine<S> {
                 Optim::Min => a.1.cmp(b.1),
                 Optim::Max => b.1.cmp(a.1),
             });
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                score = ?results.first().map(|p| f32::from(*p.1)),
+                nodes = me.counters.nodes.load(Ordering::Relaxed),
+                aborted = me.abort.load(Ordering::Relaxed),
+                "iteration finished"
+            );
             // can get rid of this part..
-            results
+            let results: Vec<_> = results
                 .into_iter()
                 .map(|(&a, &s)| ActionScorePair {
                     action: a,
                     score: s,
                 }) // copy all of the values and get rid of ordered float wrapper
                 // .take(5) // only take the top fives moves.
-                .collect()
+                .collect();
+
+            let easy = easy_move.is_some_and(|easy_move| {
+                me.easy_move_helper(
+                    &state,
+                    &action_states,
+                    &results,
+                    easy_move.margin,
+                    Depth::new(depth),
+                    zobrist_hash,
+                    new_line,
+                )
+            });
+            Iteration { results, easy }
         };
 
-        match constraint {
-            // have iterative deepening for None as well..
-            SearchConstraint::None => compute_at_depth(13),
-            SearchConstraint::Depth(dep) => compute_at_depth(*dep),
-            SearchConstraint::Time(dur) => self.iddfs_helper(compute_at_depth, *dur, None),
+        // the time manager: an action that stays far ahead of the others for long enough
+        // is played without using the rest of the time
+        let mut info = info;
+        let mut streak = 0;
+        let mut last_best = None;
+        let mut stopped_early = false;
+        let on_depth = |depth, iteration: &Iteration<S>| {
+            let best = iteration.results.first().map(|p| p.action);
+            streak = match iteration.easy {
+                true if best == last_best => streak + 1,
+                true => 1,
+                false => 0,
+            };
+            last_best = best;
+            stopped_early = matches!(easy_move, Some(e) if streak >= e.iterations);
+
+            info(&SearchInfo {
+                depth,
+                elapsed: start.elapsed(),
+                tb_hits: counters.tb_hits.load(Ordering::Relaxed),
+                easy_move: stopped_early,
+                results: &iteration.results,
+            });
+            stopped_early
+        };
+
+        // only a search that failed before finishing an iteration has nothing to show
+        let (depth, results) = self
+            .iddfs_helper(compute_at_depth, depths, deadline, stop, abort, on_depth)
+            .map_or((0, Vec::new()), |(depth, iteration)| {
+                (depth, iteration.results)
+            });
+
+        let tt_probes = counters.tt_probes.load(Ordering::Relaxed);
+        let result = SearchResult {
+            results,
+            depth,
+            max_depth: counters.max_ply.load(Ordering::Relaxed),
+            nodes: counters.nodes.load(Ordering::Relaxed),
+            tt_hit_rate: match tt_probes {
+                0 => 0.,
+                _ => counters.tt_hits.load(Ordering::Relaxed) as f64 / tt_probes as f64,
+            },
+            tb_hits: counters.tb_hits.load(Ordering::Relaxed),
+            tt_collisions: table.collisions(),
+            stopped_early,
+            elapsed: start.elapsed(),
+            state: GameState::InProgress,
+        };
+        // what a deterministic search found goes into the shared table for later lookups
+        if self.deterministic {
+            self.tt.restore(&table.entries());
+            *self.deterministic_tt.lock().unwrap() = Some((self.hash_size, table));
+        }
+        *self.last_history_table.lock().unwrap() = history_table;
+
+        if let Some(observer) = &self.observer {
+            observer.search_finished(&state, &result);
+        }
+        result
+    }
+
+    /// Returns whether the actions at the root are searched on several threads. The
+    /// order threads store into the table in changes what they find, and the tree of a
+    /// search is only recorded one node at a time.
+    fn parallel(&self) -> bool {
+        self.threads > 1 && self.pool.is_some() && !self.deterministic && self.tree.is_none()
+    }
+
+    /// Returns the score the transposition table holds for a state searched at least
+    /// `depth` deep, if there is one
+    pub fn cached_score(&self, state: &S, depth: u32) -> Option<Score> {
+        self.tt
+            .probe_exact(state.zobrist_hash(), state, Depth::new(depth))
+    }
+
+    /// Follows the best scored children in the transposition table to give the line of
+    /// play the last searches expect from `state`. The line ends early once a position
+    /// has no scored children, which happens when a null move pruned it.
+    pub fn principal_variation(&self, state: &S, length: usize) -> Vec<S::Action> {
+        let mut state = *state;
+        let mut line = Vec::new();
+
+        while line.len() < length {
+            let hash = state.zobrist_hash();
+            let scored = state.generate_all_actions().into_iter().filter_map(|p| {
+                let score = self
+                    .tt
+                    .probe_exact(hash ^ p.zobrist_diff(), p.state(), Depth::ZERO)?;
+                Some((score, p))
+            });
+
+            let best = match state.turn().optim() {
+                Optim::Max => scored.max_by_key(|(score, _)| *score),
+                Optim::Min => scored.min_by_key(|(score, _)| *score),
+            };
+
+            match best {
+                Some((_, p)) => {
+                    line.push(*p.action());
+                    state = *p.state();
+                }
+                None => break,
+            }
         }
+
+        line
+    }
+
+    /// Searches `state` to `depth`, at most `MAX_TREE_DEPTH`, and records every node the
+    /// search visits with its window, its static evaluation, the action it chose and why
+    /// it stopped there. The search runs on a table of its own, so earlier searches do
+    /// not cut the tree short and the engine's table is left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    /// use muskox::search::{Engine, NodeExit};
+    ///
+    /// let engine: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+    /// let board = Bitboard::from_fen("B:W18,32:B14,1").unwrap();
+    /// let tree = engine.debug_tree(&board, 2);
+    ///
+    /// assert!(tree.root().best().is_some());
+    /// assert!(tree.nodes().iter().any(|node| node.exit() == NodeExit::Horizon));
+    /// assert!(tree.to_dot().starts_with("digraph"));
+    /// ```
+    pub fn debug_tree(&self, state: &S, depth: u32) -> SearchTree<S> {
+        let depth = depth.clamp(1, MAX_TREE_DEPTH);
+        let recorder = Arc::new(Mutex::new(TreeRecorder::new(state, Depth::new(depth))));
+
+        let mut me = self.clone();
+        me.tt = TranspositionTable::new(TREE_HASH_SIZE);
+        me.tree = Some(recorder.clone());
+        let result = me.search_helper(
+            state,
+            &SearchConstraint::Depth(depth),
+            &AtomicBool::new(false),
+            |_| (),
+            None,
+        );
+
+        let (score, exit, best) = match result.best() {
+            Some(best) => (best.score(), NodeExit::Searched, Some(best.action())),
+            None => (state.evaluate(), NodeExit::GameOver, None),
+        };
+        let mut recorder = recorder.lock().unwrap();
+        recorder.exit(0, score, exit, best);
+        recorder.take()
+    }
+
+    /// Returns how much the last search rewarded `action` of `state` for cutoffs, with
+    /// the rewards of earlier iterations halved every iteration since. Actions the game
+    /// gives no history key score zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    /// use muskox::search::{Engine, SearchConstraint, Searchable};
+    ///
+    /// let engine: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+    /// let board = Bitboard::default();
+    /// engine.search(&board, &SearchConstraint::Depth(6));
+    ///
+    /// // the replies to the opening moves are where most cutoffs happen
+    /// let scored = board.successors().iter().any(|pair| {
+    ///     let reply = pair.board();
+    ///     reply
+    ///         .successors()
+    ///         .iter()
+    ///         .any(|p| engine.history_score(&reply, &p.action()) > 0)
+    /// });
+    /// assert!(scored);
+    /// ```
+    pub fn history_score(&self, state: &S, action: &S::Action) -> u32 {
+        self.last_history_table
+            .lock()
+            .unwrap()
+            .score(&state.turn().optim(), state.history_key(action))
+    }
+
+    /// Returns every entry of the transposition table that is in use, so what the
+    /// searches found out can be kept for later
+    pub fn table_entries(&self) -> Vec<TableEntry<S>> {
+        self.tt.entries()
+    }
+
+    /// Puts entries returned by `table_entries` back into the transposition table.
+    /// Returns false, restoring nothing, if they came from a table of another size.
+    pub fn restore_table(&self, entries: &[TableEntry<S>]) -> bool {
+        self.tt.restore(entries)
     }
 
     pub fn reset(&mut self) {
-        self.tt.resize(256);
+        self.tt.resize(self.hash_size);
     }
 
     #[allow(dead_code, unused_variables)]
@@ -108,117 +1078,948 @@ impl<S: Searchable> Engine<S> {
         }
     }
 
-    fn minmax_helper(
-        &self,
-        state: &S,
-        depth: u32,
-        mut alpha: Score,
-        mut beta: Score,
-        zobrist_hash: u64,
-    ) -> Score {
-        if let Some(value) = self.tt.probe(zobrist_hash, state, depth as u8) {
-            return value;
+    /// Answers from the opening book if it knows the state. Book actions are listed by
+    /// how often they were played, and scored with the evaluation of where they lead.
+    fn book_helper(&self, state: &S) -> Option<SearchResult<S>> {
+        let start = Instant::now();
+        let actions = self.book.as_ref()?.actions(state);
+        if actions.is_empty() {
+            return None;
         }
 
-        if (depth == 0) | (state.get_game_state() != GameState::InProgress) {
-            return state.evaluate();
-        }
+        let (actions, states): (Vec<_>, Vec<_>) = actions
+            .iter()
+            .filter_map(|(action, _)| Some((*action, state.take_action(*action).ok()?)))
+            .unzip();
+        let results = actions
+            .into_iter()
+            .zip(S::evaluate_batch(&states))
+            .map(|(action, score)| ActionScorePair::new(action, score))
+            .collect();
 
-        let eval = match state.turn().optim() {
-            Optim::Max => {
-                let mut max_eval = Score::NEG_INFINITY;
+        Some(SearchResult::new(results, 0, 0, 0, 0., start.elapsed()))
+    }
 
-                // sort it in reverse so we get higest nodes first for the max optimizer
-                let mut nodes = state.generate_all_actions();
-                nodes.sort_by_key(|n| Reverse(n.state().evaluate()));
+    /// Scores the states of `pairs`, children of the state with `zobrist_hash`, the way
+    /// `minmax_helper` does at depth zero. The ones that need evaluating are evaluated in
+    /// a single batch, which evaluation functions running on many states at once are
+    /// much faster with.
+    fn leaf_helper(
+        &self,
+        pairs: &[ActionStatePair<S>],
+        zobrist_hash: u64,
+        line: &Line,
+    ) -> Vec<Score> {
+        let known: Vec<_> = pairs
+            .iter()
+            .map(|p| {
+                self.counters.nodes.fetch_add(1, Ordering::Relaxed);
+                self.counters
+                    .max_ply
+                    .fetch_max(line.hashes.len() as u32, Ordering::Relaxed);
 
-                for (state_p, zobrist_diff) in nodes.iter().map(|a| (a.state(), a.zobrist_diff())) {
-                    let zobrist_hash_p = zobrist_hash ^ zobrist_diff;
-                    let eval = self.minmax_helper(state_p, depth - 1, alpha, beta, zobrist_hash_p);
-                    max_eval = cmp::max(max_eval, eval);
-                    alpha = cmp::max(alpha, max_eval);
-                    if beta <= alpha {
-                        break;
-                    }
+                let zobrist_hash = zobrist_hash ^ p.zobrist_diff();
+                if line.hashes.contains(&zobrist_hash) || self.history.contains(&zobrist_hash) {
+                    return Some(Score::DRAW);
                 }
 
-                max_eval
-            }
+                self.counters.tt_probes.fetch_add(1, Ordering::Relaxed);
+                if let Some(value) = self.tt.probe_exact(zobrist_hash, p.state(), Depth::ZERO) {
+                    self.counters.tt_hits.fetch_add(1, Ordering::Relaxed);
+                    return Some(value);
+                }
 
-            Optim::Min => {
-                let mut min_eval = Score::INFINITY;
+                match p.state().get_game_state() {
+                    GameState::Completed(Winner::Draw) => Some(Score::DRAW),
+                    GameState::Completed(_) => Some(p.state().evaluate()),
+                    GameState::InProgress => self.tablebase_helper(p.state(), line),
+                }
+            })
+            .collect();
 
-                let mut nodes = state.generate_all_actions();
-                nodes.sort_by_key(|n| n.state().evaluate()); // we want lowest values first
+        let states: Vec<_> = pairs
+            .iter()
+            .zip(&known)
+            .filter(|(_, score)| score.is_none())
+            .map(|(p, _)| *p.state())
+            .collect();
+        let mut evals = S::evaluate_batch(&states).into_iter();
 
-                for (state_p, zobrist_diff) in nodes.iter().map(|a| (a.state(), a.zobrist_diff())) {
-                    let zobrist_hash_p = zobrist_hash ^ zobrist_diff;
-                    let eval = self.minmax_helper(state_p, depth - 1, alpha, beta, zobrist_hash_p);
-                    min_eval = cmp::min(min_eval, eval);
-                    beta = cmp::min(beta, min_eval);
-                    if beta <= alpha {
-                        break;
-                    }
-                }
+        known
+            .into_iter()
+            .map(|score| score.or_else(|| evals.next()).unwrap())
+            .collect()
+    }
 
-                min_eval
+    fn minmax_helper(
+        &self,
+        state: &S,
+        depth: Depth,
+        alpha: Score,
+        beta: Score,
+        zobrist_hash: u64,
+        line: &mut Line,
+    ) -> Score {
+        let tree = match &self.tree {
+            Some(tree) => tree,
+            None => {
+                return self
+                    .minmax_node(state, depth, alpha, beta, zobrist_hash, line)
+                    .0
             }
         };
 
-        self.tt.save(zobrist_hash, state, depth as u8, eval);
-
+        let index = tree.lock().unwrap().enter(state, depth, alpha, beta);
+        let (eval, exit, best) = self.minmax_node(state, depth, alpha, beta, zobrist_hash, line);
+        tree.lock().unwrap().exit(index, eval, exit, best);
         eval
     }
 
-    fn iddfs_helper<T, F>(&self, f: F, duration: Duration, depth_limit: Option<u32>) -> T
-    where
-        T: 'static + Send,
-        F: Fn(u32) -> T + 'static + Send + Sync,
-    {
-        // also this quitting method hangs some extra computation
-        // will need to find out how to cut that down
-        // might add a receiver into the type F
-
-        let (eval_tx, eval_rx) = mpsc::channel();
-        let (quit_tx, quit_rx) = mpsc::channel();
+    /// Searches a state like `minmax_helper`, also returning why it stopped there and
+    /// the action it chose if it searched any
+    fn minmax_node(
+        &self,
+        state: &S,
+        depth: Depth,
+        mut alpha: Score,
+        mut beta: Score,
+        zobrist_hash: u64,
+        line: &mut Line,
+    ) -> (Score, NodeExit, Option<S::Action>) {
+        // going back to a position of this line or of the game is a draw. this has to come
+        // before the table as stored scores do not know how they were reached
+        let nodes = self.counters.nodes.fetch_add(1, Ordering::Relaxed);
+        if nodes.is_multiple_of(CLOCK_INTERVAL)
+            && matches!(line.deadline, Some(d) if Instant::now() >= d)
+        {
+            self.abort.store(true, Ordering::Relaxed);
+        }
+        self.counters
+            .max_ply
+            .fetch_max(line.hashes.len() as u32, Ordering::Relaxed);
+
+        if line.hashes.contains(&zobrist_hash) || self.history.contains(&zobrist_hash) {
+            return (Score::DRAW, NodeExit::Repetition, None);
+        }
+
+        self.counters.tt_probes.fetch_add(1, Ordering::Relaxed);
+        // a stored bound only settles the node if it falls outside the window
+        let probe = self.tt.probe(zobrist_hash, state, depth, alpha, beta);
+        search_event!(
+            hash = zobrist_hash,
+            depth = depth.plies(),
+            hit = probe.is_some(),
+            "tt probe"
+        );
+        if let Some(value) = probe {
+            self.counters.tt_hits.fetch_add(1, Ordering::Relaxed);
+            return (value, NodeExit::TableHit, None);
+        }
+
+        // the search was cancelled. whatever we return here will be thrown away
+        if self.abort.load(Ordering::Relaxed) {
+            return (Score::from(0.), NodeExit::Aborted, None);
+        }
+
+        match state.get_game_state() {
+            GameState::Completed(Winner::Draw) => return (Score::DRAW, NodeExit::GameOver, None),
+            GameState::Completed(_) => return (state.evaluate(), NodeExit::GameOver, None),
+            GameState::InProgress => (),
+        }
+
+        if let Some(score) = self.tablebase_helper(state, line) {
+            return (score, NodeExit::Tablebase, None);
+        }
+        if depth == Depth::ZERO {
+            return (state.evaluate(), NodeExit::Horizon, None);
+        }
+
+        // passing twice in a row would just search the same position shallower
+        let after_pass = mem::replace(&mut line.passed, false);
+
+        if !after_pass && depth >= NULL_MOVE_MIN_DEPTH {
+            if let Some(eval) = self.null_move_helper(state, depth, alpha, beta, zobrist_hash, line)
+            {
+                return (eval, NodeExit::NullMove, None);
+            }
+        }
+
+        line.hashes.push(zobrist_hash);
+
+        // the window the node was searched with, to tell what its score is
+        let window = (alpha, beta);
+
+        // late move reductions: the ordering puts the likely best actions first, so quiet
+        // actions far down the list are given a shallower search to begin with
+        let reduce = |i: usize, node: &ActionStatePair<S>| {
+            depth >= LATE_MOVE_MIN_DEPTH && i >= LATE_MOVE_INDEX && state.is_quiet(node)
+        };
+
+        let mut nodes = state.generate_all_actions();
+        let optim = state.turn().optim();
+        self.order_actions(state, &mut nodes, self.tt.best_action(zobrist_hash, state));
+        let mut best = None;
+        let mut exit = NodeExit::Searched;
+
+        // a single reply (often a forced capture) is not a real choice so it does not use
+        // up a ply. long forced sequences are capped to keep the search from exploding
+        let extend = nodes.len() == 1 && line.extensions < MAX_EXTENSIONS;
+        line.extensions += extend as u32;
+        let depth_p = depth.reduced(1).extended(extend as u32);
+
+        let eval = match optim {
+            Optim::Max => {
+                let mut max_eval = Score::NEG_INFINITY;
+
+                for (i, node) in nodes.iter().enumerate() {
+                    let (state_p, zobrist_hash_p) =
+                        (node.state(), zobrist_hash ^ node.zobrist_diff());
+                    let late = reduce(i, node);
+
+                    let mut eval = self.minmax_helper(
+                        state_p,
+                        depth_p.reduced(late as u32),
+                        alpha,
+                        beta,
+                        zobrist_hash_p,
+                        line,
+                    );
+                    // the reduced search thinks this action is better than expected, so it
+                    // has to prove it at full depth
+                    if late && eval > alpha {
+                        search_event!(depth = depth.plies(), index = i, "late move re-search");
+                        eval =
+                            self.minmax_helper(state_p, depth_p, alpha, beta, zobrist_hash_p, line);
+                    }
+
+                    if eval > max_eval || best.is_none() {
+                        max_eval = eval;
+                        best = Some(*node.action());
+                    }
+                    alpha = cmp::max(alpha, max_eval);
+                    if beta <= alpha {
+                        search_event!(depth = depth.plies(), index = i, "cutoff");
+                        self.reward_cutoff(state, node, &optim, depth);
+                        exit = NodeExit::Cutoff;
+                        break;
+                    }
+                }
+
+                max_eval
+            }
+
+            Optim::Min => {
+                let mut min_eval = Score::INFINITY;
+
+                for (i, node) in nodes.iter().enumerate() {
+                    let (state_p, zobrist_hash_p) =
+                        (node.state(), zobrist_hash ^ node.zobrist_diff());
+                    let late = reduce(i, node);
+
+                    let mut eval = self.minmax_helper(
+                        state_p,
+                        depth_p.reduced(late as u32),
+                        alpha,
+                        beta,
+                        zobrist_hash_p,
+                        line,
+                    );
+                    if late && eval < beta {
+                        search_event!(depth = depth.plies(), index = i, "late move re-search");
+                        eval =
+                            self.minmax_helper(state_p, depth_p, alpha, beta, zobrist_hash_p, line);
+                    }
+
+                    if eval < min_eval || best.is_none() {
+                        min_eval = eval;
+                        best = Some(*node.action());
+                    }
+                    beta = cmp::min(beta, min_eval);
+                    if beta <= alpha {
+                        search_event!(depth = depth.plies(), index = i, "cutoff");
+                        self.reward_cutoff(state, node, &optim, depth);
+                        exit = NodeExit::Cutoff;
+                        break;
+                    }
+                }
+
+                min_eval
+            }
+        };
+
+        line.extensions -= extend as u32;
+        line.hashes.pop();
+
+        // a cancelled subtree did not finish so its score must not be kept around
+        if self.abort.load(Ordering::Relaxed) {
+            return (eval, NodeExit::Aborted, best);
+        }
 
-        self.pool.spawn(move || {
-            let depths_iter: Box<dyn Iterator<Item = u32>> = match depth_limit {
-                Some(d) => Box::new(1..d),
-                None => Box::new(1..),
+        let bound = Bound::of(eval, window.0, window.1);
+        self.tt.save(zobrist_hash, state, depth, eval, bound, best);
+
+        (eval, exit, best)
+    }
+
+    /// Returns whether the best of the root `results` is an easy move: ahead of the
+    /// second best by `margin`, and still at least that far ahead of every other action
+    /// searched a little shallower with a null window on the side of the bound the best
+    /// action is on. Those searches only find out bounds, and the table keeps their
+    /// scores as such.
+    #[allow(clippy::too_many_arguments)]
+    fn easy_move_helper<F>(
+        &self,
+        state: &S,
+        pairs: &[ActionStatePair<S>],
+        results: &[ActionScorePair<S>],
+        margin: Score,
+        depth: Depth,
+        zobrist_hash: u64,
+        new_line: F,
+    ) -> bool
+    where
+        F: Fn() -> Line,
+    {
+        let optim = state.turn().optim();
+        let (best, second) = match results {
+            [] => return false,
+            [_] => return true, // a single action leaves nothing to think about
+            [best, second, ..] => (best.score, second.score),
+        };
+        let (gap, bound) = match optim {
+            Optim::Max => (best.saturating_sub(second), best.saturating_sub(margin)),
+            Optim::Min => (second.saturating_sub(best), best.saturating_add(margin)),
+        };
+        if gap < margin {
+            return false;
+        }
+        // nothing gets past a bound at a win or a loss
+        if bound.is_decisive() {
+            return true;
+        }
+
+        let best_action = results[0].action;
+        pairs
+            .iter()
+            .filter(|p| *p.action() != best_action)
+            .all(|p| {
+                let (alpha, beta) = match optim {
+                    Optim::Max => null_window_above(bound),
+                    Optim::Min => null_window_below(bound),
+                };
+                let eval = self.minmax_helper(
+                    p.state(),
+                    depth.reduced(EASY_MOVE_REDUCTION),
+                    alpha,
+                    beta,
+                    zobrist_hash ^ *p.zobrist_diff(),
+                    &mut new_line(),
+                );
+                match optim {
+                    Optim::Max => eval <= bound,
+                    Optim::Min => eval >= bound,
+                }
+            })
+    }
+
+    /// Looks a state of `line` up in the tablebase, if the engine has one and the state is
+    /// deep enough in the line to be probed. Wins and losses become scores in the band of
+    /// `Score::tablebase_win` for how far the state is from the root, and draws score
+    /// like any other draw.
+    fn tablebase_helper(&self, state: &S, line: &Line) -> Option<Score> {
+        // the line holds the root and every state after it but not this one
+        let ply = line.hashes.len() as u32;
+        if ply == 1 && !self.probe_root {
+            return None;
+        }
+
+        let wdl = self.tablebase.as_ref()?.probe(state)?;
+        self.counters.tb_hits.fetch_add(1, Ordering::Relaxed);
+        search_event!(ply, wdl = ?wdl, "tablebase hit");
+        Some(match wdl {
+            Wdl::Win => Score::tablebase_win(&state.turn(), ply),
+            Wdl::Loss => Score::tablebase_win(&state.turn().opposite(), ply),
+            Wdl::Draw => Score::DRAW,
+        })
+    }
+
+    /// Orders actions in stages without evaluating where they lead: first the best action
+    /// the table holds for the state, then the actions that are not quiet by how much they
+    /// win, then the quiet ones by how often they caused cutoffs
+    fn order_actions(
+        &self,
+        state: &S,
+        nodes: &mut [ActionStatePair<S>],
+        tt_action: Option<S::Action>,
+    ) {
+        let optim = state.turn().optim();
+
+        nodes.sort_by_cached_key(|node| {
+            let tt_first = Some(*node.action()) == tt_action;
+            let (stage, score) = match state.is_quiet(node) {
+                false => (1, state.capture_gain(node)),
+                true => (
+                    0,
+                    self.history_table
+                        .score(&optim, state.history_key(node.action())),
+                ),
             };
+            Reverse((tt_first, stage, score))
+        });
+    }
+
+    fn reward_cutoff(&self, state: &S, node: &ActionStatePair<S>, optim: &Optim, depth: Depth) {
+        if state.is_quiet(node) {
+            self.history_table
+                .reward(optim, state.history_key(node.action()), depth.plies());
+        }
+    }
+
+    /// Lets the side to move pass and searches the result with a null window at a reduced
+    /// depth. If the opponent cannot make use of the free move, some real move is
+    /// expected to do at least as well and the cutoff score is returned.
+    fn null_move_helper(
+        &self,
+        state: &S,
+        depth: Depth,
+        alpha: Score,
+        beta: Score,
+        zobrist_hash: u64,
+        line: &mut Line,
+    ) -> Option<Score> {
+        let (state_p, zobrist_diff) = state.null_move()?;
+
+        let bound = match state.turn().optim() {
+            Optim::Max => beta,
+            Optim::Min => alpha,
+        };
+        // nothing can cut at an unbounded window
+        if bound.is_decisive() {
+            return None;
+        }
+        let (null_alpha, null_beta) = match state.turn().optim() {
+            Optim::Max => null_window_below(beta),
+            Optim::Min => null_window_above(alpha),
+        };
+
+        line.hashes.push(zobrist_hash);
+        line.passed = true;
+        let eval = self.minmax_helper(
+            &state_p,
+            depth.reduced(1 + NULL_MOVE_REDUCTION),
+            null_alpha,
+            null_beta,
+            zobrist_hash ^ zobrist_diff,
+            line,
+        );
+        line.passed = false;
+        line.hashes.pop();
+
+        let cutoff = match state.turn().optim() {
+            Optim::Max => eval >= beta,
+            Optim::Min => eval <= alpha,
+        };
+        match cutoff {
+            true => {
+                search_event!(depth = depth.plies(), "null move cutoff");
+                Some(eval)
+            }
+            false => None,
+        }
+    }
+
+    fn iddfs_helper<T, F, G>(
+        &self,
+        f: F,
+        depths: Box<dyn Iterator<Item = u32> + Send>,
+        deadline: Option<Instant>,
+        stop: &AtomicBool,
+        abort: Arc<AtomicBool>,
+        mut on_depth: G,
+    ) -> Option<(u32, T)>
+    where
+        T: 'static + Send,
+        F: Fn(u32) -> T + 'static + Send + Sync,
+        G: FnMut(u32, &T) -> bool,
+    {
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => return Self::inline_iddfs_helper(f, depths, deadline, stop, abort, on_depth),
+        };
 
-            for depth in depths_iter {
+        let (eval_tx, eval_rx) = mpsc::channel();
+
+        let worker_abort = abort.clone();
+        pool.spawn(move || {
+            for depth in depths {
                 let eval = f(depth);
 
-                // this does not do well enought at all. we are running way to much extra computation
-                match quit_rx.try_recv() {
-                    Ok(()) | Err(mpsc::TryRecvError::Disconnected) => break,
-                    Err(mpsc::TryRecvError::Empty) => (),
+                // an iteration that was cut short is incomplete so it is not sent
+                if worker_abort.load(Ordering::Relaxed) {
+                    break;
                 }
 
-                // send result
-                eval_tx.send(eval).unwrap();
+                // send result. the receiver is gone if the search already returned
+                if eval_tx.send((depth, eval)).is_err() {
+                    break;
+                }
             }
         });
 
-        // maybe make duration optional later..
-        thread::sleep(duration);
+        let mut last = None;
+
+        loop {
+            let timeout = match deadline {
+                Some(d) => cmp::min(d.saturating_duration_since(Instant::now()), POLL_INTERVAL),
+                None => POLL_INTERVAL,
+            };
+
+            let mut enough = false;
+            match eval_rx.recv_timeout(timeout) {
+                Ok((depth, eval)) => {
+                    enough = on_depth(depth, &eval);
+                    last = Some((depth, eval));
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => (),
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            let expired = matches!(deadline, Some(d) if Instant::now() >= d);
+
+            // always wait for the first iteration so there is something to return
+            if last.is_some() && (expired || enough || stop.load(Ordering::Relaxed)) {
+                abort.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+
+        // pick up iterations that finished while we were stopping
+        for (depth, eval) in eval_rx.try_iter() {
+            on_depth(depth, &eval);
+            last = Some((depth, eval));
+        }
+
+        // the worker sends at least one iteration before hanging up, unless it panicked
+        last
+    }
+
+    /// Runs the iterations of a search on the calling thread, for engines without a pool
+    fn inline_iddfs_helper<T, F, G>(
+        f: F,
+        depths: Box<dyn Iterator<Item = u32> + Send>,
+        deadline: Option<Instant>,
+        stop: &AtomicBool,
+        abort: Arc<AtomicBool>,
+        mut on_depth: G,
+    ) -> Option<(u32, T)>
+    where
+        F: Fn(u32) -> T,
+        G: FnMut(u32, &T) -> bool,
+    {
+        let mut last = None;
+
+        for depth in depths {
+            let eval = f(depth);
+
+            // an iteration that ran out of time is incomplete so it is thrown away
+            if abort.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let enough = on_depth(depth, &eval);
+            last = Some((depth, eval));
+
+            let expired = matches!(deadline, Some(d) if Instant::now() >= d);
+            if expired || enough || stop.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+
+        // the first iteration never aborts
+        last
+    }
+}
+
+type BookLoader<S> = Box<dyn FnOnce() -> Result<Arc<dyn OpeningBook<S>>, BuildError>>;
+
+/// Configures an engine before it is created. The settings are checked when the engine
+/// is built.
+pub struct EngineBuilder<S: Searchable> {
+    threads: Option<usize>,
+    tt_size_mb: usize,
+    evaluator: Evaluator,
+    max_depth: Option<u32>,
+    deterministic: bool,
+    contempt: Score,
+    pub(crate) book: Option<BookLoader<S>>,
+    tablebase: Option<Arc<dyn EndgameTable<S>>>,
+    probe_root: bool,
+    tt_self_check: bool,
+    easy_move: Option<EasyMove>,
+}
+
+impl<S: Searchable> EngineBuilder<S> {
+    /// Sets the number of threads to search with, from 1 to 64. Defaults to one for
+    /// every core of the machine. The actions at the root are searched side by side, so
+    /// more threads than actions are of no use.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets the size of the transposition table in megabytes, from 1 to 4096
+    pub fn tt_size_mb(mut self, size_mb: usize) -> Self {
+        self.tt_size_mb = size_mb;
+        self
+    }
+
+    pub fn evaluator(mut self, evaluator: Evaluator) -> Self {
+        self.evaluator = evaluator;
+        self
+    }
+
+    /// Sets the deepest iteration a search may run, from 1 to 1000
+    pub fn max_depth(mut self, depth: u32) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Makes every search reproducible. See `Engine::set_deterministic`.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Sets an opening book the engine answers from before searching, such as a `Book`
+    /// or a book file
+    pub fn book<B: OpeningBook<S> + 'static>(mut self, book: B) -> Self {
+        self.book = Some(Box::new(move || Ok(Arc::new(book))));
+        self
+    }
+
+    /// Lets timed searches stop early on easy moves. See `EasyMove`.
+    pub fn easy_move(mut self, easy_move: EasyMove) -> Self {
+        self.easy_move = Some(easy_move);
+        self
+    }
+
+    /// Makes the transposition table check itself. See `Engine::set_tt_self_check`.
+    pub fn tt_self_check(mut self, check: bool) -> Self {
+        self.tt_self_check = check;
+        self
+    }
 
-        quit_tx.send(()).unwrap();
+    /// Sets an endgame table the search looks positions up in instead of searching them,
+    /// such as a [`Tablebase`](../egtb/struct.Tablebase.html)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    /// use muskox::egtb::Tablebase;
+    /// use muskox::search::{Engine, SearchConstraint};
+    ///
+    /// let dir = std::env::temp_dir().join("muskox_engine_egtb_doc");
+    /// let tablebase = Tablebase::build(2, &dir, |_| ()).unwrap();
+    /// let engine: Engine<Bitboard> = Engine::builder()
+    ///     .threads(1)
+    ///     .tablebase(tablebase)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// // the king catches the man before it is crowned
+    /// let board = Bitboard::from_fen("B:W27:BK14").unwrap();
+    /// let result = engine.search(&board, &SearchConstraint::Depth(6));
+    /// assert!(result.best().unwrap().score().is_tablebase());
+    /// assert!(result.tb_hits() > 0);
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn tablebase<T: EndgameTable<S> + 'static>(mut self, tablebase: T) -> Self {
+        self.tablebase = Some(Arc::new(tablebase));
+        self
+    }
 
-        // get the most recent move suggested by the engine
-        // will only panic if iddps didnt find a result (almost impossible)
-        eval_rx.try_iter().last().unwrap()
+    /// Sets whether the actions of the root are looked up in the tablebase. Without it
+    /// they are searched like any other, which gives their lines a principal variation
+    /// in the transposition table. Defaults to true.
+    pub fn probe_tablebase_at_root(mut self, probe_root: bool) -> Self {
+        self.probe_root = probe_root;
+        self
     }
+
+    /// Sets how much worse than even a draw is for the side to move. Negative values
+    /// make the engine look for draws, which helps when it is losing.
+    pub fn contempt(mut self, contempt: Score) -> Self {
+        self.contempt = contempt;
+        self
+    }
+
+    /// Creates the engine, or returns the first setting that is out of range or does
+    /// not go with the others
+    pub fn build(self) -> Result<Engine<S>, BuildError> {
+        let threads = match (self.threads, self.deterministic) {
+            (Some(threads), _) if !(1..=MAX_THREADS).contains(&threads) => {
+                return Err(BuildError::ThreadsError { threads })
+            }
+            (Some(threads), true) if threads > 1 => {
+                return Err(BuildError::DeterministicThreadsError { threads })
+            }
+            (Some(threads), _) => threads,
+            (None, true) => 1,
+            (None, false) => default_threads(),
+        };
+
+        if !(1..=MAX_HASH_SIZE).contains(&self.tt_size_mb) {
+            return Err(BuildError::HashSizeError {
+                size: self.tt_size_mb,
+            });
+        }
+
+        if let Some(depth) = self.max_depth.filter(|d| !(1..=MAX_DEPTH).contains(d)) {
+            return Err(BuildError::MaxDepthError { depth });
+        }
+
+        let book = match self.book {
+            Some(load) => Some(load()?),
+            None => None,
+        };
+
+        let mut engine = Engine::with_sizes(threads, self.tt_size_mb);
+        engine.evaluator = self.evaluator;
+        engine.depth_cap = self.max_depth;
+        engine.deterministic = self.deterministic;
+        engine.contempt = self.contempt;
+        engine.set_tt_self_check(self.tt_self_check);
+        engine.easy_move = self.easy_move;
+        engine.book = book;
+        engine.tablebase = self.tablebase;
+        engine.probe_root = self.probe_root;
+        Ok(engine)
+    }
+}
+
+/// Describes a finished iteration of a search
+pub struct SearchInfo<'a, S: Searchable> {
+    depth: u32,
+    elapsed: Duration,
+    tb_hits: u64,
+    easy_move: bool,
+    results: &'a [ActionScorePair<S>],
 }
 
+impl<'a, S: Searchable> SearchInfo<'a, S> {
+    /// Returns the depth the iteration searched to
+    #[inline]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns the time since the search started
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the number of positions found in the tablebase so far
+    #[inline]
+    pub fn tb_hits(&self) -> u64 {
+        self.tb_hits
+    }
+
+    /// Returns whether the search stops after this iteration because its best action is
+    /// an easy move
+    #[inline]
+    pub fn easy_move(&self) -> bool {
+        self.easy_move
+    }
+
+    /// Returns every action with its score, best first
+    #[inline]
+    pub fn results(&self) -> &[ActionScorePair<S>] {
+        self.results
+    }
+
+    /// Returns the best action found by the iteration, if there is any
+    #[inline]
+    pub fn best(&self) -> Option<&ActionScorePair<S>> {
+        self.results.first()
+    }
+}
+
+/// Represents what a search found, once the statistics are set aside: the scored
+/// actions, or how the game ended if it was already over
+pub enum SearchOutcome<S: Searchable> {
+    Moves(Vec<ActionScorePair<S>>),
+    GameOver(GameState<S>),
+}
+
+/// Represents the outcome of a search: the scored actions of the deepest finished
+/// iteration along with statistics about the work that went into them. A search of a
+/// game that is over has no actions and no statistics.
+pub struct SearchResult<S: Searchable> {
+    results: Vec<ActionScorePair<S>>,
+    depth: u32,
+    max_depth: u32,
+    nodes: u64,
+    tt_hit_rate: f64,
+    tb_hits: u64,
+    tt_collisions: u64,
+    stopped_early: bool,
+    elapsed: Duration,
+    state: GameState<S>,
+}
+
+impl<S: Searchable> SearchResult<S> {
+    pub(super) fn new(
+        results: Vec<ActionScorePair<S>>,
+        depth: u32,
+        max_depth: u32,
+        nodes: u64,
+        tt_hit_rate: f64,
+        elapsed: Duration,
+    ) -> Self {
+        SearchResult {
+            results,
+            depth,
+            max_depth,
+            nodes,
+            tt_hit_rate,
+            tb_hits: 0,
+            tt_collisions: 0,
+            stopped_early: false,
+            elapsed,
+            state: GameState::InProgress,
+        }
+    }
+
+    /// Creates the result of searching a game that ended with `winner`
+    pub(super) fn game_over(winner: Winner<S>) -> Self {
+        SearchResult {
+            results: Vec::new(),
+            depth: 0,
+            max_depth: 0,
+            nodes: 0,
+            tt_hit_rate: 0.,
+            tb_hits: 0,
+            tt_collisions: 0,
+            stopped_early: false,
+            elapsed: Duration::from_secs(0),
+            state: GameState::Completed(winner),
+        }
+    }
+
+    /// Returns whether the game was already over, leaving nothing to search
+    #[inline]
+    pub fn is_game_over(&self) -> bool {
+        self.state != GameState::InProgress
+    }
+
+    /// Returns the scored actions, or how the game ended if it was over
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Bitboard, Color};
+    /// use muskox::search::{Engine, GameState, SearchConstraint, SearchOutcome, Winner};
+    ///
+    /// let engine: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+    /// let board = Bitboard::from_fen("B:W18:B").unwrap();
+    /// let result = engine.search(&board, &SearchConstraint::Depth(4));
+    /// assert!(result.best().is_none());
+    /// match result.into_outcome() {
+    ///     SearchOutcome::GameOver(state) => {
+    ///         assert_eq!(state, GameState::Completed(Winner::Player(Color::White)))
+    ///     }
+    ///     SearchOutcome::Moves(_) => unreachable!(),
+    /// }
+    /// ```
+    pub fn into_outcome(self) -> SearchOutcome<S> {
+        match self.state {
+            GameState::InProgress => SearchOutcome::Moves(self.results),
+            state => SearchOutcome::GameOver(state),
+        }
+    }
+
+    /// Returns every action with its score, best first
+    #[inline]
+    pub fn results(&self) -> &[ActionScorePair<S>] {
+        &self.results
+    }
+
+    /// Returns the best action found, if there is any
+    #[inline]
+    pub fn best(&self) -> Option<&ActionScorePair<S>> {
+        self.results.first()
+    }
+
+    /// Returns the depth of the iteration the results come from
+    #[inline]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns how many moves from the root the deepest position visited was
+    #[inline]
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// Returns the number of positions visited over every iteration
+    #[inline]
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Returns the fraction of transposition table lookups that found a score
+    #[inline]
+    pub fn tt_hit_rate(&self) -> f64 {
+        self.tt_hit_rate
+    }
+
+    /// Returns the number of positions found in the tablebase
+    #[inline]
+    pub fn tb_hits(&self) -> u64 {
+        self.tb_hits
+    }
+
+    /// Returns the number of transposition table hits that turned out to be of another
+    /// state. Only engines checking their table find any.
+    #[inline]
+    pub fn tt_collisions(&self) -> u64 {
+        self.tt_collisions
+    }
+
+    /// Returns whether the search stopped before its time was up on an easy move
+    #[inline]
+    pub fn stopped_early(&self) -> bool {
+        self.stopped_early
+    }
+
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the number of nodes visited per second
+    pub fn nps(&self) -> u64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0. => (self.nodes as f64 / secs) as u64,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct ActionScorePair<S: Searchable> {
     action: S::Action,
     score: Score,
 }
 
 impl<S: Searchable> ActionScorePair<S> {
+    pub(super) fn new(action: S::Action, score: Score) -> Self {
+        ActionScorePair { action, score }
+    }
+
     #[inline]
     pub fn action(&self) -> S::Action {
         self.action
@@ -230,6 +2031,28 @@ impl<S: Searchable> ActionScorePair<S> {
     }
 }
 
+/// Represents which actions at the root of a search are looked at
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum RootActions<A> {
+    #[default]
+    All,
+    Only(Vec<A>),
+    Excluding(Vec<A>),
+}
+
+impl<A: PartialEq> RootActions<A> {
+    /// Returns whether the action is looked at
+    pub fn allows(&self, action: &A) -> bool {
+        match self {
+            RootActions::All => true,
+            RootActions::Only(actions) => actions.contains(action),
+            RootActions::Excluding(actions) => !actions.contains(action),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SearchConstraint {
     Depth(u32),
     Time(Duration),
@@ -237,9 +2060,11 @@ pub enum SearchConstraint {
 }
 
 impl SearchConstraint {
+    /// Creates a constraint to search `d` plies deep, up to `MAX_DEPTH`. Engines search
+    /// no deeper than their depth cap.
     pub fn depth(d: u32) -> Result<Self, &'static str> {
         if d > MAX_DEPTH {
-            return Err("Depth too large! Pick lower than 25");
+            return Err("Depth too large! Pick 1000 or lower");
         }
         Ok(SearchConstraint::Depth(d))
     }
@@ -255,3 +2080,574 @@ impl SearchConstraint {
         SearchConstraint::None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Action, Bitboard, Color};
+    use crate::symmetry;
+
+    #[test]
+    fn repetition_test() {
+        let board = Bitboard::from_fen("B:WK31:BK1,12").unwrap();
+        let action = Action::from_movetext("1-5").unwrap();
+        let repeated = board.take_action(action).unwrap();
+
+        let mut engine = Engine::builder()
+            .contempt(Score::from(0.5))
+            .build()
+            .unwrap();
+        engine.set_history(&[repeated.zobrist_hash()]);
+
+        let result = engine.search(&board, &SearchConstraint::Depth(1));
+        let last = result.results().last().unwrap();
+        assert_eq!(last.action(), action);
+        assert_eq!(last.score(), Score::from(-0.5));
+
+        // the children of a search at depth zero are evaluated in a batch, the same way
+        engine.reset();
+        let result = engine.search(&board, &SearchConstraint::Depth(0));
+        let last = result.results().last().unwrap();
+        assert_eq!(last.action(), action);
+        assert_eq!(last.score(), Score::from(-0.5));
+        assert_eq!(result.nodes(), result.results().len() as u64);
+    }
+
+    #[test]
+    fn search_result_test() {
+        let board = Bitboard::default();
+        let engine = Engine::new();
+
+        let result = engine.search(&board, &SearchConstraint::Depth(4));
+        assert_eq!(result.depth(), 4);
+        assert!(result.max_depth() >= 4);
+        assert!(result.nodes() > result.results().len() as u64);
+        assert!((0. ..=1.).contains(&result.tt_hit_rate()));
+
+        // searching again is answered by the transposition table
+        let again = engine.search(&board, &SearchConstraint::Depth(4));
+        assert_eq!(again.nodes(), 7);
+        assert_eq!(again.tt_hit_rate(), 1.);
+
+        // a table checking itself finds nothing wrong with whole states as keys. both
+        // engines start from an empty table on one thread so they search the same tree
+        let checked: Engine<Bitboard> = Engine::builder()
+            .threads(1)
+            .tt_self_check(true)
+            .build()
+            .unwrap();
+        let unchecked: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+        let result = checked.search(&board, &SearchConstraint::Depth(6));
+        assert!(checked.tt_self_check() && !engine.tt_self_check());
+        assert_eq!(result.tt_collisions(), 0);
+        assert_eq!(
+            result.best().unwrap().score(),
+            unchecked
+                .search(&board, &SearchConstraint::Depth(6))
+                .best()
+                .unwrap()
+                .score()
+        );
+    }
+
+    #[test]
+    fn game_over_test() {
+        let engine = Engine::new();
+        let constraints = [
+            SearchConstraint::Depth(3),
+            SearchConstraint::Time(Duration::from_secs(0)),
+        ];
+
+        // a side with no pieces has lost, and kings alone on both sides is a dead draw
+        for (fen, winner) in [("B:WK11,3:B", Some(Color::White)), ("B:WK5:BK28", None)] {
+            let board = Bitboard::from_fen(fen).unwrap();
+            for constraint in &constraints {
+                let result = engine.search(&board, constraint);
+                assert!(result.is_game_over());
+                assert!(result.best().is_none());
+                match result.into_outcome() {
+                    SearchOutcome::GameOver(GameState::Completed(Winner::Player(color))) => {
+                        assert_eq!(Some(color), winner)
+                    }
+                    SearchOutcome::GameOver(state) => {
+                        assert_eq!(state, GameState::Completed(Winner::Draw));
+                        assert!(winner.is_none())
+                    }
+                    SearchOutcome::Moves(_) => panic!("{} is over", fen),
+                }
+            }
+        }
+
+        // a game still going has moves even when there is no time to search
+        let board = Bitboard::default();
+        let result = engine.search(&board, &SearchConstraint::Time(Duration::from_secs(0)));
+        assert!(!result.is_game_over());
+        assert!(matches!(result.into_outcome(), SearchOutcome::Moves(m) if !m.is_empty()));
+    }
+
+    #[test]
+    fn deterministic_test() {
+        let board =
+            Bitboard::from_fen("B:W18,19,21,23,24,26,29,30,31,32:B1,2,3,4,6,7,9,10,11,12").unwrap();
+        let mut engine = Engine::new();
+        engine.set_deterministic(true);
+        engine.set_depth_cap(Some(5));
+
+        let first = engine.search(&board, &SearchConstraint::Time(Duration::from_millis(1)));
+        assert!(engine.deterministic_tt.lock().unwrap().is_some());
+        // the table kept from a search of another state is emptied before it is used
+        engine.search(&Bitboard::default(), &SearchConstraint::Depth(5));
+        let second = engine.search(&board, &SearchConstraint::Time(Duration::from_secs(60)));
+        assert_eq!(first.depth(), 5);
+        assert_eq!(first.nodes(), second.nodes());
+
+        let scored = |result: &SearchResult<Bitboard>| {
+            result
+                .results()
+                .iter()
+                .map(|p| (p.action(), p.score()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(scored(&first), scored(&second));
+    }
+
+    #[test]
+    fn parallel_test() {
+        // every move of black but 14-17 gives a man away, whichever thread searches it
+        let board = Bitboard::from_fen("B:W18,32:B14,K23").unwrap();
+        let best = |threads| {
+            let engine: Engine<Bitboard> = Engine::builder().threads(threads).build().unwrap();
+            assert_eq!(engine.parallel(), threads > 1);
+            let result = engine.search(&board, &SearchConstraint::Depth(6));
+            assert_eq!(result.results().len(), 4);
+            result.results()[0].action()
+        };
+        assert_eq!(best(4), Action::from_movetext("14-17").unwrap());
+        assert_eq!(best(4), best(1));
+
+        // deterministic searches stay on one thread
+        let mut engine: Engine<Bitboard> = Engine::builder().threads(4).build().unwrap();
+        engine.set_deterministic(true);
+        assert!(!engine.parallel());
+    }
+
+    #[test]
+    fn concurrent_test() {
+        // searches on clones of one engine keep out of each other's tables, so they find
+        // what a search on its own does
+        let engine: Engine<Bitboard> = Engine::builder().deterministic(true).build().unwrap();
+        let board = Bitboard::default();
+        let alone = engine.search(&board, &SearchConstraint::Depth(6)).nodes();
+
+        let searches: Vec<_> = (0..4)
+            .map(|_| {
+                let engine = engine.clone();
+                thread::spawn(move || engine.search(&board, &SearchConstraint::Depth(6)).nodes())
+            })
+            .collect();
+        for search in searches {
+            assert_eq!(search.join().unwrap(), alone);
+        }
+    }
+
+    #[test]
+    fn inline_test() {
+        // the way engines search where threads cannot be spawned
+        let mut engine = Engine::new();
+        engine.pool = None;
+
+        let board = Bitboard::default();
+        let start = Instant::now();
+        let result = engine.search(&board, &SearchConstraint::Time(Duration::from_millis(50)));
+        assert!(result.depth() >= 1);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let result = engine.search(&board, &SearchConstraint::Depth(4));
+        assert_eq!(result.depth(), 4);
+    }
+
+    #[test]
+    fn extension_test() {
+        let engine = Engine::new();
+
+        // after any move of black's king, white's man on 29 can only go to 25 so the
+        // search looks a ply further
+        let board = Bitboard::from_fen("B:W29:BK4").unwrap();
+        let result = engine.search(&board, &SearchConstraint::Depth(1));
+        assert_eq!(result.max_depth(), 3);
+
+        let board = Bitboard::from_fen("B:W30:BK4").unwrap();
+        let result = engine.search(&board, &SearchConstraint::Depth(1));
+        assert_eq!(result.max_depth(), 2);
+    }
+
+    #[test]
+    fn ordering_test() {
+        let engine = Engine::new();
+
+        // crowning comes before the quiet king moves, and the table's action before both
+        let board = Bitboard::from_fen("B:W32:B27,K14").unwrap();
+        let mut nodes = board.generate_all_actions();
+        engine.order_actions(&board, &mut nodes, None);
+        assert_eq!(*nodes[0].action(), Action::from_movetext("27-31").unwrap());
+
+        let last = *nodes.last().unwrap().action();
+        engine.order_actions(&board, &mut nodes, Some(last));
+        assert_eq!(*nodes[0].action(), last);
+
+        // searching remembers the best action of the positions it went through
+        let result = engine.search(&board, &SearchConstraint::Depth(3));
+        let child = board.take_action(result.best().unwrap().action()).unwrap();
+        assert!(engine
+            .tt
+            .best_action(child.zobrist_hash(), &child)
+            .is_some());
+    }
+
+    #[test]
+    fn contempt_test() {
+        // contempt is from the point of view of whoever is to move at the root, and
+        // scores are from black's
+        let cases = [
+            ("B:WK31:BK1", "1-6", 0.5, -0.5),
+            ("B:WK31:BK1", "1-6", -0.5, 0.5),
+            ("W:WK31:BK1", "31-27", 0.5, 0.5),
+        ];
+
+        for (fen, movetext, contempt, score) in cases {
+            let board = Bitboard::from_fen(fen).unwrap();
+            let action = Action::from_movetext(movetext).unwrap();
+            let repeated = board.take_action(action).unwrap();
+
+            let mut engine = Engine::builder()
+                .contempt(Score::from(contempt))
+                .build()
+                .unwrap();
+            engine.set_history(&[repeated.zobrist_hash()]);
+
+            let result = engine.search(&board, &SearchConstraint::Depth(1));
+            let pair = result
+                .results()
+                .iter()
+                .find(|p| p.action() == action)
+                .unwrap();
+            assert_eq!(pair.score(), Score::from(score));
+        }
+    }
+
+    #[test]
+    fn contempt_table_test() {
+        // the table holds the same scores whatever the contempt, since only the root
+        // actions get it
+        let board = Bitboard::from_fen("B:W31,K22:BK11,K1").unwrap();
+        let repeated = board
+            .take_action(Action::from_movetext("1-5").unwrap())
+            .unwrap();
+        let depth = 6;
+        let scores = |contempt| {
+            let mut engine = Engine::<Bitboard>::builder()
+                .threads(1)
+                .tt_size_mb(1)
+                .contempt(Score::from(contempt))
+                .build()
+                .unwrap();
+            engine.set_history(&[repeated.zobrist_hash()]);
+            engine.search(&board, &SearchConstraint::Depth(depth));
+            board
+                .generate_all_actions()
+                .iter()
+                // a repetition depends on the history, so it is never saved
+                .filter(|p| *p.state() != repeated)
+                .map(|p| engine.cached_score(p.state(), depth))
+                .collect::<Vec<_>>()
+        };
+
+        let even = scores(0.);
+        assert!(even.iter().all(Option::is_some));
+        assert_eq!(scores(0.5), even);
+        assert_eq!(scores(-0.5), even);
+    }
+
+    #[test]
+    fn tablebase_test() {
+        use crate::egtb::Tablebase;
+
+        let dir = std::env::temp_dir().join("muskox_engine_tablebase_test");
+        Tablebase::build(2, &dir, |_| ()).unwrap();
+        let engine = |probe_root| {
+            Engine::<Bitboard>::builder()
+                .threads(1)
+                .tablebase(Tablebase::load(2, &dir).unwrap())
+                .probe_tablebase_at_root(probe_root)
+                .build()
+                .unwrap()
+        };
+
+        // the king catches the man before it is crowned
+        let board = Bitboard::from_fen("B:W27:BK14").unwrap();
+        let constraint = SearchConstraint::Depth(1);
+        for probe_root in [true, false] {
+            let engine = engine(probe_root);
+            let result = engine.search(&board, &constraint);
+            let score = result.best().unwrap().score();
+            assert!(result.tb_hits() > 0);
+            assert!(score.is_tablebase() && score > Score::DRAW);
+
+            // the actions of the root only have a line when they were searched
+            let pv = engine.principal_variation(&board, 1);
+            assert_eq!(pv.is_empty(), probe_root);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn history_test() {
+        let table = HistoryTable::default();
+        table.reward(&Optim::Max, Some(3), 4);
+        table.reward(&Optim::Max, Some(3), 2);
+        table.reward(&Optim::Min, Some(5), 3);
+        table.reward(&Optim::Min, None, 3);
+        assert_eq!(table.score(&Optim::Max, Some(3)), 20);
+        // each side has a table of its own
+        assert_eq!(table.score(&Optim::Min, Some(3)), 0);
+        assert_eq!(table.score(&Optim::Min, Some(5)), 9);
+        assert_eq!(table.score(&Optim::Max, None), 0);
+
+        table.age();
+        assert_eq!(table.score(&Optim::Max, Some(3)), 10);
+        assert_eq!(table.score(&Optim::Min, Some(5)), 4);
+
+        // every iteration halves what the ones before it found
+        let board = Bitboard::from_fen("B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29").unwrap();
+        let engine: Engine<Bitboard> = Engine::builder()
+            .threads(1)
+            .deterministic(true)
+            .max_depth(6)
+            .build()
+            .unwrap();
+        let scores = |constraint| {
+            engine.search(&board, &constraint);
+            let mut scores = Vec::new();
+            for pair in board.successors() {
+                let reply = pair.board();
+                for p in reply.successors() {
+                    scores.push(engine.history_score(&reply, &p.action()));
+                }
+            }
+            scores
+        };
+        let single = scores(SearchConstraint::Depth(6));
+        let deepened = scores(SearchConstraint::Time(Duration::from_secs(1)));
+        assert!(single.iter().any(|&score| score > 0));
+        assert_ne!(single, deepened);
+    }
+
+    #[test]
+    fn null_window_test() {
+        let engine = || {
+            let builder = Engine::<Bitboard>::builder().threads(1).tt_size_mb(1);
+            builder.build().unwrap()
+        };
+        let new_line = || Line {
+            hashes: Vec::new(),
+            passed: false,
+            extensions: 0,
+            deadline: None,
+        };
+        let search = |engine: &Engine<Bitboard>, board: &Bitboard, depth, window: (_, _)| {
+            let hash = board.zobrist_hash();
+            let depth = Depth::new(depth);
+            engine.minmax_helper(board, depth, window.0, window.1, hash, &mut new_line())
+        };
+        let full = (Score::NEG_INFINITY, Score::INFINITY);
+
+        // a null window only tells which side of it the score is on, so the table must not
+        // take what it finds for the score
+        let board = Bitboard::from_fen("B:W18,32:B14,K23").unwrap();
+        let tested = engine();
+        search(&tested, &board, 4, null_window_below(Score::DRAW));
+        assert_eq!(tested.cached_score(&board, 4), None);
+        let value = search(&tested, &board, 4, full);
+        assert_eq!(tested.cached_score(&board, 4), Some(value));
+
+        // every null window search lands on the side of its bound the score is on, at a
+        // depth where nothing but the window prunes
+        let mut boards = symmetry::corpus(5, 12);
+        boards.push(board);
+        let offsets = [-1., -0.25, 0., 0.25, 1.];
+        for board in &boards {
+            let value = search(&engine(), board, 3, full);
+            for bound in offsets
+                .iter()
+                .map(|&offset| value.saturating_add(Score::from(offset)))
+            {
+                let below = search(&engine(), board, 3, null_window_below(bound));
+                assert_eq!(
+                    below >= bound,
+                    value >= bound,
+                    "{} at {}",
+                    board.fen(),
+                    bound
+                );
+                let above = search(&engine(), board, 3, null_window_above(bound));
+                assert_eq!(
+                    above <= bound,
+                    value <= bound,
+                    "{} at {}",
+                    board.fen(),
+                    bound
+                );
+            }
+        }
+
+        // a null move only cuts off when the search after it reaches the bound
+        for board in &boards {
+            let (passed, _) = match board.null_move() {
+                Some(passed) => passed,
+                None => continue,
+            };
+            let value = search(&engine(), &passed, 2, full);
+            for bound in offsets
+                .iter()
+                .map(|&offset| value.saturating_add(Score::from(offset)))
+            {
+                let (alpha, beta, reached) = match board.turn().optim() {
+                    Optim::Max => (Score::NEG_INFINITY, bound, value >= bound),
+                    Optim::Min => (bound, Score::INFINITY, value <= bound),
+                };
+                let hash = board.zobrist_hash();
+                let depth = NULL_MOVE_MIN_DEPTH.extended(1);
+                let cutoff =
+                    engine().null_move_helper(board, depth, alpha, beta, hash, &mut new_line());
+                assert_eq!(cutoff.is_some(), reached, "{} at {}", board.fen(), bound);
+            }
+        }
+    }
+
+    #[test]
+    fn easy_move_test() {
+        let mut engine: Engine<Bitboard> = Engine::builder()
+            .threads(1)
+            .easy_move(EasyMove::new(Score::from(1.), 3))
+            .build()
+            .unwrap();
+
+        // every move of black but 14-17 gives a man away
+        let board = Bitboard::from_fen("B:W18,32:B14,K23").unwrap();
+        let constraint = SearchConstraint::Time(Duration::from_secs(60));
+        let result = engine.search(&board, &constraint);
+        assert!(result.stopped_early());
+        assert!(result.elapsed() < Duration::from_secs(60));
+
+        // nothing stands out at the start, and searches to a depth never stop early
+        let board = Bitboard::default();
+        let result = engine.search(&board, &SearchConstraint::Time(Duration::from_millis(300)));
+        assert!(!result.stopped_early());
+        assert!(!engine
+            .search(&board, &SearchConstraint::Depth(4))
+            .stopped_early());
+
+        // checking the other actions against the bound leaves no exact scores behind
+        let board = Bitboard::from_fen("B:W18,32:B14,K23").unwrap();
+        let pairs = board.generate_all_actions();
+        let best = Action::from_movetext("14-17").unwrap();
+        let ahead = match board.turn().optim() {
+            Optim::Max => Score::from(0.5),
+            Optim::Min => Score::from(-0.5),
+        };
+        let others = pairs.iter().map(|p| *p.action()).filter(|&a| a != best);
+        let results: Vec<_> = iter::once(ActionScorePair::new(best, ahead))
+            .chain(others.map(|a| ActionScorePair::new(a, Score::DRAW)))
+            .collect();
+        let new_line = || Line {
+            hashes: vec![board.zobrist_hash()],
+            passed: false,
+            extensions: 0,
+            deadline: None,
+        };
+        let fresh: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+        fresh.easy_move_helper(
+            &board,
+            &pairs,
+            &results,
+            Score::from(0.25),
+            Depth::new(4),
+            board.zobrist_hash(),
+            new_line,
+        );
+        for p in pairs.iter().filter(|p| *p.action() != best) {
+            assert_eq!(fresh.cached_score(p.state(), 2), None);
+        }
+
+        engine.set_easy_move(None);
+        let result = engine.search(&board, &SearchConstraint::Time(Duration::from_millis(300)));
+        assert!(!result.stopped_early());
+
+        // with the best action only just far enough ahead of the second, an action is easy
+        // exactly when every other action is behind the bound when searched in full
+        let margin = Score::from(0.5);
+        let mut boards = symmetry::corpus(7, 6);
+        boards.push(Bitboard::default());
+        boards.push(Bitboard::from_fen("B:W18,32:B14,K23").unwrap());
+        let mut easy = Vec::new();
+        for board in &boards {
+            let fresh = || {
+                let builder = Engine::<Bitboard>::builder().threads(1).tt_size_mb(1);
+                builder.build().unwrap()
+            };
+            let new_line = || Line {
+                hashes: vec![board.zobrist_hash()],
+                passed: false,
+                extensions: 0,
+                deadline: None,
+            };
+            let optim = board.turn().optim();
+            let pairs = board.generate_all_actions();
+            let mut scored: Vec<_> = pairs
+                .iter()
+                .map(|p| {
+                    let hash = board.zobrist_hash() ^ p.zobrist_diff();
+                    let (alpha, beta) = (Score::NEG_INFINITY, Score::INFINITY);
+                    let depth = Depth::new(4).reduced(EASY_MOVE_REDUCTION);
+                    let score =
+                        fresh().minmax_helper(p.state(), depth, alpha, beta, hash, &mut new_line());
+                    ActionScorePair::<Bitboard>::new(*p.action(), score)
+                })
+                .collect();
+            scored.sort_by(|a, b| match optim {
+                Optim::Max => b.score.cmp(&a.score),
+                Optim::Min => a.score.cmp(&b.score),
+            });
+            if scored.len() < 2 || scored[0].score.is_decisive() {
+                continue;
+            }
+
+            let (best, rest) = scored.split_first().unwrap();
+            let bound = match optim {
+                Optim::Max => best.score.saturating_sub(margin),
+                Optim::Min => best.score.saturating_add(margin),
+            };
+            let expected = rest.iter().all(|pair| match optim {
+                Optim::Max => pair.score <= bound,
+                Optim::Min => pair.score >= bound,
+            });
+            let results = [
+                ActionScorePair::new(best.action, best.score),
+                ActionScorePair::new(rest[0].action, bound),
+            ];
+            let hash = board.zobrist_hash();
+            let found = fresh().easy_move_helper(
+                board,
+                &pairs,
+                &results,
+                margin,
+                Depth::new(4),
+                hash,
+                new_line,
+            );
+            assert_eq!(found, expected, "{}", board.fen());
+            easy.push(found);
+        }
+        assert!(easy.contains(&true) && easy.contains(&false));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::Duration;
+
+use super::{ActionScorePair, Engine, SearchConstraint, SearchResult, Searchable};
+
+// the search runs on a thread of its own and hands what it finds over through a mutex,
+// waking whichever task is waiting. nothing here depends on a particular runtime
+
+/// Describes a finished iteration of a search running in the background. It is an owned
+/// version of [SearchInfo](struct.SearchInfo.html).
+pub struct SearchUpdate<S: Searchable> {
+    depth: u32,
+    elapsed: Duration,
+    tb_hits: u64,
+    easy_move: bool,
+    results: Vec<ActionScorePair<S>>,
+}
+
+impl<S: Searchable> SearchUpdate<S> {
+    /// Returns the depth the iteration searched to
+    #[inline]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Returns the time since the search started
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the number of positions found in the tablebase so far
+    #[inline]
+    pub fn tb_hits(&self) -> u64 {
+        self.tb_hits
+    }
+
+    /// Returns whether the search stops after the iteration because its best action is
+    /// an easy move
+    #[inline]
+    pub fn easy_move(&self) -> bool {
+        self.easy_move
+    }
+
+    /// Returns every action with its score, best first
+    #[inline]
+    pub fn results(&self) -> &[ActionScorePair<S>] {
+        &self.results
+    }
+
+    /// Returns the best action found by the iteration, if there is any
+    #[inline]
+    pub fn best(&self) -> Option<&ActionScorePair<S>> {
+        self.results.first()
+    }
+}
+
+struct Channel<S: Searchable> {
+    updates: VecDeque<SearchUpdate<S>>,
+    result: Option<SearchResult<S>>,
+    finished: bool, // the result was sent, even if it was taken already
+    result_waker: Option<Waker>,
+    update_waker: Option<Waker>,
+}
+
+type Shared<S> = Arc<Mutex<Channel<S>>>;
+
+/// Resolves to the result of a search running in the background
+pub struct SearchFuture<S: Searchable> {
+    channel: Shared<S>,
+    stop: Arc<AtomicBool>,
+}
+
+impl<S: Searchable> SearchFuture<S> {
+    /// Asks the search to stop. The future then resolves to the deepest finished
+    /// iteration.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<S: Searchable> Future for SearchFuture<S> {
+    type Output = SearchResult<S>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut channel = self.channel.lock().unwrap();
+
+        match channel.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                channel.result_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Hands out the finished iterations of a search running in the background, in order
+pub struct SearchUpdates<S: Searchable> {
+    channel: Shared<S>,
+}
+
+impl<S: Searchable> SearchUpdates<S> {
+    /// Returns the next update if there is one, `Pending` while the search is still
+    /// working on it and `None` once the search is over and every update was handed out
+    pub fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<SearchUpdate<S>>> {
+        let mut channel = self.channel.lock().unwrap();
+
+        match channel.updates.pop_front() {
+            Some(update) => Poll::Ready(Some(update)),
+            None if channel.finished => Poll::Ready(None),
+            None => {
+                channel.update_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Returns a future resolving to the next update, or `None` after the last one
+    pub fn next_update(&mut self) -> NextUpdate<'_, S> {
+        NextUpdate { updates: self }
+    }
+}
+
+/// Resolves to the next update of a search. Created by `SearchUpdates::next_update`.
+pub struct NextUpdate<'a, S: Searchable> {
+    updates: &'a mut SearchUpdates<S>,
+}
+
+impl<'a, S: Searchable> Future for NextUpdate<'a, S> {
+    type Output = Option<SearchUpdate<S>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.updates.poll_next(cx)
+    }
+}
+
+impl<S: Searchable> Engine<S> {
+    /// Starts searching `state` on a thread of its own and returns right away. The
+    /// future resolves to the result of the search and the updates hand out every
+    /// finished iteration as it comes in. They can be polled by any executor.
+    ///
+    /// The search shares the transposition table with the engine, so later searches
+    /// benefit from it like they would from a blocking one.
+    pub fn search_async(
+        &self,
+        state: &S,
+        constraint: SearchConstraint,
+    ) -> (SearchFuture<S>, SearchUpdates<S>) {
+        let channel = Arc::new(Mutex::new(Channel {
+            updates: VecDeque::new(),
+            result: None,
+            finished: false,
+            result_waker: None,
+            update_waker: None,
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let engine = self.clone();
+        let state = *state;
+        let sender = channel.clone();
+        let flag = stop.clone();
+
+        thread::spawn(move || {
+            let result = engine.search_with(&state, &constraint, &flag, |info| {
+                let mut channel = sender.lock().unwrap();
+                channel.updates.push_back(SearchUpdate {
+                    depth: info.depth(),
+                    elapsed: info.elapsed(),
+                    tb_hits: info.tb_hits(),
+                    easy_move: info.easy_move(),
+                    results: info.results().to_vec(),
+                });
+                if let Some(waker) = channel.update_waker.take() {
+                    waker.wake();
+                }
+            });
+
+            let mut channel = sender.lock().unwrap();
+            channel.result = Some(result);
+            channel.finished = true;
+            for waker in channel
+                .result_waker
+                .take()
+                .into_iter()
+                .chain(channel.update_waker.take())
+            {
+                waker.wake();
+            }
+        });
+
+        let future = SearchFuture {
+            channel: channel.clone(),
+            stop,
+        };
+        (future, SearchUpdates { channel })
+    }
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs a future to completion on the calling thread, sleeping until it is woken. The
+/// smallest executor there is, for code that has nothing better to wait on.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Bitboard;
+
+    #[test]
+    fn search_async_test() {
+        let engine = Engine::new();
+        let board = Bitboard::default();
+
+        let constraint = SearchConstraint::Time(Duration::from_millis(200));
+        let (future, mut updates) = engine.search_async(&board, constraint);
+
+        let mut depths = Vec::new();
+        while let Some(update) = block_on(updates.next_update()) {
+            assert!(update.best().is_some());
+            depths.push(update.depth());
+        }
+        assert_eq!(depths[0], 1);
+        assert!(depths.windows(2).all(|w| w[0] < w[1]));
+
+        let result = block_on(future);
+        assert_eq!(result.depth(), *depths.last().unwrap());
+
+        // stopping ends a search without a time limit
+        let (future, _) = engine.search_async(&board, SearchConstraint::Time(Duration::MAX));
+        future.stop();
+        assert_eq!(block_on(future).results().len(), 7);
+    }
+}
@@ -0,0 +1,362 @@
+use std::cmp;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+use crate::zobrist::Prng;
+
+use super::{
+    ActionScorePair, GameState, Optim, Score, SearchConstraint, SearchResult, Searchable, Side,
+    Winner,
+};
+
+// a tree search has no depth, so depth constraints buy this many playouts per ply
+const PLAYOUTS_PER_DEPTH: u64 = 2000;
+const DEFAULT_PLAYOUTS: u64 = 20000;
+// random playouts still going after this many actions are scored by the evaluation
+const MAX_PLAYOUT_LENGTH: u32 = 150;
+// how many playouts run between looking at the clock and the stop flag
+const CHECK_INTERVAL: u64 = 64;
+// evaluations of this size are turned into a result of about 0.76 (tanh of 1)
+const EVALUATION_SCALE: f64 = 2.;
+const DEFAULT_SEED: u64 = 0x9e3779b97f4a7c15;
+
+/// Represents how the value of a newly expanded node is estimated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Playout {
+    /// Plays random actions until the game ends. Needs nothing but the rules, so it works
+    /// for games without a good evaluation function
+    Random,
+    /// Scores the node with the evaluation function straight away
+    Evaluation,
+}
+
+/// Represents a position in the search tree along with the results that went through it
+struct Node<S: Searchable> {
+    state: S,
+    action: Option<S::Action>, // the action that led here, none for the root
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // actions that do not have a child yet, along with what evaluation playouts already
+    // make of where they lead
+    untried: Vec<(S::Action, S, Option<f64>)>,
+    visits: u32,
+    value: f64, // the sum of the results from black's point of view, each from -1 to 1
+    depth: u32,
+}
+
+impl<S: Searchable> Node<S> {
+    fn new(state: S, action: Option<S::Action>, parent: Option<usize>, depth: u32) -> Self {
+        let untried = match state.get_game_state() {
+            GameState::InProgress => state
+                .generate_all_actions()
+                .iter()
+                .map(|p| (*p.action(), *p.state(), None))
+                .collect(),
+            GameState::Completed(_) => Vec::new(),
+        };
+
+        Node {
+            state,
+            action,
+            parent,
+            children: Vec::new(),
+            untried,
+            visits: 0,
+            value: 0.,
+            depth,
+        }
+    }
+
+    /// Returns the average result of the node from black's point of view
+    fn mean(&self) -> f64 {
+        self.value / f64::from(self.visits)
+    }
+}
+
+/// Turns an evaluation into a result from -1 to 1
+fn squash(score: Score) -> f64 {
+    (f64::from(f32::from(score)) / EVALUATION_SCALE).tanh()
+}
+
+/// Returns a random number from 0 up to (not including) `n`
+fn below(prng: &mut Prng, n: usize) -> usize {
+    (prng.rand64() % n as u64) as usize
+}
+
+/// Searches games with Monte Carlo tree search (UCT) instead of alpha-beta. It is an
+/// alternative to [Engine](struct.Engine.html) for experimenting and for games whose
+/// evaluation function is too weak for a minmax search to be of use.
+///
+/// Depth constraints are turned into a number of playouts, as the tree has no fixed
+/// depth. The score of an action is the average result of the playouts through it from
+/// black's point of view, from -1 (white always wins) to 1 (black always wins).
+pub struct MctsEngine<S: Searchable> {
+    playout: Playout,
+    exploration: f64,
+    seed: u64,
+    phantom: PhantomData<S>,
+}
+
+impl<S: Searchable> Default for MctsEngine<S> {
+    fn default() -> Self {
+        MctsEngine::new()
+    }
+}
+
+impl<S: Searchable> MctsEngine<S> {
+    pub fn new() -> Self {
+        MctsEngine {
+            playout: Playout::Random,
+            exploration: std::f64::consts::SQRT_2,
+            seed: DEFAULT_SEED,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets how the value of new nodes is estimated
+    pub fn set_playout(&mut self, playout: Playout) {
+        self.playout = playout;
+    }
+
+    /// Sets how much the search favours actions it has tried little over the ones that
+    /// did well so far. The default is the square root of two.
+    pub fn set_exploration(&mut self, exploration: f64) {
+        self.exploration = exploration;
+    }
+
+    /// Sets the seed of the random playouts. Searches with the same seed, state and
+    /// playout budget give the same results.
+    pub fn set_seed(&mut self, seed: u64) {
+        // the xorshift generator never leaves zero
+        self.seed = cmp::max(seed, 1);
+    }
+
+    /// Returns every action from `state` with its score, the most visited first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Bitboard;
+    /// use muskox::search::{MctsEngine, SearchConstraint};
+    ///
+    /// let engine = MctsEngine::new();
+    /// let result = engine.search(&Bitboard::default(), &SearchConstraint::Depth(1));
+    /// assert_eq!(result.results().len(), 7);
+    /// ```
+    pub fn search(&self, state: &S, constraint: &SearchConstraint) -> SearchResult<S> {
+        self.search_with(state, constraint, &AtomicBool::new(false))
+    }
+
+    /// Searches like `search`, but stops early once `stop` is set
+    pub fn search_with(
+        &self,
+        state: &S,
+        constraint: &SearchConstraint,
+        stop: &AtomicBool,
+    ) -> SearchResult<S> {
+        if let GameState::Completed(winner) = state.get_game_state() {
+            return SearchResult::game_over(winner);
+        }
+        let start = Instant::now();
+
+        // a duration too long to represent means we search until stopped
+        let (budget, deadline) = match constraint {
+            SearchConstraint::None => (DEFAULT_PLAYOUTS, None),
+            SearchConstraint::Depth(depth) => (u64::from(*depth) * PLAYOUTS_PER_DEPTH, None),
+            SearchConstraint::Time(dur) => (u64::MAX, start.checked_add(*dur)),
+        };
+
+        let mut tree = vec![Node::new(*state, None, None, 0)];
+        let mut prng = Prng::new(self.seed);
+        let (mut nodes, mut max_depth) = (0, 0);
+
+        // there is nothing to search if the game is over
+        let mut playouts = match tree[0].untried.is_empty() {
+            true => budget,
+            false => 0,
+        };
+
+        while playouts < budget {
+            if playouts % CHECK_INTERVAL == 0 && playouts > 0 {
+                let expired = matches!(deadline, Some(d) if Instant::now() >= d);
+                if expired || stop.load(Ordering::Relaxed) {
+                    break;
+                }
+            }
+
+            // selection: walk down the fully expanded part of the tree
+            let mut current = 0;
+            while tree[current].untried.is_empty() && !tree[current].children.is_empty() {
+                current = self.select(&tree, current);
+            }
+
+            // expansion: give one of the untried actions a node. evaluation playouts score
+            // every action of a node together the first time it is expanded
+            let mut estimate = None;
+            if !tree[current].untried.is_empty() {
+                if self.playout == Playout::Evaluation && tree[current].children.is_empty() {
+                    estimate_untried(&mut tree[current].untried);
+                }
+
+                let i = below(&mut prng, tree[current].untried.len());
+                let (action, state, value) = tree[current].untried.swap_remove(i);
+                let depth = tree[current].depth + 1;
+
+                tree.push(Node::new(state, Some(action), Some(current), depth));
+                let child = tree.len() - 1;
+                tree[current].children.push(child);
+                current = child;
+                estimate = value;
+            }
+
+            // simulation
+            let (result, length) = match estimate {
+                Some(result) => (result, 0),
+                None => self.playout_helper(&tree[current].state, &mut prng),
+            };
+            nodes += u64::from(length) + 1;
+            max_depth = cmp::max(max_depth, tree[current].depth + length);
+
+            // backpropagation
+            let mut node = Some(current);
+            while let Some(i) = node {
+                tree[i].visits += 1;
+                tree[i].value += result;
+                node = tree[i].parent;
+            }
+
+            playouts += 1;
+        }
+
+        let mut children: Vec<_> = tree[0].children.iter().map(|&c| &tree[c]).collect();
+        children.sort_by_key(|c| cmp::Reverse(c.visits));
+
+        let results = children
+            .iter()
+            .map(|c| ActionScorePair::new(c.action.unwrap(), Score::from(c.mean() as f32)))
+            .collect();
+        let depth = tree.iter().map(|n| n.depth).max().unwrap_or(0);
+
+        SearchResult::new(results, depth, max_depth, nodes, 0., start.elapsed())
+    }
+
+    /// Returns the child of `parent` with the highest upper confidence bound for the
+    /// player to move at `parent`
+    fn select(&self, tree: &[Node<S>], parent: usize) -> usize {
+        let parent = &tree[parent];
+        let ln_visits = f64::from(parent.visits).ln();
+
+        let uct = |child: &Node<S>| {
+            let exploitation = match parent.state.turn().optim() {
+                Optim::Max => child.mean(),
+                Optim::Min => -child.mean(),
+            };
+            exploitation + self.exploration * (ln_visits / f64::from(child.visits)).sqrt()
+        };
+
+        let mut best = parent.children[0];
+        for &child in &parent.children[1..] {
+            if uct(&tree[child]) > uct(&tree[best]) {
+                best = child;
+            }
+        }
+        best
+    }
+
+    /// Estimates the result of a state from black's point of view, returning it along
+    /// with the number of actions played to get there
+    fn playout_helper(&self, state: &S, prng: &mut Prng) -> (f64, u32) {
+        let mut state = *state;
+        let mut length = 0;
+
+        loop {
+            match state.get_game_state() {
+                GameState::Completed(Winner::Player(winner)) => match winner.optim() {
+                    Optim::Max => return (1., length),
+                    Optim::Min => return (-1., length),
+                },
+                GameState::Completed(Winner::Draw) => return (0., length),
+                GameState::InProgress => (),
+            }
+
+            if self.playout == Playout::Evaluation || length == MAX_PLAYOUT_LENGTH {
+                return (squash(state.evaluate()), length);
+            }
+
+            let actions = state.generate_all_actions();
+            if actions.is_empty() {
+                return (squash(state.evaluate()), length);
+            }
+
+            state = *actions[below(prng, actions.len())].state();
+            length += 1;
+        }
+    }
+}
+
+/// Evaluates the states of untried actions whose games go on in a single batch. The ones
+/// that are over are left to the playout, which knows their results exactly.
+fn estimate_untried<S: Searchable>(untried: &mut [(S::Action, S, Option<f64>)]) {
+    let states: Vec<_> = untried
+        .iter()
+        .filter(|(_, state, _)| matches!(state.get_game_state(), GameState::InProgress))
+        .map(|(_, state, _)| *state)
+        .collect();
+    let mut scores = S::evaluate_batch(&states).into_iter();
+
+    for (_, state, value) in untried.iter_mut() {
+        if let GameState::InProgress = state.get_game_state() {
+            *value = scores.next().map(squash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Action, Bitboard};
+
+    #[test]
+    fn mcts_test() {
+        let mut engine = MctsEngine::new();
+
+        // black wins straight away by jumping white's last piece
+        let board = Bitboard::from_fen("B:W18:B14").unwrap();
+        let result = engine.search(&board, &SearchConstraint::Depth(1));
+        let best = result.best().unwrap();
+        assert_eq!(best.action(), Action::from_movetext("14-23").unwrap());
+        assert_eq!(best.score(), Score::from(1.));
+        assert_eq!(result.nodes(), PLAYOUTS_PER_DEPTH);
+
+        // the same seed and budget give the same results
+        let board = Bitboard::default();
+        let first = engine.search(&board, &SearchConstraint::Depth(1));
+        let second = engine.search(&board, &SearchConstraint::Depth(1));
+        let scores = |result: &SearchResult<Bitboard>| {
+            result
+                .results()
+                .iter()
+                .map(|p| (p.action(), p.score()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(scores(&first), scores(&second));
+
+        engine.set_playout(Playout::Evaluation);
+        let result = engine.search(&board, &SearchConstraint::Depth(1));
+        assert!(result.depth() > 1);
+        assert_eq!(result.nodes(), PLAYOUTS_PER_DEPTH);
+
+        // nothing to search once the game is over
+        let board = Bitboard::from_fen("W:W:B1").unwrap();
+        assert!(engine
+            .search(&board, &SearchConstraint::none())
+            .best()
+            .is_none());
+    }
+}
@@ -0,0 +1,9 @@
+use super::{SearchResult, Searchable};
+
+/// Represents anything that wants to know about every search an engine finishes, such as
+/// a [`SearchLog`](../searchlog/struct.SearchLog.html)
+pub trait SearchObserver<S: Searchable>: Send + Sync {
+    /// Receives the state a search started from along with what it found. Searches of
+    /// finished games are not reported, as there is nothing to search in them.
+    fn search_finished(&self, state: &S, result: &SearchResult<S>);
+}
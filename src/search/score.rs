@@ -4,6 +4,25 @@ use std::ops::{Add, AddAssign, Div, Mul, Sub};
 
 use ordered_float::OrderedFloat;
 
+use super::{Optim, Side};
+
+// scores are from the point of view of the maximizer. wins and losses are infinite, and
+// every other score is an evaluation, which stays within a range far wider than any
+// position needs so that terms can be added up without ever reaching the decisive scores
+const MAX_EVAL: f32 = 1000.;
+
+// how quickly the chance of winning grows with the evaluation. being 0.8 pieces ahead wins
+// about three games in four
+const WIN_PROBABILITY_SCALE: f32 = 1.25;
+
+// positions an endgame table knows to be won score in a band of evaluations far above any
+// a real position gets, less a little for every ply from the root so that the search
+// heads for the nearest of them. the band ends well within the range of evaluations even
+// at the deepest searches
+const TABLEBASE_WIN: f32 = 900.;
+const TABLEBASE_PLY: f32 = 0.1;
+const TABLEBASE_BAND: f32 = 500.;
+
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
 pub struct Score {
@@ -18,6 +37,128 @@ impl Score {
     pub const NEG_INFINITY: Score = Score {
         data: OrderedFloat(f32::NEG_INFINITY),
     };
+
+    pub const DRAW: Score = Score {
+        data: OrderedFloat(0.),
+    };
+
+    /// The highest score an evaluation can have
+    pub const MAX_EVAL: Score = Score {
+        data: OrderedFloat(MAX_EVAL),
+    };
+
+    /// The lowest score an evaluation can have
+    pub const MIN_EVAL: Score = Score {
+        data: OrderedFloat(-MAX_EVAL),
+    };
+
+    /// Returns whether the score is a win or a loss rather than an evaluation
+    #[inline]
+    pub fn is_decisive(&self) -> bool {
+        self.data.is_infinite()
+    }
+
+    /// Returns whether the score is a win for `side`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Color;
+    /// use muskox::search::Score;
+    ///
+    /// assert!(Score::NEG_INFINITY.is_winning(&Color::White));
+    /// assert!(!Score::NEG_INFINITY.is_winning(&Color::Black));
+    /// assert!(!Score::from(3.).is_winning(&Color::Black));
+    /// ```
+    pub fn is_winning<S: Side>(&self, side: &S) -> bool {
+        match side.optim() {
+            Optim::Max => *self == Score::INFINITY,
+            Optim::Min => *self == Score::NEG_INFINITY,
+        }
+    }
+
+    /// Returns the score of a position an endgame table knows `side` wins, `ply` moves
+    /// from the root of the search. Nearer wins score higher.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Color;
+    /// use muskox::search::Score;
+    ///
+    /// let win = Score::tablebase_win(&Color::Black, 3);
+    /// assert!(win > Score::tablebase_win(&Color::Black, 5));
+    /// assert!(win.is_tablebase() && !win.is_decisive());
+    /// assert_eq!(Score::tablebase_win(&Color::White, 3), Score::DRAW - win);
+    /// ```
+    pub fn tablebase_win<S: Side>(side: &S, ply: u32) -> Score {
+        let score = (TABLEBASE_WIN - TABLEBASE_PLY * ply as f32).max(TABLEBASE_BAND);
+        match side.optim() {
+            Optim::Max => Score::from(score),
+            Optim::Min => Score::from(-score),
+        }
+    }
+
+    /// Returns whether the score is in the band of wins and losses found in an endgame
+    /// table
+    pub fn is_tablebase(&self) -> bool {
+        !self.is_decisive() && self.data.abs() >= TABLEBASE_BAND
+    }
+
+    /// Returns the score brought within the range of evaluations. Wins and losses become
+    /// the best and worst evaluations, and nan becomes a draw.
+    pub fn clamp_to_eval_range(self) -> Score {
+        match self.data.is_nan() {
+            true => Score::DRAW,
+            false => self.clamp(Score::MIN_EVAL, Score::MAX_EVAL),
+        }
+    }
+
+    /// Returns the chance the maximizer wins from a position with this score, from 0 to 1.
+    /// Evaluations map to it along a logistic curve, and wins and losses are certain.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::search::Score;
+    ///
+    /// assert_eq!(Score::DRAW.to_win_probability(), 0.5);
+    /// assert_eq!(Score::NEG_INFINITY.to_win_probability(), 0.);
+    /// assert!(Score::from(0.8).to_win_probability() > 0.7);
+    /// ```
+    pub fn to_win_probability(&self) -> f32 {
+        match self.data.is_nan() {
+            true => 0.5,
+            false => 1. / (1. + (-WIN_PROBABILITY_SCALE * self.data.into_inner()).exp()),
+        }
+    }
+
+    /// Adds two scores without ever giving nan. A win or a loss stays one whatever is
+    /// added to it, and sums of evaluations stay within the range of evaluations. A win
+    /// added to a loss is a draw.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::search::Score;
+    ///
+    /// assert_eq!(Score::MAX_EVAL.saturating_add(Score::from(1.)), Score::MAX_EVAL);
+    /// assert_eq!(Score::INFINITY.saturating_add(Score::from(-5.)), Score::INFINITY);
+    /// assert_eq!(Score::INFINITY.saturating_add(Score::NEG_INFINITY), Score::DRAW);
+    /// ```
+    pub fn saturating_add(self, other: Score) -> Score {
+        match (self.is_decisive(), other.is_decisive()) {
+            (true, true) if self != other => Score::DRAW,
+            (true, _) => self,
+            (_, true) => other,
+            _ => (self + other).clamp_to_eval_range(),
+        }
+    }
+
+    /// Subtracts a score from another like `saturating_add` adds them
+    pub fn saturating_sub(self, other: Score) -> Score {
+        self.saturating_add(Score::DRAW - other)
+    }
 }
 
 impl From<f32> for Score {
@@ -28,6 +169,12 @@ impl From<f32> for Score {
     }
 }
 
+impl From<Score> for f32 {
+    fn from(score: Score) -> f32 {
+        score.data.into_inner()
+    }
+}
+
 impl Ord for Score {
     fn cmp(&self, other: &Self) -> Ordering {
         self.data.cmp(&other.data)
@@ -96,8 +243,107 @@ impl AddAssign for Score {
     }
 }
 
+// json has no infinities, so wins and losses are saved as text
+
+impl serde::Serialize for Score {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.is_decisive() {
+            true => serializer.collect_str(self),
+            false => serializer.serialize_f32(self.data.into_inner()),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Score {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ScoreVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ScoreVisitor {
+            type Value = Score;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number, inf or -inf")
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Score, E> {
+                Ok(Score::from(value as f32))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Score, E> {
+                Ok(Score::from(value as f32))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Score, E> {
+                Ok(Score::from(value as f32))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Score, E> {
+                match value {
+                    "inf" => Ok(Score::INFINITY),
+                    "-inf" => Ok(Score::NEG_INFINITY),
+                    _ => Err(E::invalid_value(serde::de::Unexpected::Str(value), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ScoreVisitor)
+    }
+}
+
 impl fmt::Display for Score {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.data.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Color;
+
+    #[test]
+    fn score_test() {
+        assert!(Score::INFINITY.is_decisive() && Score::NEG_INFINITY.is_decisive());
+        assert!(!Score::MAX_EVAL.is_decisive() && !Score::DRAW.is_decisive());
+        assert!(Score::INFINITY.is_winning(&Color::Black));
+        assert!(!Score::INFINITY.is_winning(&Color::White));
+        assert!(!Score::DRAW.is_winning(&Color::Black));
+
+        assert_eq!(Score::INFINITY.clamp_to_eval_range(), Score::MAX_EVAL);
+        assert_eq!(Score::from(-2000.).clamp_to_eval_range(), Score::MIN_EVAL);
+        assert_eq!(Score::from(f32::NAN).clamp_to_eval_range(), Score::DRAW);
+        assert_eq!(Score::from(1.5).clamp_to_eval_range(), Score::from(1.5));
+
+        assert_eq!(
+            Score::from(1.).saturating_add(Score::from(2.)),
+            Score::from(3.)
+        );
+        assert_eq!(
+            Score::from(f32::MAX).saturating_add(Score::from(f32::MAX)),
+            Score::MAX_EVAL
+        );
+        assert_eq!(
+            Score::NEG_INFINITY.saturating_add(Score::MAX_EVAL),
+            Score::NEG_INFINITY
+        );
+        assert_eq!(
+            Score::from(1.).saturating_add(Score::INFINITY),
+            Score::INFINITY
+        );
+        assert_eq!(Score::INFINITY.saturating_sub(Score::INFINITY), Score::DRAW);
+        assert_eq!(
+            Score::MIN_EVAL.saturating_sub(Score::from(1.)),
+            Score::MIN_EVAL
+        );
+        assert_eq!(
+            Score::DRAW.saturating_sub(Score::NEG_INFINITY),
+            Score::INFINITY
+        );
+
+        // tablebase wins stay in their band however deep they are found
+        let deepest = Score::tablebase_win(&Color::White, u32::MAX);
+        assert!(deepest.is_tablebase() && deepest <= Score::MIN_EVAL / Score::from(2.));
+        assert!(Score::tablebase_win(&Color::Black, 0) < Score::MAX_EVAL);
+        assert!(!Score::from(12.).is_tablebase());
+    }
+}
@@ -1,14 +1,17 @@
 use std::fmt::{self, Debug};
 use std::hash::Hash;
 
+/// The number of keys `Searchable::history_key` can hand out
+pub const HISTORY_SIZE: usize = 1024;
+
 pub enum Optim {
     Max,
     Min,
 }
 
 pub trait Searchable: 'static + Sized + Copy + Eq + Hash + Default + Send + Sync {
-    type Action: Copy + Send + PartialEq;
-    type Side: Side;
+    type Action: Copy + Send + Sync + PartialEq;
+    type Side: Side + Send + Sync;
     type ActionError;
 
     fn generate_all_actions(&self) -> Vec<ActionStatePair<Self>>;
@@ -17,6 +20,44 @@ pub trait Searchable: 'static + Sized + Copy + Eq + Hash + Default + Send + Sync
     fn turn(&self) -> Self::Side;
     fn evaluate(&self) -> super::Score;
     fn zobrist_hash(&self) -> u64;
+
+    /// Evaluates many states at once, returning their scores in the same order. Searches
+    /// use it wherever they have several states to evaluate together, since evaluation
+    /// functions running on a neural network or a GPU are far faster with batches. Games
+    /// without such an evaluation evaluate the states one by one.
+    fn evaluate_batch(states: &[Self]) -> Vec<super::Score> {
+        states.iter().map(|state| state.evaluate()).collect()
+    }
+
+    /// Returns the state with the side to move skipping its turn, along with how that
+    /// changes the zobrist hash. The search uses it to prune lines where even a free move
+    /// does not help the opponent, so it should be `None` wherever passing could be
+    /// better than every real move (zugzwang). Games that cannot tell never pass.
+    fn null_move(&self) -> Option<(Self, u64)> {
+        None
+    }
+
+    /// Returns whether an action from this state changes little on the board, such as
+    /// a move that neither captures nor promotes. The search looks at quiet actions late
+    /// in its ordering less deeply. Games that cannot tell treat no action as quiet.
+    fn is_quiet(&self, _: &ActionStatePair<Self>) -> bool {
+        false
+    }
+
+    /// Returns roughly how much material an action that is not quiet wins for the side
+    /// to move, without evaluating where it leads. The search tries the actions winning
+    /// the most first. Games that cannot tell say nothing is won.
+    fn capture_gain(&self, _: &ActionStatePair<Self>) -> u32 {
+        0
+    }
+
+    /// Returns a number below `HISTORY_SIZE` identifying an action the same way in every
+    /// state, such as by its squares. The search remembers which quiet actions caused
+    /// cutoffs under this key and tries them early elsewhere. Games that cannot tell
+    /// return `None`, which keeps their quiet actions in the order they were generated.
+    fn history_key(&self, _: &Self::Action) -> Option<usize> {
+        None
+    }
 }
 
 pub struct ActionStatePair<S: Searchable> {
@@ -80,4 +121,9 @@ impl<S: Searchable> fmt::Display for GameState<S> {
 
 pub trait Side: Debug + PartialEq {
     fn optim(&self) -> Optim;
+
+    /// Returns the side that moves after this one
+    fn opposite(&self) -> Self
+    where
+        Self: Sized;
 }
@@ -0,0 +1,18 @@
+use super::Searchable;
+
+/// Represents the result of a position with perfect play, for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Represents anything the engine can look results up in during a search, such as an
+/// endgame [`Tablebase`](../egtb/struct.Tablebase.html)
+pub trait EndgameTable<S: Searchable>: Send + Sync {
+    /// Returns the result of `state` for the side to move, or `None` if the table does
+    /// not hold it. Searches call it at every node, so states with more pieces than the
+    /// table holds should be turned away before anything costly is done.
+    fn probe(&self, state: &S) -> Option<Wdl>;
+}
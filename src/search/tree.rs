@@ -0,0 +1,329 @@
+use std::fmt::{self, Display, Write};
+
+use serde_json::{json, Value};
+
+use super::{Depth, Score, Searchable};
+
+// the tree of a search is recorded as the search goes: a node is added when the search
+// enters a state and filled in when it leaves it, and the nodes it is inside of are kept
+// on a stack so every node knows its parent. the action that led to a node is found by
+// looking for its state among the children of its parent, which is slow but only ever
+// done for the small searches being debugged
+
+/// Represents why the search stopped at a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NodeExit {
+    /// The state repeated one of the line or of the game, which is a draw
+    Repetition,
+    /// The transposition table held a score for the state
+    TableHit,
+    /// The game was over
+    GameOver,
+    /// The endgame table held the result of the state
+    Tablebase,
+    /// The search reached its depth and evaluated the state
+    Horizon,
+    /// Passing the turn was already enough to fall outside the window
+    NullMove,
+    /// An action fell outside the window, so the ones after it were skipped
+    Cutoff,
+    /// Every action was searched
+    Searched,
+    /// The search was cancelled before the node was done
+    Aborted,
+}
+
+impl Display for NodeExit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            NodeExit::Repetition => "repetition",
+            NodeExit::TableHit => "table hit",
+            NodeExit::GameOver => "game over",
+            NodeExit::Tablebase => "tablebase",
+            NodeExit::Horizon => "horizon",
+            NodeExit::NullMove => "null move",
+            NodeExit::Cutoff => "cutoff",
+            NodeExit::Searched => "searched",
+            NodeExit::Aborted => "aborted",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Represents a state visited by a search recorded with `Engine::debug_tree`
+#[derive(Clone)]
+pub struct TreeNode<S: Searchable> {
+    parent: Option<usize>,
+    action: Option<S::Action>,
+    state: S,
+    ply: u32,
+    depth: Depth,
+    alpha: Score,
+    beta: Score,
+    static_eval: Score,
+    score: Score,
+    best: Option<S::Action>,
+    exit: NodeExit,
+}
+
+impl<S: Searchable> TreeNode<S> {
+    /// Returns the index of the node the search came from, or `None` for the root
+    #[inline]
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    /// Returns the action that led to the node, or `None` for the root and for states
+    /// reached by passing the turn
+    #[inline]
+    pub fn action(&self) -> Option<S::Action> {
+        self.action
+    }
+
+    #[inline]
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Returns how many moves from the root the node is
+    #[inline]
+    pub fn ply(&self) -> u32 {
+        self.ply
+    }
+
+    /// Returns how deep the search was asked to look from the node
+    #[inline]
+    pub fn depth(&self) -> Depth {
+        self.depth
+    }
+
+    /// Returns the window the node was searched with
+    #[inline]
+    pub fn window(&self) -> (Score, Score) {
+        (self.alpha, self.beta)
+    }
+
+    #[inline]
+    pub fn static_eval(&self) -> Score {
+        self.static_eval
+    }
+
+    /// Returns the score the search gave the node
+    #[inline]
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Returns the action the search chose, if it searched any
+    #[inline]
+    pub fn best(&self) -> Option<S::Action> {
+        self.best
+    }
+
+    #[inline]
+    pub fn exit(&self) -> NodeExit {
+        self.exit
+    }
+}
+
+/// Represents every state a search visited, in the order it visited them. The root is
+/// the first node, and states searched more than once, like actions the search looked
+/// at again at full depth, appear once for every visit.
+#[derive(Clone)]
+pub struct SearchTree<S: Searchable> {
+    nodes: Vec<TreeNode<S>>,
+}
+
+impl<S: Searchable> SearchTree<S> {
+    #[inline]
+    pub fn nodes(&self) -> &[TreeNode<S>] {
+        &self.nodes
+    }
+
+    #[inline]
+    pub fn root(&self) -> &TreeNode<S> {
+        &self.nodes[0]
+    }
+
+    /// Returns the indices of the nodes the search went to from the node at `index`
+    pub fn children(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        (index + 1..self.nodes.len()).filter(move |&i| self.nodes[i].parent == Some(index))
+    }
+}
+
+impl<S: Searchable> SearchTree<S>
+where
+    S::Action: Display,
+{
+    /// Returns the nodes as a JSON array, where every node refers to its parent by its
+    /// index
+    pub fn to_json(&self) -> Value {
+        let action = |action: Option<S::Action>| action.map(|a| a.to_string());
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                json!({
+                    "id": i,
+                    "parent": node.parent,
+                    "action": action(node.action),
+                    "ply": node.ply,
+                    "depth": node.depth.plies(),
+                    "alpha": node.alpha,
+                    "beta": node.beta,
+                    "static_eval": node.static_eval,
+                    "score": node.score,
+                    "best": action(node.best),
+                    "exit": node.exit.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the tree as a graph in the DOT language of graphviz. Edges to the chosen
+    /// children are drawn bold.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph search {\n    node [shape=box];\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let action = match (node.parent, node.action) {
+                (None, _) => "root".to_string(),
+                (_, Some(action)) => action.to_string(),
+                (_, None) => "pass".to_string(),
+            };
+            // writing to a string never fails
+            let _ = writeln!(
+                dot,
+                "    n{} [label=\"{}\\ndepth {} [{}, {}]\\neval {} score {}\\n{}\"];",
+                i,
+                action,
+                node.depth,
+                node.alpha,
+                node.beta,
+                node.static_eval,
+                node.score,
+                node.exit
+            );
+
+            if let Some(parent) = node.parent {
+                let best = self.nodes[parent].best;
+                let style = match best.is_some() && best == node.action {
+                    true => " [style=bold]",
+                    false => "",
+                };
+                let _ = writeln!(dot, "    n{} -> n{}{};", parent, i, style);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Records the nodes of a search as it enters and leaves them
+pub(super) struct TreeRecorder<S: Searchable> {
+    nodes: Vec<TreeNode<S>>,
+    stack: Vec<usize>, // the nodes the search is inside of, the root first
+}
+
+impl<S: Searchable> TreeRecorder<S> {
+    /// Starts a tree at `root`, which is searched `depth` deep
+    pub(super) fn new(root: &S, depth: Depth) -> Self {
+        let mut recorder = TreeRecorder {
+            nodes: Vec::new(),
+            stack: Vec::new(),
+        };
+        recorder.enter(root, depth, Score::NEG_INFINITY, Score::INFINITY);
+        recorder
+    }
+
+    /// Adds a node for a state the search enters and returns its index
+    pub(super) fn enter(&mut self, state: &S, depth: Depth, alpha: Score, beta: Score) -> usize {
+        let parent = self.stack.last().copied();
+        let action = parent.and_then(|parent| {
+            self.nodes[parent]
+                .state
+                .generate_all_actions()
+                .into_iter()
+                .find(|p| p.state() == state)
+                .map(|p| *p.action())
+        });
+
+        self.nodes.push(TreeNode {
+            parent,
+            action,
+            state: *state,
+            ply: self.stack.len() as u32,
+            depth,
+            alpha,
+            beta,
+            static_eval: state.evaluate(),
+            score: Score::DRAW,
+            best: None,
+            exit: NodeExit::Aborted,
+        });
+        self.stack.push(self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Fills in the node at `index` as the search leaves it
+    pub(super) fn exit(
+        &mut self,
+        index: usize,
+        score: Score,
+        exit: NodeExit,
+        best: Option<S::Action>,
+    ) {
+        let node = &mut self.nodes[index];
+        node.score = score;
+        node.exit = exit;
+        node.best = best;
+        if let Some(at) = self.stack.iter().position(|&i| i == index) {
+            self.stack.truncate(at);
+        }
+    }
+
+    /// Hands over the nodes recorded so far
+    pub(super) fn take(&mut self) -> SearchTree<S> {
+        self.stack.clear();
+        SearchTree {
+            nodes: std::mem::take(&mut self.nodes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Action, Bitboard};
+
+    #[test]
+    fn tree_test() {
+        let board = Bitboard::default();
+        let mut recorder = TreeRecorder::new(&board, Depth::new(2));
+        let action = Action::from_movetext("11-15").unwrap();
+        let child = board.take_action(action).unwrap();
+
+        let index = recorder.enter(&child, Depth::new(1), Score::DRAW, Score::INFINITY);
+        recorder.exit(index, Score::from(0.5), NodeExit::Horizon, None);
+        recorder.exit(0, Score::from(0.5), NodeExit::Searched, Some(action));
+        let tree = recorder.take();
+
+        assert_eq!(tree.nodes().len(), 2);
+        assert_eq!(tree.children(0).collect::<Vec<_>>(), [1]);
+        let node = &tree.nodes()[1];
+        assert_eq!(
+            (node.parent(), node.action(), node.ply()),
+            (Some(0), Some(action), 1)
+        );
+        assert_eq!(node.window(), (Score::DRAW, Score::INFINITY));
+        assert_eq!(tree.root().best(), Some(action));
+
+        let json = tree.to_json();
+        assert_eq!(json[1]["action"], "11-15");
+        assert_eq!(json[1]["exit"], "horizon");
+        assert_eq!(json[0]["beta"], "inf");
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph search {"));
+        assert!(dot.contains("n0 -> n1 [style=bold];"));
+    }
+}
@@ -1,51 +1,164 @@
+use std::collections::hash_map::DefaultHasher;
 use std::default;
+use std::hash::Hasher;
 use std::mem;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
-use crate::search::{Score, Searchable};
+use serde::{Deserialize, Serialize};
 
-const DEFAULT_FLAG: u8 = 255;
+use crate::search::{Depth, Score, Searchable};
+
+// a table can check itself: it then keeps the state of every entry next to the table,
+// and a hit whose key matches but whose state does not is a collision. collisions are
+// counted and not taken as hits. the states are guarded by the lock of their cluster,
+// which is always held while they are looked at. entries restored from a save have no
+// state to check against and are trusted
+
+// the stored depth of empty entries, deeper than any real one
+const DEFAULT_FLAG: u8 = u8::MAX;
 const CLUSTER_SIZE: usize = 3;
 
+/// Recognizes the state an entry of the table belongs to. States that land in the same
+/// cluster, or even share a zobrist hash, are told apart by their keys.
+pub trait EntryKey<S>: Copy + Default + PartialEq + Send + Sync + 'static {
+    fn new(state: &S) -> Self;
+}
+
+/// Keeps the whole state, so an entry is never mistaken for another state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FullState<S>(S);
+
+impl<S: Searchable> EntryKey<S> for FullState<S> {
+    #[inline]
+    fn new(state: &S) -> Self {
+        FullState(*state)
+    }
+}
+
+/// Keeps a second 64 bit hash of the state that is independent of its zobrist hash. It
+/// takes less room than most states, and a wrong entry is only taken for the state when
+/// both of the hashes collide.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(not(feature = "compact-tt"), allow(dead_code))]
+pub struct VerificationHash(u64);
+
+impl<S: Searchable> EntryKey<S> for VerificationHash {
+    fn new(state: &S) -> Self {
+        // the hasher of the standard library always starts from the same keys
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        VerificationHash(hasher.finish())
+    }
+}
+
+/// Tells what a stored score says about the real score of its state. A search that
+/// fails low or high on its window only finds out a bound on the score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Bound {
+    Exact,
+    Lower, // the real score is at least the stored one
+    Upper, // the real score is at most the stored one
+}
+
+impl Bound {
+    /// Returns what a score found by a search between `alpha` and `beta` is
+    #[inline]
+    pub fn of(score: Score, alpha: Score, beta: Score) -> Self {
+        if score <= alpha {
+            Bound::Upper
+        } else if score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        }
+    }
+
+    /// Returns whether a score of this bound settles a search between `alpha` and `beta`
+    #[inline]
+    fn settles(self, score: Score, alpha: Score, beta: Score) -> bool {
+        match self {
+            Bound::Exact => true,
+            Bound::Lower => score >= beta,
+            Bound::Upper => score <= alpha,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
-struct TTEntry<S: Searchable> {
-    state: S,
+struct TTEntry<S: Searchable, K> {
+    key: K,
     depth: u8,
     score: Score,
+    bound: Bound,
+    best: Option<S::Action>, // the action the search found best, tried first next time
     generation: u8,
 }
 
-impl<S: Searchable> default::Default for TTEntry<S> {
+impl<S: Searchable, K: EntryKey<S>> default::Default for TTEntry<S, K> {
     fn default() -> Self {
         TTEntry {
-            state: S::default(),
+            key: K::default(),
             depth: DEFAULT_FLAG,
             score: Score::from(0.),
+            bound: Bound::Exact,
+            best: None,
             generation: 0,
         }
     }
 }
 
-impl<S: Searchable> TTEntry<S> {
+impl<S: Searchable, K> TTEntry<S, K> {
     fn replace_value(&self, current_generation: u8) -> u8 {
-        // stockfish uses 8 as the multipler
-        self.depth - 4 * (current_generation - self.generation)
+        // stockfish uses 8 as the multipler. entries older than a few searches are worth
+        // nothing, and the generation counter is allowed to wrap around
+        let age = current_generation.wrapping_sub(self.generation);
+        self.depth.saturating_sub(age.saturating_mul(4))
     }
 }
 
-type Cluster<S> = RwLock<[TTEntry<S>; CLUSTER_SIZE]>;
+/// Represents an entry of a table along with where it is kept, so tables can be saved
+/// and restored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "K: Serialize, S::Action: Serialize",
+    deserialize = "K: Deserialize<'de>, S::Action: Deserialize<'de>"
+))]
+pub struct SavedEntry<S: Searchable, K> {
+    cluster: usize,
+    slot: usize,
+    key: K,
+    depth: u8,
+    score: Score,
+    bound: Bound,
+    best: Option<S::Action>,
+}
+
+type Cluster<S, K> = RwLock<[TTEntry<S, K>; CLUSTER_SIZE]>;
+type CheckedStates<S> = Arc<[Mutex<[Option<S>; CLUSTER_SIZE]>]>;
+
+fn checked_states<S: Searchable>(n_clusters: usize) -> CheckedStates<S> {
+    (0..n_clusters)
+        .map(|_| Mutex::new([None; CLUSTER_SIZE]))
+        .collect::<Vec<_>>()
+        .into()
+}
 
+/// Keeps what searches found out about states, recognizing states by keys of type `K`
 #[derive(Clone)]
-pub struct TranspositionTable<S: Searchable> {
-    clusters: Arc<[Cluster<S>]>,
+pub struct TranspositionTable<S: Searchable, K = FullState<S>> {
+    clusters: Arc<[Cluster<S, K>]>,
     n_clusters: usize,
-    generation: u8,
+    // shared by every clone so that searches can start from `&self`
+    generation: Arc<AtomicU8>,
+    checked: Option<CheckedStates<S>>, // the state of every entry, when checking itself
+    collisions: Arc<AtomicU64>,        // found since the search started
 }
 
-impl<S: Searchable> TranspositionTable<S> {
+impl<S: Searchable, K: EntryKey<S>> TranspositionTable<S, K> {
     pub fn new(size_mb: usize) -> Self {
         let size_b = size_mb * 1024 * 1024;
-        let cluster_size = mem::size_of::<Cluster<S>>();
+        let cluster_size = mem::size_of::<Cluster<S, K>>();
         let n_clusters = size_b / cluster_size;
 
         let clusters = (0..n_clusters)
@@ -55,25 +168,60 @@ impl<S: Searchable> TranspositionTable<S> {
 
         let clusters = Arc::from(clusters);
 
-        let generation = 1;
+        let generation = Arc::new(AtomicU8::new(1));
 
         TranspositionTable {
             clusters,
             n_clusters,
             generation,
+            checked: None,
+            collisions: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn new_search(&self) {
+        // wraps around on overflow
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.collisions.store(0, Ordering::Relaxed);
+    }
+
+    /// Makes the table check every hit against the state the entry was saved for, at the
+    /// cost of keeping every state. Entries saved before are trusted. Clones made before
+    /// keep checking or not as they did.
+    pub fn set_self_check(&mut self, check: bool) {
+        if check != self.checked.is_some() {
+            self.checked = check.then(|| checked_states(self.n_clusters));
         }
     }
 
-    pub fn new_search(&mut self) {
-        self.generation += 1;
+    #[inline]
+    pub fn self_check(&self) -> bool {
+        self.checked.is_some()
+    }
+
+    /// Returns the number of hits found to be of another state since the search started.
+    /// Only tables checking themselves find any.
+    #[inline]
+    pub fn collisions(&self) -> u64 {
+        self.collisions.load(Ordering::Relaxed)
     }
 
-    pub fn save(&self, zobrist_hash: u64, &state: &S, depth: u8, score: Score) {
-        let generation = self.generation;
+    pub fn save(
+        &self,
+        zobrist_hash: u64,
+        state: &S,
+        depth: Depth,
+        score: Score,
+        bound: Bound,
+        best: Option<S::Action>,
+    ) {
+        let generation = self.generation.load(Ordering::Relaxed);
         let entry = TTEntry {
-            state,
-            depth,
+            key: K::new(state),
+            depth: depth.stored(),
             score,
+            bound,
+            best,
             generation,
         };
 
@@ -85,6 +233,9 @@ impl<S: Searchable> TranspositionTable<S> {
                 || cluster[i].depth == DEFAULT_FLAG
             {
                 cluster[i] = entry;
+                if let Some(checked) = &self.checked {
+                    checked[key].lock().unwrap()[i] = Some(*state);
+                }
                 return;
             }
         }
@@ -110,13 +261,37 @@ impl<S: Searchable> TranspositionTable<S> {
         // }
     }
 
-    pub fn probe(&self, zobrist_hash: u64, state: &S, depth: u8) -> Option<Score> {
+    /// Returns the score stored for a state searched at least `depth` deep if it settles
+    /// a search between `alpha` and `beta`. Only exact scores settle the full window.
+    pub fn probe(
+        &self,
+        zobrist_hash: u64,
+        state: &S,
+        depth: Depth,
+        alpha: Score,
+        beta: Score,
+    ) -> Option<Score> {
         let key = zobrist_hash as usize % self.n_clusters;
         let cluster = self.clusters[key].read().unwrap();
+        let state_key = K::new(state);
 
-        // iterate over the cluster
+        // iterate over the cluster. empty entries have the default key, which may well be
+        // the key of a real state
         for i in 0..CLUSTER_SIZE {
-            if cluster[i].depth >= depth && cluster[i].state == *state {
+            if cluster[i].depth != DEFAULT_FLAG
+                && depth.covered_by(cluster[i].depth)
+                && cluster[i].key == state_key
+                && cluster[i].bound.settles(cluster[i].score, alpha, beta)
+            {
+                if self.collides(key, i, state) {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        hash = zobrist_hash,
+                        slot = i,
+                        "transposition table collision"
+                    );
+                    continue;
+                }
                 // its a match!
                 return Some(cluster[i].score);
             }
@@ -125,9 +300,111 @@ impl<S: Searchable> TranspositionTable<S> {
         None
     }
 
+    /// Returns the exact score stored for a state searched at least `depth` deep
+    #[inline]
+    pub fn probe_exact(&self, zobrist_hash: u64, state: &S, depth: Depth) -> Option<Score> {
+        self.probe(
+            zobrist_hash,
+            state,
+            depth,
+            Score::NEG_INFINITY,
+            Score::INFINITY,
+        )
+    }
+
+    // whether the entry at `slot` of cluster `key` was saved for another state, counting
+    // it if so. the cluster has to be locked
+    fn collides(&self, key: usize, slot: usize, state: &S) -> bool {
+        let stored = match &self.checked {
+            Some(checked) => checked[key].lock().unwrap()[slot],
+            None => return false,
+        };
+        let collides = matches!(stored, Some(stored) if stored != *state);
+        if collides {
+            self.collisions.fetch_add(1, Ordering::Relaxed);
+        }
+        collides
+    }
+
+    /// Returns the best action stored for a state, searched to any depth
+    pub fn best_action(&self, zobrist_hash: u64, state: &S) -> Option<S::Action> {
+        let key = zobrist_hash as usize % self.n_clusters;
+        let cluster = self.clusters[key].read().unwrap();
+        let state_key = K::new(state);
+
+        cluster
+            .iter()
+            .find(|entry| entry.depth != DEFAULT_FLAG && entry.key == state_key)
+            .and_then(|entry| entry.best)
+    }
+
+    /// Returns every entry that is in use
+    pub fn entries(&self) -> Vec<SavedEntry<S, K>> {
+        let mut entries = Vec::new();
+        for (i, cluster) in self.clusters.iter().enumerate() {
+            let cluster = cluster.read().unwrap();
+            let used = cluster
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| e.depth != DEFAULT_FLAG);
+            entries.extend(used.map(|(slot, entry)| SavedEntry {
+                cluster: i,
+                slot,
+                key: entry.key,
+                depth: entry.depth,
+                score: entry.score,
+                bound: entry.bound,
+                best: entry.best,
+            }));
+        }
+        entries
+    }
+
+    /// Puts saved entries back where they were kept, as if the current search stored
+    /// them. Entries are only where they belong in a table of the same size, so returns
+    /// false without restoring any of them if one does not fit.
+    pub fn restore(&self, entries: &[SavedEntry<S, K>]) -> bool {
+        if entries
+            .iter()
+            .any(|e| e.cluster >= self.n_clusters || e.slot >= CLUSTER_SIZE)
+        {
+            return false;
+        }
+
+        let generation = self.generation.load(Ordering::Relaxed);
+        for saved in entries {
+            self.clusters[saved.cluster].write().unwrap()[saved.slot] = TTEntry {
+                key: saved.key,
+                depth: saved.depth,
+                score: saved.score,
+                bound: saved.bound,
+                best: saved.best,
+                generation,
+            };
+            if let Some(checked) = &self.checked {
+                checked[saved.cluster].lock().unwrap()[saved.slot] = None;
+            }
+        }
+        true
+    }
+
+    /// Empties the table, keeping its size, as if it had just been made
+    pub fn clear(&self) {
+        for cluster in self.clusters.iter() {
+            *cluster.write().unwrap() = [TTEntry::default(); CLUSTER_SIZE];
+        }
+        if let Some(checked) = &self.checked {
+            for states in checked.iter() {
+                *states.lock().unwrap() = [None; CLUSTER_SIZE];
+            }
+        }
+        self.generation.store(1, Ordering::Relaxed);
+        self.collisions.store(0, Ordering::Relaxed);
+    }
+
     pub fn resize(&mut self, size_mb: usize) {
         let size_b = size_mb * 1024 * 1024;
-        let cluster_size = mem::size_of::<Cluster<S>>();
+        let cluster_size = mem::size_of::<Cluster<S, K>>();
         let n_clusters = size_b / cluster_size;
 
         let clusters = (0..n_clusters)
@@ -137,6 +414,192 @@ impl<S: Searchable> TranspositionTable<S> {
 
         self.clusters = Arc::from(clusters);
         self.n_clusters = n_clusters;
-        self.generation = 1;
+        if self.checked.is_some() {
+            self.checked = Some(checked_states(n_clusters));
+        }
+        // clones made before keep the old table along with its generation
+        self.generation = Arc::new(AtomicU8::new(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Action, Bitboard};
+    use crate::search::Searchable;
+
+    fn check_table<K: EntryKey<Bitboard>>() {
+        let table: TranspositionTable<Bitboard, K> = TranspositionTable::new(1);
+        let board = Bitboard::default();
+        let other = Bitboard::from_fen("W:W18:B14").unwrap();
+        let action = Action::from_movetext("11-15").unwrap();
+
+        table.save(
+            board.zobrist_hash(),
+            &board,
+            Depth::new(4),
+            Score::from(0.5),
+            Bound::Exact,
+            Some(action),
+        );
+        assert_eq!(
+            table.probe_exact(board.zobrist_hash(), &board, Depth::new(4)),
+            Some(Score::from(0.5))
+        );
+        assert_eq!(
+            table.probe_exact(board.zobrist_hash(), &board, Depth::new(5)),
+            None
+        );
+        assert_eq!(
+            table.best_action(board.zobrist_hash(), &board),
+            Some(action)
+        );
+
+        // a state with the same zobrist hash is still told apart
+        assert_eq!(
+            table.probe_exact(board.zobrist_hash(), &other, Depth::ZERO),
+            None
+        );
+        assert_eq!(table.best_action(board.zobrist_hash(), &other), None);
+
+        // depths beyond what an entry holds cover shallower probes, never deeper ones
+        let deep = Bitboard::from_fen("B:WK5:BK28").unwrap();
+        table.save(
+            deep.zobrist_hash(),
+            &deep,
+            Depth::new(400),
+            Score::DRAW,
+            Bound::Exact,
+            None,
+        );
+        assert!(table
+            .probe_exact(deep.zobrist_hash(), &deep, Depth::new(200))
+            .is_some());
+        assert!(table
+            .probe_exact(deep.zobrist_hash(), &deep, Depth::new(300))
+            .is_none());
+    }
+
+    // a key that takes every state for every other
+    #[derive(Clone, Copy, Default, PartialEq)]
+    struct NoKey;
+
+    impl EntryKey<Bitboard> for NoKey {
+        fn new(_: &Bitboard) -> Self {
+            NoKey
+        }
+    }
+
+    #[test]
+    fn self_check_test() {
+        let mut table: TranspositionTable<Bitboard, NoKey> = TranspositionTable::new(1);
+        let board = Bitboard::default();
+        let other = Bitboard::from_fen("W:W18:B14").unwrap();
+        let hash = board.zobrist_hash();
+        table.save(
+            hash,
+            &board,
+            Depth::new(4),
+            Score::from(0.5),
+            Bound::Exact,
+            None,
+        );
+
+        // without checking, any state of the cluster is taken for the saved one
+        assert_eq!(
+            table.probe_exact(hash, &other, Depth::ZERO),
+            Some(Score::from(0.5))
+        );
+
+        // entries saved before checking was turned on are trusted
+        table.set_self_check(true);
+        assert!(table.self_check());
+        assert!(table.probe_exact(hash, &other, Depth::ZERO).is_some());
+
+        table.save(
+            hash,
+            &board,
+            Depth::new(6),
+            Score::from(0.25),
+            Bound::Exact,
+            None,
+        );
+        table.new_search();
+        assert_eq!(
+            table.probe_exact(hash, &board, Depth::new(6)),
+            Some(Score::from(0.25))
+        );
+        assert_eq!(table.probe_exact(hash, &other, Depth::new(6)), None);
+        assert_eq!(table.collisions(), 1);
+        table.new_search();
+        assert_eq!(table.collisions(), 0);
+
+        // resizing keeps the table checking itself
+        table.resize(2);
+        table.save(hash, &board, Depth::new(2), Score::DRAW, Bound::Exact, None);
+        assert_eq!(table.probe_exact(hash, &other, Depth::ZERO), None);
+        assert_eq!(table.collisions(), 1);
+
+        // and so does clearing it, which forgets the entries along with their states
+        table.clear();
+        assert!(table.self_check());
+        assert_eq!(table.collisions(), 0);
+        assert!(table.entries().is_empty());
+        table.save(hash, &board, Depth::new(2), Score::DRAW, Bound::Exact, None);
+        assert_eq!(table.probe_exact(hash, &other, Depth::ZERO), None);
+        assert_eq!(table.collisions(), 1);
+    }
+
+    #[test]
+    fn bound_test() {
+        let table: TranspositionTable<Bitboard> = TranspositionTable::new(1);
+        let board = Bitboard::default();
+        let hash = board.zobrist_hash();
+        let (alpha, beta) = (Score::from(-1.), Score::from(1.));
+
+        // a score that failed high is at least what it says, so it only settles windows
+        // it is above
+        let score = Score::from(2.);
+        let bound = Bound::of(score, alpha, beta);
+        assert_eq!(bound, Bound::Lower);
+        table.save(hash, &board, Depth::new(4), score, bound, None);
+        assert_eq!(
+            table.probe(hash, &board, Depth::new(4), alpha, beta),
+            Some(score)
+        );
+        assert_eq!(
+            table.probe(hash, &board, Depth::new(4), alpha, Score::from(3.)),
+            None
+        );
+        assert_eq!(table.probe_exact(hash, &board, Depth::new(4)), None);
+
+        // and one that failed low at most what it says
+        let score = Score::from(-2.);
+        let bound = Bound::of(score, alpha, beta);
+        assert_eq!(bound, Bound::Upper);
+        table.save(hash, &board, Depth::new(5), score, bound, None);
+        assert_eq!(
+            table.probe(hash, &board, Depth::new(5), alpha, beta),
+            Some(score)
+        );
+        assert_eq!(
+            table.probe(hash, &board, Depth::new(5), Score::from(-3.), beta),
+            None
+        );
+
+        let score = Score::from(0.5);
+        assert_eq!(Bound::of(score, alpha, beta), Bound::Exact);
+        table.save(hash, &board, Depth::new(6), score, Bound::Exact, None);
+        assert_eq!(table.probe_exact(hash, &board, Depth::new(6)), Some(score));
+    }
+
+    #[test]
+    fn entry_key_test() {
+        check_table::<FullState<Bitboard>>();
+        check_table::<VerificationHash>();
+
+        // the verification hash is the same every time
+        let board = Bitboard::default();
+        assert!(<VerificationHash as EntryKey<Bitboard>>::new(&board) == EntryKey::new(&board));
     }
 }
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::board::Bitboard;
+use crate::search::{SearchObserver, SearchResult};
+
+// the log is a csv file with a row for every search, so whole games and tournaments can
+// be looked at with a spreadsheet or a script. rows are only ever appended, and the header
+// is written when the file starts out empty. the evaluation terms differ between
+// evaluators, so they share a single column as `name=score` pairs
+
+/// The columns of every row, in order
+pub const COLUMNS: [&str; 7] = ["fen", "depth", "score", "nodes", "time", "best", "eval"];
+
+/// Represents a CSV file every search of an engine is appended to
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use muskox::board::Bitboard;
+/// use muskox::search::{Engine, SearchConstraint};
+/// use muskox::searchlog::SearchLog;
+///
+/// let path = std::env::temp_dir().join("muskox_searchlog_doctest.csv");
+/// let _ = std::fs::remove_file(&path);
+///
+/// let mut engine: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+/// engine.set_observer(Some(Arc::new(SearchLog::open(&path).unwrap())));
+/// engine.search(&Bitboard::default(), &SearchConstraint::Depth(4));
+///
+/// let log = std::fs::read_to_string(&path).unwrap();
+/// assert_eq!(log.lines().count(), 2); // the header and the search
+/// assert!(log.starts_with("fen,depth,score,nodes,time,best,eval\n"));
+/// ```
+pub struct SearchLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl SearchLog {
+    /// Opens the log at `path` to append to, creating it if there is none
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", COLUMNS.join(","))?;
+        }
+
+        Ok(SearchLog {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a row for the search of `board`
+    pub fn record(&self, board: &Bitboard, result: &SearchResult<Bitboard>) -> io::Result<()> {
+        let row = row(board, result);
+        // the row is written all at once so rows of searches running side by side,
+        // even from other logs of the same file, never end up mixed together
+        self.file.lock().unwrap().write_all(row.as_bytes())
+    }
+}
+
+impl SearchObserver<Bitboard> for SearchLog {
+    fn search_finished(&self, board: &Bitboard, result: &SearchResult<Bitboard>) {
+        // a log that can no longer be written to is not worth stopping a game for
+        if let Err(err) = self.record(board, result) {
+            eprintln!(
+                "Warning: could not write to {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+impl fmt::Debug for SearchLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SearchLog")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+// logs are the same if they write to the same file
+impl PartialEq for SearchLog {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+/// Writes the row of a search out, along with the line break that ends it
+fn row(board: &Bitboard, result: &SearchResult<Bitboard>) -> String {
+    let eval = board
+        .explain()
+        .terms()
+        .iter()
+        .map(|(name, score)| format!("{}={}", name, score))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let fields = [
+        board.fen(),
+        result.depth().to_string(),
+        result
+            .best()
+            .map_or_else(String::new, |p| p.score().to_string()),
+        result.nodes().to_string(),
+        format!("{:.3}", result.elapsed().as_secs_f64()),
+        result
+            .best()
+            .map_or_else(String::new, |p| p.action().to_string()),
+        eval,
+    ];
+    let fields: Vec<_> = fields.iter().map(|field| quote(field)).collect();
+    format!("{}\n", fields.join(","))
+}
+
+/// Quotes a field if it holds anything that means something in CSV, like the commas
+/// between the squares of a FEN
+fn quote(field: &str) -> String {
+    match field.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{Engine, SearchConstraint};
+    use std::fs;
+
+    #[test]
+    fn search_log_test() {
+        let path = std::env::temp_dir().join("muskox_search_log_test.csv");
+        let _ = fs::remove_file(&path);
+        let board = Bitboard::from_fen("B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29").unwrap();
+        let engine: Engine<Bitboard> = Engine::builder().threads(1).build().unwrap();
+        let result = engine.search(&board, &SearchConstraint::Depth(3));
+
+        SearchLog::open(&path)
+            .unwrap()
+            .record(&board, &result)
+            .unwrap();
+        // opening the log again appends to it without a second header
+        let log = SearchLog::open(&path).unwrap();
+        log.record(&board, &result).unwrap();
+        assert_eq!(log, SearchLog::open(&path).unwrap());
+
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], lines[2]);
+        let fields: Vec<_> = lines[1].rsplitn(7, ',').collect();
+        assert_eq!(
+            fields[0],
+            "material=0 kings=0.625 center=-0.21875 tempo=0.1015625 turn=0.0625 bridge=0 dog-hole=0 apex=0 opposition=0.09375 mobility=-0.0234375"
+        );
+        assert_eq!(fields[1], result.best().unwrap().action().to_string());
+        // the commas of the fen are quoted
+        assert_eq!(fields[6], format!("\"{}\"", board.fen()));
+        assert_eq!(quote("a\"b"), "\"a\"\"b\"");
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::sync::Mutex;
+
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+use crate::board::{Action, Bitboard};
+use crate::dataset::{self, Sample};
+use crate::error::BuildError;
+use crate::players::{self, Clock, EnginePlayer, MoveRecord, PlayedGame, Player, RandomPlayer};
+use crate::search::{Engine, GameState, Score, SearchConstraint, Searchable};
+
+// self-play generates training data by having the engine play itself. every game opens
+// with a few random moves so that games differ, and every position the engine moves in
+// becomes a sample scored by the search that played it. games run side by side on a
+// pool of threads, each with an engine and a transposition table of its own so that
+// they stay out of each other's way. the random moves of every game come from a seed
+// of its own, derived from the seed of the run, and the engines search
+// deterministically, so the same settings generate the same games on any number of
+// threads
+
+const DEFAULT_OPENING_PLIES: usize = 6;
+const DEFAULT_HASH_SIZE: usize = 16;
+const DEFAULT_MOVE_LIMIT: usize = 200;
+
+/// Represents the settings of a self-play run
+///
+/// # Examples
+///
+/// ```
+/// use muskox::search::SearchConstraint;
+/// use muskox::selfplay::SelfPlay;
+///
+/// let selfplay = SelfPlay::new(2, SearchConstraint::Depth(1))
+///     .threads(2)
+///     .seed(42)
+///     .move_limit(Some(20));
+/// let games = selfplay.generate(|_| ()).unwrap();
+/// assert_eq!(games.len(), 2);
+/// assert!(games.iter().all(|game| game.samples().len() <= 20));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SelfPlay {
+    games: usize,
+    constraint: SearchConstraint,
+    opening_plies: usize,
+    seed: u64,
+    threads: Option<usize>,
+    tt_size_mb: usize,
+    move_limit: Option<usize>,
+}
+
+impl SelfPlay {
+    /// Creates a run of `games` games where the engine searches every move within
+    /// `constraint`
+    pub fn new(games: usize, constraint: SearchConstraint) -> Self {
+        SelfPlay {
+            games,
+            constraint,
+            opening_plies: DEFAULT_OPENING_PLIES,
+            seed: 1,
+            threads: None,
+            tt_size_mb: DEFAULT_HASH_SIZE,
+            move_limit: Some(DEFAULT_MOVE_LIMIT),
+        }
+    }
+
+    /// Sets the number of random moves every game opens with
+    pub fn opening_plies(mut self, plies: usize) -> Self {
+        self.opening_plies = plies;
+        self
+    }
+
+    /// Sets the seed the random moves of every game are derived from
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the number of games played at once, one per core by default
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets the size of the transposition table of every game in megabytes
+    pub fn tt_size_mb(mut self, size_mb: usize) -> Self {
+        self.tt_size_mb = size_mb;
+        self
+    }
+
+    /// Sets the number of engine moves after which a game is called a draw
+    pub fn move_limit(mut self, limit: Option<usize>) -> Self {
+        self.move_limit = limit;
+        self
+    }
+
+    /// Returns the seed of the random moves of the `index`'th game
+    pub fn game_seed(&self, index: usize) -> u64 {
+        // splitmix64, so that neighbouring games get unrelated seeds
+        let mut z = self
+            .seed
+            .wrapping_add((index as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)) | 1 // the generator gets stuck on a zero seed
+    }
+
+    /// Plays the `index`'th game of the run
+    pub fn play(&self, index: usize) -> Result<SelfPlayGame, BuildError> {
+        let engine: Engine<Bitboard> = Engine::builder()
+            .threads(1)
+            .deterministic(true)
+            .tt_size_mb(self.tt_size_mb)
+            .build()?;
+
+        let seed = self.game_seed(index);
+        let clock = Clock::unlimited();
+        let mut random = RandomPlayer::with_seed(seed);
+        let mut board = Bitboard::default();
+        let mut opening = Vec::new();
+        let mut records = Vec::new();
+        while opening.len() < self.opening_plies && board.get_game_state() == GameState::InProgress
+        {
+            let action = random.choose(&board, &clock).unwrap();
+            board = board.take_action(action).unwrap();
+            opening.push(action);
+            records.push(Player::<Bitboard>::record(&random));
+        }
+
+        // both sides search with the same engine, so they share its table
+        let positions = RefCell::new(Vec::new());
+        let mut black = Recorder {
+            player: EnginePlayer::new(engine.clone(), self.constraint),
+            positions: &positions,
+        };
+        let mut white = Recorder {
+            player: EnginePlayer::new(engine, self.constraint),
+            positions: &positions,
+        };
+        let game = players::play_game(
+            board,
+            &mut black,
+            &mut white,
+            &mut Clock::unlimited(),
+            self.move_limit,
+        );
+
+        let samples = positions
+            .into_inner()
+            .into_iter()
+            .map(|(board, score)| Sample::new(board, score, game.winner()))
+            .collect();
+        records.extend_from_slice(game.records());
+        Ok(SelfPlayGame {
+            seed,
+            opening,
+            records,
+            game,
+            samples,
+        })
+    }
+
+    /// Plays every game of the run, calling `progress` with the number of games done
+    /// after each one. Returns the games in order.
+    pub fn generate<F>(&self, progress: F) -> Result<Vec<SelfPlayGame>, BuildError>
+    where
+        F: Fn(usize) + Sync,
+    {
+        let mut pool = ThreadPoolBuilder::new();
+        if let Some(threads) = self.threads {
+            pool = pool.num_threads(threads);
+        }
+        let pool = pool.build().unwrap();
+
+        // games finish out of order, but the count only goes up
+        let done = Mutex::new(0);
+        pool.install(|| {
+            (0..self.games)
+                .into_par_iter()
+                .map(|index| {
+                    let game = self.play(index)?;
+                    let mut done = done.lock().unwrap();
+                    *done += 1;
+                    progress(*done);
+                    Ok(game)
+                })
+                .collect()
+        })
+    }
+}
+
+// an engine player that keeps every position it moves in along with its score
+struct Recorder<'a> {
+    player: EnginePlayer<Bitboard>,
+    positions: &'a RefCell<Vec<(Bitboard, Score)>>,
+}
+
+impl Player<Bitboard> for Recorder<'_> {
+    fn choose(&mut self, state: &Bitboard, clock: &Clock) -> Option<Action> {
+        let action = self.player.choose(state, clock)?;
+        let score = *self.player.scores().last().unwrap();
+        self.positions.borrow_mut().push((*state, score));
+        Some(action)
+    }
+
+    fn record(&self) -> Option<MoveRecord> {
+        self.player.record()
+    }
+}
+
+/// Represents a game the engine played against itself
+#[derive(Debug)]
+pub struct SelfPlayGame {
+    seed: u64,
+    opening: Vec<Action>,
+    records: Vec<Option<MoveRecord>>, // of the opening, then of the game
+    game: PlayedGame<Bitboard>,
+    samples: Vec<Sample>,
+}
+
+impl SelfPlayGame {
+    /// Returns the seed the random moves of the game came from
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the random moves the game opened with
+    #[inline]
+    pub fn opening(&self) -> &[Action] {
+        &self.opening
+    }
+
+    /// Returns how every move of the game was chosen, from the random ones of the
+    /// opening on
+    #[inline]
+    pub fn records(&self) -> &[Option<MoveRecord>] {
+        &self.records
+    }
+
+    /// Returns the game the engine played from the opening on
+    #[inline]
+    pub fn game(&self) -> &PlayedGame<Bitboard> {
+        &self.game
+    }
+
+    /// Returns a sample of every position the engine moved in
+    #[inline]
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+}
+
+// redraws a progress bar on stderr
+fn draw_progress(done: usize, total: usize) {
+    const WIDTH: usize = 40;
+    let filled = done * WIDTH / total.max(1);
+    eprint!(
+        "\r[{}{}] {}/{} games",
+        "#".repeat(filled),
+        " ".repeat(WIDTH - filled),
+        done,
+        total
+    );
+    if done == total {
+        eprintln!();
+    }
+}
+
+/// Plays `games` games searching every move to `depth` on every core, showing progress
+/// on stderr, and writes their samples to the file at `path` in the binary dataset format
+pub fn run(games: usize, depth: u32, path: &str) -> io::Result<()> {
+    let file = File::create(path)?;
+    let games = SelfPlay::new(games, SearchConstraint::Depth(depth))
+        .generate(|done| draw_progress(done, games))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+
+    let samples: Vec<_> = games
+        .iter()
+        .flat_map(|game| game.samples().iter().copied())
+        .collect();
+    dataset::write_binary(&samples, BufWriter::new(file))?;
+    eprintln!("wrote {} positions to {}", samples.len(), path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::players::Ending;
+
+    #[test]
+    fn selfplay_test() {
+        let selfplay = SelfPlay::new(4, SearchConstraint::Depth(2))
+            .opening_plies(4)
+            .seed(7)
+            .tt_size_mb(1)
+            .move_limit(Some(60));
+
+        // the games are the same however many threads play them
+        let games = selfplay.clone().threads(3).generate(|_| ()).unwrap();
+        let serial = selfplay.threads(1).generate(|_| ()).unwrap();
+        for (game, other) in games.iter().zip(&serial) {
+            assert_eq!(game.opening(), other.opening());
+            assert_eq!(game.game().actions(), other.game().actions());
+        }
+        assert_ne!(games[0].opening(), games[1].opening());
+
+        for game in &games {
+            assert_eq!(game.opening().len(), 4);
+            assert_eq!(game.samples().len(), game.game().actions().len());
+            let records = game.records();
+            assert_eq!(records.len(), 4 + game.game().actions().len());
+            assert_eq!(records[0].unwrap().seed(), Some(game.seed()));
+            assert_eq!(records[4].unwrap().depth(), Some(2));
+            if game.game().ending() == Ending::MoveLimit {
+                assert!(game.samples().iter().all(|sample| sample.result() == 0.5));
+            }
+        }
+
+        // progress is reported once per game
+        let calls = Mutex::new(Vec::new());
+        SelfPlay::new(3, SearchConstraint::Depth(1))
+            .threads(2)
+            .move_limit(Some(10))
+            .generate(|done| calls.lock().unwrap().push(done))
+            .unwrap();
+        assert_eq!(calls.into_inner().unwrap(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,446 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::app::{json_actions, json_score};
+use crate::board::Bitboard;
+use crate::search::{self, default_threads, Engine, SearchConstraint, Searchable};
+
+// a small http server answering analysis requests with json. connections are handed to
+// a fixed number of workers, each running one search at a time on the shared engine, so
+// busy periods queue up instead of fighting over the cores. the queue is bounded as well
+// and connections arriving while it is full are turned away. anyone who can reach the
+// server can ask for a search, so every request is bounded: in size, and in how deep or
+// long it searches
+
+// connections waiting for a worker before new ones are turned away
+const QUEUE_SIZE: usize = 32;
+// the most bytes of the request line and the headers together
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+const MAX_BODY_SIZE: usize = 64 * 1024;
+// the deepest and longest searches a request gets, deeper or longer ones are cut down
+const MAX_DEPTH: u32 = 14;
+const MAX_TIME: u32 = 10_000;
+// how long a worker waits on a client that stopped sending
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const PV_LENGTH: usize = 8;
+
+/// Represents a parsed http request
+#[derive(Debug, Clone, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    body: String,
+}
+
+/// Represents the status and json body sent back for a request
+#[derive(Debug, Clone, PartialEq)]
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+
+    fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            503 => "Service Unavailable",
+            _ => "",
+        };
+        let body = self.body.to_string();
+
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            body.len(),
+            body
+        )?;
+        writer.flush()
+    }
+}
+
+/// Decodes the `%XX` escapes and `+` spaces of a url component
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut iter = text.bytes();
+
+    while let Some(b) = iter.next() {
+        match b {
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(b),
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Returns the decoded value of a parameter of a query string
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| percent_decode(value))
+}
+
+/// Reads a request from a client. Returns an error response for requests we cannot read.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let too_large = || Ok(Err(Response::error(431, "request headers too large")));
+
+    // the request line and the headers are read through a limit, so a client cannot make
+    // us buffer a line without end. a line cut off by the limit has no newline
+    let mut head = reader.by_ref().take(MAX_HEADER_SIZE as u64);
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    if !line.ends_with('\n') && head.limit() == 0 {
+        return too_large();
+    }
+
+    let mut words = line.split_whitespace();
+    let (method, target) = match (words.next(), words.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target),
+        _ => return Ok(Err(Response::error(400, "malformed request line"))),
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (path, query) = (path.to_string(), query.to_string());
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 {
+            break;
+        }
+        if !line.ends_with('\n') && head.limit() == 0 {
+            return too_large();
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = match value.trim().parse() {
+                    Ok(length) => length,
+                    Err(_) => return Ok(Err(Response::error(400, "invalid content length"))),
+                };
+            }
+        }
+    }
+
+    if length > MAX_BODY_SIZE {
+        return Ok(Err(Response::error(413, "request body too large")));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = match String::from_utf8(body) {
+        Ok(body) => body,
+        Err(_) => return Ok(Err(Response::error(400, "request body is not utf-8"))),
+    };
+
+    Ok(Ok(Request {
+        method,
+        path,
+        query,
+        body,
+    }))
+}
+
+/// Reads the position from a fen, or from a board packed in base64
+fn parse_board(fen: Option<&str>, packed: Option<&str>) -> Result<Bitboard, Response> {
+    match (fen, packed) {
+        (Some(fen), _) => Bitboard::from_fen(fen),
+        (None, Some(packed)) => Bitboard::from_base64(packed),
+        (None, None) => return Err(Response::error(400, "missing fen")),
+    }
+    .map_err(|err| Response::error(400, &err.to_string()))
+}
+
+/// Answers `GET /legal?fen=...` or `GET /legal?board=...` with the legal actions of the
+/// position
+fn legal(query: &str) -> Result<Response, Response> {
+    let board = parse_board(
+        query_param(query, "fen").as_deref(),
+        query_param(query, "board").as_deref(),
+    )?;
+    let actions: Vec<_> = board
+        .generate_all_actions()
+        .iter()
+        .map(|p| *p.action())
+        .collect();
+    Ok(Response::ok(json!({ "actions": json_actions(&actions) })))
+}
+
+/// Answers `POST /analyze` with a search of the position in the json body. The body
+/// holds the `fen` or the packed `board` and either a `depth` or a `time` in
+/// milliseconds, cut down to `MAX_DEPTH` and `MAX_TIME`.
+fn analyze(engine: &Engine<Bitboard>, body: &str) -> Result<Response, Response> {
+    let body: Value =
+        serde_json::from_str(body).map_err(|_| Response::error(400, "body is not json"))?;
+    let board = parse_board(body["fen"].as_str(), body["board"].as_str())?;
+
+    let number = |key| match &body[key] {
+        Value::Null => Ok(None),
+        value => match value.as_u64() {
+            Some(n) if n <= u64::from(u32::MAX) => Ok(Some(n as u32)),
+            _ => Err(Response::error(400, &format!("invalid {}", key))),
+        },
+    };
+    let constraint = match (number("depth")?, number("time")?) {
+        (Some(_), Some(_)) => return Err(Response::error(400, "give a depth or a time, not both")),
+        (Some(depth), None) => SearchConstraint::depth(depth.min(MAX_DEPTH)),
+        (None, Some(time)) => SearchConstraint::time(time.min(MAX_TIME)),
+        // a search without either would hold a worker for as long as it likes
+        (None, None) => return Err(Response::error(400, "give a depth or a time")),
+    }
+    .map_err(|err| Response::error(400, err))?;
+
+    let (future, _) = engine.search_async(&board, constraint);
+    let result = search::block_on(future);
+
+    let (best, score, pv) = match result.best() {
+        Some(best) => {
+            let action = best.action();
+            let mut pv = vec![action];
+            let next = board.take_action(action).unwrap();
+            pv.extend(engine.principal_variation(&next, PV_LENGTH - 1));
+            (json!(action.to_string()), json_score(best.score()), pv)
+        }
+        None => (Value::Null, Value::Null, Vec::new()),
+    };
+
+    Ok(Response::ok(json!({
+        "best": best,
+        "score": score,
+        "pv": json_actions(&pv),
+        "depth": result.depth(),
+        "max_depth": result.max_depth(),
+        "nodes": result.nodes(),
+        "time": result.elapsed().as_secs_f64(),
+        "nps": result.nps(),
+        "tt_hit_rate": result.tt_hit_rate(),
+        "tb_hits": result.tb_hits(),
+        "stopped_early": result.stopped_early(),
+    })))
+}
+
+fn route(engine: &Engine<Bitboard>, request: &Request) -> Response {
+    let response = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/legal") => legal(&request.query),
+        ("POST", "/analyze") => analyze(engine, &request.body),
+        (_, "/legal") | (_, "/analyze") => Err(Response::error(405, "method not allowed")),
+        _ => Err(Response::error(404, "not found")),
+    };
+    response.unwrap_or_else(|err| err)
+}
+
+fn handle(engine: &Engine<Bitboard>, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+
+    let response = match read_request(&mut reader)? {
+        Ok(request) => route(engine, &request),
+        Err(response) => response,
+    };
+    response.write_to(&stream)
+}
+
+/// Serves analysis over http. Positions are given as fen strings, or as boards packed
+/// in base64 (see `Bitboard::to_base64`) under `board` instead of `fen`, and actions come
+/// back as movetext.
+///
+/// * `GET /legal?fen=...` lists the legal actions of a position
+/// * `POST /analyze` searches the position of a json body like
+///   `{"fen": "B:W18,24:B14", "time": 500}`, which can have a `depth` instead of the
+///   `time` in milliseconds. One of them is required, and they are cut down to a depth
+///   of 14 and ten seconds. It answers with the best action, its score, the principal
+///   variation and the statistics of the search.
+pub struct Server {
+    listener: TcpListener,
+    engine: Arc<Engine<Bitboard>>,
+    workers: usize,
+}
+
+impl Server {
+    /// Listens on `address` with a worker for every core
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Server {
+            listener: TcpListener::bind(address)?,
+            engine: Arc::new(Engine::new()),
+            workers: default_threads(),
+        })
+    }
+
+    /// Returns the address the server listens on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answers requests until accepting a connection fails
+    pub fn run(self) -> io::Result<()> {
+        let (sender, receiver) = mpsc::sync_channel::<TcpStream>(QUEUE_SIZE);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for _ in 0..self.workers {
+            let receiver = receiver.clone();
+            let engine = self.engine.clone();
+
+            thread::spawn(move || loop {
+                let stream = receiver.lock().unwrap().recv();
+                match stream {
+                    // one bad client is no reason to stop serving the others
+                    Ok(stream) => {
+                        let _ = handle(&engine, stream);
+                    }
+                    Err(_) => break,
+                }
+            });
+        }
+
+        for stream in self.listener.incoming() {
+            match sender.try_send(stream?) {
+                Ok(()) => (),
+                Err(TrySendError::Full(stream)) => {
+                    let _ = Response::error(503, "too many requests").write_to(&stream);
+                }
+                Err(TrySendError::Disconnected(_)) => break,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serves analysis over http on `port` until the process is killed
+pub fn run(port: u16) -> io::Result<()> {
+    let server = Server::bind(("0.0.0.0", port))?;
+    eprintln!("listening on {}", server.local_addr()?);
+    server.run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn request(method: &str, target: &str, body: &str) -> Request {
+        let text = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            target,
+            body.len(),
+            body
+        );
+        read_request(&mut text.as_bytes()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn route_test() {
+        let engine = Engine::new();
+
+        let legal = request("GET", "/legal?fen=B%3AW18%2C24%3AB14", "");
+        assert_eq!(legal.path, "/legal");
+        assert_eq!(
+            route(&engine, &legal),
+            Response::ok(json!({ "actions": ["14-23"] }))
+        );
+        let packed = Bitboard::from_fen("B:W18,24:B14").unwrap().to_base64();
+        let legal = request("GET", &format!("/legal?board={}", packed), "");
+        assert_eq!(
+            route(&engine, &legal),
+            Response::ok(json!({ "actions": ["14-23"] }))
+        );
+
+        let analyze = request("POST", "/analyze", r#"{"fen": "B:W18:B14", "depth": 3}"#);
+        let response = route(&engine, &analyze);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["best"], "14-23");
+        assert_eq!(response.body["pv"][0], "14-23");
+        assert_eq!(response.body["depth"], 3);
+        assert!(response.body["nodes"].as_u64().unwrap() > 0);
+
+        let both = request(
+            "POST",
+            "/analyze",
+            r#"{"fen": "B:W18:B14", "depth": 3, "time": 5}"#,
+        );
+        assert_eq!(route(&engine, &both).status, 400);
+
+        // searches have to be bounded, and are kept within the server's bounds
+        let unbounded = request("POST", "/analyze", r#"{"fen": "B:W18:B14"}"#);
+        assert_eq!(route(&engine, &unbounded).status, 400);
+        let deep = request("POST", "/analyze", r#"{"fen": "B:W18:B14", "depth": 1000}"#);
+        assert_eq!(route(&engine, &deep).body["depth"], MAX_DEPTH);
+
+        assert_eq!(route(&engine, &request("POST", "/legal", "")).status, 405);
+        assert_eq!(route(&engine, &request("GET", "/legal", "")).status, 400);
+        assert_eq!(route(&engine, &request("GET", "/", "")).status, 404);
+    }
+
+    #[test]
+    fn read_request_test() {
+        let read = |text: String| read_request(&mut text.as_bytes()).unwrap();
+
+        let long = format!(
+            "GET /legal?fen={} HTTP/1.1\r\n\r\n",
+            "W".repeat(MAX_HEADER_SIZE)
+        );
+        assert_eq!(read(long).unwrap_err().status, 431);
+
+        // the limit holds for the headers together, however short each of them is
+        let header = "X-Padding: 0123456789\r\n".repeat(MAX_HEADER_SIZE / 20);
+        let padded = format!("GET /legal HTTP/1.1\r\n{}\r\n", header);
+        assert_eq!(read(padded).unwrap_err().status, 431);
+
+        let body = format!(
+            "POST /analyze HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+        assert_eq!(read(body).unwrap_err().status, 413);
+        assert!(read("GET /legal HTTP/1.1\r\nHost: localhost\r\n\r\n".to_string()).is_ok());
+    }
+
+    #[test]
+    fn server_test() {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /legal?fen=W:W:B1 HTTP/1.1\r\n\r\n").unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+
+        assert!(reply.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(reply.ends_with(r#"{"actions":[]}"#));
+    }
+}
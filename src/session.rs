@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Action, Bitboard};
+use crate::error::SessionError;
+use crate::options::EngineOptions;
+use crate::pdn::Game;
+use crate::search::{Engine, TableEntry};
+
+// a session is what the REPL needs to carry on with an analysis later: the game on the
+// board, the engine options and, if asked for, the transposition table. sessions are
+// written as json along with the version of their format, so that a file written by
+// another version is turned down instead of being read wrong. boards are packed, which
+// keeps files with large tables small. version 1 wrote them as fens, and version 2 kept
+// no bounds with the scores of the table
+
+/// The version of the session format this build reads and writes
+pub const VERSION: u32 = 3;
+
+/// Represents a saved analysis session
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Action;
+/// use muskox::options::EngineOptions;
+/// use muskox::pdn::Game;
+/// use muskox::session::Session;
+///
+/// let mut game = Game::default();
+/// game.take_action(Action::from_movetext("11-15").unwrap()).unwrap();
+/// let mut options = EngineOptions::default();
+/// options.set("depth", "8").unwrap();
+///
+/// let session = Session::new(&game, &options);
+/// let restored = Session::from_json(&session.to_json()).unwrap();
+/// assert_eq!(restored.game().unwrap().actions(), game.actions());
+/// assert_eq!(restored.options().unwrap(), options);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    version: u32,
+    start: Bitboard,
+    actions: Vec<Action>,
+    move_limit: Option<usize>,
+    options: BTreeMap<String, String>,
+    table: Option<SavedTable>,
+}
+
+/// Represents the transposition table of a session along with its size, as entries only
+/// go back into a table of the same size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedTable {
+    hash_size: usize,
+    entries: Vec<TableEntry<Bitboard>>,
+}
+
+// the part of every version of the format that says which version it is
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+impl Session {
+    /// Creates a session of `game` played with `options`, without the transposition table
+    pub fn new(game: &Game, options: &EngineOptions) -> Self {
+        Session {
+            version: VERSION,
+            start: game.start(),
+            actions: game.actions().to_vec(),
+            move_limit: game.move_limit(),
+            options: options
+                .values()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+            table: None,
+        }
+    }
+
+    /// Keeps the transposition table of `engine` in the session as well. Large tables
+    /// make for large files.
+    pub fn with_table(mut self, engine: &Engine<Bitboard>) -> Self {
+        self.table = Some(SavedTable {
+            hash_size: engine.hash_size(),
+            entries: engine.table_entries(),
+        });
+        self
+    }
+
+    /// Returns whether the session holds a transposition table
+    #[inline]
+    pub fn has_table(&self) -> bool {
+        self.table.is_some()
+    }
+
+    /// Replays the game of the session
+    pub fn game(&self) -> Result<Game, SessionError> {
+        let mut game = Game::new(self.start);
+        game.set_move_limit(self.move_limit);
+        for action in &self.actions {
+            game.take_action(*action)
+                .map_err(|_| SessionError::SessionMoveError {
+                    movetext: action.to_string(),
+                })?;
+        }
+        Ok(game)
+    }
+
+    /// Returns the options of the session. Options it leaves out keep their defaults.
+    pub fn options(&self) -> Result<EngineOptions, SessionError> {
+        let mut options = EngineOptions::default();
+        for (name, value) in &self.options {
+            options
+                .set(name, value)
+                .map_err(|source| SessionError::SessionOptionError { source })?;
+        }
+        Ok(options)
+    }
+
+    /// Puts the transposition table of the session into `engine`. Returns false if the
+    /// session has none or the table of `engine` is of another size.
+    pub fn restore_table(&self, engine: &Engine<Bitboard>) -> bool {
+        match &self.table {
+            Some(table) if table.hash_size == engine.hash_size() => {
+                engine.restore_table(&table.entries)
+            }
+            _ => false,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Reads a session from json, checking the version of its format first
+    pub fn from_json(text: &str) -> Result<Self, SessionError> {
+        let format_error = |err: serde_json::Error| SessionError::SessionFormatError {
+            message: err.to_string(),
+        };
+
+        let header: Header = serde_json::from_str(text).map_err(format_error)?;
+        if header.version != VERSION {
+            return Err(SessionError::SessionVersionError {
+                version: header.version,
+                expected: VERSION,
+            });
+        }
+
+        serde_json::from_str(text).map_err(format_error)
+    }
+
+    /// Writes the session to the file at `path`
+    pub fn save(&self, path: &str) -> Result<(), SessionError> {
+        fs::write(path, self.to_json()).map_err(|source| SessionError::SessionIoError {
+            path: path.to_string(),
+            source,
+        })
+    }
+
+    /// Reads a session from the file at `path`
+    pub fn load(path: &str) -> Result<Self, SessionError> {
+        let text = fs::read_to_string(path).map_err(|source| SessionError::SessionIoError {
+            path: path.to_string(),
+            source,
+        })?;
+        Self::from_json(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchConstraint;
+
+    #[test]
+    fn session_test() {
+        let mut game = Game::new(Bitboard::from_fen("W:W18,K30:B14,K1").unwrap());
+        game.set_move_limit(Some(80));
+        game.take_action(Action::from_movetext("18-9").unwrap())
+            .unwrap();
+
+        let engine = Engine::builder().threads(1).tt_size_mb(1).build().unwrap();
+        engine.search(&game.board(), &SearchConstraint::Depth(6));
+        let mut options = EngineOptions::default();
+        options.set("hash", "1").unwrap();
+
+        let session = Session::new(&game, &options).with_table(&engine);
+        let restored = Session::from_json(&session.to_json()).unwrap();
+        let replayed = restored.game().unwrap();
+        assert_eq!(replayed.board(), game.board());
+        assert_eq!(replayed.move_limit(), Some(80));
+        assert_eq!(restored.options().unwrap(), options);
+
+        // the table comes back only into a table of the same size
+        let fresh = Engine::builder().threads(1).tt_size_mb(1).build().unwrap();
+        assert!(restored.restore_table(&fresh));
+        let line = engine.principal_variation(&game.board(), 3);
+        assert!(!line.is_empty());
+        assert_eq!(fresh.principal_variation(&game.board(), 3), line);
+        let larger = Engine::builder().threads(1).tt_size_mb(2).build().unwrap();
+        assert!(!restored.restore_table(&larger));
+
+        // other versions of the format are turned down
+        let newer = session.to_json().replace("\"version\":3", "\"version\":4");
+        assert!(matches!(
+            Session::from_json(&newer),
+            Err(SessionError::SessionVersionError { version: 4, .. })
+        ));
+        assert!(Session::from_json("{}").is_err());
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::board::Color;
+use crate::error::TournamentError;
+use crate::openings::ballot;
+use crate::search::Winner;
+use crate::tournament::{EntrantConfig, Mode, Pairing, Record, Tournament};
+
+// a sequential probability ratio test tells whether a change made the engine stronger
+// in as few games as it can. the new engine plays the base engine in pairs of games
+// from the same ballot, and after every pair the log likelihood ratio of the results
+// under two hypotheses is worked out: h0, that the new engine is elo0 stronger, and h1,
+// that it is elo1 stronger. the match goes on until the ratio crosses one of two
+// bounds set by the chances of a false positive (alpha) and of a false negative (beta).
+// the ratio uses the normal approximation of the trinomial model, so that draws count
+// as the half points they are
+
+const DEFAULT_ALPHA: f64 = 0.05;
+const DEFAULT_BETA: f64 = 0.05;
+const DEFAULT_SECONDS: u64 = 10;
+
+/// Represents the hypotheses a test chooses between
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hypothesis {
+    /// The new engine is no more than `elo0` stronger
+    H0,
+    /// The new engine is at least `elo1` stronger
+    H1,
+}
+
+impl fmt::Display for Hypothesis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Hypothesis::H0 => write!(f, "H0"),
+            Hypothesis::H1 => write!(f, "H1"),
+        }
+    }
+}
+
+/// Represents the bounds of a sequential probability ratio test
+///
+/// # Examples
+///
+/// ```
+/// use muskox::sprt::{Hypothesis, Sprt};
+/// use muskox::tournament::Record;
+///
+/// let sprt = Sprt::new(0., 5.);
+/// assert_eq!(sprt.verdict(&Record::new(10, 20, 10)), None);
+/// assert_eq!(sprt.verdict(&Record::new(600, 800, 400)), Some(Hypothesis::H1));
+/// assert_eq!(sprt.verdict(&Record::new(400, 800, 600)), Some(Hypothesis::H0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    elo0: f64,
+    elo1: f64,
+    alpha: f64,
+    beta: f64,
+}
+
+impl Sprt {
+    /// Creates a test of whether a change gains `elo1` rather than `elo0`, with five
+    /// percent chances of either kind of error
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Sprt {
+            elo0,
+            elo1,
+            alpha: DEFAULT_ALPHA,
+            beta: DEFAULT_BETA,
+        }
+    }
+
+    /// Sets the chance of accepting H1 when H0 holds
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the chance of accepting H0 when H1 holds
+    pub fn beta(mut self, beta: f64) -> Self {
+        self.beta = beta;
+        self
+    }
+
+    /// Returns the bounds of the log likelihood ratio, below which H0 is accepted and
+    /// above which H1 is
+    pub fn bounds(&self) -> (f64, f64) {
+        let lower = (self.beta / (1. - self.alpha)).ln();
+        let upper = ((1. - self.beta) / self.alpha).ln();
+        (lower, upper)
+    }
+
+    /// Returns the log likelihood ratio of H1 against H0 given the record of the new
+    /// engine
+    pub fn llr(&self, record: &Record) -> f64 {
+        let games = f64::from(record.games());
+        if record.games() == 0 {
+            return 0.;
+        }
+
+        let score = record.points() / games;
+        let squares = (f64::from(record.wins()) + f64::from(record.draws()) / 4.) / games;
+        let variance = squares - score * score;
+        if variance <= 0. {
+            return 0.;
+        }
+
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        (score1 - score0) * (2. * score - score0 - score1) / (2. * variance / games)
+    }
+
+    /// Returns the hypothesis the record of the new engine settles on, if it does yet
+    pub fn verdict(&self, record: &Record) -> Option<Hypothesis> {
+        let llr = self.llr(record);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(Hypothesis::H1)
+        } else if llr <= lower {
+            Some(Hypothesis::H0)
+        } else {
+            None
+        }
+    }
+
+    fn validate(&self) -> Result<(), TournamentError> {
+        let invalid = |message: &str| TournamentError::SprtArgumentError {
+            message: message.to_string(),
+        };
+        if self.elo0 >= self.elo1 || self.elo0.is_nan() || self.elo1.is_nan() {
+            return Err(invalid("elo0 has to be below elo1"));
+        }
+        let chance = |p: f64| p > 0. && p < 1.;
+        if !chance(self.alpha) || !chance(self.beta) {
+            return Err(invalid("alpha and beta have to be between 0 and 1"));
+        }
+        Ok(())
+    }
+}
+
+// the score expected of the side `elo` stronger
+fn expected_score(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+/// Represents a match between a new engine and a base engine played until a test is
+/// settled
+pub struct SprtMatch {
+    sprt: Sprt,
+    tournament: Tournament,
+    max_games: Option<usize>,
+}
+
+impl SprtMatch {
+    /// Creates a match between `new` and `base` settled by `sprt`, with ten seconds a
+    /// side for every game and no limit on the number of games
+    pub fn new(
+        sprt: Sprt,
+        new: EntrantConfig,
+        base: EntrantConfig,
+    ) -> Result<Self, TournamentError> {
+        sprt.validate()?;
+        let tournament = Tournament::new(Mode::RoundRobin, vec![new, base])?
+            .seconds(DEFAULT_SECONDS)
+            .ballot_count(ballot::deck().len());
+        Ok(SprtMatch {
+            sprt,
+            tournament,
+            max_games: None,
+        })
+    }
+
+    /// Sets the time each side gets for a game
+    pub fn seconds(mut self, seconds: u64) -> Self {
+        self.tournament = self.tournament.seconds(seconds);
+        self
+    }
+
+    /// Sets the seed the order of the ballots is drawn with
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.tournament = self.tournament.seed(seed);
+        self
+    }
+
+    /// Sets the file the games are appended to
+    pub fn pdn(mut self, path: Option<String>) -> Self {
+        self.tournament = self.tournament.pdn(path);
+        self
+    }
+
+    /// Sets the number of games after which the match stops if the test is not settled.
+    /// Games are played in pairs, so an odd limit is rounded up.
+    pub fn max_games(mut self, games: Option<usize>) -> Self {
+        self.max_games = games;
+        self
+    }
+
+    /// Plays pairs of games until the test is settled, calling `progress` with the record
+    /// of the new engine and the log likelihood ratio after every game
+    pub fn run<F>(&self, mut progress: F) -> Result<SprtOutcome, TournamentError>
+    where
+        F: FnMut(&Record, f64),
+    {
+        let mut entrants = self.tournament.entrants()?;
+        let mut record = Record::default();
+        let mut round = 0;
+
+        // the deck comes around again in long matches
+        for ballot in self.tournament.ballots().into_iter().cycle() {
+            if let Some(verdict) = self.sprt.verdict(&record) {
+                return Ok(self.outcome(record, Some(verdict)));
+            }
+            if matches!(self.max_games, Some(max) if round >= max) {
+                return Ok(self.outcome(record, None));
+            }
+
+            // the new engine is first, and plays either color from the ballot
+            for &(black, white) in &[(0, 1), (1, 0)] {
+                round += 1;
+                let pairing = Pairing::new(round, black, white, ballot);
+                let game = self.tournament.play(&mut entrants, &pairing, false)?;
+                self.tournament.append_pdn("muskox sprt", &pairing, &game)?;
+
+                let new_color = match black {
+                    0 => Color::Black,
+                    _ => Color::White,
+                };
+                record += match game.winner() {
+                    Winner::Player(color) if *color == new_color => Record::new(1, 0, 0),
+                    Winner::Player(_) => Record::new(0, 0, 1),
+                    Winner::Draw => Record::new(0, 1, 0),
+                };
+                progress(&record, self.sprt.llr(&record));
+            }
+        }
+        unreachable!("the ballots go around forever")
+    }
+
+    fn outcome(&self, record: Record, verdict: Option<Hypothesis>) -> SprtOutcome {
+        SprtOutcome {
+            record,
+            llr: self.sprt.llr(&record),
+            verdict,
+        }
+    }
+}
+
+/// Represents how a test ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SprtOutcome {
+    record: Record,
+    llr: f64,
+    verdict: Option<Hypothesis>,
+}
+
+impl SprtOutcome {
+    /// Returns the record of the new engine against the base engine
+    #[inline]
+    pub fn record(&self) -> Record {
+        self.record
+    }
+
+    #[inline]
+    pub fn llr(&self) -> f64 {
+        self.llr
+    }
+
+    /// Returns the hypothesis that was accepted, or nothing if the match ran out of games
+    #[inline]
+    pub fn verdict(&self) -> Option<Hypothesis> {
+        self.verdict
+    }
+}
+
+// reads an engine from the command line: `dxp=host:port` for a program over DamExchange,
+// or comma separated `name=value` options for muskox
+fn parse_engine(name: &str, spec: &str) -> Result<EntrantConfig, TournamentError> {
+    if let Some(address) = spec.strip_prefix("dxp=") {
+        return Ok(EntrantConfig::dxp(name, address));
+    }
+
+    let options = spec
+        .split(',')
+        .filter(|option| !option.is_empty())
+        .map(|option| {
+            let mut parts = option.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => Ok((name, value)),
+                _ => Err(TournamentError::SprtArgumentError {
+                    message: format!("{} is not an option of the form name=value", option),
+                }),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(EntrantConfig::engine(name, &options))
+}
+
+/// Runs a test from command line arguments, printing the record and the log likelihood
+/// ratio on stderr as games finish and the verdict on stdout. `--elo0`, `--elo1` and
+/// `--new` are required; `--base` defaults to muskox with its default options.
+pub fn run(args: &[&str]) -> Result<(), TournamentError> {
+    let invalid = |message: String| TournamentError::SprtArgumentError { message };
+    let mut flags = BTreeMap::new();
+    for pair in args.chunks(2) {
+        match pair {
+            [flag, value] if flag.starts_with("--") => {
+                flags.insert(&flag[2..], *value);
+            }
+            _ => return Err(invalid(format!("unexpected {}", pair.join(" ")))),
+        }
+    }
+
+    let known = [
+        "elo0", "elo1", "alpha", "beta", "new", "base", "pdn", "games", "seconds", "seed",
+    ];
+    if let Some(flag) = flags.keys().find(|flag| !known.contains(*flag)) {
+        return Err(invalid(format!("unknown flag --{}", flag)));
+    }
+
+    let number = |flag: &str| -> Result<Option<f64>, TournamentError> {
+        flags
+            .get(flag)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| invalid(format!("--{} needs a number, not {}", flag, value)))
+            })
+            .transpose()
+    };
+    let required = |flag: &str| invalid(format!("--{} is required", flag));
+
+    let elo0 = number("elo0")?.ok_or_else(|| required("elo0"))?;
+    let elo1 = number("elo1")?.ok_or_else(|| required("elo1"))?;
+    let sprt = Sprt::new(elo0, elo1)
+        .alpha(number("alpha")?.unwrap_or(DEFAULT_ALPHA))
+        .beta(number("beta")?.unwrap_or(DEFAULT_BETA));
+
+    let new = parse_engine("new", flags.get("new").ok_or_else(|| required("new"))?)?;
+    let base = parse_engine("base", flags.get("base").copied().unwrap_or(""))?;
+    let mut test = SprtMatch::new(sprt, new, base)?
+        .pdn(flags.get("pdn").map(|path| path.to_string()))
+        .max_games(number("games")?.map(|games| games as usize));
+    if let Some(seconds) = number("seconds")? {
+        test = test.seconds(seconds as u64);
+    }
+    if let Some(seed) = number("seed")? {
+        test = test.seed(seed as u64);
+    }
+    let (lower, upper) = sprt.bounds();
+    let outcome = test.run(|record, llr| {
+        let elo = match record.elo() {
+            Some((elo, margin)) => format!("{:+.1} +/- {:.1}", elo, margin),
+            None => "-".to_string(),
+        };
+        eprint!(
+            "\r{} games: +{} ={} -{}, elo {}, llr {:.2} ({:.2}, {:.2})   ",
+            record.games(),
+            record.wins(),
+            record.draws(),
+            record.losses(),
+            elo,
+            llr,
+            lower,
+            upper
+        );
+    })?;
+    eprintln!();
+
+    match outcome.verdict() {
+        Some(hypothesis) => println!(
+            "{} accepted after {} games (llr {:.2})",
+            hypothesis,
+            outcome.record().games(),
+            outcome.llr()
+        ),
+        None => println!(
+            "no verdict after {} games (llr {:.2})",
+            outcome.record().games(),
+            outcome.llr()
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprt_test() {
+        let sprt = Sprt::new(0., 10.);
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 1e-3);
+        assert!((lower + 2.944).abs() < 1e-3);
+
+        // an even record speaks for no gain, and a better one less so
+        let even = sprt.llr(&Record::new(50, 100, 50));
+        let better = sprt.llr(&Record::new(60, 100, 40));
+        assert!(even < 0. && better > even);
+        assert_eq!(sprt.llr(&Record::new(0, 10, 0)), 0.);
+        assert!(Sprt::new(5., 0.).validate().is_err());
+
+        // a match stops after its games, which come in pairs
+        let options = [("depth", "1"), ("threads", "1"), ("hash", "1")];
+        let test = SprtMatch::new(
+            Sprt::new(0., 5.),
+            EntrantConfig::engine("new", &[("depth", "3"), ("threads", "1"), ("hash", "1")]),
+            EntrantConfig::engine("base", &options),
+        )
+        .unwrap()
+        .max_games(Some(3))
+        .seed(5);
+        let mut calls = 0;
+        let outcome = test.run(|_, _| calls += 1).unwrap();
+        assert_eq!(calls, 4);
+        assert_eq!(outcome.record().games(), 4);
+        assert_eq!(outcome.llr(), Sprt::new(0., 5.).llr(&outcome.record()));
+
+        assert!(parse_engine("new", "depth=4,hash").is_err());
+        assert!(SprtMatch::new(
+            Sprt::new(0., 5.),
+            parse_engine("new", "depth=1001").unwrap(),
+            parse_engine("base", "").unwrap()
+        )
+        .is_err());
+        assert!(run(&["--elo0", "0", "--elo1", "5"]).is_err());
+        assert!(run(&["--elo0", "0", "--elo1", "5", "--new", "depth=2", "--bogus", "1"]).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+
+use crate::board::Direction;
+use crate::error::ParseError;
+
+/// Represents one of the 32 playable (dark) squares of a checkerboard.
+///
+/// Internally squares are indexed from 0 to 31 starting at the top left of the board
+/// (black's back rank), which is the same indexing the bitboard masks use. This type
+/// converts between that index, the standard PDN numbering (1 to 32), `(row, col)`
+/// coordinates on the 8x8 grid and algebraic names like `b6`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    /// Creates a square from an index that is already known to be on the board
+    #[inline]
+    pub(crate) fn new(index: u8) -> Self {
+        debug_assert!(index < 32);
+        Square(index)
+    }
+
+    /// Creates a square from its internal index (0 to 31).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Square;
+    ///
+    /// assert_eq!(Square::from_index(0).unwrap().pdn(), 1);
+    /// assert_eq!(Square::from_index(32), None);
+    /// ```
+    #[inline]
+    pub fn from_index(index: u8) -> Option<Self> {
+        match index {
+            0..=31 => Some(Square(index)),
+            _ => None,
+        }
+    }
+
+    /// Creates a square from its PDN number (1 to 32).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Square;
+    ///
+    /// assert_eq!(Square::from_pdn(32).unwrap().index(), 31);
+    /// assert_eq!(Square::from_pdn(0), None);
+    /// ```
+    #[inline]
+    pub fn from_pdn(number: u8) -> Option<Self> {
+        match number {
+            1..=32 => Some(Square(number - 1)),
+            _ => None,
+        }
+    }
+
+    /// Creates a square from a row and column on the 8x8 grid. Row 0 is the top of the
+    /// board and column 0 is the left edge. Returns `None` if the coordinates are off the
+    /// board or point at a light square.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Square;
+    ///
+    /// assert_eq!(Square::from_coords(0, 1).unwrap().pdn(), 1);
+    /// assert_eq!(Square::from_coords(0, 0), None);
+    /// ```
+    #[inline]
+    pub fn from_coords(row: u8, col: u8) -> Option<Self> {
+        if row > 7 || col > 7 || (row + col) & 1 == 0 {
+            return None;
+        }
+        Some(Square(row * 4 + col / 2))
+    }
+
+    /// Creates a square from its algebraic name such as `b8`. Files run from `a` to `h`
+    /// left to right and ranks from `1` to `8` bottom to top.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::Square;
+    ///
+    /// assert_eq!(Square::from_algebraic("b8").unwrap().pdn(), 1);
+    /// assert_eq!(Square::from_algebraic("g1").unwrap().pdn(), 32);
+    /// assert!(Square::from_algebraic("a8").is_err());
+    /// ```
+    pub fn from_algebraic(name: &str) -> Result<Self, ParseError> {
+        let bytes = name.as_bytes();
+
+        let err = || ParseError::SquareError {
+            square: name.to_string(),
+        };
+
+        if bytes.len() != 2 {
+            return Err(err());
+        }
+
+        let col = bytes[0].wrapping_sub(b'a');
+        let rank = bytes[1].wrapping_sub(b'1');
+
+        if col > 7 || rank > 7 {
+            return Err(err());
+        }
+
+        Square::from_coords(7 - rank, col).ok_or_else(err)
+    }
+
+    /// Returns the internal index (0 to 31) of the square
+    #[inline]
+    pub fn index(self) -> u8 {
+        self.0
+    }
+
+    /// Returns the PDN number (1 to 32) of the square
+    #[inline]
+    pub fn pdn(self) -> u8 {
+        self.0 + 1
+    }
+
+    /// Returns the row of the square where row 0 is the top of the board
+    #[inline]
+    pub fn row(self) -> u8 {
+        self.0 / 4
+    }
+
+    /// Returns the column of the square where column 0 is the left edge of the board
+    #[inline]
+    pub fn col(self) -> u8 {
+        // even rows start on a light square so their dark squares are shifted right
+        2 * (self.0 % 4) + (1 - self.row() % 2)
+    }
+
+    /// Returns the `(row, col)` pair of the square
+    #[inline]
+    pub fn coords(self) -> (u8, u8) {
+        (self.row(), self.col())
+    }
+
+    /// Returns the algebraic name of the square such as `b8`
+    pub fn algebraic(self) -> String {
+        let file = (b'a' + self.col()) as char;
+        let rank = 8 - self.row();
+        format!("{}{}", file, rank)
+    }
+
+    /// Returns the bitboard mask with only this square set
+    #[inline]
+    pub fn mask(self) -> u32 {
+        1 << self.0
+    }
+
+    /// Returns whether the square is on either back rank, where men get crowned
+    #[inline]
+    pub fn is_promotion_row(self) -> bool {
+        let row = self.row();
+        row == 0 || row == 7
+    }
+
+    /// Returns an iterator over all 32 squares in index order
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..32).map(Square)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pdn())
+    }
+}
+
+impl Direction {
+    /// Returns the square one step away from `square` in this direction, or `None` if
+    /// the step leaves the board.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{Direction, Square};
+    ///
+    /// let square = Square::from_pdn(1).unwrap();
+    /// assert_eq!(Direction::DownRight.apply(square), Square::from_pdn(6));
+    /// assert_eq!(Direction::UpLeft.apply(square), None);
+    /// ```
+    #[inline]
+    pub fn apply(&self, square: Square) -> Option<Square> {
+        self.relative_to(square.index()).map(Square)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_round_trip_test() {
+        for square in Square::all() {
+            assert_eq!(Square::from_index(square.index()), Some(square));
+            assert_eq!(Square::from_pdn(square.pdn()), Some(square));
+
+            let (row, col) = square.coords();
+            assert_eq!(Square::from_coords(row, col), Some(square));
+
+            let name = square.algebraic();
+            assert_eq!(Square::from_algebraic(&name).unwrap(), square);
+        }
+    }
+
+    #[test]
+    fn coords_test() {
+        assert_eq!(Square::from_pdn(1).unwrap().coords(), (0, 1));
+        assert_eq!(Square::from_pdn(4).unwrap().coords(), (0, 7));
+        assert_eq!(Square::from_pdn(5).unwrap().coords(), (1, 0));
+        assert_eq!(Square::from_pdn(29).unwrap().coords(), (7, 0));
+        assert_eq!(Square::from_pdn(32).unwrap().coords(), (7, 6));
+
+        assert_eq!(Square::from_coords(3, 3), None);
+        assert_eq!(Square::from_coords(8, 1), None);
+    }
+
+    #[test]
+    fn algebraic_test() {
+        assert_eq!(Square::from_pdn(1).unwrap().algebraic(), "b8");
+        assert_eq!(Square::from_pdn(5).unwrap().algebraic(), "a7");
+        assert_eq!(Square::from_pdn(29).unwrap().algebraic(), "a1");
+        assert_eq!(Square::from_pdn(32).unwrap().algebraic(), "g1");
+
+        assert!(Square::from_algebraic("b7").is_err());
+        assert!(Square::from_algebraic("i1").is_err());
+        assert!(Square::from_algebraic("b").is_err());
+    }
+
+    #[test]
+    fn direction_apply_test() {
+        let square = Square::from_pdn(15).unwrap();
+        assert_eq!(Direction::UpRight.apply(square), Square::from_pdn(11));
+        assert_eq!(Direction::DownLeft.apply(square), Square::from_pdn(18));
+
+        let square = Square::from_pdn(12).unwrap();
+        assert_eq!(Direction::DownRight.apply(square), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+
+use crate::board::{Bitboard, BoardEvaluator, Color, EvalBreakdown, Prng, RandomSpec};
+use crate::search::{GameState, Score, Searchable};
+
+// a board and its mirror, turned half way around with the colors swapped, are the same game
+// with black and white trading places, so a sound evaluation scores them opposite and so
+// does every one of its terms. a term that breaks this often only does in a few positions,
+// which is why the check is run over many random ones. tuning weights on an evaluation
+// that favors one color would only teach it the bias
+
+// random games from the start are this many plies long at most
+const MAX_PLIES: u64 = 60;
+
+/// Represents a term of an evaluation that did not come out opposite for a board and its
+/// mirror. The total is checked too, under the name `total`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asymmetry {
+    board: Bitboard,
+    term: &'static str,
+    score: Score,
+    mirrored: Score,
+}
+
+impl Asymmetry {
+    #[inline]
+    pub fn board(&self) -> &Bitboard {
+        &self.board
+    }
+
+    #[inline]
+    pub fn term(&self) -> &'static str {
+        self.term
+    }
+
+    /// Returns what the term adds to the evaluation of the board
+    #[inline]
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Returns what the term adds to the evaluation of the mirror of the board
+    #[inline]
+    pub fn mirrored(&self) -> Score {
+        self.mirrored
+    }
+}
+
+impl fmt::Display for Asymmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} is {} for {} but {} for its mirror",
+            self.term,
+            self.score,
+            self.board.fen(),
+            self.mirrored
+        )
+    }
+}
+
+/// Returns `count` random positions to check an evaluation with. Half of them have random
+/// pieces placed anywhere, as many of every kind for both sides, and the other half are
+/// reached by playing random moves from the start, which leaves one side ahead as often
+/// as not. The same seed always gives the same positions.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::symmetry;
+///
+/// let boards = symmetry::corpus(3, 10);
+/// assert_eq!(boards.len(), 10);
+/// assert_eq!(boards, symmetry::corpus(3, 10));
+/// ```
+pub fn corpus(seed: u64, count: usize) -> Vec<Bitboard> {
+    let mut prng = Prng::new(seed);
+    let mut boards = Vec::with_capacity(count);
+
+    while boards.len() < count {
+        let turn = match prng.rand64() % 2 {
+            0 => Color::Black,
+            _ => Color::White,
+        };
+        let board = match boards.len() % 2 {
+            0 => {
+                let spec = RandomSpec {
+                    men_per_side: (prng.rand64() % 9) as u32,
+                    kings_per_side: (prng.rand64() % 4) as u32,
+                    turn,
+                };
+                Bitboard::random(&mut prng, spec)
+            }
+            _ => random_game(&mut prng),
+        };
+        boards.extend(board);
+    }
+
+    boards
+}
+
+/// Plays random moves from the start, returning where the game got to unless it is over
+fn random_game(prng: &mut Prng) -> Option<Bitboard> {
+    let mut board = Bitboard::default();
+    for _ in 0..prng.rand64() % MAX_PLIES {
+        let pairs = board.generate_all_actions();
+        if pairs.is_empty() {
+            return None;
+        }
+        board = *pairs[(prng.rand64() % pairs.len() as u64) as usize].state();
+    }
+
+    match board.get_game_state() {
+        GameState::InProgress => Some(board),
+        GameState::Completed(_) => None,
+    }
+}
+
+/// Returns the terms of the evaluation of `board` that are not opposite to the same terms
+/// for its mirror
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::{current_evaluator, Bitboard};
+/// use muskox::symmetry;
+///
+/// let board = Bitboard::from_fen("B:W18,24,27,28,K10,K15:B12,16,20,K22,K25,K29").unwrap();
+/// assert!(symmetry::check(current_evaluator(), &board).is_empty());
+/// ```
+pub fn check(evaluator: &BoardEvaluator, board: &Bitboard) -> Vec<Asymmetry> {
+    let mirror = board.mirrored();
+    let mut asymmetries = compare(
+        board,
+        &evaluator.explain(board),
+        &evaluator.explain(&mirror),
+    );
+
+    let (score, mirrored) = (evaluator.eval(board), evaluator.eval(&mirror));
+    if score != Score::DRAW - mirrored {
+        asymmetries.push(Asymmetry {
+            board: *board,
+            term: "total",
+            score,
+            mirrored,
+        });
+    }
+    asymmetries
+}
+
+/// Checks every board of `boards`, returning everything found wrong with any of them
+pub fn check_all(evaluator: &BoardEvaluator, boards: &[Bitboard]) -> Vec<Asymmetry> {
+    boards
+        .iter()
+        .flat_map(|board| check(evaluator, board))
+        .collect()
+}
+
+/// Lines up the terms of a board and of its mirror by name. A term only one of the two
+/// has should have been worth nothing.
+fn compare(board: &Bitboard, ours: &EvalBreakdown, theirs: &EvalBreakdown) -> Vec<Asymmetry> {
+    let find = |breakdown: &EvalBreakdown, name: &str| {
+        breakdown
+            .terms()
+            .iter()
+            .find(|(term, _)| *term == name)
+            .map_or(Score::DRAW, |(_, score)| *score)
+    };
+
+    let mut names: Vec<_> = ours.terms().iter().map(|(name, _)| *name).collect();
+    for (name, _) in theirs.terms() {
+        if !names.contains(name) {
+            names.push(name);
+        }
+    }
+
+    names
+        .into_iter()
+        .filter_map(|term| {
+            let (score, mirrored) = (find(ours, term), find(theirs, term));
+            match score == Score::DRAW - mirrored {
+                true => None,
+                false => Some(Asymmetry {
+                    board: *board,
+                    term,
+                    score,
+                    mirrored,
+                }),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::current_evaluator;
+
+    #[test]
+    fn symmetry_test() {
+        let boards = corpus(17, 1000);
+        assert!(boards.iter().any(|b| b.turn() == Color::White));
+        assert!(boards
+            .iter()
+            .any(|b| b.blacks().count_ones() != b.whites().count_ones()));
+
+        let asymmetries = check_all(current_evaluator(), &boards);
+        let report: Vec<_> = asymmetries.iter().take(10).map(|a| a.to_string()).collect();
+        assert!(asymmetries.is_empty(), "{}", report.join("\n"));
+
+        // a term that forgets to change sides with the colors is caught
+        let board = Bitboard::default();
+        let ours = EvalBreakdown::new(vec![
+            ("material", Score::from(1.)),
+            ("turn", Score::from(0.5)),
+        ]);
+        let theirs = EvalBreakdown::new(vec![
+            ("material", Score::from(-1.)),
+            ("turn", Score::from(0.5)),
+        ]);
+        let asymmetries = compare(&board, &ours, &theirs);
+        assert_eq!(asymmetries.len(), 1);
+        assert_eq!(asymmetries[0].term(), "turn");
+        assert_eq!(
+            asymmetries[0].to_string(),
+            format!("turn is 0.5 for {} but 0.5 for its mirror", board.fen())
+        );
+
+        // as is one the mirror is missing
+        let theirs = EvalBreakdown::new(vec![("material", Score::from(-1.))]);
+        assert_eq!(compare(&board, &ours, &theirs)[0].mirrored(), Score::DRAW);
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::board::{Action, Bitboard};
+use crate::error::ParseError;
+use crate::pdn;
+use crate::search::{Engine, SearchConstraint};
+
+// test suites are files of positions with known best moves, one per line in the style of
+// chess epd files: a fen followed by operations separated by semicolons, such as
+// `B:W18,23:B9,14 bm 14-17; id W.01`. `bm` lists the moves that count as solutions and
+// `id` names the position. blank lines and lines starting with `#` are skipped
+
+/// Represents a position of a test suite along with its solutions
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestPosition {
+    id: String,
+    board: Bitboard,
+    best_moves: Vec<Action>,
+}
+
+impl TestPosition {
+    /// Returns the name of the position, or its line in the file if it has no `id`
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    #[inline]
+    pub fn board(&self) -> Bitboard {
+        self.board
+    }
+
+    /// Returns the moves that solve the position
+    #[inline]
+    pub fn best_moves(&self) -> &[Action] {
+        &self.best_moves
+    }
+}
+
+/// Parses the positions of a test suite
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::Action;
+/// use muskox::testsuite;
+///
+/// let suite = testsuite::parse_suite("B:W18:B14 bm 14x23; id first").unwrap();
+/// assert_eq!(suite[0].id(), "first");
+/// assert_eq!(suite[0].best_moves(), &[Action::from_movetext("14-23").unwrap()]);
+/// ```
+pub fn parse_suite(text: &str) -> Result<Vec<TestPosition>, ParseError> {
+    let mut suite = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let number = i + 1;
+
+        let mut operations = line.split(';').map(str::trim);
+        // the fen and the best moves are in the first operation
+        let (fen, moves) = operations
+            .next()
+            .and_then(|first| first.split_once(" bm "))
+            .ok_or(ParseError::SuiteError { line: number })?;
+
+        let board = Bitboard::from_fen(fen.trim())?;
+        let best_moves = moves
+            .split_whitespace()
+            .map(|movetext| {
+                pdn::find_action(&board, movetext).ok_or_else(|| ParseError::SuiteMoveError {
+                    line: number,
+                    movetext: movetext.to_string(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if best_moves.is_empty() {
+            return Err(ParseError::SuiteError { line: number });
+        }
+
+        let id = operations
+            .filter_map(|operation| operation.strip_prefix("id "))
+            .map(|id| id.trim().trim_matches('"').to_string())
+            .next()
+            .unwrap_or_else(|| format!("line {}", number));
+
+        suite.push(TestPosition {
+            id,
+            board,
+            best_moves,
+        });
+    }
+
+    Ok(suite)
+}
+
+/// Describes how the engine did on a position of a test suite
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestOutcome {
+    id: String,
+    found: Option<Action>,
+    solved: bool,
+    time_to_solution: Option<Duration>,
+}
+
+impl TestOutcome {
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the move the engine picked, if the position had any
+    #[inline]
+    pub fn found(&self) -> Option<Action> {
+        self.found
+    }
+
+    #[inline]
+    pub fn solved(&self) -> bool {
+        self.solved
+    }
+
+    /// Returns how long the search took to settle on a solution for good, if it did
+    #[inline]
+    pub fn time_to_solution(&self) -> Option<Duration> {
+        self.time_to_solution
+    }
+}
+
+/// Represents the outcome of every position of a test suite
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuiteReport {
+    outcomes: Vec<TestOutcome>,
+}
+
+impl SuiteReport {
+    #[inline]
+    pub fn outcomes(&self) -> &[TestOutcome] {
+        &self.outcomes
+    }
+
+    /// Returns the number of positions that were solved
+    pub fn solved(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.solved).count()
+    }
+
+    /// Returns the time it took to solve the solved positions altogether
+    pub fn total_time(&self) -> Duration {
+        self.outcomes
+            .iter()
+            .filter_map(|o| o.time_to_solution)
+            .sum()
+    }
+}
+
+impl fmt::Display for SuiteReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for outcome in &self.outcomes {
+            let found = match outcome.found {
+                Some(action) => action.to_string(),
+                None => "nothing".to_string(),
+            };
+            match outcome.time_to_solution {
+                Some(time) if outcome.solved => writeln!(
+                    f,
+                    "{}: solved with {} in {:.2}s",
+                    outcome.id,
+                    found,
+                    time.as_secs_f64()
+                )?,
+                _ => writeln!(f, "{}: unsolved, played {}", outcome.id, found)?,
+            }
+        }
+        write!(
+            f,
+            "solved {} of {} positions in {:.2}s",
+            self.solved(),
+            self.outcomes.len(),
+            self.total_time().as_secs_f64()
+        )
+    }
+}
+
+/// Searches every position of a suite under a constraint and checks the moves the engine
+/// picks against the solutions
+pub fn run(
+    engine: &Engine<Bitboard>,
+    suite: &[TestPosition],
+    constraint: &SearchConstraint,
+) -> SuiteReport {
+    // the positions have no history
+    let mut engine = engine.clone();
+    engine.set_history(&[]);
+
+    let outcomes = suite
+        .iter()
+        .map(|position| {
+            let solves = |action: &Action| position.best_moves.contains(action);
+
+            // the solution counts from the iteration the engine switched to it for good
+            let mut solved_at = None;
+            let stop = AtomicBool::new(false);
+            let result = engine.search_with(&position.board, constraint, &stop, |info| match info
+                .best()
+                .map(|p| p.action())
+            {
+                Some(action) if solves(&action) => {
+                    solved_at = solved_at.or_else(|| Some(info.elapsed()))
+                }
+                _ => solved_at = None,
+            });
+
+            let found = result.best().map(|p| p.action());
+            let solved = found.as_ref().is_some_and(solves);
+
+            TestOutcome {
+                id: position.id.clone(),
+                found,
+                solved,
+                // answers from the book come without iterations
+                time_to_solution: match solved {
+                    true => solved_at.or_else(|| Some(result.elapsed())),
+                    false => None,
+                },
+            }
+        })
+        .collect();
+
+    SuiteReport { outcomes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suite_test() {
+        let text = "# a capture and a quiet move
+B:W18:B14 bm 14x23; id capture
+
+B:W30:BK3 bm 3-7 3-8; id \"kings\"
+W:W32:B1 bm 32-27
+";
+        let suite = parse_suite(text).unwrap();
+        assert_eq!(suite.len(), 3);
+        assert_eq!(suite[1].id(), "kings");
+        assert_eq!(suite[1].best_moves().len(), 2);
+        assert_eq!(suite[2].id(), "line 5");
+
+        let report = run(&Engine::new(), &suite, &SearchConstraint::Depth(2));
+        assert!(report.outcomes()[0].solved());
+        assert!(report.outcomes()[0].time_to_solution().is_some());
+        assert!(report.to_string().contains("capture: solved with 14-23"));
+        assert!(report.to_string().ends_with(&format!(
+            "solved {} of 3 positions in {:.2}s",
+            report.solved(),
+            report.total_time().as_secs_f64()
+        )));
+
+        assert!(matches!(
+            parse_suite("B:W18:B14 bm 14-15"),
+            Err(ParseError::SuiteMoveError { line: 1, movetext }) if movetext == "14-15"
+        ));
+        assert!(matches!(
+            parse_suite("\nB:W18:B14; id x"),
+            Err(ParseError::SuiteError { line: 2 })
+        ));
+    }
+}
@@ -0,0 +1,929 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::board::{Action, Bitboard, Color, Prng, Variant};
+use crate::dxp::{Client, RemotePlayer};
+use crate::error::TournamentError;
+use crate::openings::ballot;
+use crate::options::EngineOptions;
+use crate::pdn::Game;
+use crate::players::{self, Clock, Ending, EnginePlayer, MoveRecord, Player};
+use crate::search::{Engine, Winner};
+
+// a tournament plays engines against each other from the openings of the ballot deck.
+// every pairing plays each ballot twice, once with either engine on black, so that an
+// unbalanced opening favours neither. engines are muskox engines with options of their
+// own, or programs reached over DamExchange. CheckerBoard engines are libraries that
+// would have to be loaded into the process, which muskox has no way of doing. games are
+// played one at a time on a clock, and a side that runs out of time loses. the games
+// are appended to a PDN file as they finish, and the tournament ends with a crosstable
+// rating every engine against the rest of the field
+
+const DEFAULT_SECONDS: u64 = 60;
+const DEFAULT_MOVE_LIMIT: usize = 300;
+// the number of moves DamExchange time controls are given for
+const DXP_MOVES: u16 = 75;
+
+/// Represents how the engines of a tournament are paired
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Every engine plays every other engine
+    #[default]
+    RoundRobin,
+    /// The first engine plays every other engine, which do not play each other
+    Gauntlet,
+}
+
+/// Represents an engine taking part in a tournament: either muskox with `options`, or the
+/// program at the DamExchange address `dxp`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntrantConfig {
+    name: String,
+    #[serde(default)]
+    options: BTreeMap<String, Value>,
+    dxp: Option<String>,
+}
+
+/// Represents the settings of a tournament, read from json
+///
+/// # Examples
+///
+/// ```
+/// use muskox::tournament::{Mode, Tournament};
+///
+/// let tournament = Tournament::from_json(
+///     r#"{
+///         "mode": "gauntlet",
+///         "engines": [
+///             {"name": "deep", "options": {"depth": 6}},
+///             {"name": "shallow", "options": {"depth": 2}},
+///             {"name": "remote", "dxp": "localhost:27531"}
+///         ],
+///         "seconds": 30,
+///         "ballots": 4
+///     }"#,
+/// )
+/// .unwrap();
+/// assert_eq!(tournament.mode(), Mode::Gauntlet);
+/// // two opponents, four ballots, both colors
+/// assert_eq!(tournament.schedule().len(), 16);
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tournament {
+    #[serde(default)]
+    mode: Mode,
+    engines: Vec<EntrantConfig>,
+    #[serde(default = "default_seconds")]
+    seconds: u64,
+    #[serde(default = "default_ballots")]
+    ballots: usize,
+    #[serde(default = "default_seed")]
+    seed: u64,
+    pdn: Option<String>,
+    #[serde(default = "default_move_limit")]
+    move_limit: Option<usize>,
+}
+
+fn default_seconds() -> u64 {
+    DEFAULT_SECONDS
+}
+
+fn default_ballots() -> usize {
+    1
+}
+
+fn default_seed() -> u64 {
+    1
+}
+
+fn default_move_limit() -> Option<usize> {
+    Some(DEFAULT_MOVE_LIMIT)
+}
+
+/// Represents a game of the schedule of a tournament, between the engines at `black` and
+/// `white` in the list of engines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pairing {
+    round: usize,
+    black: usize,
+    white: usize,
+    ballot: usize,
+}
+
+impl Pairing {
+    pub(crate) fn new(round: usize, black: usize, white: usize, ballot: usize) -> Self {
+        Pairing {
+            round,
+            black,
+            white,
+            ballot,
+        }
+    }
+
+    /// Returns the number of the game in the tournament, counting from 1
+    #[inline]
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    #[inline]
+    pub fn black(&self) -> usize {
+        self.black
+    }
+
+    #[inline]
+    pub fn white(&self) -> usize {
+        self.white
+    }
+
+    /// Returns the number of the ballot the game opens with
+    #[inline]
+    pub fn ballot(&self) -> usize {
+        self.ballot
+    }
+}
+
+impl Tournament {
+    /// Reads a tournament from json. Every engine needs a name, and is either muskox with
+    /// a map of `options` (the options of the `setoption` command) or a program at a
+    /// `dxp` address. `seconds` is the time each side gets for a game, `ballots` the
+    /// number of openings drawn from the deck with `seed`, `pdn` the file the games are
+    /// appended to and `move_limit` the number of moves after which a game is a draw.
+    pub fn from_json(text: &str) -> Result<Self, TournamentError> {
+        let tournament: Tournament =
+            serde_json::from_str(text).map_err(|err| TournamentError::TournamentFormatError {
+                message: err.to_string(),
+            })?;
+        tournament.validate()
+    }
+
+    /// Creates a tournament between `engines`, with the defaults of the json format: a
+    /// minute a side, a single ballot and a limit of 300 moves
+    pub fn new(mode: Mode, engines: Vec<EntrantConfig>) -> Result<Self, TournamentError> {
+        Tournament {
+            mode,
+            engines,
+            seconds: DEFAULT_SECONDS,
+            ballots: 1,
+            seed: 1,
+            pdn: None,
+            move_limit: Some(DEFAULT_MOVE_LIMIT),
+        }
+        .validate()
+    }
+
+    /// Sets the time each side gets for a game
+    pub fn seconds(mut self, seconds: u64) -> Self {
+        self.seconds = seconds;
+        self
+    }
+
+    /// Sets the number of ballots drawn from the deck, which is at least one and at most
+    /// the whole deck
+    pub fn ballot_count(mut self, ballots: usize) -> Self {
+        self.ballots = ballots.clamp(1, ballot::deck().len());
+        self
+    }
+
+    /// Sets the seed the ballots are drawn with
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the file the games are appended to
+    pub fn pdn(mut self, path: Option<String>) -> Self {
+        self.pdn = path;
+        self
+    }
+
+    /// Sets the number of moves after which a game is a draw
+    pub fn move_limit(mut self, limit: Option<usize>) -> Self {
+        self.move_limit = limit;
+        self
+    }
+
+    fn validate(self) -> Result<Self, TournamentError> {
+        let tournament = self;
+        if tournament.engines.len() < 2 {
+            return Err(TournamentError::TournamentSizeError);
+        }
+        let format_error = |message: String| TournamentError::TournamentFormatError { message };
+        if !(1..=ballot::deck().len()).contains(&tournament.ballots) {
+            return Err(format_error(format!(
+                "there are 1 to {} ballots",
+                ballot::deck().len()
+            )));
+        }
+        for (i, entrant) in tournament.engines.iter().enumerate() {
+            if tournament.engines[..i]
+                .iter()
+                .any(|e| e.name == entrant.name)
+            {
+                return Err(format_error(format!(
+                    "two engines are named {}",
+                    entrant.name
+                )));
+            }
+            if entrant.dxp.is_some() && !entrant.options.is_empty() {
+                return Err(format_error(format!(
+                    "engine {} is reached over dxp and cannot take options",
+                    entrant.name
+                )));
+            }
+            // fails early on options that would only fail once the tournament started
+            entrant.options()?;
+        }
+        Ok(tournament)
+    }
+
+    #[inline]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Returns the names of the engines, in the order they were given
+    pub fn names(&self) -> Vec<&str> {
+        self.engines.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    /// Returns the numbers of the ballots the tournament is played from, which are the
+    /// same for the same seed
+    pub fn ballots(&self) -> Vec<usize> {
+        // the first ballots of a shuffled deck
+        let mut numbers: Vec<_> = (1..=ballot::deck().len()).collect();
+        let mut prng = Prng::new(self.seed | 1);
+        for i in (1..numbers.len()).rev() {
+            let j = (prng.rand64() % (i as u64 + 1)) as usize;
+            numbers.swap(i, j);
+        }
+        numbers.truncate(self.ballots);
+        numbers
+    }
+
+    /// Returns every game of the tournament in the order they are played
+    pub fn schedule(&self) -> Vec<Pairing> {
+        let count = self.engines.len();
+        let pairs: Vec<(usize, usize)> = match self.mode {
+            Mode::RoundRobin => (0..count)
+                .flat_map(|a| (a + 1..count).map(move |b| (a, b)))
+                .collect(),
+            Mode::Gauntlet => (1..count).map(|b| (0, b)).collect(),
+        };
+
+        let mut schedule = Vec::new();
+        for ballot in self.ballots() {
+            for &(a, b) in &pairs {
+                for &(black, white) in &[(a, b), (b, a)] {
+                    schedule.push(Pairing {
+                        round: schedule.len() + 1,
+                        black,
+                        white,
+                        ballot,
+                    });
+                }
+            }
+        }
+        schedule
+    }
+
+    /// Plays every game of the schedule, calling `progress` after each one, and returns
+    /// the crosstable
+    pub fn run<F>(&self, mut progress: F) -> Result<Crosstable, TournamentError>
+    where
+        F: FnMut(&Pairing, &TournamentGame),
+    {
+        let mut entrants = self.entrants()?;
+        let event = match self.mode {
+            Mode::RoundRobin => "muskox round robin",
+            Mode::Gauntlet => "muskox gauntlet",
+        };
+        let mut crosstable = Crosstable::new(self.names());
+
+        let schedule = self.schedule();
+        for (i, pairing) in schedule.iter().enumerate() {
+            let last = i + 1 == schedule.len();
+            let game = self.play(&mut entrants, pairing, last)?;
+
+            self.append_pdn(event, pairing, &game)?;
+            crosstable.add(pairing.black, pairing.white, &game.winner);
+            progress(pairing, &game);
+        }
+        Ok(crosstable)
+    }
+
+    // connects to every engine, or starts it
+    pub(crate) fn entrants(&self) -> Result<Vec<Entrant>, TournamentError> {
+        self.engines.iter().map(|config| config.connect()).collect()
+    }
+
+    // plays a game of the schedule. `last` tells programs over dxp it is the last one
+    pub(crate) fn play(
+        &self,
+        entrants: &mut [Entrant],
+        pairing: &Pairing,
+        last: bool,
+    ) -> Result<TournamentGame, TournamentError> {
+        let ballot = ballot::get(pairing.ballot).unwrap();
+        let start = ballot.board();
+        let minutes = self.seconds.div_ceil(60).clamp(1, u64::from(u16::MAX)) as u16;
+
+        let (black, white) = pair_mut(entrants, pairing.black, pairing.white);
+        let mut black = black.seat(Color::Black, start, minutes)?;
+        let mut white = white.seat(Color::White, start, minutes)?;
+
+        let mut clock = Clock::new(Duration::from_secs(self.seconds));
+        let game = players::play_game(start, &mut black, &mut white, &mut clock, self.move_limit);
+
+        // a program over dxp may have ended the game itself, and knows best why
+        let mut winner = match game.winner() {
+            Winner::Player(color) => Winner::Player(*color),
+            Winner::Draw => Winner::Draw,
+        };
+        for (seat, color) in [(black, Color::White), (white, Color::Black)] {
+            if let Seat::Remote(name, remote) = seat {
+                winner = remote
+                    .finish(color, &winner, last)
+                    .map_err(|source| TournamentError::TournamentEngineError { name, source })?;
+            }
+        }
+
+        let mut actions = ballot.actions().to_vec();
+        actions.extend_from_slice(game.actions());
+        // the moves of the ballot were not chosen by anybody
+        let mut records = vec![None; ballot.actions().len()];
+        records.extend_from_slice(game.records());
+        Ok(TournamentGame {
+            actions,
+            records,
+            winner,
+            ending: game.ending(),
+        })
+    }
+
+    // appends a game to the pdn file of the tournament, if it has one
+    pub(crate) fn append_pdn(
+        &self,
+        event: &str,
+        pairing: &Pairing,
+        game: &TournamentGame,
+    ) -> Result<(), TournamentError> {
+        let path = match &self.pdn {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut pdn = Game::default();
+        for action in &game.actions {
+            pdn.take_action(*action).unwrap();
+        }
+
+        let round = pairing.round.to_string();
+        let tags = [
+            ("Event", event),
+            ("Round", &round),
+            ("Black", &self.engines[pairing.black].name),
+            ("White", &self.engines[pairing.white].name),
+        ];
+        // every move says how it was found, so it can be searched again the same way
+        let notes: Vec<_> = game
+            .records
+            .iter()
+            .map(|record| match record {
+                Some(record) => format!("{{{}}}", record),
+                None => String::new(),
+            })
+            .collect();
+        let text = pdn.pdn_tagged_with(&tags, &notes, game.result());
+
+        let io_error = |source| TournamentError::TournamentIoError {
+            path: path.to_string(),
+            source,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(io_error)?;
+        writeln!(file, "{}", text).map_err(io_error)
+    }
+}
+
+impl EntrantConfig {
+    /// Creates a muskox engine with options given by name and value, like `setoption`
+    pub fn engine(name: &str, options: &[(&str, &str)]) -> Self {
+        EntrantConfig {
+            name: name.to_string(),
+            options: options
+                .iter()
+                .map(|(name, value)| (name.to_string(), Value::String(value.to_string())))
+                .collect(),
+            dxp: None,
+        }
+    }
+
+    /// Creates the program at the DamExchange address `address`
+    pub fn dxp(name: &str, address: &str) -> Self {
+        EntrantConfig {
+            name: name.to_string(),
+            options: BTreeMap::new(),
+            dxp: Some(address.to_string()),
+        }
+    }
+
+    fn options(&self) -> Result<EngineOptions, TournamentError> {
+        let mut options = EngineOptions::default();
+        for (name, value) in &self.options {
+            // numbers and booleans may be written without quotes
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            options
+                .set(name, &value)
+                .map_err(|source| TournamentError::TournamentOptionError {
+                    name: self.name.clone(),
+                    source,
+                })?;
+        }
+
+        // the ballots are openings of american checkers
+        if options.variant() != Variant::American {
+            return Err(TournamentError::TournamentFormatError {
+                message: format!("engine {} does not play american checkers", self.name),
+            });
+        }
+        Ok(options)
+    }
+
+    fn connect(&self) -> Result<Entrant, TournamentError> {
+        if let Some(address) = &self.dxp {
+            let client = Client::connect(address).map_err(|source| {
+                TournamentError::TournamentEngineError {
+                    name: self.name.clone(),
+                    source,
+                }
+            })?;
+            return Ok(Entrant::Remote(self.name.clone(), client));
+        }
+
+        let options = self.options()?;
+        let mut engine = Engine::builder()
+            .threads(options.threads())
+            .tt_size_mb(options.hash_size())
+            .build()
+            .map_err(|err| TournamentError::TournamentFormatError {
+                message: format!("engine {}: {}", self.name, err),
+            })?;
+        options.apply(&mut engine);
+        Ok(Entrant::Engine(engine))
+    }
+}
+
+// an engine of a running tournament
+pub(crate) enum Entrant {
+    Engine(Engine<Bitboard>),
+    Remote(String, Client),
+}
+
+impl Entrant {
+    // gets the entrant ready to play `color` in a game from `start`
+    fn seat(
+        &mut self,
+        color: Color,
+        start: Bitboard,
+        minutes: u16,
+    ) -> Result<Seat<'_>, TournamentError> {
+        match self {
+            Entrant::Engine(engine) => {
+                // games are independent, so nothing is carried over from the last one
+                engine.reset();
+                Ok(Seat::Engine(Box::new(EnginePlayer::timed(engine.clone()))))
+            }
+            Entrant::Remote(name, client) => {
+                client
+                    .request_game(color, minutes, DXP_MOVES, Some(start))
+                    .map_err(|source| TournamentError::TournamentEngineError {
+                        name: name.clone(),
+                        source,
+                    })?;
+                Ok(Seat::Remote(name.clone(), RemotePlayer::new(client)))
+            }
+        }
+    }
+}
+
+// an entrant playing a game
+enum Seat<'a> {
+    Engine(Box<EnginePlayer<Bitboard>>),
+    Remote(String, RemotePlayer<'a>),
+}
+
+impl Player<Bitboard> for Seat<'_> {
+    fn choose(&mut self, state: &Bitboard, clock: &Clock) -> Option<Action> {
+        match self {
+            Seat::Engine(player) => player.choose(state, clock),
+            Seat::Remote(_, player) => player.choose(state, clock),
+        }
+    }
+
+    fn observe(&mut self, state: &Bitboard, action: Action) {
+        match self {
+            Seat::Engine(player) => player.observe(state, action),
+            Seat::Remote(_, player) => player.observe(state, action),
+        }
+    }
+
+    fn record(&self) -> Option<MoveRecord> {
+        match self {
+            Seat::Engine(player) => player.record(),
+            Seat::Remote(_, player) => player.record(),
+        }
+    }
+}
+
+// borrows two different elements of a slice at once
+fn pair_mut<T>(items: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
+    assert_ne!(a, b);
+    if a < b {
+        let (left, right) = items.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = items.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+/// Represents a finished game of a tournament, from the initial position on
+#[derive(Debug)]
+pub struct TournamentGame {
+    actions: Vec<Action>,
+    records: Vec<Option<MoveRecord>>,
+    winner: Winner<Bitboard>,
+    ending: Ending,
+}
+
+impl TournamentGame {
+    /// Returns the actions of the game, starting with those of its ballot
+    #[inline]
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Returns how every action was found, for the engines that could tell. The actions
+    /// of the ballot have no record.
+    #[inline]
+    pub fn records(&self) -> &[Option<MoveRecord>] {
+        &self.records
+    }
+
+    #[inline]
+    pub fn winner(&self) -> &Winner<Bitboard> {
+        &self.winner
+    }
+
+    #[inline]
+    pub fn ending(&self) -> Ending {
+        self.ending
+    }
+
+    /// Returns the PDN result of the game
+    pub fn result(&self) -> &'static str {
+        match self.winner {
+            Winner::Player(Color::Black) => "1-0",
+            Winner::Player(Color::White) => "0-1",
+            Winner::Draw => "1/2-1/2",
+        }
+    }
+}
+
+/// Represents the wins, draws and losses of an engine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Record {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Record {
+    /// Creates a record of `wins` wins, `draws` draws and `losses` losses
+    pub fn new(wins: u32, draws: u32, losses: u32) -> Self {
+        Record {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    #[inline]
+    pub fn wins(&self) -> u32 {
+        self.wins
+    }
+
+    #[inline]
+    pub fn draws(&self) -> u32 {
+        self.draws
+    }
+
+    #[inline]
+    pub fn losses(&self) -> u32 {
+        self.losses
+    }
+
+    #[inline]
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the points scored, one for a win and a half for a draw
+    pub fn points(&self) -> f64 {
+        f64::from(self.wins) + f64::from(self.draws) / 2.
+    }
+
+    /// Returns the Elo difference the record stands for along with the margin of its 95%
+    /// confidence interval. There is none without games, or when every game was won or
+    /// every game lost.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::tournament::Record;
+    ///
+    /// let (elo, margin) = Record::new(30, 40, 10).elo().unwrap();
+    /// assert_eq!(elo.round(), 89.);
+    /// assert!(margin > 0. && margin < elo);
+    /// assert!(Record::new(5, 0, 0).elo().is_none());
+    /// ```
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let games = f64::from(self.games());
+        let score = self.points() / games;
+        if self.games() == 0 || score <= 0. || score >= 1. {
+            return None;
+        }
+
+        // the spread of the result of a game around the score
+        let deviation = (f64::from(self.wins) * (1. - score).powi(2)
+            + f64::from(self.draws) * (0.5 - score).powi(2)
+            + f64::from(self.losses) * score.powi(2))
+            / games;
+        let error = 1.96 * (deviation / games).sqrt();
+
+        let low = elo_difference((score - error).max(f64::EPSILON));
+        let high = elo_difference((score + error).min(1. - f64::EPSILON));
+        Some((elo_difference(score), (high - low) / 2.))
+    }
+
+    /// Returns the likelihood of superiority: how likely it is that the engine is the
+    /// stronger one, judging from its wins and losses
+    pub fn los(&self) -> f64 {
+        let (wins, losses) = (f64::from(self.wins), f64::from(self.losses));
+        if self.wins + self.losses == 0 {
+            return 0.5;
+        }
+        0.5 * (1. + erf((wins - losses) / (2. * (wins + losses)).sqrt()))
+    }
+}
+
+impl std::ops::AddAssign for Record {
+    fn add_assign(&mut self, other: Record) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+// the Elo difference at which the stronger side is expected to score `score`
+fn elo_difference(score: f64) -> f64 {
+    -400. * (1. / score - 1.).log10()
+}
+
+// the error function, to within 1.5e-7 (Abramowitz and Stegun 7.1.26)
+fn erf(x: f64) -> f64 {
+    let t = 1. / (1. + 0.327_591_1 * x.abs());
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let y = 1. - poly * (-x * x).exp();
+    y.copysign(x)
+}
+
+/// Represents the results of a tournament, engine against engine
+#[derive(Debug, Clone)]
+pub struct Crosstable {
+    names: Vec<String>,
+    records: Vec<Vec<Record>>, // records[a][b] is the record of a against b
+}
+
+impl Crosstable {
+    fn new(names: Vec<&str>) -> Self {
+        Crosstable {
+            records: vec![vec![Record::default(); names.len()]; names.len()],
+            names: names.into_iter().map(str::to_string).collect(),
+        }
+    }
+
+    fn add(&mut self, black: usize, white: usize, winner: &Winner<Bitboard>) {
+        let (black_result, white_result) = match winner {
+            Winner::Player(Color::Black) => (Record::new(1, 0, 0), Record::new(0, 0, 1)),
+            Winner::Player(Color::White) => (Record::new(0, 0, 1), Record::new(1, 0, 0)),
+            Winner::Draw => (Record::new(0, 1, 0), Record::new(0, 1, 0)),
+        };
+        self.records[black][white] += black_result;
+        self.records[white][black] += white_result;
+    }
+
+    /// Returns the names of the engines, in the order they were given
+    #[inline]
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the record of the engine at `engine` against the one at `opponent`
+    #[inline]
+    pub fn record(&self, engine: usize, opponent: usize) -> Record {
+        self.records[engine][opponent]
+    }
+
+    /// Returns the record of the engine at `engine` against every engine it played
+    pub fn total(&self, engine: usize) -> Record {
+        let mut total = Record::default();
+        for record in &self.records[engine] {
+            total += *record;
+        }
+        total
+    }
+}
+
+impl fmt::Display for Crosstable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.names.iter().map(String::len).max().unwrap_or(0).max(6);
+
+        // engines are ranked by their points
+        let mut ranking: Vec<_> = (0..self.names.len()).collect();
+        ranking.sort_by(|a, b| {
+            let (a, b) = (self.total(*a).points(), self.total(*b).points());
+            b.partial_cmp(&a).unwrap()
+        });
+
+        write!(f, "{:>3} {:<width$}", "#", "engine", width = width)?;
+        for rank in 1..=ranking.len() {
+            write!(f, " {:>7}", rank)?;
+        }
+        writeln!(
+            f,
+            " {:>7} {:>5} {:>13} {:>6}",
+            "points", "games", "elo", "los"
+        )?;
+
+        for (rank, engine) in ranking.iter().enumerate() {
+            write!(
+                f,
+                "{:>3} {:<width$}",
+                rank + 1,
+                self.names[*engine],
+                width = width
+            )?;
+            for opponent in &ranking {
+                let record = self.record(*engine, *opponent);
+                match record.games() {
+                    0 => write!(f, " {:>7}", "-")?,
+                    games => write!(f, " {:>7}", format!("{}/{}", record.points(), games))?,
+                }
+            }
+
+            let total = self.total(*engine);
+            let elo = match total.elo() {
+                Some((elo, margin)) => format!("{:+.0} +/- {:.0}", elo, margin),
+                None => "-".to_string(),
+            };
+            writeln!(
+                f,
+                " {:>7} {:>5} {:>13} {:>5.1}%",
+                total.points(),
+                total.games(),
+                elo,
+                total.los() * 100.
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays the tournament of the json file at `path`, reporting every game on stderr, and
+/// prints the crosstable
+pub fn run(path: &str) -> Result<(), TournamentError> {
+    let text = fs::read_to_string(path).map_err(|source| TournamentError::TournamentIoError {
+        path: path.to_string(),
+        source,
+    })?;
+    let tournament = Tournament::from_json(&text)?;
+    let names = tournament.names();
+    let total = tournament.schedule().len();
+
+    let crosstable = tournament.run(|pairing, game| {
+        eprintln!(
+            "game {}/{}, ballot {}: {} - {} {} ({:?})",
+            pairing.round(),
+            total,
+            pairing.ballot(),
+            names[pairing.black()],
+            names[pairing.white()],
+            game.result(),
+            game.ending()
+        );
+    })?;
+    print!("{}", crosstable);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tournament_test() {
+        let path =
+            std::env::temp_dir().join(format!("muskox-tournament-{}.pdn", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let config = format!(
+            r#"{{
+                "engines": [
+                    {{"name": "two", "options": {{"depth": 2, "threads": 1, "hash": 1}}}},
+                    {{"name": "one", "options": {{"depth": "1", "threads": 1, "hash": 1}}}},
+                    {{"name": "three", "options": {{"depth": 3, "threads": 1, "hash": 1}}}}
+                ],
+                "seconds": 30,
+                "ballots": 2,
+                "seed": 9,
+                "pdn": {:?},
+                "move_limit": 40
+            }}"#,
+            path.to_str().unwrap()
+        );
+        let tournament = Tournament::from_json(&config).unwrap();
+
+        // three pairings, two ballots, both colors
+        let schedule = tournament.schedule();
+        assert_eq!(schedule.len(), 12);
+        assert_eq!(schedule[0].black(), schedule[1].white());
+        assert_eq!(
+            tournament.ballots(),
+            Tournament::from_json(&config).unwrap().ballots()
+        );
+        let gauntlet = config.replace("\"engines\"", "\"mode\": \"gauntlet\", \"engines\"");
+        let gauntlet = Tournament::from_json(&gauntlet).unwrap();
+        assert_eq!(gauntlet.schedule().len(), 8);
+        assert!(gauntlet
+            .schedule()
+            .iter()
+            .all(|p| p.black() == 0 || p.white() == 0));
+
+        let mut played = 0;
+        let crosstable = tournament.run(|_, _| played += 1).unwrap();
+        assert_eq!(played, 12);
+        let records: Vec<_> = (0..3).map(|engine| crosstable.total(engine)).collect();
+        assert!(records.iter().all(|record| record.games() == 8));
+        assert_eq!(records.iter().map(Record::points).sum::<f64>(), 12.);
+        assert_eq!(
+            crosstable.record(0, 1).wins(),
+            crosstable.record(1, 0).losses()
+        );
+        assert_eq!(crosstable.to_string().lines().count(), 4);
+
+        // every game is written with its players, starting with its ballot, and every move
+        // of an engine with the search that found it
+        let text = fs::read_to_string(&path).unwrap();
+        let games = crate::pdn::parse_games(&text).unwrap();
+        assert_eq!(games.len(), 12);
+        assert!(text.contains(", depth 3}"));
+        assert_eq!(
+            games[0].actions()[..3],
+            ballot::get(schedule[0].ballot()).unwrap().actions()[..]
+        );
+        let _ = fs::remove_file(&path);
+
+        assert!(Tournament::from_json(&config.replace("\"depth\": 2", "\"depth\": 1001")).is_err());
+        assert!(Tournament::from_json(r#"{"engines": [{"name": "alone"}]}"#).is_err());
+        assert!(Tournament::from_json(
+            r#"{"engines": [{"name": "a"}, {"name": "b", "cb": "x.dll"}]}"#
+        )
+        .is_err());
+
+        // statistics
+        assert_eq!(Record::new(5, 10, 5).elo().unwrap().0, 0.);
+        assert!((Record::new(3, 0, 1).points() - 3.).abs() < 1e-9);
+        assert!((Record::new(10, 0, 10).los() - 0.5).abs() < 1e-9);
+        assert!(Record::new(20, 5, 10).los() > 0.9);
+        assert!((erf(1.) - 0.842_700_79).abs() < 1e-6);
+        assert!((erf(-0.5) + 0.520_499_88).abs() < 1e-6);
+    }
+}
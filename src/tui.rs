@@ -0,0 +1,519 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color as Tint, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Gauge, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+
+use crate::app::color_name;
+use crate::board::{Action, Bitboard, Color, JumpContinuation, Square};
+use crate::pdn::Game;
+use crate::players::Clock;
+use crate::search::{Engine, GameState, Score, SearchConstraint, SearchResult, Searchable};
+
+// a game against the engine in the terminal. the board is on the left with the
+// evaluation bar below it, and the clocks, the engine's line and the moves of the game
+// are on the right. moves are entered by walking the cursor over the board with the
+// arrow keys and picking their squares with enter, as many as it takes to tell the move
+// apart from the others. the engine searches on a thread of its own, and every
+// iteration it finishes updates the bar and the line.
+
+const DEFAULT_SECONDS: u64 = 300;
+// the engine plans its time as if this many moves were left in the game
+const MOVES_TO_GO: u32 = 30;
+const PV_LENGTH: usize = 8;
+// how long to wait for a key before drawing the clocks again
+const TICK: Duration = Duration::from_millis(100);
+
+const HELP: &str =
+    "arrows move, enter picks a square, esc clears, u undoes, n starts over, q quits";
+
+/// Holds what the search of the engine found so far
+#[derive(Debug, Default)]
+struct Analysis {
+    depth: u32,
+    score: Option<Score>,
+    line: Vec<Action>,
+}
+
+/// Represents a search of the engine running on a thread of its own
+struct Thinking {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<SearchResult<Bitboard>>,
+}
+
+/// Represents a game between a player in the terminal and the engine
+struct Tui {
+    engine: Engine<Bitboard>,
+    game: Game,
+    human: Color,
+    seconds: u64,
+    clock: Clock,
+    flagged: Option<Color>, // the side that ran out of time, if one did
+    cursor: (u8, u8),       // the row and column on the screen
+    entered: Vec<Square>,   // the squares picked so far of the move being entered
+    analysis: Arc<Mutex<Analysis>>,
+    thinking: Option<Thinking>,
+    message: String,
+}
+
+impl Tui {
+    fn new(engine: Engine<Bitboard>, human: Color, seconds: u64) -> Self {
+        Tui {
+            engine,
+            game: Game::default(),
+            human,
+            seconds,
+            clock: Clock::new(Duration::from_secs(seconds)),
+            flagged: None,
+            cursor: (5, 0),
+            entered: Vec::new(),
+            analysis: Arc::new(Mutex::new(Analysis::default())),
+            thinking: None,
+            message: HELP.to_string(),
+        }
+    }
+
+    fn over(&self) -> bool {
+        self.flagged.is_some() || self.game.state() != GameState::InProgress
+    }
+
+    // the square under a spot of the screen. the player's pieces are at the bottom
+    fn square_at(&self, row: u8, col: u8) -> Option<Square> {
+        match self.human {
+            Color::Black => Square::from_coords(7 - row, 7 - col),
+            Color::White => Square::from_coords(row, col),
+        }
+    }
+
+    /// Returns the time `color` has left, counting the time the side to move is thinking
+    fn left(&self, color: Color) -> Duration {
+        let remaining = self.clock.remaining(&color);
+        match color == self.game.board().turn() && !self.over() {
+            true => remaining.saturating_sub(self.clock.thinking()),
+            false => remaining,
+        }
+    }
+
+    /// Starts or finishes the search of the engine when it is its turn, and flags the
+    /// player once their time is up
+    fn tick(&mut self) {
+        let turn = self.game.board().turn();
+        if self.over() {
+            return;
+        }
+        if turn == self.human {
+            if self.left(turn) == Duration::from_secs(0) {
+                self.flagged = Some(turn);
+                self.message = format!("{} ran out of time", color_name(turn));
+            }
+            return;
+        }
+
+        match self.thinking.take() {
+            None => self.think(),
+            Some(thinking) if !thinking.handle.is_finished() => self.thinking = Some(thinking),
+            Some(thinking) => {
+                let result = thinking.handle.join().unwrap();
+                if !self.clock.punch(&turn) {
+                    self.flagged = Some(turn);
+                    self.message = format!("{} ran out of time", color_name(turn));
+                    return;
+                }
+                // the game is in progress so there is an action
+                let action = result.best().unwrap().action();
+                self.game.take_action(action).unwrap();
+                self.message = format!("the engine played {}", action);
+                self.finish();
+            }
+        }
+    }
+
+    fn think(&mut self) {
+        let board = self.game.board();
+        let budget = self.clock.remaining(&board.turn()) / MOVES_TO_GO;
+        let mut engine = self.engine.clone();
+        engine.set_history(&self.game.hashes());
+
+        let analysis = self.analysis.clone();
+        {
+            let mut analysis = analysis.lock().unwrap();
+            analysis.depth = 0;
+            analysis.line.clear();
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+        let handle = thread::spawn(move || {
+            let constraint = SearchConstraint::Time(budget);
+            engine.search_with(&board, &constraint, &flag, |info| {
+                let mut analysis = analysis.lock().unwrap();
+                analysis.depth = info.depth();
+                analysis.score = info.best().map(|best| best.score());
+                analysis.line = engine.principal_variation(&board, PV_LENGTH);
+            })
+        });
+        self.thinking = Some(Thinking { stop, handle });
+    }
+
+    /// Stops the search of the engine, if it is searching, and forgets what it found
+    fn interrupt(&mut self) {
+        if let Some(thinking) = self.thinking.take() {
+            thinking.stop.store(true, Ordering::Relaxed);
+            let _ = thinking.handle.join();
+        }
+    }
+
+    /// Tells the player how the game ended, once it has
+    fn finish(&mut self) {
+        if let GameState::Completed(_) = self.game.state() {
+            self.message = format!("game over, {}", self.game.result());
+        }
+    }
+
+    /// Handles a key. Returns false once the player quits.
+    fn key(&mut self, code: KeyCode) -> bool {
+        let (row, col) = self.cursor;
+        match code {
+            KeyCode::Char('q') => {
+                self.interrupt();
+                return false;
+            }
+            KeyCode::Up => self.cursor = (row.saturating_sub(1), col),
+            KeyCode::Down => self.cursor = ((row + 1).min(7), col),
+            KeyCode::Left => self.cursor = (row, col.saturating_sub(1)),
+            KeyCode::Right => self.cursor = (row, (col + 1).min(7)),
+            KeyCode::Esc => self.entered.clear(),
+            KeyCode::Enter | KeyCode::Char(' ') => self.pick(),
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('n') => {
+                self.interrupt();
+                *self = Tui::new(self.engine.clone(), self.human, self.seconds);
+                self.clock.restart();
+            }
+            _ => (),
+        }
+        true
+    }
+
+    /// Adds the square under the cursor to the move being entered, and plays the move
+    /// once no other begins with the squares picked
+    fn pick(&mut self) {
+        let board = self.game.board();
+        let square = match self.square_at(self.cursor.0, self.cursor.1) {
+            Some(square) if board.turn() == self.human && !self.over() => square,
+            _ => return,
+        };
+
+        self.entered.push(square);
+        let numbers: Vec<u8> = self.entered.iter().map(|square| square.pdn()).collect();
+        let action = match board.continue_jump(&numbers) {
+            // a piece with a single move still waits for its destination
+            JumpContinuation::Forced(_) | JumpContinuation::Ambiguous(_)
+                if self.entered.len() == 1 =>
+            {
+                self.message = format!("{} picked, where to?", square.pdn());
+                return;
+            }
+            JumpContinuation::Complete(action) | JumpContinuation::Forced(action) => action,
+            JumpContinuation::Ambiguous(_) => {
+                self.message = format!("{}, where next?", movetext(&numbers));
+                return;
+            }
+            JumpContinuation::Invalid => {
+                self.message = format!("no move starts {}", movetext(&numbers));
+                self.entered.clear();
+                return;
+            }
+        };
+
+        self.entered.clear();
+        if !self.clock.punch(&self.human) {
+            self.flagged = Some(self.human);
+            self.message = format!("{} ran out of time", color_name(self.human));
+            return;
+        }
+        self.game.take_action(action).unwrap();
+        self.message = format!("you played {}", action);
+        self.finish();
+    }
+
+    /// Takes back moves until it is the player's turn again
+    fn undo(&mut self) {
+        if self.thinking.is_some() || self.game.actions().is_empty() {
+            return;
+        }
+        while self.game.undo().is_some() && self.game.board().turn() != self.human {}
+        self.flagged = None;
+        self.entered.clear();
+        self.clock.restart();
+        self.message = HELP.to_string();
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(frame.size());
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(26), Constraint::Min(20)])
+            .split(rows[0]);
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(10),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(columns[0]);
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(4),
+                Constraint::Length(5),
+                Constraint::Min(3),
+            ])
+            .split(columns[1]);
+
+        self.draw_board(frame, left[0]);
+        self.draw_eval(frame, left[1]);
+        self.draw_clock(frame, right[0]);
+        self.draw_line(frame, right[1]);
+        self.draw_history(frame, right[2]);
+        let status =
+            Paragraph::new(self.message.as_str()).block(Block::default().borders(Borders::ALL));
+        frame.render_widget(status, rows[1]);
+    }
+
+    fn draw_board(&self, frame: &mut Frame, area: Rect) {
+        let board = self.game.board();
+        let lines: Vec<Line> = (0..8)
+            .map(|row| {
+                let cells = (0..8).map(|col| {
+                    let square = match self.square_at(row, col) {
+                        Some(square) => square,
+                        None => return Span::styled("   ", Style::default().bg(Tint::Gray)),
+                    };
+                    let mask = square.mask();
+                    let king = board.kings() & mask != 0;
+                    let (text, tint) = match (board.blacks() & mask, board.whites() & mask) {
+                        (0, 0) => (format!("{:>2} ", square.pdn()), Tint::DarkGray),
+                        (_, 0) => (format!(" {} ", if king { 'B' } else { 'b' }), Tint::Red),
+                        _ => (format!(" {} ", if king { 'W' } else { 'w' }), Tint::White),
+                    };
+
+                    let mut style = Style::default().fg(tint).bg(Tint::Black);
+                    if self.entered.contains(&square) {
+                        style = style.bg(Tint::Blue);
+                    }
+                    if (row, col) == self.cursor {
+                        style = style.bg(Tint::Yellow).add_modifier(Modifier::BOLD);
+                    }
+                    Span::styled(text, style)
+                });
+                Line::from(cells.collect::<Vec<Span>>())
+            })
+            .collect();
+
+        let block = Block::default().borders(Borders::ALL).title(" muskox ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_eval(&self, frame: &mut Frame, area: Rect) {
+        let analysis = self.analysis.lock().unwrap();
+        let (ratio, label) = match analysis.score {
+            Some(score) => (
+                f64::from(score.to_win_probability()),
+                format!("{:+.2} ({:.0}%)", score, score.to_win_probability() * 100.),
+            ),
+            None => (0.5, "no search yet".to_string()),
+        };
+
+        // the bar fills with black's chance of winning
+        let gauge = Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title(" eval "))
+            .gauge_style(Style::default().fg(Tint::Red).bg(Tint::White))
+            .ratio(ratio.clamp(0., 1.))
+            .label(label);
+        frame.render_widget(gauge, area);
+    }
+
+    fn draw_clock(&self, frame: &mut Frame, area: Rect) {
+        let turn = self.game.board().turn();
+        let lines: Vec<Line> = [Color::Black, Color::White]
+            .iter()
+            .map(|&color| {
+                let left = self.left(color);
+                let mut text = format!(
+                    "{:<6}{}:{:04.1}",
+                    color_name(color),
+                    left.as_secs() / 60,
+                    left.as_secs_f64() % 60.
+                );
+                if color == self.human {
+                    text += " (you)";
+                }
+                let style = match color == turn && !self.over() {
+                    true => Style::default().add_modifier(Modifier::BOLD),
+                    false => Style::default(),
+                };
+                Line::styled(text, style)
+            })
+            .collect();
+
+        let block = Block::default().borders(Borders::ALL).title(" clock ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_line(&self, frame: &mut Frame, area: Rect) {
+        let analysis = self.analysis.lock().unwrap();
+        let line: Vec<String> = analysis.line.iter().map(Action::to_string).collect();
+        let title = match analysis.depth {
+            0 => " line ".to_string(),
+            depth => format!(" line, depth {} ", depth),
+        };
+
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let text = Paragraph::new(line.join(" ")).wrap(Wrap { trim: true });
+        frame.render_widget(text.block(block), area);
+    }
+
+    fn draw_history(&self, frame: &mut Frame, area: Rect) {
+        // a move of each side to a line, numbered, with the start left out when white
+        // moved first
+        let mut lines = Vec::new();
+        let mut turn = self.game.start().turn();
+        for action in self.game.actions() {
+            match turn {
+                Color::Black => lines.push(format!("{:>3}. {}", lines.len() + 1, action)),
+                Color::White if lines.is_empty() => lines.push(format!("  1. ... {}", action)),
+                Color::White => *lines.last_mut().unwrap() += &format!(" {}", action),
+            }
+            turn = turn.opposite();
+        }
+
+        // the latest moves are the ones that fit
+        let shown = lines
+            .len()
+            .saturating_sub(area.height.saturating_sub(2).into());
+        let lines: Vec<Line> = lines.into_iter().skip(shown).map(Line::from).collect();
+        let block = Block::default().borders(Borders::ALL).title(" moves ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+fn movetext(numbers: &[u8]) -> String {
+    let numbers: Vec<String> = numbers.iter().map(u8::to_string).collect();
+    numbers.join("-")
+}
+
+fn play<B: Backend>(terminal: &mut Terminal<B>, tui: &mut Tui) -> io::Result<()> {
+    tui.clock.restart();
+    loop {
+        tui.tick();
+        terminal.draw(|frame| tui.draw(frame))?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press && !tui.key(key.code) => {
+                return Ok(())
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Plays a game against the engine in the terminal, with the player on `human`'s side
+/// and five minutes on each clock
+pub fn run(human: Color) -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+    let mut tui = Tui::new(Engine::new(), human, DEFAULT_SECONDS);
+    let result = play(&mut terminal, &mut tui);
+
+    // the terminal is given back whether the game went well or not
+    terminal::disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    #[test]
+    fn tui_test() {
+        // thirty seconds a side has the engine think for a second a move
+        let engine = Engine::builder().threads(1).build().unwrap();
+        let mut tui = Tui::new(engine, Color::Black, 30);
+        let board = Bitboard::default();
+
+        // the cursor walks to 11 and then 15, with black at the bottom
+        let go_to = |tui: &mut Tui, number: u8| {
+            let spot = (0..8)
+                .flat_map(|row| (0..8).map(move |col| (row, col)))
+                .find(|&(row, col)| tui.square_at(row, col).map(Square::pdn) == Some(number))
+                .unwrap();
+            while tui.cursor != spot {
+                let code = match (tui.cursor, spot) {
+                    ((row, _), (to, _)) if row < to => KeyCode::Down,
+                    ((row, _), (to, _)) if row > to => KeyCode::Up,
+                    ((_, col), (_, to)) if col < to => KeyCode::Right,
+                    _ => KeyCode::Left,
+                };
+                assert!(tui.key(code));
+            }
+            tui.key(KeyCode::Enter);
+        };
+        go_to(&mut tui, 11);
+        assert_eq!(tui.entered, [Square::from_pdn(11).unwrap()]);
+        assert_eq!(tui.game.actions(), []);
+        go_to(&mut tui, 15);
+        assert_eq!(
+            tui.game.actions(),
+            [Action::from_movetext("11-15").unwrap()]
+        );
+
+        // moves nothing starts are dropped
+        tui.game = Game::new(board);
+        go_to(&mut tui, 1);
+        assert!(tui.entered.is_empty() && tui.message.starts_with("no move"));
+
+        // the engine answers on a thread of its own, filling in the line and the score
+        go_to(&mut tui, 11);
+        go_to(&mut tui, 15);
+        while tui.game.actions().len() < 2 {
+            tui.tick();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(tui.game.board().turn(), Color::Black);
+        assert!(tui.analysis.lock().unwrap().score.is_some());
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| tui.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("1. 11-15"));
+        assert!(text.contains("black") && text.contains("(you)"));
+
+        // taking back goes back to the player's turn
+        tui.key(KeyCode::Char('u'));
+        assert!(tui.game.actions().is_empty());
+        assert!(!tui.key(KeyCode::Char('q')));
+    }
+}
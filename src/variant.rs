@@ -0,0 +1,185 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::ParseError;
+
+/// Represents one of the rule sets played on the 8x8 board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Variant {
+    /// American checkers / english draughts
+    #[default]
+    American,
+    Italian,
+    /// Russian draughts (shashki)
+    Russian,
+    /// Brazilian draughts, international rules on the small board
+    Brazilian,
+    /// Giveaway (losing or suicide) checkers played with american rules
+    Giveaway,
+}
+
+/// Represents which capture sequence a player is obliged to take when several are possible
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapturePrecedence {
+    /// Any capture sequence may be chosen
+    Free,
+    /// The sequence capturing the most pieces must be taken
+    MostPieces,
+    /// Italian priorities: most pieces, then capturing with a king, then capturing the
+    /// most kings, then capturing a king earliest
+    Italian,
+}
+
+/// Represents what happens to a man that reaches the far row in the middle of a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidCaptureCrowning {
+    /// The man is crowned and its move ends there
+    Stops,
+    /// The man is crowned and goes on capturing as a king
+    ContinuesAsKing,
+    /// The man goes on capturing as a man, and is only crowned if its capture ends on the
+    /// far row
+    ContinuesAsMan,
+}
+
+/// Describes the rules of a particular variant that affect move generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    /// Whether single pieces may capture backwards
+    pub men_capture_backwards: bool,
+    /// Whether single pieces may capture kings
+    pub men_capture_kings: bool,
+    /// Which capture sequence has to be taken
+    pub capture_precedence: CapturePrecedence,
+    /// What happens to a man reaching the far row in the middle of a capture
+    pub mid_capture_crowning: MidCaptureCrowning,
+    /// Whether the player who runs out of pieces or moves wins instead of losing
+    pub giveaway: bool,
+    /// Whether kings move and capture along a whole diagonal instead of a square at a
+    /// time. Pieces captured by a flying king stay on the board until its capture is
+    /// over, so they cannot be jumped twice or passed over, and a king that can land on
+    /// several squares after a capture has to pick one it can go on capturing from.
+    pub flying_kings: bool,
+}
+
+impl Variant {
+    /// Every variant, in the order their numbers in packed boards follow
+    pub const ALL: [Variant; 5] = [
+        Variant::American,
+        Variant::Italian,
+        Variant::Russian,
+        Variant::Brazilian,
+        Variant::Giveaway,
+    ];
+
+    /// Returns the rules for a particular variant
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use muskox::board::{MidCaptureCrowning, Variant};
+    ///
+    /// assert!(Variant::Russian.rules().men_capture_backwards);
+    /// assert!(Variant::Brazilian.rules().flying_kings);
+    /// assert!(!Variant::Italian.rules().men_capture_kings);
+    /// assert_eq!(
+    ///     Variant::Russian.rules().mid_capture_crowning,
+    ///     MidCaptureCrowning::ContinuesAsKing
+    /// );
+    /// ```
+    pub fn rules(&self) -> Rules {
+        match self {
+            Variant::American => Rules {
+                men_capture_backwards: false,
+                men_capture_kings: true,
+                capture_precedence: CapturePrecedence::Free,
+                mid_capture_crowning: MidCaptureCrowning::Stops,
+                giveaway: false,
+                flying_kings: false,
+            },
+            Variant::Italian => Rules {
+                men_capture_backwards: false,
+                men_capture_kings: false,
+                capture_precedence: CapturePrecedence::Italian,
+                mid_capture_crowning: MidCaptureCrowning::Stops,
+                giveaway: false,
+                flying_kings: false,
+            },
+            Variant::Russian => Rules {
+                men_capture_backwards: true,
+                men_capture_kings: true,
+                capture_precedence: CapturePrecedence::Free,
+                mid_capture_crowning: MidCaptureCrowning::ContinuesAsKing,
+                giveaway: false,
+                flying_kings: true,
+            },
+            Variant::Brazilian => Rules {
+                men_capture_backwards: true,
+                men_capture_kings: true,
+                capture_precedence: CapturePrecedence::MostPieces,
+                mid_capture_crowning: MidCaptureCrowning::ContinuesAsMan,
+                giveaway: false,
+                flying_kings: true,
+            },
+            Variant::Giveaway => Rules {
+                giveaway: true,
+                ..Variant::American.rules()
+            },
+        }
+    }
+
+    /// Returns the PDN `GameType` number of the variant if it has one
+    pub fn game_type(&self) -> Option<u8> {
+        match self {
+            Variant::American => Some(21),
+            Variant::Italian => Some(22),
+            Variant::Russian => Some(25),
+            Variant::Brazilian => Some(26),
+            Variant::Giveaway => None,
+        }
+    }
+
+    /// Creates a variant from its PDN `GameType` number
+    pub fn from_game_type(game_type: u8) -> Option<Self> {
+        match game_type {
+            21 => Some(Variant::American),
+            22 => Some(Variant::Italian),
+            25 => Some(Variant::Russian),
+            26 => Some(Variant::Brazilian),
+            _ => None,
+        }
+    }
+
+    /// Returns the lowercase name of the variant
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::American => "american",
+            Variant::Italian => "italian",
+            Variant::Russian => "russian",
+            Variant::Brazilian => "brazilian",
+            Variant::Giveaway => "giveaway",
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "american" | "english" => Ok(Variant::American),
+            "italian" => Ok(Variant::Italian),
+            "russian" | "shashki" => Ok(Variant::Russian),
+            "brazilian" => Ok(Variant::Brazilian),
+            "giveaway" | "suicide" | "losing" => Ok(Variant::Giveaway),
+            _ => Err(ParseError::VariantError),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
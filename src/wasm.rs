@@ -0,0 +1,57 @@
+// javascript bindings for running the engine in the browser. build with the `wasm`
+// feature for the wasm32-unknown-unknown target and generate the glue with wasm-bindgen.
+// positions go in and out as fen strings and actions as movetext
+
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+
+use crate::board::{Action, Bitboard};
+use crate::search::{Engine, SearchConstraint, Searchable};
+
+// the browser gives a page little memory, so the table is kept small
+const HASH_SIZE: usize = 16;
+
+thread_local! {
+    static ENGINE: Engine<Bitboard> = Engine::builder()
+        .threads(1)
+        .tt_size_mb(HASH_SIZE)
+        .build()
+        .unwrap();
+}
+
+fn js_error(err: impl fmt::Display) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
+
+/// Returns the legal actions from the position as a json array of movetext
+#[wasm_bindgen(js_name = legalMoves)]
+pub fn legal_moves(fen: &str) -> Result<String, JsValue> {
+    let board = Bitboard::from_fen(fen).map_err(js_error)?;
+    let actions: Vec<_> = board
+        .generate_all_actions()
+        .iter()
+        .map(|p| p.action().to_string())
+        .collect();
+    Ok(serde_json::to_string(&actions).unwrap())
+}
+
+/// Plays the action given as movetext and returns the fen of the position it leads to
+#[wasm_bindgen(js_name = takeAction)]
+pub fn take_action(fen: &str, movetext: &str) -> Result<String, JsValue> {
+    let board = Bitboard::from_fen(fen).map_err(js_error)?;
+    let action = Action::from_movetext(movetext).map_err(js_error)?;
+    let board = board.take_action(action).map_err(js_error)?;
+    Ok(board.fen())
+}
+
+/// Searches the position for at most `milliseconds` and returns the movetext of the best
+/// action, or nothing if the game is over. The search runs on the calling thread, so call
+/// it from a web worker to keep the page responsive.
+#[wasm_bindgen(js_name = bestMove)]
+pub fn best_move(fen: &str, milliseconds: u32) -> Result<Option<String>, JsValue> {
+    let board = Bitboard::from_fen(fen).map_err(js_error)?;
+    let constraint = SearchConstraint::time(milliseconds).map_err(js_error)?;
+    let result = ENGINE.with(|engine| engine.search(&board, &constraint));
+    Ok(result.best().map(|p| p.action().to_string()))
+}
@@ -1,24 +1,31 @@
-use lazy_static::lazy_static;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::bitboard::Color;
 
 const SEED: u64 = 25184470690726;
 
-// maybe make an init function
-// i dont want to have to deal with mutability of static though
-lazy_static! {
-    static ref ZOBRIST_TABLE: [u64; 97] = {
-        let mut table = [0; 97];
+// the keys of the 8x8 board with men and kings, worked out at compile time. they are laid
+// out like every `ZobristKeys`: a key for each square for black and then for white, a key
+// for each square and each kind of piece after the first, and last the key of the turn
+const SQUARES: usize = 32;
+const KINDS: usize = 2;
+const DEFAULT_KEYS: [u64; key_count(SQUARES, KINDS)] = default_keys();
 
-        // maybe make the seed time or something
-        let mut prng = Prng::new(SEED);
+const fn key_count(squares: usize, kinds: usize) -> usize {
+    // both colors share the keys of the kinds beyond the first
+    squares * (kinds + 1) + 1
+}
 
-        for entry in table.iter_mut() {
-            *entry = prng.rand64()
-        }
+const fn default_keys() -> [u64; key_count(SQUARES, KINDS)] {
+    let mut table = [0; key_count(SQUARES, KINDS)];
+    let mut prng = Prng::new(SEED);
 
-        table
-    };
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = prng.rand64();
+        i += 1;
+    }
+    table
 }
 
 #[inline]
@@ -28,12 +35,12 @@ pub fn get_position_hash(position: u8, color: Color, is_king: bool) -> u64 {
 
     // get a particular hash for a color and a position
     let mut hash = match color {
-        Color::Black => ZOBRIST_TABLE[position],
-        Color::White => ZOBRIST_TABLE[32 + position],
+        Color::Black => DEFAULT_KEYS[position],
+        Color::White => DEFAULT_KEYS[SQUARES + position],
     };
 
     if is_king {
-        hash ^= ZOBRIST_TABLE[64 + position];
+        hash ^= DEFAULT_KEYS[2 * SQUARES + position];
     }
 
     hash
@@ -42,22 +49,164 @@ pub fn get_position_hash(position: u8, color: Color, is_king: bool) -> u64 {
 #[inline]
 pub fn get_turn_hash() -> u64 {
     // get the hash for the turn
-    ZOBRIST_TABLE[96]
+    DEFAULT_KEYS[key_count(SQUARES, KINDS) - 1]
+}
+
+/// Holds the random keys positions are hashed with, for boards of any number of squares
+/// and kinds of pieces.
+///
+/// A piece is hashed with the key of its square and color, combined with the key of its
+/// square and kind unless it is of the first kind. The default keys are the ones the 8x8
+/// board uses, and other seeds give other keys.
+///
+/// # Examples
+///
+/// ```
+/// use muskox::board::{Color, ZobristKeys};
+///
+/// // the 10x10 board has 50 squares
+/// let keys = ZobristKeys::with_seed(50, 2, 7);
+/// assert_ne!(keys.piece(49, Color::White, 1), keys.piece(49, Color::White, 0));
+/// assert_eq!(keys, ZobristKeys::with_seed(50, 2, 7));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZobristKeys {
+    squares: usize,
+    kinds: usize,
+    keys: Vec<u64>,
+}
+
+impl Default for ZobristKeys {
+    /// Returns the keys of the 8x8 board with men and kings
+    fn default() -> Self {
+        ZobristKeys {
+            squares: SQUARES,
+            kinds: KINDS,
+            keys: DEFAULT_KEYS.to_vec(),
+        }
+    }
 }
 
-struct Prng {
+impl ZobristKeys {
+    /// Creates keys for `squares` squares and `kinds` kinds of pieces from the default
+    /// seed. With 32 squares and 2 kinds they are the default keys.
+    pub fn new(squares: usize, kinds: usize) -> Self {
+        Self::with_seed(squares, kinds, SEED)
+    }
+
+    /// Creates keys for `squares` squares and `kinds` kinds of pieces from `seed`
+    pub fn with_seed(squares: usize, kinds: usize, seed: u64) -> Self {
+        // a zero seed would only ever give zeros
+        let mut prng = Prng::new(match seed {
+            0 => SEED,
+            seed => seed,
+        });
+
+        ZobristKeys {
+            squares,
+            kinds,
+            keys: (0..key_count(squares, kinds))
+                .map(|_| prng.rand64())
+                .collect(),
+        }
+    }
+
+    #[inline]
+    pub fn squares(&self) -> usize {
+        self.squares
+    }
+
+    #[inline]
+    pub fn kinds(&self) -> usize {
+        self.kinds
+    }
+
+    /// Returns the hash of a piece of `kind` (from 0) and `color` on `square`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the square or the kind is out of range
+    pub fn piece(&self, square: usize, color: Color, kind: usize) -> u64 {
+        assert!(square < self.squares && kind < self.kinds);
+
+        let hash = match color {
+            Color::Black => self.keys[square],
+            Color::White => self.keys[self.squares + square],
+        };
+        match kind {
+            0 => hash,
+            kind => hash ^ self.keys[(kind + 1) * self.squares + square],
+        }
+    }
+
+    /// Returns the hash of the turn, which is added while white is to move
+    #[inline]
+    pub fn turn(&self) -> u64 {
+        self.keys[self.keys.len() - 1]
+    }
+}
+
+/// A small xorshift random number generator. The same seed always gives the same numbers,
+/// which keeps randomized tests and searches reproducible.
+#[derive(Debug, Clone)]
+pub struct Prng {
     s: u64,
 }
 
 impl Prng {
-    fn new(seed: u64) -> Self {
+    /// Creates a generator from a seed, which must not be zero
+    pub const fn new(seed: u64) -> Self {
         Prng { s: seed }
     }
 
-    fn rand64(&mut self) -> u64 {
+    /// Creates a generator seeded by the clock
+    pub fn from_time() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(1)
+            | 1; // the generator gets stuck on a zero seed
+        Prng::new(seed)
+    }
+
+    /// Returns the seed of a generator that goes on to give the same numbers as this one
+    #[inline]
+    pub const fn seed(&self) -> u64 {
+        self.s
+    }
+
+    /// Returns the next random number
+    pub const fn rand64(&mut self) -> u64 {
         self.s ^= self.s >> 12;
         self.s ^= self.s << 25;
         self.s ^= self.s >> 27;
         self.s.wrapping_mul(2685821657736338717)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zobrist_keys_test() {
+        // the board hashes with the default keys
+        let keys = ZobristKeys::default();
+        for square in 0..32 {
+            for color in [Color::Black, Color::White] {
+                let hash = get_position_hash(square as u8, color, true);
+                assert_eq!(keys.piece(square, color, 1), hash);
+            }
+        }
+        assert_eq!(keys.turn(), get_turn_hash());
+        assert_eq!(ZobristKeys::new(32, 2), keys);
+
+        let keys = ZobristKeys::with_seed(50, 3, 1);
+        assert_eq!((keys.squares(), keys.kinds()), (50, 3));
+        assert_ne!(keys, ZobristKeys::new(50, 3));
+        assert_ne!(
+            keys.piece(10, Color::Black, 2),
+            keys.piece(10, Color::Black, 1)
+        );
+    }
+}